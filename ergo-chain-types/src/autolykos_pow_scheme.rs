@@ -0,0 +1,395 @@
+//! Autolykos Proof of Work (v1 and v2) verification
+//! see <https://docs.ergoplatform.com/ErgoPow.pdf> and
+//! <https://github.com/ergoplatform/ergo/blob/master/src/main/scala/org/ergoplatform/mining/AutolykosPowScheme.scala>
+
+use elliptic_curve::group::ff::PrimeField;
+use ergotree_ir::chain::header::Header;
+use ergotree_ir::serialization::SigmaSerializable;
+use ergotree_ir::serialization::SigmaSerializationError;
+use ergotree_ir::sigma_protocol::dlog_group;
+use k256::Scalar;
+use num_bigint::BigInt;
+use num_bigint::BigUint;
+use num_bigint::Sign;
+use sigma_util::hash::blake2b256_hash;
+use std::convert::TryFrom;
+use thiserror::Error;
+
+use crate::nbits::decode_compact_bits;
+
+/// Order of the secp256k1 group
+/// (FFFFFFFF FFFFFFFF FFFFFFFF FFFFFFFE BAAEDCE6 AF48A03B BFD25E8C D0364141)
+const GROUP_ORDER_BYTES: [u8; 32] = [
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE,
+    0xBA, 0xAE, 0xDC, 0xE6, 0xAF, 0x48, 0xA0, 0x3B, 0xBF, 0xD2, 0x5E, 0x8C, 0xD0, 0x36, 0x41, 0x41,
+];
+
+/// Errors on PoW verification
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum AutolykosPowSchemeError {
+    /// Header serialization error
+    #[error("header serialization error: {0}")]
+    SerializationError(#[from] SigmaSerializationError),
+    /// Difficulty decoded from nBits is not positive
+    #[error("invalid nBits {0}: difficulty is not positive")]
+    InvalidNBits(u64),
+    /// PoW hit is not below the target (v2)
+    #[error("hit {hit} is not less than target {target}")]
+    HitTooBig {
+        /// Calculated hit
+        hit: BigInt,
+        /// Target decoded from nBits
+        target: BigInt,
+    },
+    /// PoW distance `d` is out of the `(-target, target)` range (v1)
+    #[error("d = {d} is out of range for target {target}")]
+    DistanceTooBig {
+        /// PoW distance from the header
+        d: BigInt,
+        /// Target decoded from nBits
+        target: BigInt,
+    },
+    /// Miner public key or one-time public key is the point at infinity (v1)
+    #[error("miner pk or one-time pk is the point at infinity")]
+    IdentityPoint,
+    /// `w^f = g^d * pk` does not hold (v1)
+    #[error("PoW solution equation does not hold")]
+    InvalidSolution,
+    /// Number of elements in the solution `k` is greater than 32
+    #[error("k = {0} is greater than 32")]
+    InvalidK(u32),
+    /// Power of 2 of the table size `n` is not less than 32
+    #[error("n = {0} is not less than 32")]
+    InvalidN(u32),
+}
+
+/// Autolykos PoW puzzle scheme implementation.
+/// See <https://docs.ergoplatform.com/ErgoPow.pdf> for details
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct AutolykosPowScheme {
+    /// Number of elements in one solution (`k` in the paper)
+    k: u32,
+    /// Power of 2 of the initial size of the list of elements (`n` in the paper)
+    n: u32,
+    /// Constant data to be added to hash function to increase its calculation time
+    big_m: Vec<u8>,
+}
+
+impl Default for AutolykosPowScheme {
+    /// Mainnet parameters (`k = 32`, `n = 26`)
+    fn default() -> Self {
+        #[allow(clippy::unwrap_used)]
+        AutolykosPowScheme::new(32, 26).unwrap()
+    }
+}
+
+impl AutolykosPowScheme {
+    /// Height from which the table size `N` starts to increase (v2)
+    pub const INCREASE_START: u32 = 600 * 1024;
+    /// Number of blocks between `N` increases (v2)
+    pub const INCREASE_PERIOD_FOR_N: u32 = 50 * 1024;
+    /// Height after which `N` stops to increase (v2)
+    pub const N_INCREASEMENT_HEIGHT_MAX: u32 = 4198400;
+
+    /// Create a scheme with `k` elements in the solution and `2^n` initial table size.
+    /// `k` can be at most 32 (indexes are taken from a 32-byte hash) and `n` must be less than 32.
+    pub fn new(k: u32, n: u32) -> Result<Self, AutolykosPowSchemeError> {
+        if k > 32 {
+            return Err(AutolykosPowSchemeError::InvalidK(k));
+        }
+        if n >= 32 {
+            return Err(AutolykosPowSchemeError::InvalidN(n));
+        }
+        let big_m = (0u64..1024).flat_map(|i| i.to_be_bytes()).collect();
+        Ok(AutolykosPowScheme { k, n, big_m })
+    }
+
+    /// Table size `N` for the given block version and height
+    pub fn calc_big_n(&self, header_version: u8, header_height: u32) -> u32 {
+        let n_base = 2u32.pow(self.n);
+        if header_version == Header::INITIAL_VERSION {
+            n_base
+        } else {
+            let height = header_height.min(Self::N_INCREASEMENT_HEIGHT_MAX);
+            if height < Self::INCREASE_START {
+                n_base
+            } else {
+                let iters_number =
+                    (height - Self::INCREASE_START) / Self::INCREASE_PERIOD_FOR_N + 1;
+                (0..iters_number).fold(n_base, |step, _| step / 100 * 105)
+            }
+        }
+    }
+
+    /// Message to be signed by the miner, hash of the header without the PoW solution
+    pub fn msg_by_header(&self, header: &Header) -> Result<Vec<u8>, AutolykosPowSchemeError> {
+        Ok(blake2b256_hash(&header.serialize_without_pow()?).to_vec())
+    }
+
+    /// Target `b`, the hit (v2) or the distance (v1) should be less than it
+    pub fn target(&self, n_bits: u64) -> Result<BigInt, AutolykosPowSchemeError> {
+        let difficulty = decode_compact_bits(n_bits);
+        if difficulty.sign() != Sign::Plus {
+            return Err(AutolykosPowSchemeError::InvalidNBits(n_bits));
+        }
+        Ok(BigInt::from(group_order()) / difficulty)
+    }
+
+    /// PoW hit (v2), the header is valid if the hit is less than the target
+    pub fn pow_hit(&self, header: &Header) -> Result<BigInt, AutolykosPowSchemeError> {
+        let msg = self.msg_by_header(header)?;
+        let big_n = self.calc_big_n(header.version, header.height);
+        let h = header.height.to_be_bytes();
+        let msg_nonce = [msg.as_slice(), header.nonce.as_slice()].concat();
+        let prei8 = BigUint::from_bytes_be(&blake2b256_hash(&msg_nonce)[24..]);
+        let i = as_unsigned_byte_array(4, prei8 % big_n);
+        let f = blake2b256_hash(&[i.as_slice(), &h, &self.big_m].concat())[1..].to_vec();
+        let seed = [f.as_slice(), msg.as_slice(), header.nonce.as_slice()].concat();
+        let f2: BigUint = self
+            .gen_indexes(&seed, big_n)
+            .into_iter()
+            .map(|idx| {
+                let elem = blake2b256_hash(&[&idx.to_be_bytes()[..], &h, &self.big_m].concat());
+                BigUint::from_bytes_be(&elem[1..])
+            })
+            .sum();
+        let hit = blake2b256_hash(&as_unsigned_byte_array(32, f2));
+        Ok(BigInt::from_bytes_be(Sign::Plus, hit.as_ref()))
+    }
+
+    /// Checks the PoW solution of the header (v1 or v2 depending on the header version)
+    pub fn validate(&self, header: &Header) -> Result<(), AutolykosPowSchemeError> {
+        if header.version == Header::INITIAL_VERSION {
+            self.validate_v1(header)
+        } else {
+            let target = self.target(header.n_bits)?;
+            let hit = self.pow_hit(header)?;
+            if hit < target {
+                Ok(())
+            } else {
+                Err(AutolykosPowSchemeError::HitTooBig { hit, target })
+            }
+        }
+    }
+
+    fn validate_v1(&self, header: &Header) -> Result<(), AutolykosPowSchemeError> {
+        let target = self.target(header.n_bits)?;
+        let d = &header.pow_distance;
+        // d should be in (-target, target), target is positive
+        if d.magnitude() >= target.magnitude() {
+            return Err(AutolykosPowSchemeError::DistanceTooBig {
+                d: d.clone(),
+                target,
+            });
+        }
+        if dlog_group::is_identity(&header.miner_pk)
+            || dlog_group::is_identity(&header.pow_onetime_pk)
+        {
+            return Err(AutolykosPowSchemeError::IdentityPoint);
+        }
+        let q = BigInt::from(group_order());
+        let d = to_scalar(((d % &q) + &q) % &q)?;
+        let f = to_scalar(BigInt::from(self.v1_f(header)?))?;
+        let left = dlog_group::exponentiate(&header.pow_onetime_pk, &f);
+        let right = dlog_group::exponentiate(&dlog_group::generator(), &d) * &header.miner_pk;
+        if left == right {
+            Ok(())
+        } else {
+            Err(AutolykosPowSchemeError::InvalidSolution)
+        }
+    }
+
+    /// `f` value of the v1 solution, sum of `k` elements modulo group order
+    fn v1_f(&self, header: &Header) -> Result<BigUint, AutolykosPowSchemeError> {
+        let msg = self.msg_by_header(header)?;
+        let pk_bytes = header.miner_pk.sigma_serialize_bytes()?;
+        let w_bytes = header.pow_onetime_pk.sigma_serialize_bytes()?;
+        let big_n = self.calc_big_n(header.version, header.height);
+        let seed = [msg.as_slice(), header.nonce.as_slice()].concat();
+        let q = group_order();
+        let f: BigUint = self
+            .gen_indexes(&seed, big_n)
+            .into_iter()
+            .map(|idx| {
+                hash_mod_q(
+                    &[
+                        &idx.to_be_bytes()[..],
+                        &self.big_m,
+                        &pk_bytes,
+                        &msg,
+                        &w_bytes,
+                    ]
+                    .concat(),
+                )
+            })
+            .sum();
+        Ok(f % q)
+    }
+
+    /// Generates `k` element indexes in `[0, N)` from the seed
+    pub fn gen_indexes(&self, seed: &[u8], big_n: u32) -> Vec<u32> {
+        let hash = blake2b256_hash(seed);
+        let extended_hash = [&hash[..], &hash[..3]].concat();
+        (0..self.k as usize)
+            .map(|i| {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(&extended_hash[i..i + 4]);
+                u32::from_be_bytes(bytes) % big_n
+            })
+            .collect()
+    }
+}
+
+fn group_order() -> BigUint {
+    BigUint::from_bytes_be(&GROUP_ORDER_BYTES)
+}
+
+/// Hash of the input as a number modulo group order, the input is re-hashed until the hash is in
+/// the range where modulo reduction is uniform
+fn hash_mod_q(input: &[u8]) -> BigUint {
+    let q = group_order();
+    let valid_range = (BigUint::from(1u8) << 256) / &q * &q;
+    let mut hashed = blake2b256_hash(input);
+    loop {
+        let bi = BigUint::from_bytes_be(hashed.as_ref());
+        if bi < valid_range {
+            return bi % &q;
+        }
+        hashed = blake2b256_hash(hashed.as_ref());
+    }
+}
+
+/// Scalar from a number in `[0, q)`
+fn to_scalar(value: BigInt) -> Result<Scalar, AutolykosPowSchemeError> {
+    let bytes = match value.to_biguint() {
+        Some(v) => as_unsigned_byte_array(32, v),
+        None => return Err(AutolykosPowSchemeError::InvalidSolution),
+    };
+    <[u8; 32]>::try_from(bytes)
+        .ok()
+        .and_then(|bytes| Scalar::from_repr(bytes.into()))
+        .ok_or(AutolykosPowSchemeError::InvalidSolution)
+}
+
+/// Big-endian bytes of the value, left-padded with zeros to `length` (the value is assumed to fit)
+fn as_unsigned_byte_array(length: usize, value: BigUint) -> Vec<u8> {
+    let bytes = value.to_bytes_be();
+    let mut res = vec![0u8; length.saturating_sub(bytes.len())];
+    res.extend_from_slice(&bytes);
+    res
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+pub(crate) mod tests {
+    use super::*;
+    use sigma_test_util::HEADER_471746_JSON;
+
+    /// Mainnet header at height 471746 (block version 2)
+    pub(crate) fn header_471746() -> Header {
+        serde_json::from_str(HEADER_471746_JSON).unwrap()
+    }
+
+    #[test]
+    fn new_checks_parameters() {
+        assert!(AutolykosPowScheme::new(32, 31).is_ok());
+        assert_eq!(
+            AutolykosPowScheme::new(33, 26),
+            Err(AutolykosPowSchemeError::InvalidK(33))
+        );
+        assert_eq!(
+            AutolykosPowScheme::new(32, 32),
+            Err(AutolykosPowSchemeError::InvalidN(32))
+        );
+    }
+
+    #[test]
+    fn calc_big_n() {
+        let pow = AutolykosPowScheme::default();
+        assert_eq!(pow.calc_big_n(1, 700000), 67108864);
+        assert_eq!(pow.calc_big_n(2, 500000), 67108864);
+        assert_eq!(pow.calc_big_n(2, 614400), 70464240);
+        assert_eq!(pow.calc_big_n(2, 665600), 73987410);
+        assert_eq!(pow.calc_big_n(2, 4198400), 2143944600);
+        assert_eq!(pow.calc_big_n(2, 41984000), 2143944600);
+    }
+
+    #[test]
+    fn validate_v2_mainnet_header() {
+        let pow = AutolykosPowScheme::default();
+        let header = header_471746();
+        assert_eq!(pow.validate(&header), Ok(()));
+        let tampered = Header {
+            nonce: vec![0x59, 0x39, 0xec, 0xfe, 0xe6, 0xb0, 0xd7, 0xf5],
+            ..header.clone()
+        };
+        assert!(matches!(
+            pow.validate(&tampered),
+            Err(AutolykosPowSchemeError::HitTooBig { .. })
+        ));
+        let invalid_n_bits = Header {
+            n_bits: 0,
+            ..header
+        };
+        assert_eq!(
+            pow.validate(&invalid_n_bits),
+            Err(AutolykosPowSchemeError::InvalidNBits(0))
+        );
+    }
+
+    /// Sets a valid Autolykos v1 solution (the difficulty should be 1) and the id of the header
+    pub(crate) fn mine_v1(header: &mut Header) {
+        let pow = AutolykosPowScheme::default();
+        let x = Scalar::from(2u64);
+        let y = Scalar::from(3u64);
+        *header.miner_pk = dlog_group::exponentiate(&dlog_group::generator(), &x);
+        *header.pow_onetime_pk = dlog_group::exponentiate(&dlog_group::generator(), &y);
+        let f = to_scalar(BigInt::from(pow.v1_f(header).unwrap())).unwrap();
+        let d = y * f - x;
+        header.pow_distance = BigInt::from_bytes_be(Sign::Plus, &d.to_bytes()[..]);
+        header.id = header.compute_id().unwrap();
+    }
+
+    #[test]
+    fn validate_v1() {
+        let pow = AutolykosPowScheme::default();
+        let mut header = Header {
+            version: Header::INITIAL_VERSION,
+            // the last block mined with Autolykos v1
+            height: 417791,
+            // difficulty 1
+            n_bits: 0x01010000,
+            ..header_471746()
+        };
+        mine_v1(&mut header);
+        // the solution survives the wire format roundtrip
+        let bytes = header.sigma_serialize_bytes().unwrap();
+        let header = Header::sigma_parse_bytes(&bytes).unwrap();
+        assert!(header.check_id().is_ok());
+        assert_eq!(pow.validate(&header), Ok(()));
+        let wrong_d = Header {
+            pow_distance: &header.pow_distance + 1,
+            ..header.clone()
+        };
+        assert_eq!(
+            pow.validate(&wrong_d),
+            Err(AutolykosPowSchemeError::InvalidSolution)
+        );
+        let wrong_pk = Header {
+            miner_pk: Box::new(dlog_group::generator()),
+            ..header.clone()
+        };
+        assert_eq!(
+            pow.validate(&wrong_pk),
+            Err(AutolykosPowSchemeError::InvalidSolution)
+        );
+        let too_big_d = Header {
+            pow_distance: -BigInt::from(group_order()),
+            ..header
+        };
+        assert!(matches!(
+            pow.validate(&too_big_d),
+            Err(AutolykosPowSchemeError::DistanceTooBig { .. })
+        ));
+    }
+}
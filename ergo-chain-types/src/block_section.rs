@@ -0,0 +1,112 @@
+//! Block sections (besides the header): AD proofs and extension (the block transactions section
+//! is in `ergo-lib`, next to the transaction)
+
+use ergotree_ir::chain::block_id::BlockId;
+use ergotree_ir::chain::digest32::blake2b256_hash;
+use ergotree_ir::chain::digest32::Digest32;
+use ergotree_ir::serialization::SigmaSerializationError;
+use thiserror::Error;
+
+pub mod ad_proofs;
+pub mod extension;
+
+pub use ad_proofs::ADProofs;
+pub use extension::Extension;
+
+/// Type id of the header
+pub const HEADER_TYPE_ID: u8 = 101;
+/// Type id of the block transactions section
+pub const BLOCK_TRANSACTIONS_TYPE_ID: u8 = 102;
+/// Type id of the AD proofs section
+pub const AD_PROOFS_TYPE_ID: u8 = 104;
+/// Type id of the extension section
+pub const EXTENSION_TYPE_ID: u8 = 108;
+
+/// Id of the block section, hash of the section type id, header id and the section digest
+/// (the root stored in the header)
+pub fn compute_section_id(type_id: u8, header_id: &BlockId, digest: &Digest32) -> Digest32 {
+    let mut bytes = vec![type_id];
+    bytes.extend_from_slice(&header_id.0 .0[..]);
+    bytes.extend_from_slice(&digest.0[..]);
+    blake2b256_hash(&bytes)
+}
+
+/// Errors on checking the block section against the header
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum BlockSectionError {
+    /// Section belongs to another header
+    #[error("section header id {actual:?} differs from the header id {expected:?}")]
+    HeaderIdMismatch {
+        /// Id of the header
+        expected: BlockId,
+        /// Header id of the section
+        actual: BlockId,
+    },
+    /// Section digest differs from the root in the header (or the digest in JSON)
+    #[error("section digest {computed:?} differs from the expected {expected:?}")]
+    DigestMismatch {
+        /// Root from the header (or the digest from JSON)
+        expected: Digest32,
+        /// Digest computed from the section
+        computed: Digest32,
+    },
+    /// Section serialization failed
+    #[error("serialization error: {0}")]
+    SerializationError(#[from] SigmaSerializationError),
+}
+
+/// Checks that the section header id is the id of the header
+pub fn check_header_id(expected: &BlockId, actual: &BlockId) -> Result<(), BlockSectionError> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(BlockSectionError::HeaderIdMismatch {
+            expected: expected.clone(),
+            actual: actual.clone(),
+        })
+    }
+}
+
+/// Checks that the digest computed from the section is the expected one (the root in the header)
+pub fn check_digest(expected: &Digest32, computed: Digest32) -> Result<(), BlockSectionError> {
+    if expected == &computed {
+        Ok(())
+    } else {
+        Err(BlockSectionError::DigestMismatch {
+            expected: expected.clone(),
+            computed,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use sigma_test_util::HEADER_471746_JSON;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn section_ids_of_mainnet_block() {
+        let json: serde_json::Value = serde_json::from_str(HEADER_471746_JSON).unwrap();
+        let digest =
+            |field: &str| Digest32::try_from(json[field].as_str().unwrap().to_string()).unwrap();
+        let header_id = BlockId(digest("id"));
+        assert_eq!(
+            compute_section_id(
+                BLOCK_TRANSACTIONS_TYPE_ID,
+                &header_id,
+                &digest("transactionsRoot")
+            ),
+            digest("transactionsId")
+        );
+        assert_eq!(
+            compute_section_id(AD_PROOFS_TYPE_ID, &header_id, &digest("adProofsRoot")),
+            digest("adProofsId")
+        );
+        assert_eq!(
+            compute_section_id(EXTENSION_TYPE_ID, &header_id, &digest("extensionHash")),
+            digest("extensionId")
+        );
+    }
+}
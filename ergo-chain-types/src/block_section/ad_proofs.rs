@@ -0,0 +1,107 @@
+//! AD proofs section (proofs of the UTXO set changes in the block)
+
+use ergotree_ir::chain::block_id::BlockId;
+use ergotree_ir::chain::digest32::blake2b256_hash;
+use ergotree_ir::chain::digest32::Digest32;
+use ergotree_ir::chain::header::Header;
+use ergotree_ir::serialization::sigma_byte_reader::SigmaByteRead;
+use ergotree_ir::serialization::sigma_byte_writer::SigmaByteWrite;
+use ergotree_ir::serialization::SigmaParsingError;
+use ergotree_ir::serialization::SigmaSerializable;
+use ergotree_ir::serialization::SigmaSerializeResult;
+
+use super::check_digest;
+use super::check_header_id;
+use super::compute_section_id;
+use super::BlockSectionError;
+use super::AD_PROOFS_TYPE_ID;
+
+/// Proofs of the authenticated UTXO set (AVL+ tree) changes made by the block transactions
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "json",
+    serde(
+        try_from = "crate::json::ADProofsJson",
+        into = "crate::json::ADProofsJson"
+    )
+)]
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ADProofs {
+    /// Id of the header of the block
+    pub header_id: BlockId,
+    /// Serialized batch proof
+    pub proof_bytes: Vec<u8>,
+}
+
+impl ADProofs {
+    /// Digest of the proofs (`ad_proofs_root` in the header)
+    pub fn digest(&self) -> Digest32 {
+        blake2b256_hash(&self.proof_bytes)
+    }
+
+    /// Section id
+    pub fn id(&self) -> Digest32 {
+        compute_section_id(AD_PROOFS_TYPE_ID, &self.header_id, &self.digest())
+    }
+
+    /// Checks that the section belongs to the header and its digest is `ad_proofs_root`
+    pub fn check_header(&self, header: &Header) -> Result<(), BlockSectionError> {
+        check_header_id(&header.id, &self.header_id)?;
+        check_digest(&header.ad_proofs_root, self.digest())
+    }
+}
+
+impl SigmaSerializable for ADProofs {
+    fn sigma_serialize<W: SigmaByteWrite>(&self, w: &mut W) -> SigmaSerializeResult {
+        self.header_id.0.sigma_serialize(w)?;
+        w.put_usize_as_u32_unwrapped(self.proof_bytes.len())?;
+        w.write_all(&self.proof_bytes)?;
+        Ok(())
+    }
+
+    fn sigma_parse<R: SigmaByteRead>(r: &mut R) -> Result<Self, SigmaParsingError> {
+        let header_id = BlockId(Digest32::sigma_parse(r)?);
+        let size = r.get_u32()?;
+        let proof_bytes = r.get_bytes(size as usize)?;
+        Ok(ADProofs {
+            header_id,
+            proof_bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use ergotree_ir::serialization::sigma_serialize_roundtrip;
+
+    #[test]
+    fn roundtrip() {
+        let proofs = ADProofs {
+            header_id: BlockId(Digest32::from([1u8; 32])),
+            proof_bytes: vec![1, 2, 3],
+        };
+        assert_eq!(sigma_serialize_roundtrip(&proofs), proofs);
+        let json = serde_json::to_string(&proofs).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                r#"{{"headerId":"{}","proofBytes":"010203","digest":"{}"}}"#,
+                "01".repeat(32),
+                String::from(proofs.digest())
+            )
+        );
+        assert_eq!(serde_json::from_str::<ADProofs>(&json).unwrap(), proofs);
+        let invalid_digest = json.replace(r#""proofBytes":"010203""#, r#""proofBytes":"0102""#);
+        assert!(serde_json::from_str::<ADProofs>(&invalid_digest).is_err());
+    }
+
+    #[test]
+    fn parse_huge_size() {
+        let mut bytes = vec![1u8; 32];
+        // VLQ encoded u32::MAX
+        bytes.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0x0f, 1, 2, 3]);
+        assert!(ADProofs::sigma_parse_bytes(&bytes).is_err());
+    }
+}
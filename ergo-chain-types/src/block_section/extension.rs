@@ -0,0 +1,188 @@
+//! Extension section (key-value fields: system parameters, interlinks, etc.)
+
+use std::convert::TryFrom;
+
+use ergotree_ir::chain::block_id::BlockId;
+use ergotree_ir::chain::digest32::Digest32;
+use ergotree_ir::chain::header::Header;
+use ergotree_ir::serialization::sigma_byte_reader::SigmaByteRead;
+use ergotree_ir::serialization::sigma_byte_writer::SigmaByteWrite;
+use ergotree_ir::serialization::SigmaParsingError;
+use ergotree_ir::serialization::SigmaSerializable;
+use ergotree_ir::serialization::SigmaSerializationError;
+use ergotree_ir::serialization::SigmaSerializeResult;
+
+use super::check_digest;
+use super::check_header_id;
+use super::compute_section_id;
+use super::BlockSectionError;
+use super::EXTENSION_TYPE_ID;
+use crate::merkle_tree::BatchMerkleProof;
+use crate::merkle_tree::MerkleTree;
+
+/// Extension section of the block, key-value fields
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "json",
+    serde(
+        try_from = "crate::json::ExtensionJson",
+        into = "crate::json::ExtensionJson"
+    )
+)]
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Extension {
+    /// Id of the header of the block
+    pub header_id: BlockId,
+    /// Fields (key, value)
+    pub fields: Vec<([u8; Extension::FIELD_KEY_SIZE], Vec<u8>)>,
+}
+
+impl Extension {
+    /// Size of the field key
+    pub const FIELD_KEY_SIZE: usize = 2;
+    /// Maximum size of the field value
+    pub const FIELD_VALUE_MAX_SIZE: usize = 64;
+
+    /// Value of the field with the given key
+    pub fn field(&self, key: [u8; Extension::FIELD_KEY_SIZE]) -> Option<&Vec<u8>> {
+        self.fields.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Merkle tree root of the fields (`extension_root` in the header), the leaf of the field is
+    /// `key length ++ key ++ value`
+    pub fn digest(&self) -> Digest32 {
+        self.merkle_tree().root_hash()
+    }
+
+    /// Merkle tree of the fields
+    pub fn merkle_tree(&self) -> MerkleTree {
+        let leaves: Vec<Vec<u8>> = self
+            .fields
+            .iter()
+            .map(|(key, value)| field_leaf(key, value))
+            .collect();
+        MerkleTree::new(&leaves)
+    }
+
+    /// Compact proof of inclusion of the fields with the given keys, `None` if any of them is
+    /// absent
+    pub fn batch_proof_for(
+        &self,
+        keys: &[[u8; Extension::FIELD_KEY_SIZE]],
+    ) -> Option<BatchMerkleProof> {
+        let indices = keys
+            .iter()
+            .map(|key| self.fields.iter().position(|(k, _)| k == key))
+            .collect::<Option<Vec<usize>>>()?;
+        self.merkle_tree().proof_by_indices(&indices)
+    }
+
+    /// Section id
+    pub fn id(&self) -> Digest32 {
+        compute_section_id(EXTENSION_TYPE_ID, &self.header_id, &self.digest())
+    }
+
+    /// Checks that the section belongs to the header and its digest is `extension_root`
+    pub fn check_header(&self, header: &Header) -> Result<(), BlockSectionError> {
+        check_header_id(&header.id, &self.header_id)?;
+        check_digest(&header.extension_root, self.digest())
+    }
+}
+
+/// Merkle tree leaf data of the field
+pub fn field_leaf(key: &[u8], value: &[u8]) -> Vec<u8> {
+    let mut leaf = vec![key.len() as u8];
+    leaf.extend_from_slice(key);
+    leaf.extend_from_slice(value);
+    leaf
+}
+
+impl SigmaSerializable for Extension {
+    fn sigma_serialize<W: SigmaByteWrite>(&self, w: &mut W) -> SigmaSerializeResult {
+        self.header_id.0.sigma_serialize(w)?;
+        let fields_count = u16::try_from(self.fields.len()).map_err(|_| {
+            SigmaSerializationError::NotSupported("more than 65535 extension fields")
+        })?;
+        w.put_u16(fields_count)?;
+        for (key, value) in &self.fields {
+            w.write_all(key)?;
+            let value_size = u8::try_from(value.len()).map_err(|_| {
+                SigmaSerializationError::NotSupported("extension field value is too long")
+            })?;
+            w.put_u8(value_size)?;
+            w.write_all(value)?;
+        }
+        Ok(())
+    }
+
+    fn sigma_parse<R: SigmaByteRead>(r: &mut R) -> Result<Self, SigmaParsingError> {
+        let header_id = BlockId(Digest32::sigma_parse(r)?);
+        let fields_count = r.get_u16()?;
+        let mut fields = Vec::with_capacity(fields_count as usize);
+        for _ in 0..fields_count {
+            let mut key = [0u8; Extension::FIELD_KEY_SIZE];
+            r.read_exact(&mut key)?;
+            let value_size = r.get_u8()?;
+            let mut value = vec![0u8; value_size as usize];
+            r.read_exact(&mut value)?;
+            fields.push((key, value));
+        }
+        Ok(Extension { header_id, fields })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::merkle_tree::leaf_hash;
+    use ergotree_ir::serialization::sigma_serialize_roundtrip;
+
+    #[test]
+    fn roundtrip() {
+        let extension = Extension {
+            header_id: BlockId(Digest32::from([1u8; 32])),
+            fields: vec![([0, 1], vec![0, 0, 0, 5]), ([1, 0], vec![1; 33])],
+        };
+        assert_eq!(sigma_serialize_roundtrip(&extension), extension);
+        assert_eq!(extension.field([0, 1]), Some(&vec![0, 0, 0, 5]));
+        assert_eq!(extension.field([0, 2]), None);
+        let json = serde_json::to_string(&extension).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                r#"{{"headerId":"{}","digest":"{}","fields":[["0001","00000005"],["0100","{}"]]}}"#,
+                "01".repeat(32),
+                String::from(extension.digest()),
+                "01".repeat(33)
+            )
+        );
+        assert_eq!(serde_json::from_str::<Extension>(&json).unwrap(), extension);
+        let invalid_digest = json.replace(r#"["0001","00000005"]"#, r#"["0001","00000006"]"#);
+        assert!(serde_json::from_str::<Extension>(&invalid_digest).is_err());
+    }
+
+    #[test]
+    fn digest() {
+        let extension = Extension {
+            header_id: BlockId(Digest32::zero()),
+            fields: vec![([0, 1], vec![5])],
+        };
+        assert_eq!(
+            extension.digest(),
+            crate::merkle_tree::internal_node_hash(&leaf_hash(&[2, 0, 1, 5]), None)
+        );
+    }
+
+    #[test]
+    fn batch_proof_for() {
+        let extension = Extension {
+            header_id: BlockId(Digest32::zero()),
+            fields: vec![([0, 1], vec![5]), ([1, 0], vec![6]), ([1, 1], vec![7])],
+        };
+        let proof = extension.batch_proof_for(&[[1, 1], [0, 1]]).unwrap();
+        assert!(proof.valid(&extension.digest()));
+        assert_eq!(proof.indices[0].1, leaf_hash(&field_leaf(&[0, 1], &[5])));
+        assert_eq!(extension.batch_proof_for(&[[2, 0]]), None);
+    }
+}
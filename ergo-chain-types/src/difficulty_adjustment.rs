@@ -0,0 +1,594 @@
+//! Difficulty adjustment (retargeting) for the next block
+//! see <https://github.com/ergoplatform/ergo/blob/master/src/main/scala/org/ergoplatform/mining/difficulty/DifficultyAdjustment.scala>
+
+use ergotree_ir::chain::block_id::BlockId;
+use ergotree_ir::chain::header::Header;
+use num_bigint::BigInt;
+use thiserror::Error;
+
+use crate::nbits::decode_compact_bits;
+use crate::nbits::encode_compact_bits;
+
+/// Precision of the linear least squares coefficients
+const PRECISION: u64 = 1_000_000_000;
+
+/// Errors on difficulty recalculation and `n_bits` validation
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum DifficultyAdjustmentError {
+    /// Not enough headers for the recalculation
+    #[error("not enough headers for the difficulty recalculation")]
+    NotEnoughHeaders,
+    /// Header at the given height is required for the recalculation
+    #[error("header at height {0} is required for the difficulty recalculation")]
+    MissingHeader(u32),
+    /// Parent of the header is not found
+    #[error("parent header {0:?} is not found")]
+    MissingParent(BlockId),
+    /// Headers used for the recalculation are not one epoch apart
+    #[error("headers at heights {start} and {end} are not one epoch apart")]
+    InvalidEpochInterval {
+        /// Height of the epoch start header
+        start: u32,
+        /// Height of the epoch end header
+        end: u32,
+    },
+    /// Headers used for the recalculation have the same timestamp
+    #[error("headers at heights {start} and {end} have the same timestamp")]
+    SameTimestamp {
+        /// Height of the epoch start header
+        start: u32,
+        /// Height of the epoch end header
+        end: u32,
+    },
+    /// Header `n_bits` differ from the required difficulty
+    #[error("nBits {actual} differ from the required {expected}")]
+    NBitsMismatch {
+        /// Encoded required difficulty
+        expected: u64,
+        /// Header `n_bits`
+        actual: u64,
+    },
+}
+
+/// Difficulty recalculation parameters and algorithms: linear least squares regression over the
+/// last epochs (before EIP-37) and the average of the regression and the Bitcoin-like
+/// recalculation limited to 50% change per epoch (EIP-37)
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct DifficultyAdjustment {
+    /// Number of blocks in the epoch, the difficulty is recalculated at the epoch boundary
+    pub epoch_length: u32,
+    /// Number of last epochs used in the regression
+    pub use_last_epochs: u32,
+    /// Desired interval between blocks in ms
+    pub desired_interval_ms: u64,
+    /// Difficulty of the genesis block
+    pub initial_difficulty: BigInt,
+    /// Height at which Autolykos v2 (block version 2) is activated
+    pub version2_activation_height: u32,
+    /// Difficulty set at the Autolykos v2 activation
+    pub version2_activation_difficulty: BigInt,
+    /// Height at which EIP-37 is activated (`None` if not activated)
+    pub eip37_activation_height: Option<u32>,
+    /// Epoch length after the EIP-37 activation
+    pub eip37_epoch_length: u32,
+}
+
+impl Default for DifficultyAdjustment {
+    fn default() -> Self {
+        DifficultyAdjustment::mainnet()
+    }
+}
+
+impl DifficultyAdjustment {
+    /// Mainnet parameters
+    pub fn mainnet() -> Self {
+        DifficultyAdjustment {
+            epoch_length: 1024,
+            use_last_epochs: 8,
+            desired_interval_ms: 120_000,
+            initial_difficulty: BigInt::from(0x011765000000u64),
+            version2_activation_height: 417792,
+            version2_activation_difficulty: BigInt::from(0x6f98d5000000u64),
+            eip37_activation_height: Some(844673),
+            eip37_epoch_length: 128,
+        }
+    }
+
+    /// Heights of the previous headers required for the difficulty recalculation of the block at
+    /// the given height
+    pub fn previous_heights_required_for_recalculation(
+        &self,
+        height: u32,
+        epoch_length: u32,
+    ) -> Vec<u32> {
+        let parent_height = height.saturating_sub(1);
+        if parent_height % epoch_length == 0
+            && (epoch_length > 1 || height > epoch_length.saturating_mul(self.use_last_epochs))
+        {
+            (0..=self.use_last_epochs)
+                .rev()
+                .filter_map(|i| parent_height.checked_sub(i.checked_mul(epoch_length)?))
+                .collect()
+        } else {
+            vec![parent_height]
+        }
+    }
+
+    /// Required difficulty of the header, `chain` should contain its ancestors needed for the
+    /// recalculation (see [`Self::heights_required_after`])
+    pub fn required_difficulty(
+        &self,
+        header: &Header,
+        chain: &[Header],
+    ) -> Result<BigInt, DifficultyAdjustmentError> {
+        if header.height == Header::GENESIS_HEIGHT {
+            Ok(self.initial_difficulty.clone())
+        } else {
+            let parent = chain
+                .iter()
+                .find(|h| h.id == header.parent_id)
+                .ok_or_else(|| {
+                    DifficultyAdjustmentError::MissingParent(header.parent_id.clone())
+                })?;
+            self.required_difficulty_after(parent, chain)
+        }
+    }
+
+    /// Checks that `n_bits` of the header encode the required difficulty
+    pub fn check_n_bits(
+        &self,
+        header: &Header,
+        chain: &[Header],
+    ) -> Result<(), DifficultyAdjustmentError> {
+        let expected = encode_compact_bits(&self.required_difficulty(header, chain)?);
+        if expected == header.n_bits {
+            Ok(())
+        } else {
+            Err(DifficultyAdjustmentError::NBitsMismatch {
+                expected,
+                actual: header.n_bits,
+            })
+        }
+    }
+
+    /// Heights of the headers [`Self::required_difficulty_after`] needs for the block following
+    /// the parent at the given height (the parent itself included)
+    pub fn heights_required_after(&self, parent_height: u32) -> Vec<u32> {
+        match self.eip37_activation_height {
+            Some(activation_height) if parent_height + 1 >= activation_height => {
+                if parent_height % self.eip37_epoch_length == 0 {
+                    self.previous_heights_required_for_recalculation(
+                        parent_height + 1,
+                        self.eip37_epoch_length,
+                    )
+                } else {
+                    vec![parent_height]
+                }
+            }
+            Some(_) | None => {
+                if parent_height == self.version2_activation_height
+                    || parent_height + 1 == self.version2_activation_height
+                {
+                    vec![parent_height]
+                } else {
+                    self.previous_heights_required_for_recalculation(
+                        parent_height + 1,
+                        self.epoch_length,
+                    )
+                }
+            }
+        }
+    }
+
+    /// Required difficulty of the block following `parent`, `chain` should contain the headers
+    /// needed for the recalculation (see [`Self::heights_required_after`])
+    pub fn required_difficulty_after(
+        &self,
+        parent: &Header,
+        chain: &[Header],
+    ) -> Result<BigInt, DifficultyAdjustmentError> {
+        let parent_height = parent.height;
+        match self.eip37_activation_height {
+            Some(activation_height) if parent_height + 1 >= activation_height => {
+                let epoch_length = self.eip37_epoch_length;
+                if parent_height % epoch_length == 0 {
+                    let heights = self.heights_required_after(parent_height);
+                    let headers = headers_at(&heights, parent, chain)?;
+                    self.eip37_calculate(&headers, epoch_length)
+                } else {
+                    Ok(decode_compact_bits(parent.n_bits))
+                }
+            }
+            Some(_) | None => {
+                if parent_height == self.version2_activation_height
+                    || parent_height + 1 == self.version2_activation_height
+                {
+                    // PoW has changed, the difficulty is set explicitly
+                    Ok(self.version2_activation_difficulty.clone())
+                } else {
+                    let heights = self.heights_required_after(parent_height);
+                    let headers = headers_at(&heights, parent, chain)?;
+                    self.calculate(&headers, self.epoch_length)
+                }
+            }
+        }
+    }
+
+    /// Difficulty predicted by the linear least squares regression over the epochs, headers should
+    /// be one epoch apart
+    pub fn calculate(
+        &self,
+        previous_headers: &[Header],
+        epoch_length: u32,
+    ) -> Result<BigInt, DifficultyAdjustmentError> {
+        let (first, last) = match (previous_headers.first(), previous_headers.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err(DifficultyAdjustmentError::NotEnoughHeaders),
+        };
+        let uncompressed_diff = if previous_headers.len() == 1 || first.timestamp >= last.timestamp
+        {
+            decode_compact_bits(first.n_bits)
+        } else {
+            let data = previous_headers
+                .windows(2)
+                .map(|pair| {
+                    let (start, end) = (&pair[0], &pair[1]);
+                    if end.height.checked_sub(start.height) != Some(epoch_length) {
+                        return Err(DifficultyAdjustmentError::InvalidEpochInterval {
+                            start: start.height,
+                            end: end.height,
+                        });
+                    }
+                    let diff = self.bitcoin_calculate(start, end, epoch_length)?;
+                    Ok((end.height, diff))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let diff = interpolate(&data, epoch_length);
+            if diff >= BigInt::from(1) {
+                diff
+            } else {
+                self.initial_difficulty.clone()
+            }
+        };
+        Ok(normalize(&uncompressed_diff))
+    }
+
+    /// Difficulty recalculation after the EIP-37 activation, the average of the regression and
+    /// Bitcoin-like recalculation, limited to 50% change relative to the last difficulty
+    pub fn eip37_calculate(
+        &self,
+        previous_headers: &[Header],
+        epoch_length: u32,
+    ) -> Result<BigInt, DifficultyAdjustmentError> {
+        let (start, end) = match previous_headers {
+            [.., start, end] => (start, end),
+            _ => return Err(DifficultyAdjustmentError::NotEnoughHeaders),
+        };
+        let last_diff = decode_compact_bits(end.n_bits);
+        let predictive_diff = self.calculate(previous_headers, epoch_length)?;
+        let limited_predictive_diff = limit_change(predictive_diff, &last_diff);
+        let classic_diff = self.bitcoin_calculate(start, end, epoch_length)?;
+        let avg = (classic_diff + limited_predictive_diff) / 2;
+        Ok(normalize(&limit_change(avg, &last_diff)))
+    }
+
+    /// Bitcoin-like recalculation, the difficulty of the `end` header scaled by the ratio of the
+    /// desired and the actual epoch duration
+    fn bitcoin_calculate(
+        &self,
+        start: &Header,
+        end: &Header,
+        epoch_length: u32,
+    ) -> Result<BigInt, DifficultyAdjustmentError> {
+        let duration = BigInt::from(end.timestamp) - BigInt::from(start.timestamp);
+        if duration == BigInt::from(0) {
+            return Err(DifficultyAdjustmentError::SameTimestamp {
+                start: start.height,
+                end: end.height,
+            });
+        }
+        Ok(decode_compact_bits(end.n_bits)
+            * BigInt::from(self.desired_interval_ms)
+            * BigInt::from(epoch_length)
+            / duration)
+    }
+}
+
+/// Headers at the given heights from the chain (genesis has height 1, so 0 is skipped)
+fn headers_at(
+    heights: &[u32],
+    parent: &Header,
+    chain: &[Header],
+) -> Result<Vec<Header>, DifficultyAdjustmentError> {
+    heights
+        .iter()
+        .filter(|height| **height >= Header::GENESIS_HEIGHT)
+        .map(|height| {
+            if *height == parent.height {
+                Ok(parent.clone())
+            } else {
+                chain
+                    .iter()
+                    .find(|h| h.height == *height)
+                    .cloned()
+                    .ok_or(DifficultyAdjustmentError::MissingHeader(*height))
+            }
+        })
+        .collect()
+}
+
+/// Linear least squares regression over `(height, difficulty)` points, predicts the difficulty
+/// one epoch after the last point
+fn interpolate(data: &[(u32, BigInt)], epoch_length: u32) -> BigInt {
+    match data {
+        [] => BigInt::from(0),
+        [(_, diff)] => diff.clone(),
+        _ => {
+            let size = BigInt::from(data.len());
+            let precision = BigInt::from(PRECISION);
+            let x_sum: BigInt = data.iter().map(|(x, _)| BigInt::from(*x)).sum();
+            let y_sum: BigInt = data.iter().map(|(_, y)| y).sum();
+            let xy_sum: BigInt = data.iter().map(|(x, y)| BigInt::from(*x) * y).sum();
+            let x2_sum: BigInt = data
+                .iter()
+                .map(|(x, _)| BigInt::from(*x) * BigInt::from(*x))
+                .sum();
+            let k = (&xy_sum * &size - &x_sum * &y_sum) * &precision
+                / (&x2_sum * &size - &x_sum * &x_sum);
+            let b = (&y_sum * &precision - &k * &x_sum) / &size / &precision;
+            let point = data.iter().map(|(x, _)| *x).max().unwrap_or(0) + epoch_length;
+            b + k * BigInt::from(point) / precision
+        }
+    }
+}
+
+fn limit_change(diff: BigInt, last_diff: &BigInt) -> BigInt {
+    if &diff > last_diff {
+        diff.min(last_diff * 3 / 2)
+    } else {
+        diff.max(last_diff / 2)
+    }
+}
+
+/// Drops the precision that does not fit in the compact representation
+fn normalize(diff: &BigInt) -> BigInt {
+    decode_compact_bits(encode_compact_bits(diff))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use ergotree_ir::chain::digest32::ADDigest;
+    use ergotree_ir::chain::digest32::Digest32;
+    use ergotree_ir::chain::votes::Votes;
+    use ergotree_ir::sigma_protocol::dlog_group;
+
+    fn header(height: u32, timestamp: u64, difficulty: &BigInt) -> Header {
+        Header {
+            version: 2,
+            id: BlockId(Digest32::zero()),
+            parent_id: BlockId(Digest32::zero()),
+            ad_proofs_root: Digest32::zero(),
+            state_root: ADDigest::zero(),
+            transaction_root: Digest32::zero(),
+            timestamp,
+            n_bits: encode_compact_bits(difficulty),
+            height,
+            extension_root: Digest32::zero(),
+            miner_pk: Box::new(dlog_group::generator()),
+            pow_onetime_pk: Box::new(dlog_group::generator()),
+            nonce: vec![0; 8],
+            pow_distance: BigInt::from(0),
+            votes: Votes([0, 0, 0]),
+        }
+    }
+
+    /// Headers at the epoch boundaries, `interval(epoch)` is the block interval in ms
+    fn epochs(
+        epoch_length: u32,
+        count: u32,
+        difficulty: &BigInt,
+        interval: impl Fn(u32) -> u64,
+    ) -> Vec<Header> {
+        let mut timestamp = 1_600_000_000_000;
+        (1..=count)
+            .map(|epoch| {
+                timestamp += interval(epoch) * epoch_length as u64;
+                header(epoch * epoch_length, timestamp, difficulty)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn required_heights() {
+        let da = DifficultyAdjustment::mainnet();
+        assert_eq!(
+            da.previous_heights_required_for_recalculation(1025, 1024),
+            vec![0, 1024]
+        );
+        assert_eq!(
+            da.previous_heights_required_for_recalculation(9217, 1024),
+            (1..=9).map(|i| i * 1024).collect::<Vec<u32>>()
+        );
+        assert_eq!(
+            da.previous_heights_required_for_recalculation(9218, 1024),
+            vec![9217]
+        );
+        assert_eq!(
+            da.previous_heights_required_for_recalculation(1, 1024),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn difficulty_needs_exactly_required_heights() {
+        let da = DifficultyAdjustment::mainnet();
+        let diff = BigInt::from(626412390187008u64);
+        for parent_height in [
+            1,
+            1024,
+            9216,
+            9217,
+            da.version2_activation_height - 1,
+            da.version2_activation_height,
+            da.version2_activation_height + 1024 * 8,
+            844672,
+            844800,
+            844801,
+        ]
+        .iter()
+        .copied()
+        {
+            let heights = da.heights_required_after(parent_height);
+            let chain: Vec<Header> = heights
+                .iter()
+                .map(|h| header(*h, *h as u64 * 120_000, &diff))
+                .collect();
+            let parent = header(parent_height, parent_height as u64 * 120_000, &diff);
+            assert!(
+                da.required_difficulty_after(&parent, &chain).is_ok(),
+                "parent height {}",
+                parent_height
+            );
+            // heights before the genesis are skipped
+            for missing in heights
+                .iter()
+                .filter(|h| **h != parent_height && **h >= Header::GENESIS_HEIGHT)
+            {
+                let partial: Vec<Header> = chain
+                    .iter()
+                    .filter(|h| h.height != *missing)
+                    .cloned()
+                    .collect();
+                assert_eq!(
+                    da.required_difficulty_after(&parent, &partial),
+                    Err(DifficultyAdjustmentError::MissingHeader(*missing))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn interpolate_linear_data() {
+        let data: Vec<(u32, BigInt)> = (1..=8u32)
+            .map(|i| (i * 1024, BigInt::from(i as u64 * 1_000_000_000_000)))
+            .collect();
+        assert_eq!(interpolate(&data, 1024), BigInt::from(9_000_000_000_000u64));
+        let data: Vec<(u32, BigInt)> = (1..=8u32)
+            .map(|i| (i * 1024, BigInt::from(1_000_000_000_000u64)))
+            .collect();
+        assert_eq!(interpolate(&data, 1024), BigInt::from(1_000_000_000_000u64));
+    }
+
+    #[test]
+    fn calculate() {
+        let da = DifficultyAdjustment::mainnet();
+        let diff = BigInt::from(626412390187008u64);
+        // blocks on time
+        let headers = epochs(1024, 9, &diff, |_| 120_000);
+        assert_eq!(da.calculate(&headers, 1024).unwrap(), diff);
+        // blocks twice as fast
+        let headers = epochs(1024, 9, &diff, |_| 60_000);
+        assert_eq!(
+            da.calculate(&headers, 1024).unwrap(),
+            normalize(&(&diff * 2))
+        );
+        // single header
+        assert_eq!(da.calculate(&headers[..1], 1024).unwrap(), diff);
+        assert_eq!(
+            da.calculate(&[], 1024),
+            Err(DifficultyAdjustmentError::NotEnoughHeaders)
+        );
+        assert_eq!(
+            da.calculate(&[headers[0].clone(), headers[2].clone()], 1024),
+            Err(DifficultyAdjustmentError::InvalidEpochInterval {
+                start: 1024,
+                end: 3072
+            })
+        );
+    }
+
+    #[test]
+    fn eip37_calculate_limits_change() {
+        let da = DifficultyAdjustment::mainnet();
+        let diff = BigInt::from(626412390187008u64);
+        let headers = epochs(128, 9, &diff, |_| 120_000);
+        assert_eq!(da.eip37_calculate(&headers, 128).unwrap(), diff);
+        // blocks 10 times faster, at most +50%
+        let headers = epochs(128, 9, &diff, |_| 12_000);
+        assert_eq!(
+            da.eip37_calculate(&headers, 128).unwrap(),
+            normalize(&(&diff * 3 / 2))
+        );
+        // blocks 10 times slower, at most -50%
+        let headers = epochs(128, 9, &diff, |_| 1_200_000);
+        assert_eq!(
+            da.eip37_calculate(&headers, 128).unwrap(),
+            normalize(&(&diff / 2))
+        );
+        assert_eq!(
+            da.eip37_calculate(&headers[..1], 128),
+            Err(DifficultyAdjustmentError::NotEnoughHeaders)
+        );
+    }
+
+    #[test]
+    fn check_n_bits() {
+        let da = DifficultyAdjustment::mainnet();
+        let diff = BigInt::from(626412390187008u64);
+        let genesis = header(1, 0, &da.initial_difficulty);
+        assert_eq!(da.check_n_bits(&genesis, &[]), Ok(()));
+
+        // within the epoch the difficulty is the same as the parent's
+        let parent = Header {
+            id: BlockId(Digest32::from([1u8; 32])),
+            ..header(471745, 0, &diff)
+        };
+        let child = Header {
+            parent_id: parent.id.clone(),
+            ..header(471746, 0, &diff)
+        };
+        assert_eq!(
+            da.check_n_bits(&child, std::slice::from_ref(&parent)),
+            Ok(())
+        );
+        let invalid = Header {
+            n_bits: child.n_bits + 1,
+            ..child.clone()
+        };
+        assert_eq!(
+            da.check_n_bits(&invalid, std::slice::from_ref(&parent)),
+            Err(DifficultyAdjustmentError::NBitsMismatch {
+                expected: child.n_bits,
+                actual: child.n_bits + 1
+            })
+        );
+        assert_eq!(
+            da.check_n_bits(&child, &[]),
+            Err(DifficultyAdjustmentError::MissingParent(parent.id.clone()))
+        );
+
+        // Autolykos v2 activation
+        let parent = Header {
+            height: da.version2_activation_height - 1,
+            ..parent
+        };
+        assert_eq!(
+            da.required_difficulty_after(&parent, &[]).unwrap(),
+            da.version2_activation_difficulty
+        );
+
+        // epoch boundary, recalculation over the last epochs
+        let chain = epochs(1024, 9, &diff, |_| 60_000);
+        let parent = chain.last().unwrap();
+        assert_eq!(
+            da.required_difficulty_after(parent, &chain).unwrap(),
+            normalize(&(&diff * 2))
+        );
+        assert_eq!(
+            da.required_difficulty_after(parent, &chain[1..]),
+            Err(DifficultyAdjustmentError::MissingHeader(1024))
+        );
+    }
+}
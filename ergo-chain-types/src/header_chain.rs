@@ -0,0 +1,688 @@
+//! Chain of the block headers (SPV light client): validation of the incoming headers, the best
+//! chain selection by the cumulative difficulty and the forks
+
+use std::collections::HashMap;
+
+use ergotree_ir::chain::block_id::BlockId;
+use ergotree_ir::chain::digest32::Digest32;
+use ergotree_ir::chain::header::Header;
+use ergotree_ir::chain::header::HeaderIdError;
+use ergotree_ir::chain::preheader::PreHeader;
+use num_bigint::BigInt;
+use thiserror::Error;
+
+use crate::autolykos_pow_scheme::AutolykosPowScheme;
+use crate::autolykos_pow_scheme::AutolykosPowSchemeError;
+use crate::difficulty_adjustment::DifficultyAdjustment;
+use crate::difficulty_adjustment::DifficultyAdjustmentError;
+use crate::nbits::decode_compact_bits;
+use crate::nbits::encode_compact_bits;
+
+/// Errors on appending the header to the chain
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum HeaderChainError {
+    /// Header is already in the chain
+    #[error("header {0:?} is already in the chain")]
+    AlreadyKnown(BlockId),
+    /// Header id is not the hash of the header
+    #[error("invalid header id: {0}")]
+    InvalidId(#[from] HeaderIdError),
+    /// Genesis header has a non-zero parent id or the chain is not empty
+    #[error("unexpected genesis header {0:?}")]
+    InvalidGenesis(BlockId),
+    /// Parent of the header is not in the chain
+    #[error("parent {0:?} of the header is not in the chain")]
+    UnknownParent(BlockId),
+    /// Height is not the parent height + 1
+    #[error("header height {actual} differs from the expected {expected}")]
+    InvalidHeight {
+        /// Parent height + 1
+        expected: u32,
+        /// Header height
+        actual: u32,
+    },
+    /// Block version is lower than the parent one or is not supported
+    #[error("block version {actual} is invalid (parent version {parent})")]
+    InvalidVersion {
+        /// Parent block version
+        parent: u8,
+        /// Header block version
+        actual: u8,
+    },
+    /// Timestamp is not greater than the median timestamp of the last headers
+    #[error("timestamp {timestamp} is not greater than the median {median} of the last headers")]
+    TimestampTooOld {
+        /// Header timestamp
+        timestamp: u64,
+        /// Median timestamp of the last headers
+        median: u64,
+    },
+    /// Timestamp is too far in the future
+    #[error("timestamp {timestamp} is too far in the future (now {now})")]
+    TimestampInFuture {
+        /// Header timestamp
+        timestamp: u64,
+        /// Current time
+        now: u64,
+    },
+    /// Difficulty (nBits) check failed
+    #[error("difficulty error: {0}")]
+    DifficultyError(#[from] DifficultyAdjustmentError),
+    /// PoW check failed
+    #[error("PoW error: {0}")]
+    PowError(#[from] AutolykosPowSchemeError),
+    /// Not enough headers in the best chain
+    #[error("{required} headers are required, only {available} are in the best chain")]
+    NotEnoughHeaders {
+        /// Required number of headers
+        required: usize,
+        /// Number of headers in the best chain
+        available: usize,
+    },
+}
+
+/// Header chain validation settings
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct HeaderChainSettings {
+    /// Difficulty recalculation
+    pub difficulty_adjustment: DifficultyAdjustment,
+    /// PoW scheme
+    pub pow_scheme: AutolykosPowScheme,
+    /// Number of the last headers the median timestamp is taken from
+    pub median_time_window: usize,
+    /// Maximum distance of the header timestamp into the future (ms)
+    pub max_time_drift: u64,
+    /// Maximum supported block version
+    pub max_block_version: u8,
+}
+
+impl HeaderChainSettings {
+    /// Mainnet settings
+    pub fn mainnet() -> Self {
+        HeaderChainSettings {
+            difficulty_adjustment: DifficultyAdjustment::mainnet(),
+            pow_scheme: AutolykosPowScheme::default(),
+            median_time_window: 11,
+            // 10 block intervals
+            max_time_drift: 10 * 2 * 60 * 1000,
+            max_block_version: 3,
+        }
+    }
+}
+
+impl Default for HeaderChainSettings {
+    fn default() -> Self {
+        HeaderChainSettings::mainnet()
+    }
+}
+
+/// Result of appending the header
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum AppendResult {
+    /// Header extends the best chain
+    Extended,
+    /// Header made its branch the best chain, the best chain headers above the common ancestor
+    /// are replaced
+    Reorganized {
+        /// Id of the last header common for the old and the new best chain
+        common_ancestor: BlockId,
+    },
+    /// Header is stored on a branch with a lower cumulative difficulty
+    Fork,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+struct ChainEntry {
+    header: Header,
+    /// Cumulative difficulty of the chain ending with the header
+    score: BigInt,
+}
+
+/// Headers accepted in order (parent before child) with all the branches kept, the best chain is
+/// the one with the highest cumulative difficulty
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct HeaderChain {
+    settings: HeaderChainSettings,
+    entries: HashMap<BlockId, ChainEntry>,
+    best: Option<BlockId>,
+}
+
+impl HeaderChain {
+    /// Empty chain, the first header should be the genesis
+    pub fn new(settings: HeaderChainSettings) -> Self {
+        HeaderChain {
+            settings,
+            entries: HashMap::new(),
+            best: None,
+        }
+    }
+
+    /// Chain starting from the trusted headers (e.g. the suffix of the NiPoPoW proof) which are
+    /// only checked to be linked, there should be enough of them for the difficulty recalculation
+    /// of the following headers
+    pub fn from_trusted_headers(
+        settings: HeaderChainSettings,
+        headers: Vec<Header>,
+    ) -> Result<Self, HeaderChainError> {
+        let mut chain = HeaderChain::new(settings);
+        for header in headers {
+            header.check_id()?;
+            let parent_score = match chain.best_header() {
+                Some(parent) => {
+                    if header.parent_id != parent.id {
+                        return Err(HeaderChainError::UnknownParent(header.parent_id));
+                    }
+                    check_height(&header, parent)?;
+                    chain
+                        .score(&parent.id)
+                        .cloned()
+                        .unwrap_or_else(|| BigInt::from(0))
+                }
+                None => BigInt::from(0),
+            };
+            chain.insert(header, &parent_score);
+        }
+        Ok(chain)
+    }
+
+    /// Validation settings
+    pub fn settings(&self) -> &HeaderChainSettings {
+        &self.settings
+    }
+
+    /// Header with the given id (on any branch)
+    pub fn header(&self, id: &BlockId) -> Option<&Header> {
+        self.entries.get(id).map(|e| &e.header)
+    }
+
+    /// Cumulative difficulty of the chain ending with the header
+    pub fn score(&self, id: &BlockId) -> Option<&BigInt> {
+        self.entries.get(id).map(|e| &e.score)
+    }
+
+    /// Last header of the best chain
+    pub fn best_header(&self) -> Option<&Header> {
+        self.best.as_ref().and_then(|id| self.header(id))
+    }
+
+    /// Height of the best chain (0 for the empty chain)
+    pub fn best_height(&self) -> u32 {
+        self.best_header().map(|h| h.height).unwrap_or(0)
+    }
+
+    /// Headers from the given one back to the first known ancestor
+    pub fn ancestors<'a>(&'a self, id: &BlockId) -> impl Iterator<Item = &'a Header> + 'a {
+        let mut next = self.header(id);
+        std::iter::from_fn(move || {
+            let current = next?;
+            next = self.header(&current.parent_id);
+            Some(current)
+        })
+    }
+
+    /// Last `count` headers of the best chain (or fewer), the newest first
+    pub fn last_headers(&self, count: usize) -> Vec<&Header> {
+        match &self.best {
+            Some(best) => self.ancestors(best).take(count).collect(),
+            None => vec![],
+        }
+    }
+
+    /// Checks the header against its parent and ancestors: linkage, height, version, timestamp
+    /// (greater than the median of the last headers and not too far ahead of `now`), difficulty
+    /// and PoW
+    pub fn validate(&self, header: &Header, now: u64) -> Result<(), HeaderChainError> {
+        if self.entries.contains_key(&header.id) {
+            return Err(HeaderChainError::AlreadyKnown(header.id.clone()));
+        }
+        header.check_id()?;
+        if header.timestamp > now.saturating_add(self.settings.max_time_drift) {
+            return Err(HeaderChainError::TimestampInFuture {
+                timestamp: header.timestamp,
+                now,
+            });
+        }
+        if header.height == Header::GENESIS_HEIGHT {
+            if !self.entries.is_empty() || header.parent_id != BlockId(Digest32::zero()) {
+                return Err(HeaderChainError::InvalidGenesis(header.id.clone()));
+            }
+            self.check_version(header, Header::INITIAL_VERSION)?;
+        } else {
+            let parent = self
+                .header(&header.parent_id)
+                .ok_or_else(|| HeaderChainError::UnknownParent(header.parent_id.clone()))?;
+            check_height(header, parent)?;
+            self.check_version(header, parent.version)?;
+            self.check_timestamp(header)?;
+        }
+        let ancestors = self.difficulty_ancestors(header);
+        self.settings
+            .difficulty_adjustment
+            .check_n_bits(header, &ancestors)?;
+        self.settings.pow_scheme.validate(header)?;
+        Ok(())
+    }
+
+    /// Validates and stores the header, switches the best chain if the header's branch has a
+    /// higher cumulative difficulty
+    pub fn append(&mut self, header: Header, now: u64) -> Result<AppendResult, HeaderChainError> {
+        self.validate(&header, now)?;
+        let parent_score = self
+            .entries
+            .get(&header.parent_id)
+            .map(|e| e.score.clone())
+            .unwrap_or_else(|| BigInt::from(0));
+        let old_best = self.best.clone();
+        let id = header.id.clone();
+        let parent_id = header.parent_id.clone();
+        self.insert(header, &parent_score);
+        Ok(match old_best {
+            None => AppendResult::Extended,
+            Some(old_best) if self.best.as_ref() == Some(&id) => {
+                if parent_id == old_best {
+                    AppendResult::Extended
+                } else {
+                    AppendResult::Reorganized {
+                        common_ancestor: self.common_ancestor(&old_best, &id),
+                    }
+                }
+            }
+            Some(_) => AppendResult::Fork,
+        })
+    }
+
+    /// Predicted pre-header of the next block of the best chain (the miner key and the votes are
+    /// taken from the best header, the timestamp is at least the best header one + 1)
+    pub fn next_pre_header(&self, timestamp: u64) -> Result<PreHeader, HeaderChainError> {
+        let best = self
+            .best_header()
+            .ok_or(HeaderChainError::NotEnoughHeaders {
+                required: 1,
+                available: 0,
+            })?;
+        let ancestors = self.ancestors_at(
+            best,
+            &self
+                .settings
+                .difficulty_adjustment
+                .heights_required_after(best.height),
+        );
+        let difficulty = self
+            .settings
+            .difficulty_adjustment
+            .required_difficulty_after(best, &ancestors)?;
+        Ok(PreHeader {
+            version: best.version,
+            parent_id: best.id.clone(),
+            timestamp: timestamp.max(best.timestamp + 1),
+            n_bits: encode_compact_bits(&difficulty),
+            height: best.height + 1,
+            miner_pk: best.miner_pk.clone(),
+            votes: best.votes.clone(),
+        })
+    }
+
+    fn insert(&mut self, header: Header, parent_score: &BigInt) {
+        let score = parent_score + decode_compact_bits(header.n_bits);
+        let is_best = match self.best.as_ref().and_then(|id| self.entries.get(id)) {
+            Some(best) => score > best.score,
+            None => true,
+        };
+        let id = header.id.clone();
+        self.entries
+            .insert(id.clone(), ChainEntry { header, score });
+        if is_best {
+            self.best = Some(id);
+        }
+    }
+
+    fn check_version(&self, header: &Header, parent_version: u8) -> Result<(), HeaderChainError> {
+        if header.version < parent_version || header.version > self.settings.max_block_version {
+            Err(HeaderChainError::InvalidVersion {
+                parent: parent_version,
+                actual: header.version,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_timestamp(&self, header: &Header) -> Result<(), HeaderChainError> {
+        let mut timestamps: Vec<u64> = self
+            .ancestors(&header.parent_id)
+            .take(self.settings.median_time_window)
+            .map(|h| h.timestamp)
+            .collect();
+        timestamps.sort_unstable();
+        match timestamps.get(timestamps.len() / 2) {
+            Some(median) if header.timestamp <= *median => Err(HeaderChainError::TimestampTooOld {
+                timestamp: header.timestamp,
+                median: *median,
+            }),
+            Some(_) | None => Ok(()),
+        }
+    }
+
+    /// Ancestors of the header needed for its difficulty check (the parent included)
+    fn difficulty_ancestors(&self, header: &Header) -> Vec<Header> {
+        match self.header(&header.parent_id) {
+            Some(parent) => self.ancestors_at(
+                parent,
+                &self
+                    .settings
+                    .difficulty_adjustment
+                    .heights_required_after(parent.height),
+            ),
+            None => vec![],
+        }
+    }
+
+    /// Headers of the branch ending with `tip` at the given heights (the tip included)
+    fn ancestors_at(&self, tip: &Header, heights: &[u32]) -> Vec<Header> {
+        let min_height = heights.iter().copied().min().unwrap_or(tip.height);
+        let mut headers: Vec<Header> = self
+            .ancestors(&tip.id)
+            .take_while(|h| h.height >= min_height)
+            .filter(|h| h.height == tip.height || heights.contains(&h.height))
+            .cloned()
+            .collect();
+        if headers.is_empty() {
+            headers.push(tip.clone());
+        }
+        headers
+    }
+
+    /// Walks both branches down (the higher one first) until they meet
+    fn common_ancestor(&self, left: &BlockId, right: &BlockId) -> BlockId {
+        let mut left = self.header(left);
+        let mut right = self.header(right);
+        while let (Some(l), Some(r)) = (left, right) {
+            if l.id == r.id {
+                return l.id.clone();
+            }
+            if l.height >= r.height {
+                left = self.header(&l.parent_id);
+            }
+            if r.height >= l.height {
+                right = self.header(&r.parent_id);
+            }
+        }
+        BlockId(Digest32::zero())
+    }
+}
+
+fn check_height(header: &Header, parent: &Header) -> Result<(), HeaderChainError> {
+    if header.height == parent.height + 1 {
+        Ok(())
+    } else {
+        Err(HeaderChainError::InvalidHeight {
+            expected: parent.height + 1,
+            actual: header.height,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::autolykos_pow_scheme::tests::mine_v1;
+    use ergotree_ir::chain::digest32::ADDigest;
+    use ergotree_ir::chain::votes::Votes;
+    use ergotree_ir::sigma_protocol::dlog_group;
+    use num_bigint::BigInt;
+
+    const NOW: u64 = 1_600_000_000_000;
+    const INTERVAL: u64 = 120_000;
+
+    fn settings() -> HeaderChainSettings {
+        HeaderChainSettings {
+            difficulty_adjustment: DifficultyAdjustment {
+                initial_difficulty: BigInt::from(1),
+                version2_activation_height: u32::MAX - 1,
+                eip37_activation_height: None,
+                ..DifficultyAdjustment::mainnet()
+            },
+            ..HeaderChainSettings::mainnet()
+        }
+    }
+
+    /// Mined v1 header (difficulty 1) following `parent`, `salt` distinguishes the forks
+    fn child(parent: Option<&Header>, salt: u8) -> Header {
+        let mut header = Header {
+            version: 1,
+            id: BlockId(Digest32::zero()),
+            parent_id: parent
+                .map(|p| p.id.clone())
+                .unwrap_or_else(|| BlockId(Digest32::zero())),
+            ad_proofs_root: Digest32::zero(),
+            state_root: ADDigest::zero(),
+            transaction_root: Digest32::zero(),
+            timestamp: parent.map(|p| p.timestamp + INTERVAL).unwrap_or(NOW),
+            n_bits: 0x01010000,
+            height: parent
+                .map(|p| p.height + 1)
+                .unwrap_or(Header::GENESIS_HEIGHT),
+            extension_root: Digest32::zero(),
+            miner_pk: Box::new(dlog_group::generator()),
+            pow_onetime_pk: Box::new(dlog_group::generator()),
+            nonce: vec![salt; 8],
+            pow_distance: BigInt::from(0),
+            votes: Votes([0, 0, 0]),
+        };
+        mine_v1(&mut header);
+        header
+    }
+
+    /// Chain of `len` headers starting with the genesis
+    fn headers(len: usize) -> Vec<Header> {
+        let mut headers: Vec<Header> = vec![child(None, 0)];
+        while headers.len() < len {
+            let next = child(headers.last(), 0);
+            headers.push(next);
+        }
+        headers
+    }
+
+    fn remine(mut header: Header) -> Header {
+        mine_v1(&mut header);
+        header
+    }
+
+    #[test]
+    fn best_chain_and_forks() {
+        let mut chain = HeaderChain::new(settings());
+        let main = headers(4);
+        for h in &main {
+            assert_eq!(chain.append(h.clone(), NOW), Ok(AppendResult::Extended));
+        }
+        assert_eq!(chain.best_header(), main.last());
+        assert_eq!(chain.best_height(), 4);
+        assert_eq!(
+            chain.append(main[1].clone(), NOW),
+            Err(HeaderChainError::AlreadyKnown(main[1].id.clone()))
+        );
+
+        // fork from the second header, the equal score does not switch the best chain
+        let fork_3 = child(Some(&main[1]), 1);
+        assert_eq!(chain.append(fork_3.clone(), NOW), Ok(AppendResult::Fork));
+        let fork_4 = child(Some(&fork_3), 1);
+        assert_eq!(chain.append(fork_4.clone(), NOW), Ok(AppendResult::Fork));
+        assert_eq!(chain.best_header(), main.last());
+        let fork_5 = child(Some(&fork_4), 1);
+        assert_eq!(
+            chain.append(fork_5.clone(), NOW),
+            Ok(AppendResult::Reorganized {
+                common_ancestor: main[1].id.clone()
+            })
+        );
+        assert_eq!(chain.best_header(), Some(&fork_5));
+        assert_eq!(chain.last_headers(3), vec![&fork_5, &fork_4, &fork_3]);
+        assert_eq!(chain.score(&fork_5.id), Some(&BigInt::from(5)));
+        assert!(chain.header(&main[3].id).is_some());
+    }
+
+    #[test]
+    fn common_ancestor() {
+        let mut chain = HeaderChain::new(settings());
+        let main = headers(6);
+        for h in &main {
+            chain.append(h.clone(), NOW + 6 * INTERVAL).unwrap();
+        }
+        let fork_3 = child(Some(&main[1]), 1);
+        let fork_4 = child(Some(&fork_3), 1);
+        for h in &[fork_3.clone(), fork_4.clone()] {
+            chain.append(h.clone(), NOW + 6 * INTERVAL).unwrap();
+        }
+        // branches of different heights
+        assert_eq!(chain.common_ancestor(&main[5].id, &fork_4.id), main[1].id);
+        assert_eq!(chain.common_ancestor(&fork_3.id, &main[5].id), main[1].id);
+        // branches of the same height
+        assert_eq!(chain.common_ancestor(&main[3].id, &fork_4.id), main[1].id);
+        // one is the ancestor of the other
+        assert_eq!(chain.common_ancestor(&main[5].id, &main[2].id), main[2].id);
+        assert_eq!(chain.common_ancestor(&main[4].id, &main[4].id), main[4].id);
+
+        // no common header in the chain started from the trusted headers
+        let trusted = HeaderChain::from_trusted_headers(settings(), main[2..].to_vec()).unwrap();
+        assert_eq!(
+            trusted.common_ancestor(&main[5].id, &fork_4.id),
+            BlockId(Digest32::zero())
+        );
+    }
+
+    #[test]
+    fn invalid_headers() {
+        let mut chain = HeaderChain::new(settings());
+        let main = headers(12);
+        for h in &main {
+            chain.append(h.clone(), NOW + 12 * INTERVAL).unwrap();
+        }
+        let last = main.last().unwrap();
+        let now = last.timestamp;
+
+        let unknown_parent = child(Some(&child(Some(last), 1)), 1);
+        assert_eq!(
+            chain.validate(&unknown_parent, now),
+            Err(HeaderChainError::UnknownParent(
+                unknown_parent.parent_id.clone()
+            ))
+        );
+        assert!(matches!(
+            chain.validate(&child(None, 1), now),
+            Err(HeaderChainError::InvalidGenesis(_))
+        ));
+        let wrong_height = remine(Header {
+            height: last.height + 2,
+            ..child(Some(last), 1)
+        });
+        assert_eq!(
+            chain.validate(&wrong_height, now),
+            Err(HeaderChainError::InvalidHeight {
+                expected: last.height + 1,
+                actual: last.height + 2
+            })
+        );
+        let wrong_version = remine(Header {
+            version: 4,
+            ..child(Some(last), 1)
+        });
+        assert_eq!(
+            chain.validate(&wrong_version, now),
+            Err(HeaderChainError::InvalidVersion {
+                parent: 1,
+                actual: 4
+            })
+        );
+        // median of the last 11 headers is the 6th from the end
+        let median = main[main.len() - 6].timestamp;
+        let too_old = remine(Header {
+            timestamp: median,
+            ..child(Some(last), 1)
+        });
+        assert_eq!(
+            chain.validate(&too_old, now),
+            Err(HeaderChainError::TimestampTooOld {
+                timestamp: median,
+                median
+            })
+        );
+        let after_median = remine(Header {
+            timestamp: median + 1,
+            ..child(Some(last), 1)
+        });
+        assert_eq!(chain.validate(&after_median, now), Ok(()));
+        let in_future = child(Some(last), 1);
+        assert_eq!(
+            chain.validate(&in_future, now - chain.settings().max_time_drift),
+            Err(HeaderChainError::TimestampInFuture {
+                timestamp: in_future.timestamp,
+                now: now - chain.settings().max_time_drift
+            })
+        );
+        let wrong_n_bits = remine(Header {
+            n_bits: 0x01020000,
+            ..child(Some(last), 1)
+        });
+        assert!(matches!(
+            chain.validate(&wrong_n_bits, now),
+            Err(HeaderChainError::DifficultyError(
+                DifficultyAdjustmentError::NBitsMismatch { .. }
+            ))
+        ));
+        let mut wrong_pow = child(Some(last), 1);
+        wrong_pow.pow_distance += 1;
+        wrong_pow.id = wrong_pow.compute_id().unwrap();
+        assert_eq!(
+            chain.validate(&wrong_pow, now),
+            Err(HeaderChainError::PowError(
+                AutolykosPowSchemeError::InvalidSolution
+            ))
+        );
+        let mut wrong_id = child(Some(last), 1);
+        wrong_id.id = last.id.clone();
+        assert!(matches!(
+            chain.validate(&wrong_id, now),
+            Err(HeaderChainError::AlreadyKnown(_))
+        ));
+        wrong_id.id = BlockId(Digest32::zero());
+        assert!(matches!(
+            chain.validate(&wrong_id, now),
+            Err(HeaderChainError::InvalidId(_))
+        ));
+    }
+
+    #[test]
+    fn next_pre_header() {
+        assert_eq!(
+            HeaderChain::new(settings()).next_pre_header(NOW),
+            Err(HeaderChainError::NotEnoughHeaders {
+                required: 1,
+                available: 0
+            })
+        );
+        let main = headers(12);
+        let chain = HeaderChain::from_trusted_headers(settings(), main.clone()).unwrap();
+        let last = main.last().unwrap();
+        let pre_header = chain.next_pre_header(last.timestamp + INTERVAL).unwrap();
+        assert_eq!(pre_header.parent_id, last.id);
+        assert_eq!(pre_header.height, last.height + 1);
+        assert_eq!(pre_header.timestamp, last.timestamp + INTERVAL);
+        assert_eq!(pre_header.n_bits, last.n_bits);
+        assert_eq!(pre_header.version, last.version);
+        assert_eq!(
+            chain.next_pre_header(NOW).unwrap().timestamp,
+            last.timestamp + 1
+        );
+
+        // the predicted pre-header is valid for the next header
+        let next = child(Some(last), 0);
+        assert_eq!(pre_header.n_bits, next.n_bits);
+        assert_eq!(chain.validate(&next, next.timestamp), Ok(()));
+
+        assert!(matches!(
+            HeaderChain::from_trusted_headers(settings(), vec![main[0].clone(), main[2].clone()]),
+            Err(HeaderChainError::UnknownParent(_))
+        ));
+    }
+}
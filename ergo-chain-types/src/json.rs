@@ -0,0 +1,253 @@
+//! JSON serialization (node API format)
+
+use std::convert::TryFrom;
+
+use ergotree_ir::chain::base16_bytes::Base16DecodedBytes;
+use ergotree_ir::chain::base16_bytes::Base16EncodedBytes;
+use ergotree_ir::chain::block_id::BlockId;
+use ergotree_ir::chain::digest32::Digest32;
+use serde::{Deserialize, Serialize};
+
+use crate::block_section::check_digest;
+use crate::block_section::ADProofs;
+use crate::block_section::BlockSectionError;
+use crate::block_section::Extension;
+use crate::merkle_tree::BatchMerkleProof;
+use crate::merkle_tree::LevelNode;
+use crate::merkle_tree::MerkleProof;
+use crate::merkle_tree::NodeSide;
+
+/// [`ADProofs`] with the digest (checked on parsing)
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub(crate) struct ADProofsJson {
+    #[serde(rename = "headerId")]
+    header_id: BlockId,
+    #[serde(rename = "proofBytes")]
+    proof_bytes: Base16DecodedBytes,
+    #[serde(rename = "digest")]
+    digest: Digest32,
+}
+
+impl From<ADProofs> for ADProofsJson {
+    fn from(proofs: ADProofs) -> Self {
+        ADProofsJson {
+            digest: proofs.digest(),
+            header_id: proofs.header_id,
+            proof_bytes: Base16DecodedBytes(proofs.proof_bytes),
+        }
+    }
+}
+
+impl TryFrom<ADProofsJson> for ADProofs {
+    type Error = BlockSectionError;
+
+    fn try_from(json: ADProofsJson) -> Result<Self, Self::Error> {
+        let proofs = ADProofs {
+            header_id: json.header_id,
+            proof_bytes: json.proof_bytes.0,
+        };
+        check_digest(&json.digest, proofs.digest())?;
+        Ok(proofs)
+    }
+}
+
+/// [`Extension`] with the digest (checked on parsing), fields are `[key, value]` pairs
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub(crate) struct ExtensionJson {
+    #[serde(rename = "headerId")]
+    header_id: BlockId,
+    #[serde(rename = "digest")]
+    digest: Digest32,
+    #[serde(rename = "fields")]
+    fields: Vec<(Base16DecodedBytes, Base16DecodedBytes)>,
+}
+
+/// Errors on parsing [`Extension`] from JSON
+#[derive(thiserror::Error, PartialEq, Eq, Debug, Clone)]
+pub enum ExtensionFromJsonError {
+    /// Field key is not [`Extension::FIELD_KEY_SIZE`] bytes long
+    #[error("invalid field key {0:?}")]
+    InvalidKey(Base16EncodedBytes),
+    /// Digest differs from the computed one
+    #[error("{0}")]
+    DigestMismatch(#[from] BlockSectionError),
+}
+
+impl From<Extension> for ExtensionJson {
+    fn from(extension: Extension) -> Self {
+        ExtensionJson {
+            digest: extension.digest(),
+            header_id: extension.header_id,
+            fields: extension
+                .fields
+                .into_iter()
+                .map(|(key, value)| (Base16DecodedBytes(key.to_vec()), Base16DecodedBytes(value)))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<ExtensionJson> for Extension {
+    type Error = ExtensionFromJsonError;
+
+    fn try_from(json: ExtensionJson) -> Result<Self, Self::Error> {
+        let fields = json
+            .fields
+            .into_iter()
+            .map(|(key, value)| {
+                <[u8; Extension::FIELD_KEY_SIZE]>::try_from(key.0.as_slice())
+                    .map(|key| (key, value.0))
+                    .map_err(|_| {
+                        ExtensionFromJsonError::InvalidKey(Base16EncodedBytes::new(&key.0))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let extension = Extension {
+            header_id: json.header_id,
+            fields,
+        };
+        check_digest(&json.digest, extension.digest())?;
+        Ok(extension)
+    }
+}
+
+/// Sibling hash (empty string if absent) and side, as in the node API
+pub(crate) type LevelNodeJson = (Base16DecodedBytes, u8);
+
+/// [`MerkleProof`] in the format of the node `/blocks/{headerId}/proofFor/{txId}` response
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub(crate) struct MerkleProofJson {
+    #[serde(rename = "leafData")]
+    leaf_data: Base16DecodedBytes,
+    #[serde(rename = "levels")]
+    levels: Vec<LevelNodeJson>,
+}
+
+/// [`BatchMerkleProof`] in the node format (`interlinksProof` of the NiPoPoW header)
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub(crate) struct BatchMerkleProofJson {
+    #[serde(rename = "indices")]
+    indices: Vec<BatchMerkleProofIndexJson>,
+    #[serde(rename = "proofs")]
+    proofs: Vec<BatchMerkleProofNodeJson>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+struct BatchMerkleProofIndexJson {
+    #[serde(rename = "index")]
+    index: usize,
+    #[serde(rename = "digest")]
+    digest: Digest32,
+}
+
+impl From<(usize, Digest32)> for BatchMerkleProofIndexJson {
+    fn from((index, digest): (usize, Digest32)) -> Self {
+        BatchMerkleProofIndexJson { index, digest }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+struct BatchMerkleProofNodeJson {
+    #[serde(rename = "digest")]
+    digest: Base16DecodedBytes,
+    #[serde(rename = "side")]
+    side: u8,
+}
+
+impl From<LevelNodeJson> for BatchMerkleProofNodeJson {
+    fn from((digest, side): LevelNodeJson) -> Self {
+        BatchMerkleProofNodeJson { digest, side }
+    }
+}
+
+/// Errors on parsing Merkle proofs from JSON
+#[derive(thiserror::Error, PartialEq, Eq, Debug, Clone)]
+pub enum MerkleProofFromJsonError {
+    /// Sibling hash is neither empty nor 32 bytes long
+    #[error("invalid sibling hash {0:?}")]
+    InvalidHash(Base16EncodedBytes),
+    /// Side is neither 0 nor 1
+    #[error("invalid node side {0}")]
+    InvalidSide(u8),
+}
+
+fn level_node_to_json(node: LevelNode) -> LevelNodeJson {
+    let hash = node.hash.map(|h| h.0.to_vec()).unwrap_or_default();
+    (Base16DecodedBytes(hash), node.side as u8)
+}
+
+fn level_node_from_json(
+    (hash, side): LevelNodeJson,
+) -> Result<LevelNode, MerkleProofFromJsonError> {
+    let hash =
+        if hash.0.is_empty() {
+            None
+        } else {
+            Some(Digest32::try_from(hash.0.clone()).map_err(|_| {
+                MerkleProofFromJsonError::InvalidHash(Base16EncodedBytes::new(&hash.0))
+            })?)
+        };
+    let side = NodeSide::try_from(side).map_err(|_| MerkleProofFromJsonError::InvalidSide(side))?;
+    Ok(LevelNode { hash, side })
+}
+
+impl From<MerkleProof> for MerkleProofJson {
+    fn from(proof: MerkleProof) -> Self {
+        MerkleProofJson {
+            leaf_data: Base16DecodedBytes(proof.leaf_data),
+            levels: proof.levels.into_iter().map(level_node_to_json).collect(),
+        }
+    }
+}
+
+impl TryFrom<MerkleProofJson> for MerkleProof {
+    type Error = MerkleProofFromJsonError;
+
+    fn try_from(json: MerkleProofJson) -> Result<Self, Self::Error> {
+        Ok(MerkleProof {
+            leaf_data: json.leaf_data.0,
+            levels: json
+                .levels
+                .into_iter()
+                .map(level_node_from_json)
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+}
+
+impl From<BatchMerkleProof> for BatchMerkleProofJson {
+    fn from(proof: BatchMerkleProof) -> Self {
+        BatchMerkleProofJson {
+            indices: proof
+                .indices
+                .into_iter()
+                .map(BatchMerkleProofIndexJson::from)
+                .collect(),
+            proofs: proof
+                .proofs
+                .into_iter()
+                .map(level_node_to_json)
+                .map(BatchMerkleProofNodeJson::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<BatchMerkleProofJson> for BatchMerkleProof {
+    type Error = MerkleProofFromJsonError;
+
+    fn try_from(json: BatchMerkleProofJson) -> Result<Self, Self::Error> {
+        Ok(BatchMerkleProof {
+            indices: json
+                .indices
+                .into_iter()
+                .map(|i| (i.index, i.digest))
+                .collect(),
+            proofs: json
+                .proofs
+                .into_iter()
+                .map(|node| level_node_from_json((node.digest, node.side)))
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+}
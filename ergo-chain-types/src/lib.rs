@@ -16,3 +16,13 @@
 #![deny(clippy::todo)]
 #![deny(clippy::unimplemented)]
 #![deny(clippy::panic)]
+
+pub mod autolykos_pow_scheme;
+pub mod block_section;
+pub mod difficulty_adjustment;
+pub mod header_chain;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod merkle_tree;
+pub mod nbits;
+pub mod parameters;
@@ -0,0 +1,526 @@
+//! Merkle tree (used for the roots of the block sections in the header) and Merkle proofs
+//! see <https://github.com/ergoplatform/scorex-util> (MerkleTree, MerkleProof, BatchMerkleProof)
+
+use std::convert::TryFrom;
+
+use ergotree_ir::chain::digest32::blake2b256_hash;
+use ergotree_ir::chain::digest32::Digest32;
+use ergotree_ir::serialization::sigma_byte_reader::SigmaByteRead;
+use ergotree_ir::serialization::sigma_byte_writer::SigmaByteWrite;
+use ergotree_ir::serialization::SigmaParsingError;
+use ergotree_ir::serialization::SigmaSerializable;
+use ergotree_ir::serialization::SigmaSerializationError;
+use ergotree_ir::serialization::SigmaSerializeResult;
+
+/// Prefix of the leaf data before hashing
+pub const LEAF_PREFIX: u8 = 0;
+/// Prefix of the children hashes before hashing
+pub const INTERNAL_NODE_PREFIX: u8 = 1;
+
+/// Hash of the leaf with the given data
+pub fn leaf_hash(data: &[u8]) -> Digest32 {
+    prefixed_hash(LEAF_PREFIX, &[data])
+}
+
+/// Hash of the internal node, the right child is absent for the last node on the level with the
+/// odd number of nodes
+pub fn internal_node_hash(left: &Digest32, right: Option<&Digest32>) -> Digest32 {
+    match right {
+        Some(right) => prefixed_hash(INTERNAL_NODE_PREFIX, &[&left.0[..], &right.0[..]]),
+        None => prefixed_hash(INTERNAL_NODE_PREFIX, &[&left.0[..]]),
+    }
+}
+
+/// Root hash of the Merkle tree with the given leaves data, hash of the empty byte array for no
+/// leaves
+pub fn merkle_tree_root<T: AsRef<[u8]>>(leaves: &[T]) -> Digest32 {
+    MerkleTree::new(leaves).root_hash()
+}
+
+/// Merkle tree with all the node hashes, for the proofs generation
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MerkleTree {
+    leaves: Vec<Vec<u8>>,
+    /// Node hashes level by level, from the leaves to the root
+    levels: Vec<Vec<Digest32>>,
+}
+
+impl MerkleTree {
+    /// Builds the tree from the leaves data
+    pub fn new<T: AsRef<[u8]>>(leaves: &[T]) -> Self {
+        let leaves: Vec<Vec<u8>> = leaves.iter().map(|l| l.as_ref().to_vec()).collect();
+        let mut levels = Vec::new();
+        if !leaves.is_empty() {
+            let mut level: Vec<Digest32> = leaves.iter().map(|l| leaf_hash(l)).collect();
+            loop {
+                let next: Vec<Digest32> = level
+                    .chunks(2)
+                    .map(|pair| internal_node_hash(&pair[0], pair.get(1)))
+                    .collect();
+                levels.push(level);
+                if next.len() == 1 {
+                    levels.push(next);
+                    break;
+                }
+                level = next;
+            }
+        }
+        MerkleTree { leaves, levels }
+    }
+
+    /// Root hash, hash of the empty byte array for the tree without leaves
+    pub fn root_hash(&self) -> Digest32 {
+        self.levels
+            .last()
+            .and_then(|root| root.first())
+            .cloned()
+            .unwrap_or_else(|| blake2b256_hash(&[]))
+    }
+
+    /// Leaves data
+    pub fn leaves(&self) -> &[Vec<u8>] {
+        &self.leaves
+    }
+
+    /// Proof of inclusion of the leaf with the given index
+    pub fn proof_by_index(&self, index: usize) -> Option<MerkleProof> {
+        let leaf_data = self.leaves.get(index)?.clone();
+        let mut i = index;
+        let levels = self.levels[..self.levels.len() - 1]
+            .iter()
+            .map(|level| {
+                let node = if i % 2 == 0 {
+                    LevelNode {
+                        hash: level.get(i + 1).cloned(),
+                        side: NodeSide::Left,
+                    }
+                } else {
+                    LevelNode {
+                        hash: level.get(i - 1).cloned(),
+                        side: NodeSide::Right,
+                    }
+                };
+                i /= 2;
+                node
+            })
+            .collect();
+        Some(MerkleProof { leaf_data, levels })
+    }
+
+    /// Proof of inclusion of the first leaf with the given data
+    pub fn proof_by_element(&self, data: &[u8]) -> Option<MerkleProof> {
+        let index = self.leaves.iter().position(|l| l.as_slice() == data)?;
+        self.proof_by_index(index)
+    }
+
+    /// Compact proof of inclusion of the leaves with the given indices, `None` if there are no
+    /// indices or any of them is out of range
+    pub fn proof_by_indices(&self, indices: &[usize]) -> Option<BatchMerkleProof> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if indices.is_empty() || indices.iter().any(|i| *i >= self.leaves.len()) {
+            return None;
+        }
+        let leaf_hashes = self.levels.first()?;
+        let proven: Vec<(usize, Digest32)> = indices
+            .iter()
+            .map(|i| (*i, leaf_hashes[*i].clone()))
+            .collect();
+        let mut proofs = Vec::new();
+        let mut a = indices;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let mut parents = Vec::with_capacity(a.len());
+            let mut k = 0;
+            while k < a.len() {
+                let i = a[k];
+                let sibling = i ^ 1;
+                if a.get(k + 1) == Some(&sibling) {
+                    // both children are known to the verifier
+                    k += 2;
+                } else {
+                    proofs.push(LevelNode {
+                        hash: level.get(sibling).cloned(),
+                        side: if sibling % 2 == 0 {
+                            NodeSide::Left
+                        } else {
+                            NodeSide::Right
+                        },
+                    });
+                    k += 1;
+                }
+                parents.push(i / 2);
+            }
+            a = parents;
+        }
+        Some(BatchMerkleProof {
+            indices: proven,
+            proofs,
+        })
+    }
+}
+
+/// Side of the node in the pair of siblings
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum NodeSide {
+    /// Left child
+    Left = 0,
+    /// Right child
+    Right = 1,
+}
+
+impl TryFrom<u8> for NodeSide {
+    type Error = SigmaParsingError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(NodeSide::Left),
+            1 => Ok(NodeSide::Right),
+            _ => Err(SigmaParsingError::ValueOutOfBounds(format!(
+                "invalid Merkle tree node side {}",
+                value
+            ))),
+        }
+    }
+}
+
+/// Level of the Merkle proof, the sibling hash (absent for the last node on the level with the
+/// odd number of nodes) and a side
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct LevelNode {
+    /// Hash of the sibling node
+    pub hash: Option<Digest32>,
+    /// In [`MerkleProof`] the side of the node being proven, in [`BatchMerkleProof`] the side of
+    /// the sibling (as in scorex)
+    pub side: NodeSide,
+}
+
+/// Proof of inclusion of the leaf in the Merkle tree
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "json",
+    serde(
+        try_from = "crate::json::MerkleProofJson",
+        into = "crate::json::MerkleProofJson"
+    )
+)]
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MerkleProof {
+    /// Leaf data
+    pub leaf_data: Vec<u8>,
+    /// Sibling hashes from the leaf level up to the root
+    pub levels: Vec<LevelNode>,
+}
+
+impl MerkleProof {
+    /// Root hash computed from the leaf and the sibling hashes
+    pub fn root_hash(&self) -> Digest32 {
+        self.levels
+            .iter()
+            .fold(leaf_hash(&self.leaf_data), |prev, node| match node.side {
+                NodeSide::Left => internal_node_hash(&prev, node.hash.as_ref()),
+                NodeSide::Right => match &node.hash {
+                    Some(hash) => internal_node_hash(hash, Some(&prev)),
+                    None => internal_node_hash(&prev, None),
+                },
+            })
+    }
+
+    /// Checks the proof against the expected root hash
+    pub fn valid(&self, expected_root: &Digest32) -> bool {
+        &self.root_hash() == expected_root
+    }
+}
+
+/// Compact proof of inclusion of several leaves in the Merkle tree (proven leaf hashes with their
+/// indices and only the sibling hashes not computable from them)
+/// see <https://deepai.org/publication/compact-merkle-multiproofs>
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "json",
+    serde(
+        try_from = "crate::json::BatchMerkleProofJson",
+        into = "crate::json::BatchMerkleProofJson"
+    )
+)]
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct BatchMerkleProof {
+    /// Indices and hashes of the proven leaves
+    pub indices: Vec<(usize, Digest32)>,
+    /// Sibling hashes in the order they are needed on verification, level by level
+    pub proofs: Vec<LevelNode>,
+}
+
+impl BatchMerkleProof {
+    /// Root hash computed from the proven leaves and the sibling hashes, `None` for the malformed
+    /// proof
+    pub fn root_hash(&self) -> Option<Digest32> {
+        let mut known = self.indices.clone();
+        known.sort_by_key(|(i, _)| *i);
+        known.dedup_by_key(|(i, _)| *i);
+        if known.is_empty() {
+            return None;
+        }
+        let mut proofs = self.proofs.iter();
+        loop {
+            let mut parents: Vec<(usize, Digest32)> = Vec::with_capacity(known.len());
+            let mut k = 0;
+            while k < known.len() {
+                let (i, hash) = &known[k];
+                match known.get(k + 1) {
+                    Some((j, right)) if i % 2 == 0 && *j == i + 1 => {
+                        parents.push((i / 2, internal_node_hash(hash, Some(right))));
+                        k += 2;
+                    }
+                    _ => {
+                        let sibling = proofs.next()?;
+                        let parent = match (&sibling.hash, sibling.side) {
+                            (Some(left), NodeSide::Left) => internal_node_hash(left, Some(hash)),
+                            (Some(right), NodeSide::Right) => internal_node_hash(hash, Some(right)),
+                            (None, _) => internal_node_hash(hash, None),
+                        };
+                        parents.push((i / 2, parent));
+                        k += 1;
+                    }
+                }
+            }
+            known = parents;
+            if known.len() == 1 && proofs.as_slice().is_empty() {
+                return known.pop().map(|(_, root)| root);
+            }
+        }
+    }
+
+    /// Checks the proof against the expected root hash
+    pub fn valid(&self, expected_root: &Digest32) -> bool {
+        self.root_hash().as_ref() == Some(expected_root)
+    }
+}
+
+/// Binary format of scorex `BatchMerkleProofSerializer`: big-endian 4-byte counts and indices,
+/// absent sibling hashes are written as zeros
+impl SigmaSerializable for BatchMerkleProof {
+    fn sigma_serialize<W: SigmaByteWrite>(&self, w: &mut W) -> SigmaSerializeResult {
+        let to_u32 = |v: usize| {
+            u32::try_from(v)
+                .map_err(|_| SigmaSerializationError::NotSupported("batch Merkle proof is too big"))
+        };
+        w.write_all(&to_u32(self.indices.len())?.to_be_bytes())?;
+        w.write_all(&to_u32(self.proofs.len())?.to_be_bytes())?;
+        for (index, hash) in &self.indices {
+            w.write_all(&to_u32(*index)?.to_be_bytes())?;
+            w.write_all(&hash.0[..])?;
+        }
+        for node in &self.proofs {
+            match &node.hash {
+                Some(hash) => w.write_all(&hash.0[..])?,
+                None => w.write_all(&[0u8; Digest32::SIZE])?,
+            }
+            w.put_u8(node.side as u8)?;
+        }
+        Ok(())
+    }
+
+    fn sigma_parse<R: SigmaByteRead>(r: &mut R) -> Result<Self, SigmaParsingError> {
+        let read_u32 = |r: &mut R| -> Result<usize, SigmaParsingError> {
+            let mut bytes = [0u8; 4];
+            r.read_exact(&mut bytes)?;
+            Ok(u32::from_be_bytes(bytes) as usize)
+        };
+        let indices_count = read_u32(r)?;
+        let proofs_count = read_u32(r)?;
+        let mut indices = Vec::new();
+        for _ in 0..indices_count {
+            let index = read_u32(r)?;
+            indices.push((index, Digest32::sigma_parse(r)?));
+        }
+        let mut proofs = Vec::new();
+        for _ in 0..proofs_count {
+            let hash = Digest32::sigma_parse(r)?;
+            let side = NodeSide::try_from(r.get_u8()?)?;
+            proofs.push(LevelNode {
+                hash: if hash == Digest32::zero() {
+                    None
+                } else {
+                    Some(hash)
+                },
+                side,
+            });
+        }
+        Ok(BatchMerkleProof { indices, proofs })
+    }
+}
+
+fn prefixed_hash(prefix: u8, data: &[&[u8]]) -> Digest32 {
+    let mut bytes = vec![prefix];
+    data.iter().for_each(|d| bytes.extend_from_slice(d));
+    blake2b256_hash(&bytes)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use ergotree_ir::serialization::sigma_serialize_roundtrip;
+
+    fn leaves(count: u8) -> Vec<Vec<u8>> {
+        (0..count).map(|i| vec![i; 32]).collect()
+    }
+
+    #[test]
+    fn root() {
+        assert_eq!(merkle_tree_root::<Vec<u8>>(&[]), blake2b256_hash(&[]));
+        let a = vec![1u8; 32];
+        let b = vec![2u8; 32];
+        let c = vec![3u8; 32];
+        // a single leaf is hashed with the empty sibling
+        assert_eq!(
+            merkle_tree_root(std::slice::from_ref(&a)),
+            internal_node_hash(&leaf_hash(&a), None)
+        );
+        assert_eq!(
+            merkle_tree_root(&[a.clone(), b.clone()]),
+            internal_node_hash(&leaf_hash(&a), Some(&leaf_hash(&b)))
+        );
+        assert_eq!(
+            merkle_tree_root(&[a.clone(), b.clone(), c.clone()]),
+            internal_node_hash(
+                &internal_node_hash(&leaf_hash(&a), Some(&leaf_hash(&b))),
+                Some(&internal_node_hash(&leaf_hash(&c), None))
+            )
+        );
+    }
+
+    #[test]
+    fn proof_by_index() {
+        for count in 1..=9 {
+            let tree = MerkleTree::new(&leaves(count));
+            let root = tree.root_hash();
+            for i in 0..count as usize {
+                let proof = tree.proof_by_index(i).unwrap();
+                assert_eq!(proof.leaf_data, vec![i as u8; 32]);
+                assert!(proof.valid(&root));
+                let mut tampered = proof.clone();
+                tampered.leaf_data[0] ^= 1;
+                assert!(!tampered.valid(&root));
+            }
+            assert_eq!(tree.proof_by_index(count as usize), None);
+        }
+        assert_eq!(MerkleTree::new::<Vec<u8>>(&[]).proof_by_index(0), None);
+        let tree = MerkleTree::new(&leaves(5));
+        assert_eq!(tree.proof_by_element(&[3; 32]), tree.proof_by_index(3));
+        assert_eq!(tree.proof_by_element(&[7; 32]), None);
+    }
+
+    #[test]
+    fn proof_by_indices() {
+        for count in 1..=7u8 {
+            let tree = MerkleTree::new(&leaves(count));
+            let root = tree.root_hash();
+            // every non-empty subset of the leaves
+            for mask in 1..(1u32 << count) {
+                let indices: Vec<usize> = (0..count as usize)
+                    .filter(|i| mask & (1 << i) != 0)
+                    .collect();
+                let proof = tree.proof_by_indices(&indices).unwrap();
+                assert!(proof.valid(&root), "{} leaves, {:?}", count, indices);
+                assert_eq!(sigma_serialize_roundtrip(&proof), proof);
+                let mut tampered = proof.clone();
+                tampered.indices[0].1 = leaf_hash(&[]);
+                assert!(!tampered.valid(&root));
+            }
+            assert_eq!(tree.proof_by_indices(&[]), None);
+            assert_eq!(tree.proof_by_indices(&[count as usize]), None);
+        }
+        let tree = MerkleTree::new(&leaves(8));
+        // siblings on the leaf level are not included in the proof
+        assert_eq!(tree.proof_by_indices(&[2, 3]).unwrap().proofs.len(), 2);
+        let mut missing_sibling = tree.proof_by_indices(&[0, 5]).unwrap();
+        missing_sibling.proofs.pop();
+        assert!(!missing_sibling.valid(&tree.root_hash()));
+    }
+
+    #[test]
+    fn merkle_proof_json() {
+        // node response for a transaction of a mainnet block
+        let json = r#"{
+          "leafData" : "563b34b96e65788d767a10b0c2ce4a9ef5dcb9f7f7919781624870d56506dc5b",
+          "levels" : [
+            ["274d105b42c2da3e03519865470ccef5072d389b153535ca7192fef4abf3b3ed", 0],
+            ["c1887cee0c42318ac04dfa93b8ef6b40c2b53a83b0e111f91a16b0842166e76e", 0],
+            ["58be076cd9ef596a739ec551cbb6b467b95044c05a80a66a7f256d4ebafd787f", 0]
+          ]
+        }"#;
+        let proof: MerkleProof = serde_json::from_str(json).unwrap();
+        assert!(proof.valid(
+            &Digest32::try_from(
+                "250063ac1cec3bf56f727f644f49b70515616afa6009857a29b1fe298441e69a".to_string()
+            )
+            .unwrap()
+        ));
+        let encoded = serde_json::to_string(&proof).unwrap();
+        assert_eq!(
+            serde_json::from_str::<MerkleProof>(&encoded).unwrap(),
+            proof
+        );
+
+        let tree = MerkleTree::new(&leaves(3));
+        let proof = tree.proof_by_index(2).unwrap();
+        let encoded = serde_json::to_string(&proof).unwrap();
+        assert_eq!(
+            encoded,
+            format!(
+                r#"{{"leafData":"{}","levels":[["",0],["{}",1]]}}"#,
+                "02".repeat(32),
+                String::from(tree.levels[1][0].clone())
+            )
+        );
+        assert_eq!(
+            serde_json::from_str::<MerkleProof>(&encoded).unwrap(),
+            proof
+        );
+        assert!(serde_json::from_str::<MerkleProof>(&encoded.replace(",1]", ",2]")).is_err());
+        assert!(
+            serde_json::from_str::<MerkleProof>(&encoded.replace(r#"["",0]"#, r#"["00",0]"#))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn batch_merkle_proof_json() {
+        let tree = MerkleTree::new(&leaves(5));
+        let batch = tree.proof_by_indices(&[0, 4]).unwrap();
+        let digest = |d: &Digest32| String::from(d.clone());
+        let node_hash = |n: &LevelNode| n.hash.as_ref().map(digest).unwrap_or_default();
+        // node format
+        let indices: Vec<String> = batch
+            .indices
+            .iter()
+            .map(|(i, d)| format!(r#"{{"index":{},"digest":"{}"}}"#, i, digest(d)))
+            .collect();
+        let proofs: Vec<String> = batch
+            .proofs
+            .iter()
+            .map(|n| format!(r#"{{"digest":"{}","side":{}}}"#, node_hash(n), n.side as u8))
+            .collect();
+        let encoded = serde_json::to_string(&batch).unwrap();
+        assert_eq!(
+            encoded,
+            format!(
+                r#"{{"indices":[{}],"proofs":[{}]}}"#,
+                indices.join(","),
+                proofs.join(",")
+            )
+        );
+        assert!(encoded.contains(r#"{"digest":"","side":1}"#));
+        assert_eq!(
+            serde_json::from_str::<BatchMerkleProof>(&encoded).unwrap(),
+            batch
+        );
+        assert!(serde_json::from_str::<BatchMerkleProof>(
+            &encoded.replace(r#""side":1"#, r#""side":2"#)
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! Compact representation of the difficulty (nBits)
+
+use num_bigint::BigInt;
+use num_bigint::Sign;
+use num_traits::ToPrimitive;
+
+/// Decodes difficulty from the compact representation used in the header (`n_bits`).
+/// Compact form is a 4-byte MPI number: the highest byte is the size, the rest are the most
+/// significant bytes of the value, the highest bit of the mantissa is the sign.
+pub fn decode_compact_bits(n_bits: u64) -> BigInt {
+    let size = ((n_bits >> 24) & 0xFF) as usize;
+    let mut bytes = vec![0u8; size];
+    if size >= 1 {
+        bytes[0] = ((n_bits >> 16) & 0xFF) as u8;
+    }
+    if size >= 2 {
+        bytes[1] = ((n_bits >> 8) & 0xFF) as u8;
+    }
+    if size >= 3 {
+        bytes[2] = (n_bits & 0xFF) as u8;
+    }
+    match bytes.first_mut() {
+        Some(first) if *first & 0x80 != 0 => {
+            *first &= 0x7F;
+            BigInt::from_bytes_be(Sign::Minus, &bytes)
+        }
+        Some(_) | None => BigInt::from_bytes_be(Sign::Plus, &bytes),
+    }
+}
+
+/// Encodes difficulty to the compact representation (`n_bits`), see [`decode_compact_bits`].
+/// The precision is limited to the 3 most significant bytes, so `decode(encode(d))` normalizes
+/// the difficulty.
+pub fn encode_compact_bits(difficulty: &BigInt) -> u64 {
+    let magnitude = BigInt::from(difficulty.magnitude().clone());
+    let mut size = magnitude.to_signed_bytes_be().len() as u64;
+    let mut result = if size <= 3 {
+        magnitude.to_u64().unwrap_or(0) << (8 * (3 - size))
+    } else {
+        (magnitude >> (8 * (size - 3))).to_u64().unwrap_or(0)
+    };
+    // The 0x00800000 bit denotes the sign.
+    // Thus, if it is already set, divide the mantissa by 256 and increase the exponent.
+    if result & 0x00800000 != 0 {
+        result >>= 8;
+        size += 1;
+    }
+    result |= size << 24;
+    if difficulty.sign() == Sign::Minus {
+        result |= 0x00800000;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        // mainnet header at height 471746
+        assert_eq!(
+            decode_compact_bits(117586360),
+            BigInt::from(626412390187008u64)
+        );
+        assert_eq!(decode_compact_bits(0x01003456), BigInt::from(0));
+        assert_eq!(decode_compact_bits(0x01123456), BigInt::from(0x12));
+        assert_eq!(decode_compact_bits(0x02008000), BigInt::from(0x80));
+        assert_eq!(decode_compact_bits(0x05009234), BigInt::from(0x92340000u64));
+        assert_eq!(decode_compact_bits(0x04923456), BigInt::from(-0x12345600));
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(
+            encode_compact_bits(&BigInt::from(626412390187008u64)),
+            117586360
+        );
+        assert_eq!(encode_compact_bits(&BigInt::from(0)), 0x01000000);
+        assert_eq!(encode_compact_bits(&BigInt::from(0x12)), 0x01120000);
+        assert_eq!(encode_compact_bits(&BigInt::from(0x80)), 0x02008000);
+        assert_eq!(
+            encode_compact_bits(&BigInt::from(0x92340000u64)),
+            0x05009234
+        );
+        assert_eq!(encode_compact_bits(&BigInt::from(-0x12345600)), 0x04923456);
+        assert_eq!(encode_compact_bits(&BigInt::from(0x12345600)), 0x04123456);
+        // precision is limited to 3 bytes
+        assert_eq!(
+            decode_compact_bits(encode_compact_bits(&BigInt::from(0x123456789u64))),
+            BigInt::from(0x123450000u64)
+        );
+    }
+}
@@ -0,0 +1,613 @@
+//! Blockchain parameters adjustable by the miners voting
+//! see <https://github.com/ergoplatform/ergo/blob/master/papers/yellow/voting.md>
+
+use std::convert::TryFrom;
+
+use ergotree_ir::chain::header::Header;
+use ergotree_ir::chain::votes::Votes;
+use thiserror::Error;
+
+use crate::block_section::Extension;
+
+/// Parameters changeable by voting, the discriminant is the parameter id (vote byte)
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum Parameter {
+    /// Storage fee factor (per byte per storage period)
+    StorageFeeFactor = 1,
+    /// Minimum monetary value of a box per byte
+    MinValuePerByte = 2,
+    /// Maximum block size
+    MaxBlockSize = 3,
+    /// Maximum cumulative computational cost of the block transactions
+    MaxBlockCost = 4,
+    /// Token access cost
+    TokenAccessCost = 5,
+    /// Cost per input
+    InputCost = 6,
+    /// Cost per data input
+    DataInputCost = 7,
+    /// Cost per output
+    OutputCost = 8,
+    /// Block version (changed by the soft-fork voting)
+    BlockVersion = 123,
+}
+
+impl Parameter {
+    /// All the parameters
+    pub const ALL: [Parameter; 9] = [
+        Parameter::StorageFeeFactor,
+        Parameter::MinValuePerByte,
+        Parameter::MaxBlockSize,
+        Parameter::MaxBlockCost,
+        Parameter::TokenAccessCost,
+        Parameter::InputCost,
+        Parameter::DataInputCost,
+        Parameter::OutputCost,
+        Parameter::BlockVersion,
+    ];
+
+    /// Parameter id
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Value change on the approved vote, 1% of the current value unless set explicitly
+    pub fn step(self, current_value: i32) -> i32 {
+        match self {
+            Parameter::StorageFeeFactor => 25000,
+            Parameter::MinValuePerByte => 10,
+            Parameter::MaxBlockSize
+            | Parameter::MaxBlockCost
+            | Parameter::TokenAccessCost
+            | Parameter::InputCost
+            | Parameter::DataInputCost
+            | Parameter::OutputCost
+            | Parameter::BlockVersion => (current_value / 100).max(1),
+        }
+    }
+
+    /// Minimum value reachable by voting
+    pub fn min_value(self) -> i32 {
+        match self {
+            Parameter::MaxBlockSize | Parameter::MaxBlockCost => 16 * 1024,
+            Parameter::StorageFeeFactor
+            | Parameter::MinValuePerByte
+            | Parameter::TokenAccessCost
+            | Parameter::InputCost
+            | Parameter::DataInputCost
+            | Parameter::OutputCost
+            | Parameter::BlockVersion => 0,
+        }
+    }
+
+    /// Maximum value reachable by voting
+    pub fn max_value(self) -> i32 {
+        match self {
+            Parameter::StorageFeeFactor => 2_500_000,
+            Parameter::MinValuePerByte => 10_000,
+            Parameter::MaxBlockSize
+            | Parameter::MaxBlockCost
+            | Parameter::TokenAccessCost
+            | Parameter::InputCost
+            | Parameter::DataInputCost
+            | Parameter::OutputCost
+            | Parameter::BlockVersion => i32::MAX / 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for Parameter {
+    type Error = ParametersError;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        Parameter::ALL
+            .iter()
+            .find(|p| p.id() == id)
+            .copied()
+            .ok_or(ParametersError::UnknownParameter(id))
+    }
+}
+
+/// Decoded vote byte of the header
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Vote {
+    /// No vote (0)
+    NoVote,
+    /// Vote for the parameter increase (parameter id)
+    Increase(Parameter),
+    /// Vote for the parameter decrease (negated parameter id as a signed byte)
+    Decrease(Parameter),
+    /// Vote for the soft-fork (120)
+    SoftFork,
+}
+
+impl Vote {
+    /// Vote byte of the soft-fork
+    pub const SOFT_FORK: u8 = 120;
+
+    /// Encoded vote byte
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Vote::NoVote => 0,
+            Vote::Increase(p) => p.id(),
+            Vote::Decrease(p) => (-(p.id() as i8)) as u8,
+            Vote::SoftFork => Self::SOFT_FORK,
+        }
+    }
+}
+
+impl TryFrom<u8> for Vote {
+    type Error = ParametersError;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte as i8 {
+            0 => Ok(Vote::NoVote),
+            _ if byte == Self::SOFT_FORK => Ok(Vote::SoftFork),
+            s if s > 0 => changeable(byte).map(Vote::Increase),
+            s => changeable(s.unsigned_abs()).map(Vote::Decrease),
+        }
+        .map_err(|_| ParametersError::InvalidVote(byte))
+    }
+}
+
+/// Parameter changeable by the regular voting (the block version is changed by the soft-fork)
+fn changeable(id: u8) -> Result<Parameter, ParametersError> {
+    let parameter = Parameter::try_from(id)?;
+    if parameter == Parameter::BlockVersion {
+        Err(ParametersError::UnknownParameter(id))
+    } else {
+        Ok(parameter)
+    }
+}
+
+/// Decodes the votes of the header, the header can't vote twice for the same change
+pub fn decode_votes(votes: &Votes) -> Result<[Vote; 3], ParametersError> {
+    let decoded = [
+        Vote::try_from(votes.0[0])?,
+        Vote::try_from(votes.0[1])?,
+        Vote::try_from(votes.0[2])?,
+    ];
+    for (i, vote) in decoded.iter().enumerate() {
+        if *vote != Vote::NoVote && decoded[..i].contains(vote) {
+            return Err(ParametersError::DuplicateVote(vote.to_byte()));
+        }
+    }
+    Ok(decoded)
+}
+
+/// Errors of the parameters parsing and the votes decoding
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum ParametersError {
+    /// Unknown parameter id
+    #[error("unknown parameter id {0}")]
+    UnknownParameter(u8),
+    /// Vote byte is not a known parameter id (or its negation) or the soft-fork vote
+    #[error("invalid vote {0}")]
+    InvalidVote(u8),
+    /// Same vote is given twice in the header
+    #[error("duplicate vote {0}")]
+    DuplicateVote(u8),
+    /// Parameter is absent in the extension
+    #[error("parameter {0:?} is missing in the extension")]
+    MissingParameter(Parameter),
+    /// Parameter value is not a 4-byte integer
+    #[error("invalid value {value:?} of the parameter {id}")]
+    InvalidValue {
+        /// Parameter id
+        id: u8,
+        /// Field value
+        value: Vec<u8>,
+    },
+}
+
+/// Voting epochs settings
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct VotingSettings {
+    /// Length of the voting epoch in blocks
+    pub voting_length: u32,
+    /// Number of the voting epochs for the soft-fork
+    pub soft_fork_epochs: u32,
+    /// Number of epochs between the approved soft-fork and its activation
+    pub activation_epochs: u32,
+}
+
+impl VotingSettings {
+    /// Mainnet settings
+    pub fn mainnet() -> Self {
+        VotingSettings {
+            voting_length: 1024,
+            soft_fork_epochs: 32,
+            activation_epochs: 32,
+        }
+    }
+
+    /// Parameter change is approved by more than a half of the epoch blocks
+    pub fn change_approved(&self, count: u32) -> bool {
+        count > self.voting_length / 2
+    }
+}
+
+impl Default for VotingSettings {
+    fn default() -> Self {
+        VotingSettings::mainnet()
+    }
+}
+
+/// Votes collected during the voting epoch, as in the node only the changes proposed by the
+/// first block of the epoch are voted for
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct VoteTally {
+    /// Votes proposed at the epoch start with the number of blocks voted for them
+    pub votes: Vec<(Vote, u32)>,
+}
+
+impl VoteTally {
+    /// Tally of the votes of the epoch headers, the first one is the epoch start
+    pub fn from_headers<'a, I: IntoIterator<Item = &'a Header>>(
+        headers: I,
+    ) -> Result<Self, ParametersError> {
+        let mut headers = headers.into_iter();
+        let mut tally = match headers.next() {
+            Some(epoch_start) => VoteTally::start_epoch(&epoch_start.votes)?,
+            None => return Ok(VoteTally::default()),
+        };
+        for header in headers {
+            tally.add(&header.votes)?;
+        }
+        Ok(tally)
+    }
+
+    /// Tally of the epoch started by the header with the given votes (proposing the changes)
+    pub fn start_epoch(votes: &Votes) -> Result<Self, ParametersError> {
+        Ok(VoteTally {
+            votes: decode_votes(votes)?
+                .iter()
+                .filter(|vote| **vote != Vote::NoVote)
+                .map(|vote| (*vote, 1))
+                .collect(),
+        })
+    }
+
+    /// Counts the votes of the header, votes for the changes not proposed at the epoch start are
+    /// ignored
+    pub fn add(&mut self, votes: &Votes) -> Result<(), ParametersError> {
+        for vote in decode_votes(votes)?.iter() {
+            if let Some((_, count)) = self.votes.iter_mut().find(|(v, _)| v == vote) {
+                *count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of blocks voted for the vote
+    pub fn count(&self, vote: Vote) -> u32 {
+        self.votes
+            .iter()
+            .find(|(v, _)| *v == vote)
+            .map(|(_, count)| *count)
+            .unwrap_or(0)
+    }
+}
+
+/// Blockchain parameters, written to the extension of the first block of every voting epoch
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Parameters {
+    /// Height of the block the parameters are from
+    pub height: u32,
+    /// Storage fee factor (per byte per storage period)
+    pub storage_fee_factor: i32,
+    /// Minimum monetary value of a box per byte
+    pub min_value_per_byte: i32,
+    /// Maximum block size
+    pub max_block_size: i32,
+    /// Maximum cumulative computational cost of the block transactions
+    pub max_block_cost: i32,
+    /// Token access cost
+    pub token_access_cost: i32,
+    /// Cost per input
+    pub input_cost: i32,
+    /// Cost per data input
+    pub data_input_cost: i32,
+    /// Cost per output
+    pub output_cost: i32,
+    /// Block version
+    pub block_version: i32,
+}
+
+impl Default for Parameters {
+    /// Launch parameters of the mainnet
+    fn default() -> Self {
+        Parameters {
+            height: 0,
+            storage_fee_factor: 1_250_000,
+            min_value_per_byte: 30 * 12,
+            max_block_size: 512 * 1024,
+            max_block_cost: 1_000_000,
+            token_access_cost: 100,
+            input_cost: 2000,
+            data_input_cost: 100,
+            output_cost: 100,
+            block_version: Header::INITIAL_VERSION as i32,
+        }
+    }
+}
+
+impl Parameters {
+    /// First byte of the keys of the parameters fields in the extension
+    pub const SYSTEM_PARAMETERS_PREFIX: u8 = 0x00;
+
+    /// Value of the parameter
+    pub fn get(&self, parameter: Parameter) -> i32 {
+        match parameter {
+            Parameter::StorageFeeFactor => self.storage_fee_factor,
+            Parameter::MinValuePerByte => self.min_value_per_byte,
+            Parameter::MaxBlockSize => self.max_block_size,
+            Parameter::MaxBlockCost => self.max_block_cost,
+            Parameter::TokenAccessCost => self.token_access_cost,
+            Parameter::InputCost => self.input_cost,
+            Parameter::DataInputCost => self.data_input_cost,
+            Parameter::OutputCost => self.output_cost,
+            Parameter::BlockVersion => self.block_version,
+        }
+    }
+
+    /// Sets the value of the parameter
+    pub fn set(&mut self, parameter: Parameter, value: i32) {
+        let field = match parameter {
+            Parameter::StorageFeeFactor => &mut self.storage_fee_factor,
+            Parameter::MinValuePerByte => &mut self.min_value_per_byte,
+            Parameter::MaxBlockSize => &mut self.max_block_size,
+            Parameter::MaxBlockCost => &mut self.max_block_cost,
+            Parameter::TokenAccessCost => &mut self.token_access_cost,
+            Parameter::InputCost => &mut self.input_cost,
+            Parameter::DataInputCost => &mut self.data_input_cost,
+            Parameter::OutputCost => &mut self.output_cost,
+            Parameter::BlockVersion => &mut self.block_version,
+        };
+        *field = value;
+    }
+
+    /// Parses the parameters from the extension fields (key `[0x00, parameter id]`, value is a
+    /// big-endian 4-byte integer) of the block at the given height, all the parameters should be
+    /// present, unknown parameter ids are ignored
+    pub fn from_extension(height: u32, extension: &Extension) -> Result<Self, ParametersError> {
+        let mut parameters = Parameters {
+            height,
+            ..Parameters::default()
+        };
+        for parameter in Parameter::ALL.iter().copied() {
+            let value = extension
+                .field([Self::SYSTEM_PARAMETERS_PREFIX, parameter.id()])
+                .ok_or(ParametersError::MissingParameter(parameter))?;
+            let bytes = <[u8; 4]>::try_from(value.as_slice()).map_err(|_| {
+                ParametersError::InvalidValue {
+                    id: parameter.id(),
+                    value: value.clone(),
+                }
+            })?;
+            parameters.set(parameter, i32::from_be_bytes(bytes));
+        }
+        Ok(parameters)
+    }
+
+    /// Extension fields of the parameters
+    pub fn to_extension_fields(&self) -> Vec<([u8; Extension::FIELD_KEY_SIZE], Vec<u8>)> {
+        Parameter::ALL
+            .iter()
+            .map(|p| {
+                (
+                    [Self::SYSTEM_PARAMETERS_PREFIX, p.id()],
+                    self.get(*p).to_be_bytes().to_vec(),
+                )
+            })
+            .collect()
+    }
+
+    /// Parameters for the next voting epoch starting at the given height: every parameter with
+    /// the approved change is moved by its step (within the min/max bounds). Soft-fork votes are
+    /// not applied here.
+    pub fn update(&self, height: u32, tally: &VoteTally, settings: &VotingSettings) -> Self {
+        let mut next = Parameters {
+            height,
+            ..self.clone()
+        };
+        for (vote, count) in &tally.votes {
+            if !settings.change_approved(*count) {
+                continue;
+            }
+            match vote {
+                Vote::Increase(p) => {
+                    let current = next.get(*p);
+                    if current < p.max_value() {
+                        next.set(*p, current + p.step(current));
+                    }
+                }
+                Vote::Decrease(p) => {
+                    let current = next.get(*p);
+                    if current > p.min_value() {
+                        next.set(*p, current - p.step(current));
+                    }
+                }
+                Vote::NoVote | Vote::SoftFork => (),
+            }
+        }
+        next
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::autolykos_pow_scheme::tests::header_471746;
+    use ergotree_ir::chain::block_id::BlockId;
+    use ergotree_ir::chain::digest32::Digest32;
+    use ergotree_ir::chain::ergo_box::box_value::BoxValue;
+
+    #[test]
+    fn votes_decoding() {
+        assert_eq!(
+            decode_votes(&Votes([4, 0, 0])),
+            Ok([
+                Vote::Increase(Parameter::MaxBlockCost),
+                Vote::NoVote,
+                Vote::NoVote
+            ])
+        );
+        assert_eq!(
+            decode_votes(&Votes([0xfe, 120, 8])),
+            Ok([
+                Vote::Decrease(Parameter::MinValuePerByte),
+                Vote::SoftFork,
+                Vote::Increase(Parameter::OutputCost)
+            ])
+        );
+        assert_eq!(
+            decode_votes(&Votes([9, 0, 0])),
+            Err(ParametersError::InvalidVote(9))
+        );
+        assert_eq!(Vote::try_from(123), Err(ParametersError::InvalidVote(123)));
+        assert_eq!(
+            Vote::try_from(0x88),
+            Err(ParametersError::InvalidVote(0x88))
+        );
+        for byte in 0..=u8::MAX {
+            if let Ok(vote) = Vote::try_from(byte) {
+                assert_eq!(vote.to_byte(), byte);
+            }
+        }
+    }
+
+    #[test]
+    fn extension_roundtrip() {
+        assert_eq!(
+            Parameters::default().min_value_per_byte as u32,
+            BoxValue::MIN_VALUE_PER_BOX_BYTE
+        );
+        let parameters = Parameters {
+            height: 1024,
+            max_block_cost: 1_234_567,
+            block_version: 2,
+            ..Parameters::default()
+        };
+        let mut fields = vec![([1, 0], vec![1; 33])];
+        fields.extend(parameters.to_extension_fields());
+        let extension = Extension {
+            header_id: BlockId(Digest32::zero()),
+            fields,
+        };
+        assert_eq!(extension.field([0, 4]), Some(&vec![0x00, 0x12, 0xd6, 0x87]));
+        assert_eq!(Parameters::from_extension(1024, &extension), Ok(parameters));
+
+        let mut missing = extension.clone();
+        missing.fields.retain(|(k, _)| *k != [0, 123]);
+        assert_eq!(
+            Parameters::from_extension(1024, &missing),
+            Err(ParametersError::MissingParameter(Parameter::BlockVersion))
+        );
+        let mut invalid = extension;
+        invalid.fields[1].1 = vec![1, 2];
+        assert!(matches!(
+            Parameters::from_extension(1024, &invalid),
+            Err(ParametersError::InvalidValue { id: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn epoch_update() {
+        let settings = VotingSettings {
+            voting_length: 4,
+            ..VotingSettings::mainnet()
+        };
+        let votes = [
+            Votes([4, 0xfe, 0]),
+            Votes([4, 0xfe, 120]),
+            Votes([4, 1, 0]),
+            Votes([0, 0xfe, 3]),
+        ];
+        let mut tally = VoteTally::start_epoch(&votes[0]).unwrap();
+        votes[1..].iter().for_each(|v| tally.add(v).unwrap());
+        assert_eq!(tally.count(Vote::Increase(Parameter::MaxBlockCost)), 3);
+        assert_eq!(tally.count(Vote::Decrease(Parameter::MinValuePerByte)), 3);
+        // not proposed at the epoch start
+        assert_eq!(tally.count(Vote::Increase(Parameter::StorageFeeFactor)), 0);
+        assert_eq!(tally.count(Vote::SoftFork), 0);
+        assert_eq!(tally.count(Vote::Increase(Parameter::MaxBlockSize)), 0);
+        assert_eq!(tally.votes.len(), 2);
+
+        let parameters = Parameters::default();
+        let next = parameters.update(4, &tally, &settings);
+        assert_eq!(
+            next,
+            Parameters {
+                height: 4,
+                max_block_cost: 1_010_000,
+                min_value_per_byte: 350,
+                ..parameters
+            }
+        );
+        // bounds
+        let at_min = Parameters {
+            min_value_per_byte: 0,
+            ..Parameters::default()
+        };
+        assert_eq!(at_min.update(4, &tally, &settings).min_value_per_byte, 0);
+    }
+
+    #[test]
+    fn votes_not_proposed_at_epoch_start() {
+        let settings = VotingSettings {
+            voting_length: 4,
+            ..VotingSettings::mainnet()
+        };
+        let headers: Vec<Header> = [[0, 0, 0], [3, 0, 0], [3, 4, 0], [0, 3, 0]]
+            .iter()
+            .map(|votes| Header {
+                votes: Votes(*votes),
+                ..header_471746()
+            })
+            .collect();
+        let tally = VoteTally::from_headers(&headers).unwrap();
+        assert_eq!(tally, VoteTally::default());
+        let parameters = Parameters::default();
+        assert_eq!(
+            parameters.update(4, &tally, &settings),
+            Parameters {
+                height: 4,
+                ..parameters
+            }
+        );
+
+        let proposed = VoteTally::from_headers(&headers[1..]).unwrap();
+        assert_eq!(
+            proposed.votes,
+            vec![(Vote::Increase(Parameter::MaxBlockSize), 3)]
+        );
+        assert_eq!(VoteTally::from_headers(&[]), Ok(VoteTally::default()));
+    }
+
+    #[test]
+    fn duplicate_votes() {
+        assert_eq!(
+            decode_votes(&Votes([4, 4, 0])),
+            Err(ParametersError::DuplicateVote(4))
+        );
+        assert_eq!(
+            decode_votes(&Votes([120, 0, 120])),
+            Err(ParametersError::DuplicateVote(120))
+        );
+        assert!(decode_votes(&Votes([4, 0xfc, 0])).is_ok());
+        assert!(decode_votes(&Votes([0, 0, 4])).is_ok());
+        assert_eq!(
+            VoteTally::start_epoch(&Votes([0, 4, 4])),
+            Err(ParametersError::DuplicateVote(4))
+        );
+        let mut tally = VoteTally::start_epoch(&Votes([4, 0, 0])).unwrap();
+        assert_eq!(
+            tally.add(&Votes([4, 4, 0])),
+            Err(ParametersError::DuplicateVote(4))
+        );
+        assert_eq!(tally.count(Vote::Increase(Parameter::MaxBlockCost)), 1);
+    }
+}
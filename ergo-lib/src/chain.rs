@@ -3,7 +3,9 @@
 #[cfg(feature = "json")]
 pub mod json;
 
+pub mod block_transactions;
 pub mod contract;
+pub mod contract_registry;
 pub mod ergo_box;
 pub mod ergo_state_context;
 pub mod transaction;
@@ -0,0 +1,350 @@
+//! Block transactions section
+
+use std::convert::TryFrom;
+
+use ergo_chain_types::block_section::check_digest;
+use ergo_chain_types::block_section::check_header_id;
+use ergo_chain_types::block_section::compute_section_id;
+use ergo_chain_types::block_section::BlockSectionError;
+use ergo_chain_types::block_section::BLOCK_TRANSACTIONS_TYPE_ID;
+use ergo_chain_types::merkle_tree::BatchMerkleProof;
+use ergo_chain_types::merkle_tree::MerkleProof;
+use ergo_chain_types::merkle_tree::MerkleTree;
+use ergotree_ir::chain::block_id::BlockId;
+use ergotree_ir::chain::digest32::blake2b256_hash;
+use ergotree_ir::chain::digest32::Digest32;
+use ergotree_ir::chain::header::Header;
+use ergotree_ir::serialization::sigma_byte_reader::SigmaByteRead;
+use ergotree_ir::serialization::sigma_byte_writer::SigmaByteWrite;
+use ergotree_ir::serialization::SigmaParsingError;
+use ergotree_ir::serialization::SigmaSerializable;
+use ergotree_ir::serialization::SigmaSerializeResult;
+
+use super::transaction::Transaction;
+use super::transaction::TxId;
+
+/// Transactions of the block
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Debug, Clone)]
+pub struct BlockTransactions {
+    /// Id of the header of the block
+    #[cfg_attr(feature = "json", serde(rename = "headerId"))]
+    pub header_id: BlockId,
+    /// Block version, the transactions root depends on it
+    #[cfg_attr(feature = "json", serde(rename = "blockVersion"))]
+    pub block_version: u8,
+    /// Transactions
+    #[cfg_attr(feature = "json", serde(rename = "transactions"))]
+    pub transactions: Vec<Transaction>,
+}
+
+impl BlockTransactions {
+    /// Block versions are encoded in place of the transactions count (for versions > 1) as
+    /// `MAX_TRANSACTIONS_IN_BLOCK + version`
+    pub const MAX_TRANSACTIONS_IN_BLOCK: u32 = 10_000_000;
+
+    /// Merkle tree root of the transactions (`transaction_root` in the header), the leaves are
+    /// transaction ids and (for block version > 1) witness ids
+    pub fn digest(&self) -> Digest32 {
+        self.merkle_tree().root_hash()
+    }
+
+    /// Merkle tree of the transactions, the leaves are transaction ids and (for block
+    /// version > 1) witness ids
+    pub fn merkle_tree(&self) -> MerkleTree {
+        let mut leaves: Vec<Vec<u8>> = self
+            .transactions
+            .iter()
+            .map(|tx| tx.id().0 .0.to_vec())
+            .collect();
+        if self.block_version > Header::INITIAL_VERSION {
+            leaves.extend(self.transactions.iter().map(witness_id));
+        }
+        MerkleTree::new(&leaves)
+    }
+
+    /// Proof of inclusion of the transaction with the given id (checked with
+    /// [`verify_tx_inclusion`])
+    pub fn proof_for(&self, tx_id: &TxId) -> Option<MerkleProof> {
+        self.merkle_tree().proof_by_element(&tx_id.0 .0[..])
+    }
+
+    /// Compact proof of inclusion of the transactions with the given ids, `None` if any of them
+    /// is not in the block
+    pub fn batch_proof_for(&self, tx_ids: &[TxId]) -> Option<BatchMerkleProof> {
+        let indices = tx_ids
+            .iter()
+            .map(|id| self.transactions.iter().position(|tx| &tx.id() == id))
+            .collect::<Option<Vec<usize>>>()?;
+        self.merkle_tree().proof_by_indices(&indices)
+    }
+
+    /// Section id
+    pub fn id(&self) -> Digest32 {
+        compute_section_id(BLOCK_TRANSACTIONS_TYPE_ID, &self.header_id, &self.digest())
+    }
+
+    /// Checks that the section belongs to the header and its digest is `transaction_root`
+    pub fn check_header(&self, header: &Header) -> Result<(), BlockSectionError> {
+        check_header_id(&header.id, &self.header_id)?;
+        check_digest(&header.transaction_root, self.digest())
+    }
+}
+
+/// Checks that the proof is for the transaction with the given id and leads to
+/// `transaction_root` of the header
+pub fn verify_tx_inclusion(proof: &MerkleProof, tx_id: &TxId, header: &Header) -> bool {
+    proof.leaf_data.as_slice() == &tx_id.0 .0[..] && proof.valid(&header.transaction_root)
+}
+
+/// Commitment to the spending proofs of the transaction, hash of the concatenated input proofs
+/// without the first byte (248 bits, to distinguish it from the transaction id in the tree)
+pub fn witness_id(tx: &Transaction) -> Vec<u8> {
+    let proofs: Vec<u8> = tx
+        .inputs
+        .iter()
+        .flat_map(|input| Vec::<u8>::from(input.spending_proof.proof.clone()))
+        .collect();
+    blake2b256_hash(&proofs).0[1..].to_vec()
+}
+
+impl SigmaSerializable for BlockTransactions {
+    fn sigma_serialize<W: SigmaByteWrite>(&self, w: &mut W) -> SigmaSerializeResult {
+        self.header_id.0.sigma_serialize(w)?;
+        if self.block_version > Header::INITIAL_VERSION {
+            w.put_u32(Self::MAX_TRANSACTIONS_IN_BLOCK + self.block_version as u32)?;
+        }
+        w.put_usize_as_u32_unwrapped(self.transactions.len())?;
+        self.transactions
+            .iter()
+            .try_for_each(|tx| tx.sigma_serialize(w))
+    }
+
+    fn sigma_parse<R: SigmaByteRead>(r: &mut R) -> Result<Self, SigmaParsingError> {
+        let header_id = BlockId(Digest32::sigma_parse(r)?);
+        let version_or_count = r.get_u32()?;
+        let (block_version, count) = if version_or_count > Self::MAX_TRANSACTIONS_IN_BLOCK {
+            let version = u8::try_from(version_or_count - Self::MAX_TRANSACTIONS_IN_BLOCK)
+                .map_err(|_| SigmaParsingError::ValueOutOfBounds("block version".to_string()))?;
+            (version, r.get_u32()?)
+        } else {
+            (Header::INITIAL_VERSION, version_or_count)
+        };
+        if count > Self::MAX_TRANSACTIONS_IN_BLOCK {
+            return Err(SigmaParsingError::ValueOutOfBounds(
+                "transactions count".to_string(),
+            ));
+        }
+        let transactions = (0..count)
+            .map(|_| Transaction::sigma_parse(r))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BlockTransactions {
+            header_id,
+            block_version,
+            transactions,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use ergo_chain_types::merkle_tree::internal_node_hash;
+    use ergo_chain_types::merkle_tree::leaf_hash;
+    use ergotree_ir::serialization::sigma_serialize_roundtrip;
+
+    /// Mainnet transaction 0e6acf3f18b95bdc5bb1b060baa1eafe53bd89fb08b0e86d6cc00fbdd9e43189
+    fn mainnet_tx() -> Transaction {
+        let json = r#"
+        {
+          "id": "0e6acf3f18b95bdc5bb1b060baa1eafe53bd89fb08b0e86d6cc00fbdd9e43189",
+          "inputs": [
+            {
+              "boxId": "f353ae1b2027e40ea318e7a2673ea4bbaa281b7acee518a0994c5cbdefb05f55",
+              "spendingProof": {
+                "proofBytes": "",
+                "extension": {}
+              }
+            },
+            {
+              "boxId": "56111b039b86f71004b768d2e8b4579f1d79e28e7a617fd5add57a5239498c26",
+              "spendingProof": {
+                "proofBytes": "6542a8b8914b103dcbc36d77da3bd58e42ca35755a5190b507764b0bae330b924ce86acfa1b5f9bfc8216c3c4628738e8274d902bea06b48",
+                "extension": {}
+              }
+            }
+          ],
+          "dataInputs": [
+            {
+              "boxId": "e26d41ed030a30cd563681e72f0b9c07825ac983f8c253a87a43c1da21958ece"
+            }
+          ],
+          "outputs": [
+            {
+              "boxId": "55be517150fcb7f0f1661ad3ab30f1ac62084b83ad6aa772579bc06cbb52832e",
+              "value": 1000000,
+              "ergoTree": "100604000400050004000e20b662db51cf2dc39f110a021c2a31c74f0a1a18ffffbf73e8a051a7b8c0f09ebc0e2079974b2314c531e62776e6bc4babff35b37b178cebf0976fc0f416ff34ddbc4fd803d601b2a5730000d602e4c6a70407d603b2db6501fe730100ea02d1ededededed93e4c672010407720293e4c67201050ec5720391e4c672010605730293c27201c2a793db63087201db6308a7ed938cb2db6308720373030001730493cbc272037305cd7202",
+              "assets": [
+                {
+                  "tokenId": "12caaacb51c89646fac9a3786eb98d0113bd57d68223ccc11754a4f67281daed",
+                  "amount": 1
+                }
+              ],
+              "creationHeight": 299218,
+              "additionalRegisters": {
+                "R4": "070327e65711a59378c59359c3e1d0f7abe906479eccb76094e50fe79d743ccc15e6",
+                "R5": "0e20e26d41ed030a30cd563681e72f0b9c07825ac983f8c253a87a43c1da21958ece",
+                "R6": "05feaff5de0f"
+              },
+              "transactionId": "0e6acf3f18b95bdc5bb1b060baa1eafe53bd89fb08b0e86d6cc00fbdd9e43189",
+              "index": 0
+            },
+            {
+              "boxId": "fa4a484c855d32a60987a4ddcf1c506aa6bab1c4cb0293c2d5ff35fcd11f2c7b",
+              "value": 1000000,
+              "ergoTree": "1005040004000e36100204a00b08cd0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798ea02d192a39a8cc7a701730073011001020402d19683030193a38cc7b2a57300000193c2b2a57301007473027303830108cdeeac93b1a57304",
+              "assets": [],
+              "creationHeight": 299218,
+              "additionalRegisters": {},
+              "transactionId": "0e6acf3f18b95bdc5bb1b060baa1eafe53bd89fb08b0e86d6cc00fbdd9e43189",
+              "index": 1
+            },
+            {
+              "boxId": "3dee27d0dfb193fd6a263cf2b5b58cab99cb640d1443cd1ce63d909ad3a54197",
+              "value": 44516500000,
+              "ergoTree": "0008cd0327e65711a59378c59359c3e1d0f7abe906479eccb76094e50fe79d743ccc15e6",
+              "assets": [],
+              "creationHeight": 299218,
+              "additionalRegisters": {},
+              "transactionId": "0e6acf3f18b95bdc5bb1b060baa1eafe53bd89fb08b0e86d6cc00fbdd9e43189",
+              "index": 2
+            }
+          ],
+          "size": 673
+        }
+        "#;
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn digest() {
+        let tx = mainnet_tx();
+        let v1 = BlockTransactions {
+            header_id: BlockId(Digest32::zero()),
+            block_version: 1,
+            transactions: vec![tx.clone()],
+        };
+        assert_eq!(
+            v1.digest(),
+            internal_node_hash(&leaf_hash(&tx.id().0 .0[..]), None)
+        );
+        let v2 = BlockTransactions {
+            block_version: 2,
+            ..v1
+        };
+        assert_eq!(
+            v2.digest(),
+            internal_node_hash(
+                &leaf_hash(&tx.id().0 .0[..]),
+                Some(&leaf_hash(&witness_id(&tx)))
+            )
+        );
+        assert_eq!(witness_id(&tx).len(), 31);
+    }
+
+    #[test]
+    fn roundtrip_and_check_header() {
+        let mut block_txs = BlockTransactions {
+            header_id: BlockId(Digest32::from([1u8; 32])),
+            block_version: 2,
+            transactions: vec![mainnet_tx(), mainnet_tx()],
+        };
+        assert_eq!(sigma_serialize_roundtrip(&block_txs), block_txs);
+        let json = serde_json::to_string(&block_txs).unwrap();
+        assert_eq!(
+            serde_json::from_str::<BlockTransactions>(&json).unwrap(),
+            block_txs
+        );
+        block_txs.block_version = 1;
+        assert_eq!(sigma_serialize_roundtrip(&block_txs), block_txs);
+
+        let header = Header {
+            version: 1,
+            id: block_txs.header_id.clone(),
+            parent_id: BlockId(Digest32::zero()),
+            ad_proofs_root: Digest32::zero(),
+            state_root: ergotree_ir::chain::digest32::ADDigest::zero(),
+            transaction_root: block_txs.digest(),
+            timestamp: 0,
+            n_bits: 0,
+            height: 1,
+            extension_root: Digest32::zero(),
+            miner_pk: Box::new(ergotree_ir::sigma_protocol::dlog_group::generator()),
+            pow_onetime_pk: Box::new(ergotree_ir::sigma_protocol::dlog_group::generator()),
+            nonce: vec![0; 8],
+            pow_distance: 0.into(),
+            votes: ergotree_ir::chain::votes::Votes([0, 0, 0]),
+        };
+        assert_eq!(block_txs.check_header(&header), Ok(()));
+        let other_header = Header {
+            id: BlockId(Digest32::zero()),
+            ..header.clone()
+        };
+        assert!(matches!(
+            block_txs.check_header(&other_header),
+            Err(BlockSectionError::HeaderIdMismatch { .. })
+        ));
+        block_txs.transactions.pop();
+        assert!(matches!(
+            block_txs.check_header(&header),
+            Err(BlockSectionError::DigestMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn tx_inclusion_proofs() {
+        let tx = mainnet_tx();
+        let block_txs = BlockTransactions {
+            header_id: BlockId(Digest32::zero()),
+            block_version: 2,
+            transactions: vec![tx.clone()],
+        };
+        let header = Header {
+            version: 2,
+            id: BlockId(Digest32::zero()),
+            parent_id: BlockId(Digest32::zero()),
+            ad_proofs_root: Digest32::zero(),
+            state_root: ergotree_ir::chain::digest32::ADDigest::zero(),
+            transaction_root: block_txs.digest(),
+            timestamp: 0,
+            n_bits: 0,
+            height: 1,
+            extension_root: Digest32::zero(),
+            miner_pk: Box::new(ergotree_ir::sigma_protocol::dlog_group::generator()),
+            pow_onetime_pk: Box::new(ergotree_ir::sigma_protocol::dlog_group::generator()),
+            nonce: vec![0; 8],
+            pow_distance: 0.into(),
+            votes: ergotree_ir::chain::votes::Votes([0, 0, 0]),
+        };
+        let proof = block_txs.proof_for(&tx.id()).unwrap();
+        // the witness id is the sibling of the transaction id
+        assert_eq!(proof.levels[0].hash, Some(leaf_hash(&witness_id(&tx))));
+        assert!(verify_tx_inclusion(&proof, &tx.id(), &header));
+        assert!(!verify_tx_inclusion(
+            &proof,
+            &TxId(Digest32::zero()),
+            &header
+        ));
+        let other_header = Header {
+            transaction_root: Digest32::zero(),
+            ..header
+        };
+        assert!(!verify_tx_inclusion(&proof, &tx.id(), &other_header));
+        assert_eq!(block_txs.proof_for(&TxId(Digest32::zero())), None);
+
+        let batch = block_txs.batch_proof_for(&[tx.id()]).unwrap();
+        assert!(batch.valid(&block_txs.digest()));
+        assert_eq!(block_txs.batch_proof_for(&[TxId(Digest32::zero())]), None);
+    }
+}
@@ -0,0 +1,464 @@
+//! Identification of the contract kind by its ErgoTree template
+//!
+//! Trees are compared by the hash of their template (the root expression with all the constants
+//! segregated), so trees built with and without constant segregation of the same script share
+//! the template. The values of the segregated constants are the parameters of the contract.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use ergotree_ir::chain::address::Address;
+use ergotree_ir::chain::address::AddressEncoder;
+use ergotree_ir::chain::address::NetworkPrefix;
+use ergotree_ir::chain::digest32::blake2b256_hash;
+use ergotree_ir::chain::digest32::Digest32;
+use ergotree_ir::ergo_tree::ErgoTree;
+use ergotree_ir::ergo_tree::ErgoTreeError;
+use ergotree_ir::ergo_tree::ErgoTreeHeader;
+use ergotree_ir::mir::bin_op::BinOp;
+use ergotree_ir::mir::bin_op::RelationOp;
+use ergotree_ir::mir::bool_to_sigma::BoolToSigmaProp;
+use ergotree_ir::mir::calc_blake2b256::CalcBlake2b256;
+use ergotree_ir::mir::coll_slice::Slice;
+use ergotree_ir::mir::constant::Constant;
+use ergotree_ir::mir::constant::TryExtractInto;
+use ergotree_ir::mir::deserialize_context::DeserializeContext;
+use ergotree_ir::mir::get_var::GetVar;
+use ergotree_ir::mir::option_get::OptionGet;
+use ergotree_ir::mir::sigma_and::SigmaAnd;
+use ergotree_ir::mir::unary_op::OneArgOpTryBuild;
+use ergotree_ir::sigma_protocol::dlog_group;
+use ergotree_ir::sigma_protocol::sigma_boolean::ProveDlog;
+use ergotree_ir::types::stype::SType;
+use thiserror::Error;
+
+use crate::constants::EMISSION_MAINNET_ADDRESS;
+use crate::constants::FOUNDATION_MAINNET_ADDRESS;
+use crate::constants::MINERS_FEE_MAINNET_ADDRESS;
+
+/// Kind of the contract
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+pub enum ContractKind {
+    /// Pay-to-public-key (parameter `pk`)
+    P2Pk,
+    /// Pay-to-script-hash (parameter `scriptHash`)
+    P2Sh,
+    /// Miner fee contract
+    MinerFee,
+    /// Emission contract
+    Emission,
+    /// Foundation contract
+    Foundation,
+    /// Contract registered by the user with the given name
+    Registered(String),
+}
+
+/// Named parameter of the contract (segregated constant of the tree)
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ContractParameter {
+    /// Name
+    pub name: String,
+    /// Value
+    pub value: Constant,
+}
+
+/// Identified contract
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ContractDescription {
+    /// Kind of the contract
+    pub kind: ContractKind,
+    /// Hash of the contract template (see [`template_hash`])
+    pub template_hash: Digest32,
+    /// Values of the named parameters, in the order of the segregated constants
+    pub parameters: Vec<ContractParameter>,
+}
+
+impl ContractDescription {
+    /// Value of the parameter with the given name
+    pub fn parameter(&self, name: &str) -> Option<&Constant> {
+        self.parameters
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| &p.value)
+    }
+
+    /// P2PK and P2SH address of the contract (`None` for other contract kinds)
+    pub fn address(&self) -> Option<Address> {
+        match self.kind {
+            ContractKind::P2Pk => self
+                .parameter(P2PK_PARAM)
+                .and_then(|c| ProveDlog::try_from(c.v.clone()).ok())
+                .map(Address::P2Pk),
+            ContractKind::P2Sh => self
+                .parameter(P2SH_PARAM)
+                .and_then(|c| c.clone().try_extract_into::<Vec<u8>>().ok())
+                .and_then(|bytes| <[u8; 24]>::try_from(bytes).ok())
+                .map(Address::P2SH),
+            ContractKind::MinerFee
+            | ContractKind::Emission
+            | ContractKind::Foundation
+            | ContractKind::Registered(_) => None,
+        }
+    }
+}
+
+/// Errors of the contract registration and identification
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum ContractRegistryError {
+    /// ErgoTree parsing or serialization error
+    #[error("ErgoTree error: {0:?}")]
+    ErgoTreeError(#[from] ErgoTreeError),
+    /// Number of the parameter names does not match the number of the constants in the tree
+    #[error("expected {expected} parameter names (one per constant), got {actual}")]
+    ParameterCountMismatch {
+        /// Number of the constants in the tree
+        expected: usize,
+        /// Number of the provided names
+        actual: usize,
+    },
+}
+
+const P2PK_PARAM: &str = "pk";
+const P2SH_PARAM: &str = "scriptHash";
+
+/// Registered template
+#[derive(PartialEq, Eq, Debug, Clone)]
+struct TemplateEntry {
+    kind: ContractKind,
+    /// Parameter name for each segregated constant, `None` for the constants that are part of
+    /// the template (should have the same values as in the registered tree)
+    names: Vec<Option<String>>,
+    /// Constants of the registered tree
+    constants: Vec<Constant>,
+}
+
+/// Registry of the contract templates
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct ContractRegistry {
+    templates: HashMap<Digest32, TemplateEntry>,
+}
+
+impl ContractRegistry {
+    /// Empty registry
+    pub fn new() -> Self {
+        ContractRegistry::default()
+    }
+
+    /// Registry with the standard contracts: P2PK, P2SH, the miner fee and the emission and
+    /// foundation contracts of the mainnet genesis boxes.
+    pub fn with_standard_contracts() -> Self {
+        let mut registry = ContractRegistry::new();
+        #[allow(clippy::unwrap_used)]
+        // the trees are built from the valid addresses, have the expected number of constants
+        {
+            let p2pk = Address::P2Pk(ProveDlog::new(dlog_group::generator()));
+            registry
+                .register(
+                    ContractKind::P2Pk,
+                    &p2pk.script().unwrap(),
+                    vec![Some(P2PK_PARAM.to_string())],
+                )
+                .unwrap();
+            // slice bounds (0, 24) are part of the template
+            registry
+                .register(
+                    ContractKind::P2Sh,
+                    &p2sh_tree([0u8; 24]).unwrap(),
+                    vec![None, None, Some(P2SH_PARAM.to_string())],
+                )
+                .unwrap();
+            // all the constants are part of the template
+            for (kind, address) in [
+                (ContractKind::MinerFee, MINERS_FEE_MAINNET_ADDRESS),
+                (ContractKind::Emission, EMISSION_MAINNET_ADDRESS),
+                (ContractKind::Foundation, FOUNDATION_MAINNET_ADDRESS),
+            ] {
+                let tree = AddressEncoder::new(NetworkPrefix::Mainnet)
+                    .parse_address_from_str(address)
+                    .unwrap()
+                    .script()
+                    .unwrap();
+                let names = vec![None; segregated(&tree).unwrap().constants_len().unwrap()];
+                registry.register(kind, &tree, names).unwrap();
+            }
+        }
+        registry
+    }
+
+    /// Registers the template of the `tree` as the contract of the given `kind`.
+    /// `names` has a parameter name for each constant of the tree (in the order of the
+    /// constants in the tree with all the constants segregated), or `None` if the constant is
+    /// part of the template, i.e. the trees of this kind have the same value there.
+    /// Returns the template hash.
+    pub fn register(
+        &mut self,
+        kind: ContractKind,
+        tree: &ErgoTree,
+        names: Vec<Option<String>>,
+    ) -> Result<Digest32, ContractRegistryError> {
+        let segregated = segregated(tree)?;
+        let constants = constants(&segregated)?;
+        if names.len() != constants.len() {
+            return Err(ContractRegistryError::ParameterCountMismatch {
+                expected: constants.len(),
+                actual: names.len(),
+            });
+        }
+        let hash = blake2b256_hash(&segregated.template_bytes()?);
+        self.templates.insert(
+            hash.clone(),
+            TemplateEntry {
+                kind,
+                names,
+                constants,
+            },
+        );
+        Ok(hash)
+    }
+
+    /// Identifies the contract of the tree, returns `None` if the tree's template is not
+    /// registered (or the constants that are part of the template have different values)
+    pub fn identify(
+        &self,
+        tree: &ErgoTree,
+    ) -> Result<Option<ContractDescription>, ContractRegistryError> {
+        let segregated = segregated(tree)?;
+        let template_hash = blake2b256_hash(&segregated.template_bytes()?);
+        let entry = match self.templates.get(&template_hash) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let mut parameters = Vec::new();
+        for ((name, expected), value) in entry
+            .names
+            .iter()
+            .zip(entry.constants.iter())
+            .zip(constants(&segregated)?)
+        {
+            match name {
+                Some(name) => parameters.push(ContractParameter {
+                    name: name.clone(),
+                    value,
+                }),
+                None if value != *expected => return Ok(None),
+                None => (),
+            }
+        }
+        let description = ContractDescription {
+            kind: entry.kind.clone(),
+            template_hash,
+            parameters,
+        };
+        // any SigmaProp constant fits the P2PK template
+        if description.kind == ContractKind::P2Pk && description.address().is_none() {
+            return Ok(None);
+        }
+        Ok(Some(description))
+    }
+}
+
+/// Hash (Blake2b256) of the template of the tree with all the constants segregated
+pub fn template_hash(tree: &ErgoTree) -> Result<Digest32, ErgoTreeError> {
+    Ok(blake2b256_hash(&segregated(tree)?.template_bytes()?))
+}
+
+/// P2SH tree as the node builds it,
+/// `sigmaProp(blake2b256(getVar[Coll[Byte]](1).get).slice(0, 24) == scriptHash) && executeFromVar[SigmaProp](1)`
+fn p2sh_tree(script_hash: [u8; 24]) -> Result<ErgoTree, ErgoTreeError> {
+    #[allow(clippy::unwrap_used)]
+    // the operand types are fixed and valid
+    let script_bytes = OptionGet::try_build(
+        GetVar {
+            var_id: 1,
+            var_tpe: SType::SColl(Box::new(SType::SByte)),
+        }
+        .into(),
+    )
+    .unwrap();
+    let hash_equals = BinOp {
+        kind: RelationOp::Eq.into(),
+        left: Box::new(
+            Slice {
+                input: Box::new(
+                    CalcBlake2b256 {
+                        input: Box::new(script_bytes.into()),
+                    }
+                    .into(),
+                ),
+                from: Box::new(0i32.into()),
+                until: Box::new(24i32.into()),
+            }
+            .into(),
+        ),
+        right: Box::new(Constant::from(script_hash.to_vec()).into()),
+    };
+    let script_is_correct = DeserializeContext {
+        tpe: SType::SSigmaProp,
+        id: 1,
+    };
+    #[allow(clippy::unwrap_used)]
+    // two items
+    let expr = SigmaAnd::new(vec![
+        BoolToSigmaProp {
+            input: Box::new(hash_equals.into()),
+        }
+        .into(),
+        script_is_correct.into(),
+    ])
+    .unwrap();
+    ErgoTree::new(ErgoTreeHeader::v0(false), &expr.into())
+}
+
+/// The same tree with all the constants segregated
+fn segregated(tree: &ErgoTree) -> Result<ErgoTree, ErgoTreeError> {
+    ErgoTree::new(ErgoTreeHeader::v0(true), &*tree.proposition()?)
+}
+
+fn constants(tree: &ErgoTree) -> Result<Vec<Constant>, ErgoTreeError> {
+    let len = tree
+        .constants_len()
+        .map_err(ErgoTreeError::ConstantsParsingError)?;
+    (0..len)
+        .filter_map(|i| tree.get_constant(i).transpose())
+        .collect::<Result<Vec<Constant>, _>>()
+        .map_err(ErgoTreeError::ConstantsParsingError)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::constants::MINERS_FEE_TESTNET_ADDRESS;
+    use ergotree_ir::mir::expr::Expr;
+    use ergotree_ir::mir::global_vars::GlobalVars;
+    use ergotree_ir::serialization::SigmaSerializable;
+    use ergotree_ir::sigma_protocol::sigma_boolean::SigmaBoolean;
+    use ergotree_ir::sigma_protocol::sigma_boolean::SigmaProp;
+
+    fn height_ge(height: i32, segregated: bool) -> ErgoTree {
+        let expr: Expr = BoolToSigmaProp {
+            input: Box::new(
+                BinOp {
+                    kind: RelationOp::Ge.into(),
+                    left: Box::new(GlobalVars::Height.into()),
+                    right: Box::new(height.into()),
+                }
+                .into(),
+            ),
+        }
+        .into();
+        ErgoTree::new(ErgoTreeHeader::v0(segregated), &expr).unwrap()
+    }
+
+    #[test]
+    fn p2pk() {
+        let registry = ContractRegistry::with_standard_contracts();
+        let pk = ProveDlog::new(dlog_group::exponentiate(
+            &dlog_group::generator(),
+            &dlog_group::random_scalar_in_group_range(rand::thread_rng()),
+        ));
+        let tree = Address::P2Pk(pk.clone()).script().unwrap();
+        let description = registry.identify(&tree).unwrap().unwrap();
+        assert_eq!(description.kind, ContractKind::P2Pk);
+        assert_eq!(description.parameter("pk"), Some(&pk.clone().into()));
+        assert_eq!(description.address(), Some(Address::P2Pk(pk)));
+        let trivial = ErgoTree::try_from(Expr::Const(
+            SigmaProp::new(SigmaBoolean::TrivialProp(true)).into(),
+        ))
+        .unwrap();
+        assert_eq!(registry.identify(&trivial).unwrap(), None);
+    }
+
+    #[test]
+    fn p2sh() {
+        let registry = ContractRegistry::with_standard_contracts();
+        // tree of 8UApt8czfFVuTgQmMwtsRBZ4nfWquNiSwCWUjMg built by the node
+        let tree = ErgoTree::sigma_parse_bytes(&base16::decode("00ea02d193b4cbe4e3010e040004300e18d62151f990f191c102a6fe995b89ed3d0f343a96f13789a3d40801").unwrap()).unwrap();
+        let address = AddressEncoder::new(NetworkPrefix::Mainnet)
+            .parse_address_from_str("8UApt8czfFVuTgQmMwtsRBZ4nfWquNiSwCWUjMg")
+            .unwrap();
+        let description = registry.identify(&tree).unwrap().unwrap();
+        assert_eq!(description.kind, ContractKind::P2Sh);
+        assert_eq!(description.parameters.len(), 1);
+        assert_eq!(description.address(), Some(address));
+        let address = Address::P2SH([7u8; 24]);
+        let description = registry
+            .identify(&p2sh_tree([7u8; 24]).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(description.address(), Some(address));
+    }
+
+    #[test]
+    fn miner_fee() {
+        let registry = ContractRegistry::with_standard_contracts();
+        // the same script on both networks
+        let tree = AddressEncoder::new(NetworkPrefix::Testnet)
+            .parse_address_from_str(MINERS_FEE_TESTNET_ADDRESS)
+            .unwrap()
+            .script()
+            .unwrap();
+        let description = registry.identify(&tree).unwrap().unwrap();
+        assert_eq!(description.kind, ContractKind::MinerFee);
+        assert!(description.parameters.is_empty());
+        assert_eq!(description.address(), None);
+    }
+
+    #[test]
+    fn emission_and_foundation() {
+        let registry = ContractRegistry::with_standard_contracts();
+        // trees of the mainnet genesis boxes
+        let emission = "101004020e36100204a00b08cd0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798ea02d192a39a8cc7a7017300730110010204020404040004c0fd4f05808c82f5f6030580b8c9e5ae040580f882ad16040204c0944004c0f407040004000580f882ad16d19683030191a38cc7a7019683020193c2b2a57300007473017302830108cdeeac93a38cc7b2a573030001978302019683040193b1a5730493c2a7c2b2a573050093958fa3730673079973089c73097e9a730a9d99a3730b730c0599c1a7c1b2a5730d00938cc7b2a5730e0001a390c1a7730f";
+        let foundation = "100e040004c094400580809cde91e7b0010580acc7f03704be944004808948058080c7b7e4992c0580b4c4c32104fe884804c0fd4f0580bcc1960b04befd4f05000400ea03d192c1b2a5730000958fa373019a73029c73037e997304a305958fa373059a73069c73077e997308a305958fa373099c730a7e99730ba305730cd193c2a7c2b2a5730d00d5040800";
+        for (tree_hex, kind) in [
+            (emission, ContractKind::Emission),
+            (foundation, ContractKind::Foundation),
+        ] {
+            let tree = ErgoTree::sigma_parse_bytes(&base16::decode(tree_hex).unwrap()).unwrap();
+            let description = registry.identify(&tree).unwrap().unwrap();
+            assert_eq!(description.kind, kind);
+            assert!(description.parameters.is_empty());
+            assert_eq!(description.address(), None);
+        }
+    }
+
+    #[test]
+    fn registered() {
+        let mut registry = ContractRegistry::new();
+        let kind = ContractKind::Registered("timelock".to_string());
+        assert_eq!(
+            registry.register(kind.clone(), &height_ge(100, true), vec![]),
+            Err(ContractRegistryError::ParameterCountMismatch {
+                expected: 1,
+                actual: 0
+            })
+        );
+        let hash = registry
+            .register(
+                kind.clone(),
+                &height_ge(100, true),
+                vec![Some("deadline".to_string())],
+            )
+            .unwrap();
+        // not segregated tree has the same template
+        let tree = height_ge(500, false);
+        assert_eq!(template_hash(&tree).unwrap(), hash);
+        assert_eq!(
+            registry.identify(&tree).unwrap(),
+            Some(ContractDescription {
+                kind,
+                template_hash: hash,
+                parameters: vec![ContractParameter {
+                    name: "deadline".to_string(),
+                    value: 500i32.into(),
+                }],
+            })
+        );
+        assert_eq!(
+            ContractRegistry::with_standard_contracts()
+                .identify(&tree)
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -1,9 +1,16 @@
 //! Blockchain state
+use std::convert::TryFrom;
+
+use ergo_chain_types::header_chain::HeaderChain;
+use ergo_chain_types::header_chain::HeaderChainError;
 use ergotree_ir::chain::header::Header;
 use ergotree_ir::chain::preheader::PreHeader;
 
+/// Number of the last block headers in the state context
+pub const NUM_LAST_HEADERS: usize = 10;
+
 /// Fixed number of last block headers in descending order (first header is the newest one)
-pub type Headers = [Header; 10];
+pub type Headers = [Header; NUM_LAST_HEADERS];
 
 /// Blockchain state (last headers, etc.)
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -23,6 +30,24 @@ impl ErgoStateContext {
             headers,
         }
     }
+
+    /// State context for signing the transaction in the next block of the best chain: the last
+    /// headers and the predicted pre-header (see [`HeaderChain::next_pre_header`])
+    pub fn from_header_chain(
+        chain: &HeaderChain,
+        timestamp: u64,
+    ) -> Result<ErgoStateContext, HeaderChainError> {
+        let last = chain.last_headers(NUM_LAST_HEADERS);
+        let headers = Headers::try_from(last.iter().map(|h| (*h).clone()).collect::<Vec<_>>())
+            .map_err(|_| HeaderChainError::NotEnoughHeaders {
+                required: NUM_LAST_HEADERS,
+                available: last.len(),
+            })?;
+        Ok(ErgoStateContext::new(
+            chain.next_pre_header(timestamp)?,
+            headers,
+        ))
+    }
 }
 
 #[cfg(feature = "arbitrary")]
@@ -41,3 +66,52 @@ mod arbitrary {
         }
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use ergo_chain_types::header_chain::HeaderChainSettings;
+    use ergotree_ir::chain::block_id::BlockId;
+    use sigma_test_util::force_any_val;
+
+    /// Linked headers (only the ids and heights are consistent)
+    fn headers(len: usize) -> Vec<Header> {
+        let mut headers: Vec<Header> = vec![];
+        for height in 1..=len as u32 {
+            let mut header = Header {
+                version: 1,
+                parent_id: headers
+                    .last()
+                    .map(|p| p.id.clone())
+                    .unwrap_or_else(|| BlockId(force_any_val())),
+                height,
+                timestamp: height as u64,
+                n_bits: 0x01010000,
+                ..force_any_val::<Header>()
+            };
+            header.id = header.compute_id().unwrap();
+            headers.push(header);
+        }
+        headers
+    }
+
+    #[test]
+    fn from_header_chain() {
+        let settings = HeaderChainSettings::mainnet();
+        let short = HeaderChain::from_trusted_headers(settings.clone(), headers(9)).unwrap();
+        assert_eq!(
+            ErgoStateContext::from_header_chain(&short, 0),
+            Err(HeaderChainError::NotEnoughHeaders {
+                required: NUM_LAST_HEADERS,
+                available: 9
+            })
+        );
+        let main = headers(12);
+        let chain = HeaderChain::from_trusted_headers(settings, main.clone()).unwrap();
+        let ctx = ErgoStateContext::from_header_chain(&chain, 100).unwrap();
+        let expected_headers: Vec<Header> = main.iter().rev().take(10).cloned().collect();
+        assert_eq!(ctx.headers.to_vec(), expected_headers);
+        assert_eq!(ctx.pre_header, chain.next_pre_header(100).unwrap());
+    }
+}
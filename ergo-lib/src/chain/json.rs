@@ -3,7 +3,9 @@
 pub(crate) mod context_extension;
 pub(crate) mod transaction;
 
+use ergotree_interpreter::eval::ReductionResult;
 use ergotree_interpreter::sigma_protocol::prover::ProofBytes;
+use ergotree_ir::sigma_protocol::sigma_boolean::SigmaBoolean;
 use serde::{Deserialize, Serialize};
 
 /// Serde remote type
@@ -20,3 +22,19 @@ pub enum ProofBytesSerde {
     /// Non-empty proof
     Some(Vec<u8>),
 }
+
+/// Serde remote type
+#[cfg_attr(
+    feature = "json",
+    derive(Serialize, Deserialize),
+    serde(remote = "ReductionResult")
+)]
+#[derive(PartialEq, Debug, Clone)]
+pub struct ReductionResultSerde {
+    /// value of SigmaProp type which represents a statement verifiable via sigma protocol.
+    #[cfg_attr(feature = "json", serde(rename = "sigmaProp"))]
+    pub sigma_prop: SigmaBoolean,
+    /// estimated cost of expression evaluation
+    #[cfg_attr(feature = "json", serde(rename = "cost"))]
+    pub cost: u64,
+}
@@ -1,3 +1,5 @@
+use crate::chain::transaction::reduced::ReducedInput;
+use crate::chain::transaction::unsigned::UnsignedTransaction;
 use crate::chain::transaction::{DataInput, Input, UnsignedInput};
 use ergotree_ir::chain::ergo_box::ErgoBox;
 use ergotree_ir::chain::ergo_box::ErgoBoxCandidate;
@@ -22,6 +24,12 @@ pub struct TransactionJson {
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct UnsignedTransactionJson {
+    /// transaction id, checked against the computed one if present
+    #[cfg_attr(
+        feature = "json",
+        serde(rename = "id", default, skip_serializing_if = "Option::is_none")
+    )]
+    pub tx_id: Option<TxId>,
     /// unsigned inputs, that will be spent by this transaction.
     #[cfg_attr(feature = "json", serde(rename = "inputs"))]
     pub inputs: Vec<UnsignedInput>,
@@ -35,10 +43,23 @@ pub struct UnsignedTransactionJson {
     pub outputs: Vec<ErgoBoxCandidate>,
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ReducedTransactionJson {
+    /// unsigned transaction
+    #[cfg_attr(feature = "json", serde(rename = "unsignedTx"))]
+    pub unsigned_tx: UnsignedTransaction,
+    /// reduction result for each input of the unsigned transaction
+    #[cfg_attr(feature = "json", serde(rename = "reducedInputs"))]
+    pub reduced_inputs: Vec<ReducedInput>,
+    /// transaction cost according to the prover
+    #[cfg_attr(feature = "json", serde(rename = "txCost"))]
+    pub tx_cost: u32,
+}
+
 #[cfg(test)]
-#[allow(clippy::panic)]
+#[allow(clippy::panic, clippy::unwrap_used)]
 mod tests {
-    use crate::chain::transaction::unsigned::UnsignedTransaction;
+    use super::*;
     use crate::chain::transaction::Transaction;
     use proptest::prelude::*;
 
@@ -62,5 +83,15 @@ mod tests {
             prop_assert_eq![t, t_parsed];
         }
 
+        #[test]
+        fn unsigned_tx_id_check(t in any::<UnsignedTransaction>()) {
+            let mut j = serde_json::to_value(&t)?;
+            j["id"] = serde_json::Value::String(String::from(TxId::zero()));
+            prop_assert!(serde_json::from_value::<UnsignedTransaction>(j.clone()).is_err());
+            j.as_object_mut().unwrap().remove("id");
+            let t_parsed: UnsignedTransaction = serde_json::from_value(j)?;
+            prop_assert_eq![t, t_parsed];
+        }
+
     }
 }
@@ -24,18 +24,67 @@ use crate::wallet::signing::TxSigningError;
 
 use super::unsigned::UnsignedTransaction;
 use super::TxIoVec;
+#[cfg(feature = "json")]
+use crate::chain::json;
+#[cfg(feature = "json")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "json")]
+use std::convert::TryFrom;
+#[cfg(feature = "json")]
+use std::convert::TryInto;
 
 /// Input box script reduced to SigmaBoolean
 /// see EIP-19 for more details -
 /// <https://github.com/ergoplatform/eips/blob/f280890a4163f2f2e988a0091c078e36912fc531/eip-0019.md>
 #[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "json", derive(Deserialize))]
 pub struct ReducedInput {
     /// Input box script reduced to SigmaBoolean
+    #[cfg_attr(
+        feature = "json",
+        serde(rename = "reductionResult", with = "json::ReductionResultSerde")
+    )]
     pub reduction_result: ReductionResult,
     /// ContextExtension for the input
+    #[cfg_attr(
+        feature = "json",
+        serde(
+            rename = "extension",
+            with = "json::context_extension::ContextExtensionSerde"
+        )
+    )]
     pub extension: ContextExtension,
 }
 
+#[cfg(feature = "json")]
+impl Serialize for ReducedInput {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+        struct ReductionResultRef<'a>(&'a ReductionResult);
+        impl Serialize for ReductionResultRef<'_> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                json::ReductionResultSerde::serialize(self.0, serializer)
+            }
+        }
+        let mut s = serializer.serialize_struct("ReducedInput", 2)?;
+        s.serialize_field(
+            "reductionResult",
+            &ReductionResultRef(&self.reduction_result),
+        )?;
+        s.serialize_field(
+            "extension",
+            &json::context_extension::ContextExtensionSerde::from(self.extension.clone()),
+        )?;
+        s.end()
+    }
+}
+
 /// Represent `reduced` transaction, i.e. unsigned transaction where each unsigned input
 /// is augmented with ReducedInput which contains a script reduction result.
 /// After an unsigned transaction is reduced it can be signed without context.
@@ -46,6 +95,14 @@ pub struct ReducedInput {
 /// Reference Scala implementation -
 /// <https://github.com/ergoplatform/ergo-appkit/blob/1b7347caa863ecb0b9ba49ae57b090d1f386c906/common/src/main/java/org/ergoplatform/appkit/AppkitProvingInterpreter.scala#L261-L266>
 #[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "json",
+    serde(
+        try_from = "json::transaction::ReducedTransactionJson",
+        into = "json::transaction::ReducedTransactionJson"
+    )
+)]
 pub struct ReducedTransaction {
     /// Unsigned transation
     pub unsigned_tx: UnsignedTransaction,
@@ -145,6 +202,50 @@ impl SigmaSerializable for ReducedTransaction {
     }
 }
 
+#[cfg(feature = "json")]
+impl From<ReducedTransaction> for json::transaction::ReducedTransactionJson {
+    fn from(v: ReducedTransaction) -> Self {
+        json::transaction::ReducedTransactionJson {
+            unsigned_tx: v.unsigned_tx,
+            reduced_inputs: v.reduced_inputs.as_vec().clone(),
+            tx_cost: v.tx_cost,
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl TryFrom<json::transaction::ReducedTransactionJson> for ReducedTransaction {
+    type Error = String;
+    fn try_from(tx_json: json::transaction::ReducedTransactionJson) -> Result<Self, Self::Error> {
+        let unsigned_inputs = tx_json.unsigned_tx.inputs.as_vec();
+        if unsigned_inputs.len() != tx_json.reduced_inputs.len() {
+            return Err(format!(
+                "expected {} reduced inputs, got {}",
+                unsigned_inputs.len(),
+                tx_json.reduced_inputs.len()
+            ));
+        }
+        if let Some(idx) = unsigned_inputs
+            .iter()
+            .zip(tx_json.reduced_inputs.iter())
+            .position(|(ui, ri)| ui.extension != ri.extension)
+        {
+            return Err(format!(
+                "context extension of reduced input {} does not match the unsigned input",
+                idx
+            ));
+        }
+        Ok(ReducedTransaction {
+            unsigned_tx: tx_json.unsigned_tx,
+            reduced_inputs: tx_json
+                .reduced_inputs
+                .try_into()
+                .map_err(|e: bounded_vec::BoundedVecOutOfBounds| e.to_string())?,
+            tx_cost: tx_json.tx_cost,
+        })
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::panic)]
 mod tests {
@@ -186,5 +287,12 @@ mod tests {
         fn ser_roundtrip(v in any::<ReducedTransaction>()) {
             prop_assert_eq![sigma_serialize_roundtrip(&v), v];
         }
+
+        #[test]
+        fn json_roundtrip(v in any::<ReducedTransaction>()) {
+            let j = serde_json::to_string(&v)?;
+            let parsed: ReducedTransaction = serde_json::from_str(&j)?;
+            prop_assert_eq![parsed, v];
+        }
     }
 }
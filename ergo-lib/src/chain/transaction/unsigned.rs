@@ -107,6 +107,7 @@ impl UnsignedTransaction {
 impl From<UnsignedTransaction> for json::transaction::UnsignedTransactionJson {
     fn from(v: UnsignedTransaction) -> Self {
         json::transaction::UnsignedTransactionJson {
+            tx_id: Some(v.tx_id),
             inputs: v.inputs.as_vec().clone(),
             data_inputs: v
                 .data_inputs
@@ -119,10 +120,9 @@ impl From<UnsignedTransaction> for json::transaction::UnsignedTransactionJson {
 
 #[cfg(feature = "json")]
 impl TryFrom<json::transaction::UnsignedTransactionJson> for UnsignedTransaction {
-    // We never return this type but () fails to compile (can't format) and ! is experimental
     type Error = String;
     fn try_from(tx_json: json::transaction::UnsignedTransactionJson) -> Result<Self, Self::Error> {
-        UnsignedTransaction::new(
+        let tx = UnsignedTransaction::new(
             tx_json
                 .inputs
                 .try_into()
@@ -133,7 +133,14 @@ impl TryFrom<json::transaction::UnsignedTransactionJson> for UnsignedTransaction
                 .try_into()
                 .map_err(|e: bounded_vec::BoundedVecOutOfBounds| e.to_string())?,
         )
-        .map_err(|e| format!("unsigned tx serialization failed: {0}", e))
+        .map_err(|e| format!("unsigned tx serialization failed: {0}", e))?;
+        match tx_json.tx_id {
+            Some(tx_id) if tx_id != tx.tx_id => Err(format!(
+                "unsigned tx id mismatch: expected {:?}, computed {:?}",
+                tx_id, tx.tx_id
+            )),
+            _ => Ok(tx),
+        }
     }
 }
 
@@ -17,3 +17,12 @@
 #![deny(clippy::todo)]
 #![deny(clippy::unimplemented)]
 #![deny(clippy::panic)]
+
+mod nipopow_algos;
+mod nipopow_proof;
+mod popow_header;
+
+pub use nipopow_algos::NipopowAlgos;
+pub use nipopow_algos::NipopowAlgosError;
+pub use nipopow_proof::NipopowProof;
+pub use popow_header::PoPowHeader;
@@ -0,0 +1,415 @@
+//! NiPoPoW algorithms: header levels, chain scoring and common ancestor
+
+use ergo_chain_types::autolykos_pow_scheme::AutolykosPowScheme;
+use ergo_chain_types::autolykos_pow_scheme::AutolykosPowSchemeError;
+use ergo_chain_types::block_section::Extension;
+use ergo_chain_types::merkle_tree::BatchMerkleProof;
+use ergotree_ir::chain::block_id::BlockId;
+use ergotree_ir::chain::digest32::Digest32;
+use ergotree_ir::chain::header::Header;
+use num_bigint::BigInt;
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use std::convert::TryFrom;
+use thiserror::Error;
+
+/// Errors of the interlinks computation and decoding
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum NipopowAlgosError {
+    /// Header level computation failed
+    #[error("PoW error: {0}")]
+    PowError(#[from] AutolykosPowSchemeError),
+    /// Interlinks of the non-genesis header are empty
+    #[error("interlinks of the non-genesis header {0:?} are empty")]
+    EmptyInterlinks(BlockId),
+    /// Extension field value is not the links count followed by the block id
+    #[error("invalid interlinks field value {0:?}")]
+    InvalidInterlinksField(Vec<u8>),
+}
+
+/// Algorithms from the NiPoPoW paper (KMZ17, FC20 version)
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct NipopowAlgos {
+    /// PoW scheme to compute the header hits
+    pub pow_scheme: AutolykosPowScheme,
+}
+
+impl NipopowAlgos {
+    /// Levels above this one are only reachable by the genesis header (the hit is at least 1 and
+    /// the target is less than `2^256`)
+    const MAX_NON_GENESIS_LEVEL: i32 = 256;
+
+    /// First byte of the keys of the interlinks fields in the extension
+    pub const INTERLINKS_VECTOR_PREFIX: u8 = 0x01;
+
+    /// Create with the given PoW scheme
+    pub fn new(pow_scheme: AutolykosPowScheme) -> Self {
+        NipopowAlgos { pow_scheme }
+    }
+
+    /// Maximum level `μ` of the header, `log2(target) - log2(hit)` where the hit is the PoW
+    /// distance for Autolykos v1. The genesis header is of the maximum level.
+    pub fn max_level_of(&self, header: &Header) -> Result<i32, AutolykosPowSchemeError> {
+        if header.height == Header::GENESIS_HEIGHT {
+            return Ok(i32::MAX);
+        }
+        let required_target = self.pow_scheme.target(header.n_bits)?;
+        let real_target = if header.version == Header::INITIAL_VERSION {
+            header.pow_distance.clone()
+        } else {
+            self.pow_scheme.pow_hit(header)?
+        };
+        let level = log2(&required_target) - log2(&real_target);
+        Ok(level as i32)
+    }
+
+    /// Score of the chain, the maximum of `2^μ * |C↑μ|` over the levels `μ` with at least `m`
+    /// headers (level 0 always counts)
+    pub fn best_arg(&self, chain: &[&Header], m: u32) -> Result<BigUint, AutolykosPowSchemeError> {
+        let levels = chain
+            .iter()
+            .map(|h| self.max_level_of(h))
+            .collect::<Result<Vec<i32>, _>>()?;
+        let mut best = BigUint::from(chain.len());
+        for level in 1..=Self::MAX_NON_GENESIS_LEVEL {
+            let size = levels.iter().filter(|l| **l >= level).count();
+            if size < m as usize {
+                break;
+            }
+            best = best.max(BigUint::from(size) << level as usize);
+        }
+        Ok(best)
+    }
+
+    /// Last common header of the chains (compared position by position from the first one),
+    /// `None` if the chains start from different headers
+    pub fn lowest_common_ancestor<'a>(
+        &self,
+        left_chain: &[&'a Header],
+        right_chain: &[&Header],
+    ) -> Option<&'a Header> {
+        if left_chain.first()? != right_chain.first()? {
+            return None;
+        }
+        left_chain
+            .iter()
+            .zip(right_chain.iter())
+            .take_while(|(l, r)| l == r)
+            .last()
+            .map(|(l, _)| *l)
+    }
+
+    /// Interlinks of the header following `prev_header` with the given interlinks, as in the
+    /// node: the first link is always the genesis block id, the last `level` links (the level of
+    /// the previous header) are replaced by the previous header id
+    pub fn update_interlinks(
+        &self,
+        prev_header: &Header,
+        prev_interlinks: &[BlockId],
+    ) -> Result<Vec<BlockId>, NipopowAlgosError> {
+        if prev_header.height == Header::GENESIS_HEIGHT {
+            return Ok(vec![prev_header.id.clone()]);
+        }
+        let (genesis, tail) = prev_interlinks
+            .split_first()
+            .ok_or_else(|| NipopowAlgosError::EmptyInterlinks(prev_header.id.clone()))?;
+        let prev_level = self.max_level_of(prev_header)?.max(0) as usize;
+        let kept = tail.len().saturating_sub(prev_level);
+        Ok(std::iter::once(genesis.clone())
+            .chain(tail[..kept].iter().cloned())
+            .chain(std::iter::repeat(prev_header.id.clone()).take(prev_level))
+            .collect())
+    }
+
+    /// Extension fields of the interlinks, runs of the same id are packed into one field with the
+    /// key `[INTERLINKS_VECTOR_PREFIX, index of the run start]` and the value `run length ++ id`
+    pub fn pack_interlinks(
+        &self,
+        interlinks: &[BlockId],
+    ) -> Vec<([u8; Extension::FIELD_KEY_SIZE], Vec<u8>)> {
+        let mut fields = Vec::new();
+        let mut idx = 0;
+        while idx < interlinks.len() {
+            let id = &interlinks[idx];
+            let run = interlinks[idx..]
+                .iter()
+                .take(u8::MAX as usize)
+                .take_while(|l| *l == id)
+                .count();
+            let mut value = vec![run as u8];
+            value.extend_from_slice(&id.0 .0[..]);
+            fields.push(([Self::INTERLINKS_VECTOR_PREFIX, idx as u8], value));
+            idx += run;
+        }
+        fields
+    }
+
+    /// Interlinks from the extension fields (only the ones with the interlinks key prefix are
+    /// taken, in the order of the fields)
+    pub fn unpack_interlinks(
+        &self,
+        fields: &[([u8; Extension::FIELD_KEY_SIZE], Vec<u8>)],
+    ) -> Result<Vec<BlockId>, NipopowAlgosError> {
+        let mut interlinks = Vec::new();
+        for (_, value) in fields
+            .iter()
+            .filter(|(key, _)| key[0] == Self::INTERLINKS_VECTOR_PREFIX)
+        {
+            match value.split_first() {
+                Some((run, id)) if id.len() == Digest32::SIZE => {
+                    let id =
+                        BlockId(Digest32::try_from(id.to_vec()).map_err(|_| {
+                            NipopowAlgosError::InvalidInterlinksField(value.clone())
+                        })?);
+                    interlinks.extend(std::iter::repeat(id).take(*run as usize));
+                }
+                _ => return Err(NipopowAlgosError::InvalidInterlinksField(value.clone())),
+            }
+        }
+        Ok(interlinks)
+    }
+
+    /// Proof of the interlinks fields against the extension root, `None` if there are no
+    /// interlinks in the extension
+    pub fn proof_for_interlink_vector(&self, extension: &Extension) -> Option<BatchMerkleProof> {
+        let keys: Vec<[u8; Extension::FIELD_KEY_SIZE]> = extension
+            .fields
+            .iter()
+            .filter(|(key, _)| key[0] == Self::INTERLINKS_VECTOR_PREFIX)
+            .map(|(key, _)| *key)
+            .collect();
+        if keys.is_empty() {
+            None
+        } else {
+            extension.batch_proof_for(&keys)
+        }
+    }
+}
+
+fn log2(value: &BigInt) -> f64 {
+    value.to_f64().map(f64::log2).unwrap_or(f64::NAN)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::PoPowHeader;
+    use ergo_chain_types::nbits::encode_compact_bits;
+    use ergotree_ir::chain::digest32::ADDigest;
+    use ergotree_ir::chain::votes::Votes;
+    use ergotree_ir::sigma_protocol::dlog_group;
+
+    /// Difficulty of the test headers
+    pub(crate) fn difficulty() -> BigInt {
+        BigInt::from(1u64 << 40)
+    }
+
+    /// Autolykos v1 header (the hit is the PoW distance) of the given level, the id is computed
+    pub(crate) fn header(parent: Option<&Header>, level: u32) -> Header {
+        let n_bits = encode_compact_bits(&difficulty());
+        let target = AutolykosPowScheme::default().target(n_bits).unwrap();
+        // slightly below `target / 2^level`
+        let pow_distance = (target >> level as usize) - 1;
+        let mut header = Header {
+            version: 1,
+            id: BlockId(Digest32::zero()),
+            parent_id: parent
+                .map(|p| p.id.clone())
+                .unwrap_or_else(|| BlockId(Digest32::zero())),
+            ad_proofs_root: Digest32::zero(),
+            state_root: ADDigest::zero(),
+            transaction_root: Digest32::zero(),
+            timestamp: 0,
+            n_bits,
+            height: parent
+                .map(|p| p.height + 1)
+                .unwrap_or(Header::GENESIS_HEIGHT),
+            extension_root: Digest32::zero(),
+            miner_pk: Box::new(dlog_group::generator()),
+            pow_onetime_pk: Box::new(dlog_group::generator()),
+            nonce: vec![0; 8],
+            pow_distance,
+            votes: Votes([0, 0, 0]),
+        };
+        header.id = header.compute_id().unwrap();
+        header
+    }
+
+    /// Chain starting from the genesis header with the given levels of the following headers
+    pub(crate) fn chain_from(genesis: &Header, levels: &[u32]) -> Vec<Header> {
+        let mut chain = vec![genesis.clone()];
+        for level in levels {
+            let next = header(chain.last(), *level);
+            chain.push(next);
+        }
+        chain
+    }
+
+    /// Chain of the headers with the given levels after the genesis header, the interlinks are
+    /// packed into the extension (after a parameter field) and proven against the extension root
+    pub(crate) fn popow_chain(genesis: &Header, levels: &[u32]) -> Vec<PoPowHeader> {
+        let mut chain = vec![PoPowHeader {
+            header: genesis.clone(),
+            interlinks: vec![],
+            interlinks_proof: BatchMerkleProof {
+                indices: vec![],
+                proofs: vec![],
+            },
+        }];
+        extend_popow_chain(&mut chain, levels);
+        chain
+    }
+
+    /// Appends headers of the given levels to the chain
+    pub(crate) fn extend_popow_chain(chain: &mut Vec<PoPowHeader>, levels: &[u32]) {
+        let algos = NipopowAlgos::default();
+        for level in levels {
+            let prev = chain.last().unwrap();
+            let interlinks = algos
+                .update_interlinks(&prev.header, &prev.interlinks)
+                .unwrap();
+            let mut fields = vec![([0, 1], vec![0, 0, 0, 1])];
+            fields.extend(algos.pack_interlinks(&interlinks));
+            let extension = Extension {
+                header_id: BlockId(Digest32::zero()),
+                fields,
+            };
+            let mut header = header(Some(&prev.header), *level);
+            header.extension_root = extension.digest();
+            header.id = header.compute_id().unwrap();
+            let interlinks_proof = algos.proof_for_interlink_vector(&extension).unwrap();
+            chain.push(PoPowHeader {
+                header,
+                interlinks,
+                interlinks_proof,
+            });
+        }
+    }
+
+    #[test]
+    fn max_level_of() {
+        let algos = NipopowAlgos::default();
+        let genesis = header(None, 0);
+        assert_eq!(algos.max_level_of(&genesis), Ok(i32::MAX));
+        for level in 0..10 {
+            let h = header(Some(&genesis), level);
+            assert_eq!(algos.max_level_of(&h), Ok(level as i32));
+        }
+    }
+
+    #[test]
+    fn best_arg() {
+        let algos = NipopowAlgos::default();
+        let chain = chain_from(&header(None, 0), &[0, 1, 0, 2, 0, 1, 0, 3]);
+        let headers: Vec<&Header> = chain[1..].iter().collect();
+        // level 0: 8, level 1: 2 * 4, level 2: 4 * 2, level 3: 8 * 1
+        assert_eq!(algos.best_arg(&headers, 1), Ok(BigUint::from(8u32)));
+        let high = chain_from(&header(None, 0), &[0, 4, 4, 0]);
+        let headers: Vec<&Header> = high[1..].iter().collect();
+        // level 4: 16 * 2
+        assert_eq!(algos.best_arg(&headers, 2), Ok(BigUint::from(32u32)));
+        assert_eq!(algos.best_arg(&headers, 3), Ok(BigUint::from(4u32)));
+        // the genesis header does not make the loop unbounded
+        let with_genesis: Vec<&Header> = high.iter().collect();
+        assert_eq!(
+            algos.best_arg(&with_genesis, 1),
+            Ok(BigUint::from(1u32) << 256)
+        );
+    }
+
+    #[test]
+    fn lowest_common_ancestor() {
+        let algos = NipopowAlgos::default();
+        let genesis = header(None, 0);
+        let left = chain_from(&genesis, &[0, 1, 0]);
+        let mut right = left[..2].to_vec();
+        right.push(header(right.last(), 2));
+        let left_refs: Vec<&Header> = left.iter().collect();
+        let right_refs: Vec<&Header> = right.iter().collect();
+        assert_eq!(
+            algos.lowest_common_ancestor(&left_refs, &right_refs),
+            Some(&left[1])
+        );
+        let other = chain_from(&header(None, 1), &[0]);
+        let other_refs: Vec<&Header> = other.iter().collect();
+        assert_eq!(algos.lowest_common_ancestor(&left_refs, &other_refs), None);
+        assert_eq!(algos.lowest_common_ancestor(&left_refs, &[]), None);
+    }
+
+    #[test]
+    fn update_interlinks() {
+        let algos = NipopowAlgos::default();
+        let chain = popow_chain(&header(None, 0), &[2, 0, 1, 3, 0]);
+        let ids: Vec<BlockId> = chain.iter().map(|h| h.id().clone()).collect();
+        assert_eq!(chain[1].interlinks, vec![ids[0].clone()]);
+        assert_eq!(
+            chain[2].interlinks,
+            vec![ids[0].clone(), ids[1].clone(), ids[1].clone()]
+        );
+        // level 0 header does not change the interlinks
+        assert_eq!(chain[3].interlinks, chain[2].interlinks);
+        // the last link is replaced by the level 1 header
+        assert_eq!(
+            chain[4].interlinks,
+            vec![ids[0].clone(), ids[1].clone(), ids[3].clone()]
+        );
+        assert_eq!(
+            chain[5].interlinks,
+            vec![
+                ids[0].clone(),
+                ids[4].clone(),
+                ids[4].clone(),
+                ids[4].clone()
+            ]
+        );
+        assert!(matches!(
+            algos.update_interlinks(&chain[1].header, &[]),
+            Err(NipopowAlgosError::EmptyInterlinks(_))
+        ));
+    }
+
+    #[test]
+    fn pack_unpack_interlinks() {
+        let algos = NipopowAlgos::default();
+        let id = |b: u8| BlockId(Digest32::from([b; 32]));
+        let interlinks = vec![id(0), id(1), id(1), id(1), id(2), id(3), id(3)];
+        let fields = algos.pack_interlinks(&interlinks);
+        assert_eq!(
+            fields.iter().map(|(k, v)| (*k, v[0])).collect::<Vec<_>>(),
+            vec![([1, 0], 1), ([1, 1], 3), ([1, 4], 1), ([1, 5], 2)]
+        );
+        assert_eq!(fields[1].1[1..], [1u8; 32]);
+        let mut with_other = vec![([0, 1], vec![5])];
+        with_other.extend(fields);
+        assert_eq!(algos.unpack_interlinks(&with_other), Ok(interlinks));
+        assert_eq!(algos.unpack_interlinks(&[]), Ok(vec![]));
+        assert_eq!(
+            algos.unpack_interlinks(&[([1, 0], vec![1, 2, 3])]),
+            Err(NipopowAlgosError::InvalidInterlinksField(vec![1, 2, 3]))
+        );
+    }
+
+    #[test]
+    fn interlinks_proof() {
+        let chain = popow_chain(&header(None, 0), &[1, 2, 0]);
+        assert!(chain.iter().all(PoPowHeader::check_interlinks_proof));
+        let mut wrong_links = chain[3].clone();
+        wrong_links.interlinks[1] = wrong_links.interlinks[0].clone();
+        assert!(!wrong_links.check_interlinks_proof());
+        let mut other_header = chain[3].clone();
+        other_header.header = chain[2].header.clone();
+        assert!(!other_header.check_interlinks_proof());
+        let mut genesis = chain[0].clone();
+        genesis.interlinks_proof = chain[1].interlinks_proof.clone();
+        assert!(!genesis.check_interlinks_proof());
+        let no_interlinks = Extension {
+            header_id: BlockId(Digest32::zero()),
+            fields: vec![([0, 1], vec![5])],
+        };
+        assert_eq!(
+            NipopowAlgos::default().proof_for_interlink_vector(&no_interlinks),
+            None
+        );
+    }
+}
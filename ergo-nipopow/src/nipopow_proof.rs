@@ -0,0 +1,335 @@
+//! NiPoPoW proof
+
+use ergo_chain_types::autolykos_pow_scheme::AutolykosPowSchemeError;
+use ergotree_ir::chain::header::Header;
+use ergotree_ir::serialization::sigma_byte_reader::SigmaByteRead;
+use ergotree_ir::serialization::sigma_byte_writer::SigmaByteWrite;
+use ergotree_ir::serialization::SigmaParsingError;
+use ergotree_ir::serialization::SigmaSerializable;
+use ergotree_ir::serialization::SigmaSerializeResult;
+
+use crate::popow_header::read_header;
+use crate::popow_header::write_header;
+use crate::NipopowAlgos;
+use crate::PoPowHeader;
+
+/// Non-interactive proof of proof-of-work, a sparse prefix of the chain (superblocks linked by
+/// the interlinks) followed by the `k` last headers
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct NipopowProof {
+    /// Security parameter (minimum number of superblocks in the prefix on the level)
+    #[cfg_attr(feature = "json", serde(rename = "m"))]
+    pub m: u32,
+    /// Security parameter (suffix length)
+    #[cfg_attr(feature = "json", serde(rename = "k"))]
+    pub k: u32,
+    /// Superblocks of the chain before the suffix
+    #[cfg_attr(feature = "json", serde(rename = "prefix"))]
+    pub prefix: Vec<PoPowHeader>,
+    /// First header of the suffix
+    #[cfg_attr(feature = "json", serde(rename = "suffixHead"))]
+    pub suffix_head: PoPowHeader,
+    /// The rest of the suffix (`k - 1` headers)
+    #[cfg_attr(feature = "json", serde(rename = "suffixTail"))]
+    pub suffix_tail: Vec<Header>,
+}
+
+impl NipopowProof {
+    /// Headers of the prefix
+    pub fn prefix_headers(&self) -> Vec<&Header> {
+        self.prefix.iter().map(|p| &p.header).collect()
+    }
+
+    /// Headers of the suffix
+    pub fn suffix_headers(&self) -> Vec<&Header> {
+        std::iter::once(&self.suffix_head.header)
+            .chain(self.suffix_tail.iter())
+            .collect()
+    }
+
+    /// All headers of the proof
+    pub fn headers_chain(&self) -> Vec<&Header> {
+        let mut chain = self.prefix_headers();
+        chain.extend(self.suffix_headers());
+        chain
+    }
+
+    /// Superblocks of the prefix of at least the given level
+    pub fn chain_of_level(
+        &self,
+        algos: &NipopowAlgos,
+        level: i32,
+    ) -> Result<Vec<&PoPowHeader>, AutolykosPowSchemeError> {
+        let mut chain = Vec::new();
+        for h in &self.prefix {
+            if algos.max_level_of(&h.header)? >= level {
+                chain.push(h);
+            }
+        }
+        Ok(chain)
+    }
+
+    /// Checks that every prefix header (and the suffix head) links to the previous prefix header
+    /// via the interlinks or the parent id, and the suffix headers are linked via parent ids
+    pub fn has_valid_connections(&self) -> bool {
+        let prefix_linked = self
+            .prefix
+            .iter()
+            .zip(
+                self.prefix
+                    .iter()
+                    .skip(1)
+                    .chain(std::iter::once(&self.suffix_head)),
+            )
+            .all(|(prev, next)| {
+                next.interlinks.contains(prev.id()) || &next.header.parent_id == prev.id()
+            });
+        let suffix = self.suffix_headers();
+        let suffix_linked = suffix
+            .iter()
+            .zip(suffix.iter().skip(1))
+            .all(|(prev, next)| next.parent_id == prev.id);
+        prefix_linked && suffix_linked
+    }
+
+    /// Checks that the heights of the headers are increasing
+    pub fn has_valid_heights(&self) -> bool {
+        let chain = self.headers_chain();
+        chain
+            .iter()
+            .zip(chain.iter().skip(1))
+            .all(|(prev, next)| prev.height < next.height)
+    }
+
+    /// Checks the interlinks proofs of the prefix headers and the suffix head
+    pub fn has_valid_proofs(&self) -> bool {
+        self.prefix.iter().all(PoPowHeader::check_interlinks_proof)
+            && self.suffix_head.check_interlinks_proof()
+    }
+
+    /// Checks the proof structure (connections, heights and interlinks proofs)
+    pub fn is_valid(&self) -> bool {
+        self.has_valid_connections() && self.has_valid_heights() && self.has_valid_proofs()
+    }
+
+    /// Proof comparison from the paper (`≥` operator of the verifier): a valid proof is better
+    /// than an invalid one, of two valid proofs the better one has the higher score of the part
+    /// after the lowest common ancestor
+    pub fn is_better_than(
+        &self,
+        that: &NipopowProof,
+        algos: &NipopowAlgos,
+    ) -> Result<bool, AutolykosPowSchemeError> {
+        if !(self.is_valid() && that.is_valid()) {
+            return Ok(self.is_valid());
+        }
+        let this_chain = self.headers_chain();
+        let that_chain = that.headers_chain();
+        match algos.lowest_common_ancestor(&this_chain, &that_chain) {
+            Some(lca) => {
+                let this_score = algos.best_arg(&headers_after(&this_chain, lca.height), self.m)?;
+                let that_score = algos.best_arg(&headers_after(&that_chain, lca.height), self.m)?;
+                Ok(this_score > that_score)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Binary format of the node: `m`, `k`, the prefix, the suffix head and the suffix tail, every
+/// header is prefixed with its length
+impl SigmaSerializable for NipopowProof {
+    fn sigma_serialize<W: SigmaByteWrite>(&self, w: &mut W) -> SigmaSerializeResult {
+        w.put_u32(self.m)?;
+        w.put_u32(self.k)?;
+        w.put_usize_as_u32_unwrapped(self.prefix.len())?;
+        for h in &self.prefix {
+            let bytes = h.sigma_serialize_bytes()?;
+            w.put_usize_as_u32_unwrapped(bytes.len())?;
+            w.write_all(&bytes)?;
+        }
+        let suffix_head_bytes = self.suffix_head.sigma_serialize_bytes()?;
+        w.put_usize_as_u32_unwrapped(suffix_head_bytes.len())?;
+        w.write_all(&suffix_head_bytes)?;
+        w.put_usize_as_u32_unwrapped(self.suffix_tail.len())?;
+        self.suffix_tail.iter().try_for_each(|h| write_header(h, w))
+    }
+
+    fn sigma_parse<R: SigmaByteRead>(r: &mut R) -> Result<Self, SigmaParsingError> {
+        let m = r.get_u32()?;
+        let k = r.get_u32()?;
+        let prefix_len = r.get_u32()?;
+        let prefix = (0..prefix_len)
+            .map(|_| read_popow_header(r))
+            .collect::<Result<Vec<_>, _>>()?;
+        let suffix_head = read_popow_header(r)?;
+        let suffix_tail_len = r.get_u32()?;
+        let suffix_tail = (0..suffix_tail_len)
+            .map(|_| read_header(r))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(NipopowProof {
+            m,
+            k,
+            prefix,
+            suffix_head,
+            suffix_tail,
+        })
+    }
+}
+
+fn headers_after<'a>(chain: &[&'a Header], height: u32) -> Vec<&'a Header> {
+    chain
+        .iter()
+        .filter(|h| h.height > height)
+        .copied()
+        .collect()
+}
+
+fn read_popow_header<R: SigmaByteRead>(r: &mut R) -> Result<PoPowHeader, SigmaParsingError> {
+    let size = r.get_u32()?;
+    let bytes = r.get_bytes(size as usize)?;
+    PoPowHeader::sigma_parse_bytes(&bytes)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::nipopow_algos::tests::extend_popow_chain;
+    use crate::nipopow_algos::tests::header;
+    use crate::nipopow_algos::tests::popow_chain;
+    use ergotree_ir::serialization::sigma_byte_writer::SigmaByteWriter;
+    use ergotree_ir::serialization::sigma_serialize_roundtrip;
+
+    /// Proof with every header but the last `k` in the prefix
+    fn proof_of(chain: &[PoPowHeader], k: usize) -> NipopowProof {
+        let suffix_start = chain.len() - k;
+        NipopowProof {
+            m: 2,
+            k: k as u32,
+            prefix: chain[..suffix_start].to_vec(),
+            suffix_head: chain[suffix_start].clone(),
+            suffix_tail: chain[suffix_start + 1..]
+                .iter()
+                .map(|h| h.header.clone())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn validity() {
+        let chain = popow_chain(&header(None, 0), &[1, 1, 2, 1, 0, 0]);
+        let proof = proof_of(&chain, 3);
+        assert!(proof.is_valid());
+        assert_eq!(proof.headers_chain().len(), chain.len());
+        let algos = NipopowAlgos::default();
+        // genesis and the level 2 header
+        assert_eq!(proof.chain_of_level(&algos, 2).unwrap().len(), 2);
+
+        let mut broken_interlinks = proof.clone();
+        // the level 1 header is replaced in the interlinks of the next one by the dropped one
+        broken_interlinks.prefix.remove(2);
+        assert!(!broken_interlinks.prefix[2]
+            .interlinks
+            .contains(broken_interlinks.prefix[1].id()));
+        assert!(!broken_interlinks.has_valid_connections());
+
+        let mut broken_suffix = proof.clone();
+        broken_suffix.suffix_tail.swap(0, 1);
+        assert!(!broken_suffix.has_valid_connections());
+        assert!(!broken_suffix.is_valid());
+
+        let mut broken_heights = proof.clone();
+        broken_heights.prefix[1].header.height = 10;
+        assert!(!broken_heights.has_valid_heights());
+
+        let mut broken_proof = proof;
+        broken_proof.suffix_head.interlinks_proof = chain[2].interlinks_proof.clone();
+        assert!(broken_proof.has_valid_connections());
+        assert!(!broken_proof.has_valid_proofs());
+    }
+
+    #[test]
+    fn parse_huge_lengths() {
+        // VLQ encoded u32::MAX
+        let max_len = [0xff, 0xff, 0xff, 0xff, 0x0f];
+        // prefix header (m = 2, k = 2, one prefix header)
+        let mut proof = vec![2, 2, 1];
+        proof.extend_from_slice(&max_len);
+        assert!(NipopowProof::sigma_parse_bytes(&proof).is_err());
+        // header of the PoPoW header
+        assert!(PoPowHeader::sigma_parse_bytes(&max_len).is_err());
+        // interlinks proof (no interlinks)
+        let mut popow_header = Vec::new();
+        write_header(
+            &header(None, 0),
+            &mut SigmaByteWriter::new(&mut popow_header, None),
+        )
+        .unwrap();
+        popow_header.push(0);
+        popow_header.extend_from_slice(&max_len);
+        assert!(PoPowHeader::sigma_parse_bytes(&popow_header).is_err());
+    }
+
+    #[test]
+    fn prefix_linked_by_parent_ids() {
+        // the level 0 headers are not in the interlinks of the following ones
+        let chain = popow_chain(&header(None, 0), &[1, 0, 0, 0]);
+        assert!(!chain[4].interlinks.contains(chain[3].id()));
+        let proof = proof_of(&chain, 1);
+        assert!(proof.has_valid_connections());
+        assert!(proof.is_valid());
+
+        let mut gap = proof;
+        gap.prefix.remove(3);
+        assert!(!gap.has_valid_connections());
+    }
+
+    #[test]
+    fn is_better_than() {
+        let algos = NipopowAlgos::default();
+        let common = popow_chain(&header(None, 0), &[1, 1]);
+        let fork = |levels: &[u32]| {
+            let mut chain = common.clone();
+            extend_popow_chain(&mut chain, levels);
+            chain
+        };
+        let strong = proof_of(&fork(&[3, 3, 0, 0]), 2);
+        let weak = proof_of(&fork(&[1, 1, 0, 0]), 2);
+        assert!(strong.is_valid() && weak.is_valid());
+        assert_eq!(strong.is_better_than(&weak, &algos), Ok(true));
+        assert_eq!(weak.is_better_than(&strong, &algos), Ok(false));
+
+        let mut invalid = strong.clone();
+        invalid.suffix_tail.clear();
+        invalid.suffix_head.interlinks.clear();
+        assert_eq!(invalid.is_better_than(&weak, &algos), Ok(false));
+        assert_eq!(weak.is_better_than(&invalid, &algos), Ok(true));
+
+        let unrelated = proof_of(&popow_chain(&header(None, 1), &[5, 5, 5]), 2);
+        assert_eq!(strong.is_better_than(&unrelated, &algos), Ok(false));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let chain = popow_chain(&header(None, 0), &[1, 1, 0, 2]);
+        let proof = proof_of(&chain, 2);
+        assert_eq!(sigma_serialize_roundtrip(&proof), proof);
+        assert_eq!(sigma_serialize_roundtrip(&proof.prefix[1]), proof.prefix[1]);
+        let json = serde_json::to_string(&proof).unwrap();
+        assert!(json.starts_with(r#"{"m":2,"k":2,"prefix":[{"header":{"#));
+        assert!(json.contains(r#""interlinksProof":{"indices":[{"index":1,"digest":"#));
+        assert!(json.contains(r#""suffixHead":{"header":"#));
+        assert!(json.contains(r#""suffixTail":[{"#));
+        assert_eq!(serde_json::from_str::<NipopowProof>(&json).unwrap(), proof);
+        // the node always sends the interlinks proof
+        let mut header_json = serde_json::to_value(&proof.suffix_head).unwrap();
+        assert!(header_json
+            .as_object_mut()
+            .unwrap()
+            .remove("interlinksProof")
+            .is_some());
+        assert!(serde_json::from_value::<PoPowHeader>(header_json).is_err());
+    }
+}
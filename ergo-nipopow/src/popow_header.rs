@@ -0,0 +1,111 @@
+//! Header with the interlinks
+
+use ergo_chain_types::block_section::extension::field_leaf;
+use ergo_chain_types::merkle_tree::leaf_hash;
+use ergo_chain_types::merkle_tree::BatchMerkleProof;
+use ergotree_ir::chain::block_id::BlockId;
+use ergotree_ir::chain::digest32::Digest32;
+use ergotree_ir::chain::header::Header;
+use ergotree_ir::serialization::sigma_byte_reader::SigmaByteRead;
+use ergotree_ir::serialization::sigma_byte_writer::SigmaByteWrite;
+use ergotree_ir::serialization::SigmaParsingError;
+use ergotree_ir::serialization::SigmaSerializable;
+use ergotree_ir::serialization::SigmaSerializeResult;
+
+use crate::NipopowAlgos;
+
+/// Block header with the interlinks (ids of the last headers of each level before this one,
+/// stored in the block extension)
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct PoPowHeader {
+    /// Block header
+    #[cfg_attr(feature = "json", serde(rename = "header"))]
+    pub header: Header,
+    /// Interlinks, the first one is the genesis block id
+    #[cfg_attr(feature = "json", serde(rename = "interlinks"))]
+    pub interlinks: Vec<BlockId>,
+    /// Proof of the packed interlinks fields against the extension root of the header
+    #[cfg_attr(feature = "json", serde(rename = "interlinksProof"))]
+    pub interlinks_proof: BatchMerkleProof,
+}
+
+impl PoPowHeader {
+    /// Header id
+    pub fn id(&self) -> &BlockId {
+        &self.header.id
+    }
+
+    /// Header height
+    pub fn height(&self) -> u32 {
+        self.header.height
+    }
+
+    /// Checks that the proven leaves are the packed interlinks fields and the proof leads to
+    /// `extension_root` of the header. Empty interlinks (genesis) go with the empty proof.
+    pub fn check_interlinks_proof(&self) -> bool {
+        if self.interlinks.is_empty() {
+            return self.interlinks_proof.indices.is_empty();
+        }
+        let field_hashes: Vec<_> = NipopowAlgos::default()
+            .pack_interlinks(&self.interlinks)
+            .iter()
+            .map(|(key, value)| leaf_hash(&field_leaf(key, value)))
+            .collect();
+        let mut proven = self.interlinks_proof.indices.clone();
+        proven.sort_by_key(|(i, _)| *i);
+        field_hashes.len() == proven.len()
+            && field_hashes
+                .iter()
+                .zip(proven.iter())
+                .all(|(field, (_, leaf))| field == leaf)
+            && self.interlinks_proof.valid(&self.header.extension_root)
+    }
+}
+
+/// Binary format of the node: length-prefixed header bytes, the interlinks count and ids, then
+/// the length-prefixed interlinks proof
+impl SigmaSerializable for PoPowHeader {
+    fn sigma_serialize<W: SigmaByteWrite>(&self, w: &mut W) -> SigmaSerializeResult {
+        write_header(&self.header, w)?;
+        w.put_usize_as_u32_unwrapped(self.interlinks.len())?;
+        self.interlinks
+            .iter()
+            .try_for_each(|id| id.0.sigma_serialize(w))?;
+        let proof_bytes = self.interlinks_proof.sigma_serialize_bytes()?;
+        w.put_usize_as_u32_unwrapped(proof_bytes.len())?;
+        w.write_all(&proof_bytes)?;
+        Ok(())
+    }
+
+    fn sigma_parse<R: SigmaByteRead>(r: &mut R) -> Result<Self, SigmaParsingError> {
+        let header = read_header(r)?;
+        let interlinks_count = r.get_u32()?;
+        let interlinks = (0..interlinks_count)
+            .map(|_| Digest32::sigma_parse(r).map(BlockId))
+            .collect::<Result<Vec<_>, _>>()?;
+        let proof_size = r.get_u32()?;
+        let proof_bytes = r.get_bytes(proof_size as usize)?;
+        let interlinks_proof = BatchMerkleProof::sigma_parse_bytes(&proof_bytes)?;
+        Ok(PoPowHeader {
+            header,
+            interlinks,
+            interlinks_proof,
+        })
+    }
+}
+
+/// Writes the header bytes prefixed with their length
+pub(crate) fn write_header<W: SigmaByteWrite>(header: &Header, w: &mut W) -> SigmaSerializeResult {
+    let bytes = header.sigma_serialize_bytes()?;
+    w.put_usize_as_u32_unwrapped(bytes.len())?;
+    w.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Reads the header bytes prefixed with their length
+pub(crate) fn read_header<R: SigmaByteRead>(r: &mut R) -> Result<Header, SigmaParsingError> {
+    let size = r.get_u32()?;
+    let bytes = r.get_bytes(size as usize)?;
+    Header::sigma_parse_bytes(&bytes)
+}
@@ -17,6 +17,7 @@
 #![deny(clippy::unimplemented)]
 #![deny(clippy::panic)]
 
+mod message;
 mod peer_addr;
 mod peer_database;
 mod peer_feature;
@@ -24,6 +25,7 @@ mod peer_info;
 mod peer_spec;
 mod protocol_version;
 
+pub use message::{MagicBytes, MessageCodec, MessageCodecError, MessageDecoder, RawMessage};
 pub use peer_addr::PeerAddr;
 pub use peer_database::{in_memory::InMemoryPeerDatabase, PeerDatabase, PeerDatabaseError};
 pub use peer_feature::{LocalAddressPeerFeature, PeerFeature, PeerFeatureId};
@@ -0,0 +1,405 @@
+//! Network message envelope
+//! see <https://github.com/ergoplatform/ergo/blob/master/src/main/scala/scorex/core/network/message/MessageSerializer.scala>
+
+use std::convert::TryFrom;
+
+use sigma_util::hash::blake2b256_hash;
+use thiserror::Error;
+
+/// Network magic bytes, the first bytes of every message
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Hash)]
+pub struct MagicBytes(pub [u8; MagicBytes::SIZE]);
+
+impl MagicBytes {
+    /// Size in bytes
+    pub const SIZE: usize = 4;
+    /// Mainnet magic bytes
+    pub const MAINNET: MagicBytes = MagicBytes([1, 0, 2, 4]);
+    /// Testnet magic bytes
+    pub const TESTNET: MagicBytes = MagicBytes([2, 0, 2, 3]);
+}
+
+/// Network message with the body not parsed yet
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct RawMessage {
+    /// Message code (type of the message)
+    pub code: u8,
+    /// Serialized message
+    pub body: Vec<u8>,
+}
+
+/// Message framing errors
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum MessageCodecError {
+    /// Message starts with unexpected magic bytes
+    #[error("invalid magic bytes {actual:?}, expected {expected:?}")]
+    InvalidMagic {
+        /// Network magic bytes
+        expected: MagicBytes,
+        /// Received magic bytes
+        actual: [u8; MagicBytes::SIZE],
+    },
+    /// Body length is negative or exceeds the limit
+    #[error("message body length {length} exceeds the limit {max}")]
+    MessageTooLarge {
+        /// Body length from the message header (signed in the wire format)
+        length: i64,
+        /// Maximum body length
+        max: usize,
+    },
+    /// Checksum differs from the hash of the body
+    #[error("invalid checksum {actual:?}, expected {expected:?}")]
+    InvalidChecksum {
+        /// Checksum of the received body
+        expected: [u8; MessageCodec::CHECKSUM_LENGTH],
+        /// Checksum from the message
+        actual: [u8; MessageCodec::CHECKSUM_LENGTH],
+    },
+    /// IO error
+    #[error("IO error: {0}")]
+    Io(String),
+}
+
+impl From<std::io::Error> for MessageCodecError {
+    fn from(e: std::io::Error) -> Self {
+        MessageCodecError::Io(e.to_string())
+    }
+}
+
+/// Message framing: magic bytes, message code, body length (4 bytes, big-endian), checksum (the
+/// first 4 bytes of Blake2b256 of the body, omitted for the empty body) and the body
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct MessageCodec {
+    magic: MagicBytes,
+    max_body_length: usize,
+}
+
+impl MessageCodec {
+    /// Length of the message header (magic bytes, code and body length)
+    pub const HEADER_LENGTH: usize = MagicBytes::SIZE + 1 + 4;
+    /// Length of the body checksum
+    pub const CHECKSUM_LENGTH: usize = 4;
+    /// Default maximum body length (2 MiB)
+    pub const DEFAULT_MAX_BODY_LENGTH: usize = 2 * 1024 * 1024;
+
+    /// Create new MessageCodec instance
+    pub fn new(magic: MagicBytes, max_body_length: usize) -> Self {
+        MessageCodec {
+            magic,
+            max_body_length,
+        }
+    }
+
+    /// Mainnet codec with the default body length limit
+    pub fn mainnet() -> Self {
+        MessageCodec::new(MagicBytes::MAINNET, Self::DEFAULT_MAX_BODY_LENGTH)
+    }
+
+    /// Network magic bytes
+    pub fn magic(&self) -> MagicBytes {
+        self.magic
+    }
+
+    /// Maximum body length
+    pub fn max_body_length(&self) -> usize {
+        self.max_body_length
+    }
+
+    /// Appends the framed message to `dst`
+    pub fn encode(&self, msg: &RawMessage, dst: &mut Vec<u8>) -> Result<(), MessageCodecError> {
+        let length = self.check_length(msg.body.len() as i64)?;
+        dst.reserve(Self::HEADER_LENGTH + Self::CHECKSUM_LENGTH + length);
+        dst.extend_from_slice(&self.magic.0);
+        dst.push(msg.code);
+        dst.extend_from_slice(&(length as u32).to_be_bytes());
+        if length > 0 {
+            dst.extend_from_slice(&checksum(&msg.body));
+            dst.extend_from_slice(&msg.body);
+        }
+        Ok(())
+    }
+
+    /// Frames the message
+    pub fn encode_to_vec(&self, msg: &RawMessage) -> Result<Vec<u8>, MessageCodecError> {
+        let mut bytes = Vec::new();
+        self.encode(msg, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Decodes the first message in `src` (which may contain a part of the message only).
+    /// Returns the message and the number of bytes it takes, or `None` if more bytes are needed.
+    /// The header is checked as soon as it is received, so an oversized message is rejected
+    /// before its body arrives.
+    pub fn decode(&self, src: &[u8]) -> Result<Option<(RawMessage, usize)>, MessageCodecError> {
+        let header = match src.get(..Self::HEADER_LENGTH) {
+            Some(header) => header,
+            None => return self.check_magic(src).map(|_| None),
+        };
+        self.check_magic(header)?;
+        let code = header[MagicBytes::SIZE];
+        let mut length_bytes = [0u8; 4];
+        length_bytes.copy_from_slice(&header[MagicBytes::SIZE + 1..]);
+        let length = self.check_length(i32::from_be_bytes(length_bytes) as i64)?;
+        if length == 0 {
+            return Ok(Some((
+                RawMessage { code, body: vec![] },
+                Self::HEADER_LENGTH,
+            )));
+        }
+        let body_start = Self::HEADER_LENGTH + Self::CHECKSUM_LENGTH;
+        let message_length = body_start + length;
+        let (actual_checksum, body) = match (
+            src.get(Self::HEADER_LENGTH..body_start),
+            src.get(body_start..message_length),
+        ) {
+            (Some(actual_checksum), Some(body)) => (actual_checksum, body),
+            _ => return Ok(None),
+        };
+        let expected = checksum(body);
+        if actual_checksum != expected {
+            let mut actual = [0u8; Self::CHECKSUM_LENGTH];
+            actual.copy_from_slice(actual_checksum);
+            return Err(MessageCodecError::InvalidChecksum { expected, actual });
+        }
+        Ok(Some((
+            RawMessage {
+                code,
+                body: body.to_vec(),
+            },
+            message_length,
+        )))
+    }
+
+    /// Checks the received magic bytes (`bytes` may be shorter than the magic)
+    fn check_magic(&self, bytes: &[u8]) -> Result<(), MessageCodecError> {
+        let received = &bytes[..bytes.len().min(MagicBytes::SIZE)];
+        if self.magic.0.starts_with(received) {
+            Ok(())
+        } else {
+            let mut actual = [0u8; MagicBytes::SIZE];
+            actual[..received.len()].copy_from_slice(received);
+            Err(MessageCodecError::InvalidMagic {
+                expected: self.magic,
+                actual,
+            })
+        }
+    }
+
+    fn check_length(&self, length: i64) -> Result<usize, MessageCodecError> {
+        match usize::try_from(length) {
+            Ok(length) if length <= self.max_body_length && length <= i32::MAX as usize => {
+                Ok(length)
+            }
+            Ok(_) | Err(_) => Err(MessageCodecError::MessageTooLarge {
+                length,
+                max: self.max_body_length,
+            }),
+        }
+    }
+}
+
+impl Default for MessageCodec {
+    fn default() -> Self {
+        MessageCodec::mainnet()
+    }
+}
+
+/// Streaming decoder buffering the received bytes until complete messages can be decoded
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MessageDecoder {
+    codec: MessageCodec,
+    buffer: Vec<u8>,
+}
+
+impl MessageDecoder {
+    /// Create new MessageDecoder instance
+    pub fn new(codec: MessageCodec) -> Self {
+        MessageDecoder {
+            codec,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Appends the received bytes
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Number of the received bytes not decoded yet
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Decodes the next message from the received bytes, `None` if more bytes are needed. After
+    /// an error the stream is out of sync and the connection should be dropped.
+    pub fn next_message(&mut self) -> Result<Option<RawMessage>, MessageCodecError> {
+        Ok(self.codec.decode(&self.buffer)?.map(|(msg, length)| {
+            self.buffer.drain(..length);
+            msg
+        }))
+    }
+}
+
+#[cfg(feature = "tokio-codec")]
+impl tokio_util::codec::Encoder<RawMessage> for MessageCodec {
+    type Error = MessageCodecError;
+
+    fn encode(&mut self, item: RawMessage, dst: &mut bytes::BytesMut) -> Result<(), Self::Error> {
+        let bytes = self.encode_to_vec(&item)?;
+        dst.extend_from_slice(&bytes);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio-codec")]
+impl tokio_util::codec::Decoder for MessageCodec {
+    type Item = RawMessage;
+    type Error = MessageCodecError;
+
+    fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        use bytes::Buf;
+        Ok(MessageCodec::decode(self, src)?.map(|(msg, length)| {
+            src.advance(length);
+            msg
+        }))
+    }
+}
+
+/// The first bytes of Blake2b256 hash of the body
+fn checksum(body: &[u8]) -> [u8; MessageCodec::CHECKSUM_LENGTH] {
+    let mut checksum = [0u8; MessageCodec::CHECKSUM_LENGTH];
+    checksum.copy_from_slice(&blake2b256_hash(body)[..MessageCodec::CHECKSUM_LENGTH]);
+    checksum
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn msg(code: u8, len: usize) -> RawMessage {
+        RawMessage {
+            code,
+            body: (0..len).map(|i| i as u8).collect(),
+        }
+    }
+
+    #[test]
+    fn frame_format() {
+        let codec = MessageCodec::mainnet();
+        let body = vec![1, 2, 3];
+        let bytes = codec
+            .encode_to_vec(&RawMessage {
+                code: 1,
+                body: body.clone(),
+            })
+            .unwrap();
+        let mut expected = vec![1, 0, 2, 4, 1, 0, 0, 0, 3];
+        expected.extend_from_slice(&blake2b256_hash(&body)[..4]);
+        expected.extend_from_slice(&body);
+        assert_eq!(bytes, expected);
+        // no checksum for the empty body
+        assert_eq!(
+            codec.encode_to_vec(&msg(55, 0)).unwrap(),
+            vec![1, 0, 2, 4, 55, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let codec = MessageCodec::new(MagicBytes::TESTNET, 1000);
+        for m in [msg(1, 0), msg(2, 1), msg(65, 1000)] {
+            let bytes = codec.encode_to_vec(&m).unwrap();
+            assert_eq!(codec.decode(&bytes), Ok(Some((m, bytes.len()))));
+        }
+    }
+
+    #[test]
+    fn streaming() {
+        let codec = MessageCodec::mainnet();
+        let messages = vec![msg(1, 10), msg(2, 0), msg(3, 300)];
+        let mut bytes = Vec::new();
+        for m in &messages {
+            codec.encode(m, &mut bytes).unwrap();
+        }
+        // feed the bytes in chunks not aligned with the messages
+        let mut decoder = MessageDecoder::new(codec);
+        let mut decoded = Vec::new();
+        for chunk in bytes.chunks(7) {
+            decoder.push(chunk);
+            while let Some(m) = decoder.next_message().unwrap() {
+                decoded.push(m);
+            }
+        }
+        assert_eq!(decoded, messages);
+        assert_eq!(decoder.buffered(), 0);
+        // incomplete message
+        assert_eq!(
+            codec.decode(&bytes[..bytes.len() - 1]).unwrap().unwrap().0,
+            messages[0]
+        );
+        assert_eq!(codec.decode(&bytes[..3]), Ok(None));
+        assert_eq!(codec.decode(&bytes[..20]), Ok(None));
+    }
+
+    #[test]
+    fn invalid_messages() {
+        let codec = MessageCodec::new(MagicBytes::MAINNET, 100);
+        let mut bytes = codec.encode_to_vec(&msg(1, 100)).unwrap();
+
+        let testnet = MessageCodec::new(MagicBytes::TESTNET, 100);
+        assert_eq!(
+            testnet.decode(&bytes[..1]),
+            Err(MessageCodecError::InvalidMagic {
+                expected: MagicBytes::TESTNET,
+                actual: [1, 0, 0, 0]
+            })
+        );
+
+        let small = MessageCodec::new(MagicBytes::MAINNET, 99);
+        // rejected as soon as the header is received
+        assert_eq!(
+            small.decode(&bytes[..MessageCodec::HEADER_LENGTH]),
+            Err(MessageCodecError::MessageTooLarge {
+                length: 100,
+                max: 99
+            })
+        );
+        assert!(small.encode_to_vec(&msg(1, 100)).is_err());
+        let negative = [1, 0, 2, 4, 1, 0xff, 0xff, 0xff, 0xff];
+        assert_eq!(
+            codec.decode(&negative),
+            Err(MessageCodecError::MessageTooLarge {
+                length: -1,
+                max: 100
+            })
+        );
+
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(matches!(
+            codec.decode(&bytes),
+            Err(MessageCodecError::InvalidChecksum { .. })
+        ));
+    }
+
+    #[cfg(feature = "tokio-codec")]
+    #[test]
+    fn tokio_codec() {
+        use bytes::BytesMut;
+        use tokio_util::codec::{Decoder, Encoder};
+
+        let mut codec = MessageCodec::mainnet();
+        let mut buf = BytesMut::new();
+        Encoder::encode(&mut codec, msg(1, 10), &mut buf).unwrap();
+        Encoder::encode(&mut codec, msg(2, 20), &mut buf).unwrap();
+        let mut partial = buf.split_to(5);
+        assert_eq!(Decoder::decode(&mut codec, &mut partial), Ok(None));
+        partial.unsplit(buf);
+        let mut buf = partial;
+        assert_eq!(Decoder::decode(&mut codec, &mut buf), Ok(Some(msg(1, 10))));
+        assert_eq!(Decoder::decode(&mut codec, &mut buf), Ok(Some(msg(2, 20))));
+        assert_eq!(Decoder::decode(&mut codec, &mut buf), Ok(None));
+        assert!(buf.is_empty());
+    }
+}
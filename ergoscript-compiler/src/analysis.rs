@@ -0,0 +1,511 @@
+//! Source code analysis for editor tooling (language server, etc.)
+
+use ergotree_ir::pretty_printer::print_type;
+use ergotree_ir::types::stype::SType;
+use ergotree_ir::types::stype_companion::STypeCompanion;
+use rowan::TextRange;
+use rowan::TextSize;
+
+use crate::binder::Binder;
+use crate::compiler::compile_hir;
+use crate::error::trim_trailing_whitespace;
+use crate::hir;
+use crate::hir::ExprKind;
+use crate::parser::parse;
+use crate::script_env::ScriptEnv;
+use crate::syntax::SyntaxElement;
+use crate::syntax::SyntaxKind;
+use crate::syntax::SyntaxNode;
+use crate::syntax::SyntaxToken;
+use crate::type_infer::assign_type;
+
+/// Global variables available in every script
+const GLOBALS: [(&str, SType); 1] = [("HEIGHT", SType::SInt)];
+
+/// Functions evaluated at compile time (see [`crate::binder`])
+const PREDEF_FUNCS: [(&str, &str); 4] = [
+    ("fromBase16", "(String) => Coll[Byte]"),
+    ("fromBase58", "(String) => Coll[Byte]"),
+    ("fromBase64", "(String) => Coll[Byte]"),
+    ("bigInt", "(String) => BigInt"),
+];
+
+/// Well-known receivers whose methods are completed even when they are not in scope
+const KNOWN_RECEIVERS: [(&str, SType); 2] = [("SELF", SType::SBox), ("CONTEXT", SType::SContext)];
+
+/// Inferred type of the innermost expression at the given offset along with the span of that
+/// expression. Returns `None` if the source does not type check.
+pub fn type_at(source: &str, offset: TextSize) -> Option<(TextRange, SType)> {
+    let hir = compile_hir(source).ok()?;
+    let bound = Binder::new(ScriptEnv::new()).bind(hir).ok()?;
+    let typed = assign_type(bound).ok()?;
+    innermost_typed_expr(&typed, offset).and_then(|e| {
+        e.tpe
+            .clone()
+            .map(|tpe| (trim_trailing_whitespace(source, e.span), tpe))
+    })
+}
+
+/// Span of the name in the definition (`val`, `def` or a function parameter) referenced by the
+/// identifier at the given offset
+pub fn definition_at(source: &str, offset: TextSize) -> Option<TextRange> {
+    let root = parse(source).syntax();
+    let token = ident_token_at(&root, offset)?;
+    if token.parent().kind() != SyntaxKind::Ident {
+        return None;
+    }
+    visible_definitions(&root, token.text_range().end())
+        .into_iter()
+        .find(|def| def.name.text() == token.text())
+        .map(|def| def.name.text_range())
+}
+
+/// Kind of the completion item
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CompletionKind {
+    /// `val` or a function parameter
+    Value,
+    /// `def` or a built-in function
+    Function,
+    /// Global variable
+    Global,
+    /// Method (or property) of an object
+    Method,
+}
+
+/// Completion item
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Completion {
+    /// Text to insert
+    pub label: String,
+    /// Type (signature) in ErgoScript syntax
+    pub detail: String,
+    /// Kind of the item
+    pub kind: CompletionKind,
+}
+
+/// Completion items for the identifier being typed at the given offset.
+/// After a `.` the methods of the receiver's type are suggested (from the
+/// [`STypeCompanion`] method tables), otherwise the names in scope, globals and built-in
+/// functions. Only items starting with the already typed prefix are returned.
+pub fn completions(source: &str, offset: TextSize) -> Vec<Completion> {
+    let offset_usize = usize::from(offset).min(source.len());
+    let before = &source[..offset_usize];
+    let prefix_start = before
+        .rfind(|c: char| !is_ident_char(c))
+        .map_or(0, |idx| idx + 1);
+    let prefix = &before[prefix_start..];
+    let items = match before[..prefix_start].strip_suffix('.') {
+        Some(receiver_src) => {
+            let receiver_start = receiver_src
+                .rfind(|c: char| !is_ident_char(c))
+                .map_or(0, |idx| idx + 1);
+            let receiver = &receiver_src[receiver_start..];
+            if receiver.is_empty() {
+                return Vec::new();
+            }
+            // type the source without the member access being typed
+            let stripped = format!("{}{}", receiver_src, &source[offset_usize..]);
+            receiver_type(receiver, &stripped, receiver_src.len())
+                .map(|tpe| method_completions(&tpe))
+                .unwrap_or_default()
+        }
+        None => scope_completions(source, prefix_start, offset_usize),
+    };
+    items
+        .into_iter()
+        .filter(|c| c.label.starts_with(prefix))
+        .collect()
+}
+
+/// Type name in ErgoScript syntax (e.g. `Coll[Byte]`, `(Int, Long) => Boolean`)
+pub fn type_name(tpe: &SType) -> String {
+    print_type(tpe)
+}
+
+fn type_names<'a>(types: impl Iterator<Item = &'a SType>) -> String {
+    types.map(type_name).collect::<Vec<String>>().join(", ")
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn receiver_type(receiver: &str, source: &str, receiver_end: usize) -> Option<SType> {
+    KNOWN_RECEIVERS
+        .iter()
+        .chain(GLOBALS.iter())
+        .find(|(name, _)| *name == receiver)
+        .map(|(_, tpe)| tpe.clone())
+        .or_else(|| type_at(source, TextSize::from(receiver_end as u32 - 1)).map(|(_, tpe)| tpe))
+}
+
+fn type_companion(tpe: &SType) -> Option<STypeCompanion> {
+    Some(match tpe {
+        SType::SContext => STypeCompanion::Context,
+        SType::SBox => STypeCompanion::Box,
+        SType::SColl(_) => STypeCompanion::Coll,
+        SType::SGroupElement => STypeCompanion::GroupElem,
+        SType::SGlobal => STypeCompanion::Global,
+        SType::SHeader => STypeCompanion::Header,
+        SType::SPreHeader => STypeCompanion::PreHeader,
+        SType::SOption(_) => STypeCompanion::Option,
+        SType::SAvlTree => STypeCompanion::AvlTree,
+        _ => return None,
+    })
+}
+
+fn method_completions(tpe: &SType) -> Vec<Completion> {
+    type_companion(tpe)
+        .map(|companion| {
+            companion
+                .methods()
+                .into_iter()
+                .map(|m| {
+                    // the first argument is the receiver (object) itself
+                    let args = m.tpe().t_dom.iter().skip(1);
+                    let res = type_name(&m.tpe().t_range);
+                    let detail = if args.len() == 0 {
+                        res
+                    } else {
+                        format!("({}) => {}", type_names(args), res)
+                    };
+                    Completion {
+                        label: m.name().to_string(),
+                        detail,
+                        kind: CompletionKind::Method,
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn scope_completions(source: &str, prefix_start: usize, offset: usize) -> Vec<Completion> {
+    let root = parse(source).syntax();
+    let prefix = &source[prefix_start..offset];
+    let mut items: Vec<Completion> = Vec::new();
+    for def in visible_definitions(&root, TextSize::from(offset as u32)) {
+        let label = def.name.text().to_string();
+        // the innermost definition shadows the outer ones
+        if !label.starts_with(prefix) || items.iter().any(|c| c.label == label) {
+            continue;
+        }
+        // the prefix being typed is not in scope, so type the source with the completed name
+        let completed = format!("{}{}{}", &source[..prefix_start], label, &source[offset..]);
+        let tpe = type_at(&completed, TextSize::from(prefix_start as u32))
+            .map(|(_, tpe)| type_name(&tpe));
+        items.push(Completion {
+            label,
+            detail: def.tpe.or(tpe).unwrap_or_default(),
+            kind: def.kind,
+        });
+    }
+    items.extend(GLOBALS.iter().map(|(name, tpe)| Completion {
+        label: name.to_string(),
+        detail: type_name(tpe),
+        kind: CompletionKind::Global,
+    }));
+    items.extend(PREDEF_FUNCS.iter().map(|(name, signature)| Completion {
+        label: name.to_string(),
+        detail: signature.to_string(),
+        kind: CompletionKind::Function,
+    }));
+    items
+}
+
+fn innermost_typed_expr(expr: &hir::Expr, offset: TextSize) -> Option<&hir::Expr> {
+    if !expr.span.contains_inclusive(offset) {
+        return None;
+    }
+    let children: Vec<&hir::Expr> = match &expr.kind {
+        ExprKind::Binary(b) => vec![&b.lhs, &b.rhs],
+        ExprKind::Block(b) => b.items.iter().chain(std::iter::once(&*b.result)).collect(),
+        ExprKind::Val(v) => vec![&v.rhs],
+        ExprKind::Lambda(l) => vec![&l.body],
+        ExprKind::Apply(a) => std::iter::once(&*a.func).chain(a.args.iter()).collect(),
+        ExprKind::Ident(_)
+        | ExprKind::GlobalVars(_)
+        | ExprKind::Literal(_)
+        | ExprKind::Const(_)
+        | ExprKind::ConstPlaceholder(_) => Vec::new(),
+    };
+    // the later child wins when the offset is on the boundary between two children
+    children
+        .into_iter()
+        .rev()
+        .find_map(|child| innermost_typed_expr(child, offset))
+        .or_else(|| expr.tpe.as_ref().map(|_| expr))
+}
+
+fn ident_token_at(root: &SyntaxNode, offset: TextSize) -> Option<SyntaxToken> {
+    root.token_at_offset(offset)
+        .find(|token| token.kind() == SyntaxKind::Ident)
+}
+
+/// Named definition visible in some scope
+struct Definition {
+    name: SyntaxToken,
+    /// declared type (only known for function parameters without type inference)
+    tpe: Option<String>,
+    kind: CompletionKind,
+}
+
+/// Definitions visible at `offset`, the innermost scope first and the latest definition in the
+/// scope first
+fn visible_definitions(root: &SyntaxNode, offset: TextSize) -> Vec<Definition> {
+    let mut defs = Vec::new();
+    collect_definitions(root, offset, &mut defs);
+    defs.reverse();
+    defs
+}
+
+fn collect_definitions(node: &SyntaxNode, offset: TextSize, defs: &mut Vec<Definition>) {
+    match node.kind() {
+        SyntaxKind::Root | SyntaxKind::BlockExpr => {
+            for stmt in node.children() {
+                let stmt_range = non_trivia_range(&stmt);
+                if stmt_range.end() < offset {
+                    let kind = match stmt.kind() {
+                        SyntaxKind::VariableDef => CompletionKind::Value,
+                        SyntaxKind::FnDef => CompletionKind::Function,
+                        _ => continue,
+                    };
+                    if let Some(name) = name_token(&stmt) {
+                        defs.push(Definition {
+                            name,
+                            tpe: None,
+                            kind,
+                        });
+                    }
+                } else if is_inside(stmt_range, offset) {
+                    collect_definitions(&stmt, offset, defs);
+                }
+            }
+        }
+        _ => {
+            if node.kind() == SyntaxKind::FnDef && is_in_fn_body(node, offset) {
+                let params = node
+                    .children()
+                    .filter(|n| n.kind() == SyntaxKind::ParamList)
+                    .flat_map(|params| params.children())
+                    .filter_map(|param| {
+                        Some(Definition {
+                            name: name_token(&param)?,
+                            tpe: param
+                                .children()
+                                .find(|n| n.kind() == SyntaxKind::TypeRef)
+                                .map(|t| non_trivia_text(&t)),
+                            kind: CompletionKind::Value,
+                        })
+                    });
+                defs.extend(params);
+            }
+            if let Some(child) = node
+                .children()
+                .find(|child| is_inside(non_trivia_range(child), offset))
+            {
+                collect_definitions(&child, offset, defs);
+            }
+        }
+    }
+}
+
+/// Offset is after the first token of the range and not after its end (cursor at the end of the
+/// node being typed)
+fn is_inside(range: TextRange, offset: TextSize) -> bool {
+    range.start() < offset && offset <= range.end()
+}
+
+fn is_in_fn_body(fn_def: &SyntaxNode, offset: TextSize) -> bool {
+    fn_def
+        .children_with_tokens()
+        .filter_map(SyntaxElement::into_token)
+        .filter(|token| token.kind() == SyntaxKind::Equals)
+        .any(|eq| eq.text_range().end() <= offset)
+}
+
+fn non_trivia_tokens(node: &SyntaxNode) -> impl Iterator<Item = SyntaxToken> {
+    node.descendants_with_tokens()
+        .filter_map(SyntaxElement::into_token)
+        .filter(|token| !matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment))
+}
+
+fn non_trivia_range(node: &SyntaxNode) -> TextRange {
+    let mut tokens = non_trivia_tokens(node);
+    match tokens.next() {
+        Some(first) => {
+            let last = tokens.last().unwrap_or_else(|| first.clone());
+            TextRange::new(first.text_range().start(), last.text_range().end())
+        }
+        None => TextRange::empty(node.text_range().start()),
+    }
+}
+
+fn non_trivia_text(node: &SyntaxNode) -> String {
+    non_trivia_tokens(node)
+        .map(|t| t.text().to_string())
+        .collect()
+}
+
+fn name_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.children_with_tokens()
+        .filter_map(SyntaxElement::into_token)
+        .find(|token| token.kind() == SyntaxKind::Ident)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::expect;
+    use expect_test::Expect;
+
+    /// `$0` in the input marks the cursor position
+    fn cursor(input: &str) -> (String, TextSize) {
+        let offset = input.find("$0").unwrap();
+        (input.replace("$0", ""), TextSize::from(offset as u32))
+    }
+
+    fn check_type_at(input: &str, expected: Expect) {
+        let (source, offset) = cursor(input);
+        let res = type_at(&source, offset)
+            .map(|(span, tpe)| format!("{}: {}", &source[span], type_name(&tpe)));
+        expected.assert_eq(&format!("{:?}", res));
+    }
+
+    fn check_definition_at(input: &str, expected: Expect) {
+        let (source, offset) = cursor(input);
+        let res = definition_at(&source, offset).map(|span| format!("{:?}", span));
+        expected.assert_eq(&format!("{:?}", res));
+    }
+
+    fn check_completions(input: &str, expected: Expect) {
+        let (source, offset) = cursor(input);
+        let res = completions(&source, offset)
+            .into_iter()
+            .map(|c| format!("{:?} {}: {}", c.kind, c.label, c.detail))
+            .collect::<Vec<String>>()
+            .join("\n");
+        expected.assert_eq(&res);
+    }
+
+    #[test]
+    fn type_of_ident() {
+        check_type_at(
+            "{ val a = 1L \n a$0 + 2L }",
+            expect![[r#"Some("a: Long")"#]],
+        );
+    }
+
+    #[test]
+    fn type_of_fn() {
+        check_type_at(
+            "{ def f$0(x: Int) = x \n f(1) }",
+            expect![[r#"Some("def f(x: Int) = x: (Int) => Int")"#]],
+        );
+    }
+
+    #[test]
+    fn type_of_ill_typed() {
+        check_type_at("{ val a = 1L \n a$0 + b }", expect!["None"]);
+    }
+
+    #[test]
+    fn definition_of_val() {
+        check_definition_at(
+            "{ val a = 1 \n val b = a \n b$0 }",
+            expect![[r#"Some("18..19")"#]],
+        );
+    }
+
+    #[test]
+    fn definition_of_param() {
+        check_definition_at(
+            "{ val x = 1 \n def f(x: Int) = x$0 \n f(x) }",
+            expect![[r#"Some("20..21")"#]],
+        );
+    }
+
+    #[test]
+    fn definition_shadowed() {
+        check_definition_at(
+            "{ val x = 1 \n val y = { val x = 2 \n x$0 } \n y }",
+            expect![[r#"Some("28..29")"#]],
+        );
+    }
+
+    #[test]
+    fn definition_not_found() {
+        check_definition_at("{ val x = 1 \n y$0 }", expect!["None"]);
+    }
+
+    #[test]
+    fn complete_scope() {
+        check_completions(
+            "{ val a = 1 \n def f(x: Box) = HEIGHT \n $0 }",
+            expect![[r#"
+                Function f: (Box) => Int
+                Value a: Int
+                Global HEIGHT: Int
+                Function fromBase16: (String) => Coll[Byte]
+                Function fromBase58: (String) => Coll[Byte]
+                Function fromBase64: (String) => Coll[Byte]
+                Function bigInt: (String) => BigInt"#]],
+        );
+    }
+
+    #[test]
+    fn complete_scope_with_types() {
+        check_completions(
+            "{ val abc = 1 \n def f(x: Box) = HEIGHT \n a$0 }",
+            expect!["Value abc: Int"],
+        );
+    }
+
+    #[test]
+    fn complete_prefix() {
+        check_completions(
+            "{ val abc = 1L \n fromB$0 }",
+            expect![[r#"
+            Function fromBase16: (String) => Coll[Byte]
+            Function fromBase58: (String) => Coll[Byte]
+            Function fromBase64: (String) => Coll[Byte]"#]],
+        );
+    }
+
+    #[test]
+    fn complete_self_methods() {
+        check_completions(
+            "SELF.$0",
+            expect![[r#"
+            Method getReg: (Byte) => Option[T]
+            Method value: Long
+            Method tokens: Coll[(Coll[Byte], Long)]"#]],
+        );
+    }
+
+    #[test]
+    fn complete_param_methods() {
+        check_completions(
+            "{ def f(b: Box) = b.to$0 \n 1 }",
+            expect!["Method tokens: Coll[(Coll[Byte], Long)]"],
+        );
+    }
+
+    #[test]
+    fn complete_context_methods() {
+        check_completions("CONTEXT.da$0", expect!["Method dataInputs: Coll[Box]"]);
+    }
+
+    #[test]
+    fn type_names() {
+        let tpe = SType::SFunc(ergotree_ir::types::sfunc::SFunc::new(
+            vec![
+                SType::SColl(SType::SByte.into()),
+                SType::SOption(SType::SLong.into()),
+            ],
+            SType::SBoolean,
+        ));
+        assert_eq!(type_name(&tpe), "(Coll[Byte], Option[Long]) => Boolean");
+    }
+}
@@ -31,8 +31,8 @@ impl Root {
         }
     }
 
-    pub fn children(&self) -> impl Iterator<Item = Expr> {
-        self.0.children().filter_map(Expr::cast)
+    pub fn stmts(&self) -> impl Iterator<Item = Stmt> {
+        self.0.children().filter_map(Stmt::cast)
     }
 
     pub fn span(&self) -> TextRange {
@@ -57,12 +57,171 @@ impl Ident {
     }
 }
 
+#[derive(Debug)]
+pub enum Stmt {
+    VariableDef(VariableDef),
+    FnDef(FnDef),
+    Expr(Expr),
+}
+
+impl Stmt {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        let result = match node.kind() {
+            SyntaxKind::VariableDef => Self::VariableDef(VariableDef(node)),
+            SyntaxKind::FnDef => Self::FnDef(FnDef(node)),
+            _ => Self::Expr(Expr::cast(node)?),
+        };
+
+        Some(result)
+    }
+}
+
+#[derive(Debug)]
+pub struct VariableDef(SyntaxNode);
+
+impl VariableDef {
+    pub fn name(&self) -> Result<SyntaxToken, AstError> {
+        ident_token(&self.0)
+    }
+
+    pub fn rhs(&self) -> Result<Expr, AstError> {
+        self.0.children().find_map(Expr::cast).ok_or_else(|| {
+            AstError::new(
+                format!("Cannot find rhs in {:?}", self.0),
+                self.0.text_range(),
+            )
+        })
+    }
+
+    pub fn span(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+#[derive(Debug)]
+pub struct FnDef(SyntaxNode);
+
+impl FnDef {
+    pub fn name(&self) -> Result<SyntaxToken, AstError> {
+        ident_token(&self.0)
+    }
+
+    pub fn params(&self) -> Result<impl Iterator<Item = Param>, AstError> {
+        self.0
+            .children()
+            .find(|node| node.kind() == SyntaxKind::ParamList)
+            .map(|params| {
+                params
+                    .children()
+                    .filter(|node| node.kind() == SyntaxKind::Param)
+                    .map(Param)
+            })
+            .ok_or_else(|| {
+                AstError::new(
+                    format!("Cannot find params in {:?}", self.0),
+                    self.0.text_range(),
+                )
+            })
+    }
+
+    pub fn res_tpe(&self) -> Option<TypeRef> {
+        self.0.children().find_map(TypeRef::cast)
+    }
+
+    pub fn body(&self) -> Result<Expr, AstError> {
+        self.0.children().find_map(Expr::cast).ok_or_else(|| {
+            AstError::new(
+                format!("Cannot find body in {:?}", self.0),
+                self.0.text_range(),
+            )
+        })
+    }
+
+    pub fn span(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+#[derive(Debug)]
+pub struct Param(SyntaxNode);
+
+impl Param {
+    pub fn name(&self) -> Result<SyntaxToken, AstError> {
+        ident_token(&self.0)
+    }
+
+    pub fn tpe(&self) -> Result<TypeRef, AstError> {
+        self.0.children().find_map(TypeRef::cast).ok_or_else(|| {
+            AstError::new(
+                format!("Cannot find type in {:?}", self.0),
+                self.0.text_range(),
+            )
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct TypeRef(SyntaxNode);
+
+impl TypeRef {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        if node.kind() == SyntaxKind::TypeRef {
+            Some(Self(node))
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> Result<SyntaxToken, AstError> {
+        ident_token(&self.0)
+    }
+
+    /// Type arguments (`Coll[Byte]`), tuple items (`(Int, Long)`) or the domain and the range of
+    /// the function type (`Int => Long`)
+    pub fn type_args(&self) -> impl Iterator<Item = TypeRef> {
+        self.0.children().filter_map(TypeRef::cast)
+    }
+
+    pub fn is_tuple(&self) -> bool {
+        self.has_token(SyntaxKind::LParen)
+    }
+
+    pub fn is_func(&self) -> bool {
+        self.has_token(SyntaxKind::FatArrow)
+    }
+
+    fn has_token(&self, kind: SyntaxKind) -> bool {
+        self.0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .any(|token| token.kind() == kind)
+    }
+
+    pub fn span(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+fn ident_token(node: &SyntaxNode) -> Result<SyntaxToken, AstError> {
+    node.children_with_tokens()
+        .filter_map(SyntaxElement::into_token)
+        .find(|token| token.kind() == SyntaxKind::Ident)
+        .ok_or_else(|| {
+            AstError::new(
+                format!("Cannot find name in: {:?}", node),
+                node.text_range(),
+            )
+        })
+}
+
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum Expr {
     Ident(Ident),
     BinaryExpr(BinaryExpr),
     Literal(Literal),
+    BlockExpr(BlockExpr),
+    CallExpr(CallExpr),
     // ParenExpr(ParenExpr),
     // UnaryExpr(UnaryExpr),
 }
@@ -74,6 +233,9 @@ impl Expr {
             SyntaxKind::InfixExpr => Self::BinaryExpr(BinaryExpr(node)),
             SyntaxKind::IntNumber => Self::Literal(Literal(node)),
             SyntaxKind::LongNumber => Self::Literal(Literal(node)),
+            SyntaxKind::StringLiteral => Self::Literal(Literal(node)),
+            SyntaxKind::BlockExpr => Self::BlockExpr(BlockExpr(node)),
+            SyntaxKind::CallExpr => Self::CallExpr(CallExpr(node)),
             // SyntaxKind::ParenExpr => Self::ParenExpr(ParenExpr(node)),
             // SyntaxKind::PrefixExpr => Self::UnaryExpr(UnaryExpr(node)),
             _ => return None,
@@ -82,12 +244,15 @@ impl Expr {
         Some(result)
     }
 
-    // pub fn span(&self) -> TextRange {
-    //     match self {
-    //         Expr::Ident(node) => node.0.text_range(),
-    //         _ => todo!(),
-    //     }
-    // }
+    pub fn span(&self) -> TextRange {
+        match self {
+            Expr::Ident(node) => node.span(),
+            Expr::BinaryExpr(node) => node.span(),
+            Expr::Literal(node) => node.span(),
+            Expr::BlockExpr(node) => node.span(),
+            Expr::CallExpr(node) => node.span(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -143,10 +308,55 @@ impl BinaryExpr {
     }
 }
 
+#[derive(Debug)]
+pub struct BlockExpr(SyntaxNode);
+
+impl BlockExpr {
+    pub fn stmts(&self) -> impl Iterator<Item = Stmt> {
+        self.0.children().filter_map(Stmt::cast)
+    }
+
+    pub fn span(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
+#[derive(Debug)]
+pub struct CallExpr(SyntaxNode);
+
+impl CallExpr {
+    pub fn func(&self) -> Result<Expr, AstError> {
+        self.0.children().find_map(Expr::cast).ok_or_else(|| {
+            AstError::new(
+                format!("Cannot find function in {:?}", self.0),
+                self.0.text_range(),
+            )
+        })
+    }
+
+    pub fn args(&self) -> Result<impl Iterator<Item = Expr>, AstError> {
+        self.0
+            .children()
+            .find(|node| node.kind() == SyntaxKind::ArgList)
+            .map(|args| args.children().filter_map(Expr::cast))
+            .ok_or_else(|| {
+                AstError::new(
+                    format!("Cannot find args in {:?}", self.0),
+                    self.0.text_range(),
+                )
+            })
+    }
+
+    pub fn span(&self) -> TextRange {
+        self.0.text_range()
+    }
+}
+
 #[derive(Debug)]
 pub enum LiteralValue {
     Int(i32),
     Long(i64),
+    Str(String),
 }
 
 #[derive(Debug)]
@@ -155,12 +365,12 @@ pub struct Literal(SyntaxNode);
 impl Literal {
     pub fn parse(&self) -> Result<LiteralValue, AstError> {
         let text = self.0.first_token().unwrap().text().to_string();
-        if text.ends_with('L') {
-            text.strip_suffix('L')
-                .unwrap()
-                .parse()
-                .ok()
-                .map(LiteralValue::Long)
+        if self.0.kind() == SyntaxKind::StringLiteral {
+            text.strip_prefix('"')
+                .and_then(|s| s.strip_suffix('"'))
+                .map(|s| LiteralValue::Str(s.to_string()))
+        } else if let Some(digits) = text.strip_suffix(|c| c == 'L' || c == 'l') {
+            digits.parse().ok().map(LiteralValue::Long)
         } else {
             text.parse().ok().map(LiteralValue::Int)
         }
@@ -0,0 +1,85 @@
+//! ErgoScript source code formatter
+//!
+//! Usage: `ergoscript-fmt [--check] [FILE]...`
+//!
+//! Formats the given files in place, or formats stdin to stdout if no files are given.
+//! With `--check` nothing is written, the files that are not formatted are listed and the exit
+//! code is 1 if there are any. Syntax errors and IO errors result in the exit code 2.
+
+use std::io::Read;
+use std::process::exit;
+
+use ergoscript_compiler::formatter::format;
+
+const USAGE: &str = "Usage: ergoscript-fmt [--check] [FILE]...";
+
+fn main() {
+    let mut check = false;
+    let mut files = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option: {}\n{}", arg, USAGE);
+                exit(2);
+            }
+            _ => files.push(arg),
+        }
+    }
+    let res = if files.is_empty() {
+        format_stdin(check)
+    } else {
+        format_files(&files, check)
+    };
+    match res {
+        Ok(true) => (),
+        Ok(false) => exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
+    }
+}
+
+/// Returns `Ok(false)` if `check` is set and the input is not formatted
+fn format_stdin(check: bool) -> Result<bool, String> {
+    let mut source = String::new();
+    std::io::stdin()
+        .read_to_string(&mut source)
+        .map_err(|e| format!("cannot read stdin: {}", e))?;
+    let formatted = format(&source).map_err(|e| e.pretty_desc(&source))?;
+    if check {
+        if formatted != source {
+            println!("<stdin>");
+            return Ok(false);
+        }
+    } else {
+        print!("{}", formatted);
+    }
+    Ok(true)
+}
+
+/// Returns `Ok(false)` if `check` is set and any of the files is not formatted
+fn format_files(files: &[String], check: bool) -> Result<bool, String> {
+    let mut all_formatted = true;
+    for file in files {
+        let source =
+            std::fs::read_to_string(file).map_err(|e| format!("cannot read {}: {}", file, e))?;
+        let formatted =
+            format(&source).map_err(|e| format!("{}:\n{}", file, e.pretty_desc(&source)))?;
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", file);
+            all_formatted = false;
+        } else {
+            std::fs::write(file, formatted).map_err(|e| format!("cannot write {}: {}", file, e))?;
+        }
+    }
+    Ok(all_formatted)
+}
@@ -1,47 +1,113 @@
+use std::collections::HashMap;
+
+use ergotree_ir::bigint256::BigInt256;
+use ergotree_ir::mir::constant::Constant;
+use ergotree_ir::mir::constant::ConstantPlaceholder;
+use num_traits::Num;
 use rowan::TextRange;
 
+use crate::compiler::ErrorCode;
 use crate::error::pretty_error_desc;
 use crate::hir;
+use crate::hir::Apply;
 use crate::hir::Expr;
 use crate::hir::ExprKind;
 use crate::hir::GlobalVars;
+use crate::hir::Literal;
 use crate::script_env::ScriptEnv;
 
 #[derive(Debug, PartialEq)]
 pub struct BinderError {
+    code: ErrorCode,
     msg: String,
     span: TextRange,
 }
 
 impl BinderError {
-    pub fn new(msg: String, span: TextRange) -> Self {
-        Self { msg, span }
+    pub fn new(code: ErrorCode, msg: String, span: TextRange) -> Self {
+        Self { code, msg, span }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
     }
 
     pub fn pretty_desc(&self, source: &str) -> String {
         pretty_error_desc(source, self.span, &self.msg)
     }
+
+    pub fn span(&self) -> TextRange {
+        self.span
+    }
 }
 
 pub struct Binder {
     env: ScriptEnv,
+    constant_segregation: bool,
 }
 
 impl Binder {
     pub fn new(env: ScriptEnv) -> Self {
-        Binder { env }
+        Binder {
+            env,
+            constant_segregation: false,
+        }
+    }
+
+    /// Binder that replaces identifiers bound in `env` with [`ConstantPlaceholder`]s, where the
+    /// placeholder index is the position of the binding in [`ScriptEnv::sorted_bindings`]
+    pub fn with_constant_segregation(env: ScriptEnv) -> Self {
+        Binder {
+            env,
+            constant_segregation: true,
+        }
     }
 
     pub fn bind(&self, expr: Expr) -> Result<Expr, BinderError> {
-        rewrite(expr, &self.env)
+        let placeholders = if self.constant_segregation {
+            self.env
+                .sorted_bindings()
+                .into_iter()
+                .enumerate()
+                .map(|(idx, (ident, c))| {
+                    (
+                        ident.clone(),
+                        ConstantPlaceholder {
+                            id: idx as u32,
+                            tpe: c.tpe.clone(),
+                        },
+                    )
+                })
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        rewrite(expr, &self.env, &placeholders)
     }
 }
 
-fn rewrite(expr: Expr, env: &ScriptEnv) -> Result<Expr, BinderError> {
-    hir::rewrite(expr, |e| {
+fn rewrite(
+    expr: Expr,
+    env: &ScriptEnv,
+    placeholders: &HashMap<String, ConstantPlaceholder>,
+) -> Result<Expr, BinderError> {
+    hir::rewrite_in_scope(expr, |e, scope| {
         Ok(match &e.kind {
+            // local definitions shadow the environment and the global names
+            ExprKind::Ident(ident) if scope.contains(ident) => None,
             ExprKind::Ident(ident) => match env.get(ident) {
-                Some(_) => todo!(),
+                Some(c) => Some(match placeholders.get(ident) {
+                    Some(cp) => Expr {
+                        kind: cp.clone().into(),
+                        span: e.span,
+                        tpe: cp.tpe.clone().into(),
+                    },
+                    None => Expr {
+                        kind: c.clone().into(),
+                        span: e.span,
+                        tpe: c.tpe.clone().into(),
+                    },
+                }),
                 None => match ident.as_ref() {
                     "HEIGHT" => {
                         let v = GlobalVars::Height;
@@ -55,7 +121,309 @@ fn rewrite(expr: Expr, env: &ScriptEnv) -> Result<Expr, BinderError> {
                     _ => None,
                 },
             },
+            ExprKind::Apply(Apply { func, args }) => match &func.kind {
+                ExprKind::Ident(name) if scope.contains(name) => None,
+                ExprKind::Ident(name) => eval_predef_func(name, args, e.span)?.map(|c| Expr {
+                    tpe: c.tpe.clone().into(),
+                    kind: c.into(),
+                    span: e.span,
+                }),
+                _ => None,
+            },
             _ => None,
         })
     })
 }
+
+/// Evaluates predefined functions whose arguments are known at compile time, returns `None` if
+/// `name` is not one of them
+fn eval_predef_func(
+    name: &str,
+    args: &[Expr],
+    span: TextRange,
+) -> Result<Option<Constant>, BinderError> {
+    let decode: fn(&str) -> Result<Constant, String> = match name {
+        "fromBase16" => |s| {
+            base16::decode(s)
+                .map(Constant::from)
+                .map_err(|e| e.to_string())
+        },
+        "fromBase58" => |s| {
+            bs58::decode(s)
+                .into_vec()
+                .map(Constant::from)
+                .map_err(|e| e.to_string())
+        },
+        "fromBase64" => |s| {
+            base64::decode(s)
+                .map(Constant::from)
+                .map_err(|e| e.to_string())
+        },
+        "bigInt" => |s| BigInt256::from_str_radix(s, 10).map(Constant::from),
+        _ => return Ok(None),
+    };
+    match args {
+        [Expr {
+            kind: ExprKind::Literal(Literal::Str(s)),
+            span: arg_span,
+            ..
+        }] => decode(s).map(Some).map_err(|e| {
+            BinderError::new(
+                ErrorCode::InvalidLiteralEncoding,
+                format!("{0}: cannot decode {1:?}: {2}", name, s, e),
+                *arg_span,
+            )
+        }),
+        _ => Err(BinderError::new(
+            ErrorCode::InvalidPredefFuncArgs,
+            format!("{0}: expected a single string literal argument", name),
+            span,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::compile_hir;
+    use expect_test::expect;
+
+    fn check(input: &str, env: ScriptEnv, expected_tree: expect_test::Expect) {
+        let res = compile_hir(input)
+            .map_err(|e| e.pretty_desc(input))
+            .and_then(|hir| Binder::new(env).bind(hir).map_err(|e| e.pretty_desc(input)));
+        let expected_out = res.map(|tree| tree.debug_tree()).unwrap_or_else(|e| e);
+        expected_tree.assert_eq(&expected_out);
+    }
+
+    #[test]
+    fn from_base16() {
+        check(
+            "fromBase16(\"0e20\")",
+            ScriptEnv::new(),
+            expect![[r#"
+            Expr {
+                kind: Const(
+                    Constant {
+                        tpe: SColl(
+                            SByte,
+                        ),
+                        v: Coll(
+                            NativeColl(
+                                CollByte(
+                                    [
+                                        14,
+                                        32,
+                                    ],
+                                ),
+                            ),
+                        ),
+                    },
+                ),
+                span: 0..18,
+                tpe: Some(
+                    SColl(
+                        SByte,
+                    ),
+                ),
+            }"#]],
+        );
+    }
+
+    #[test]
+    fn from_base58() {
+        check(
+            "fromBase58(\"2Ns\")",
+            ScriptEnv::new(),
+            expect![[r#"
+            Expr {
+                kind: Const(
+                    Constant {
+                        tpe: SColl(
+                            SByte,
+                        ),
+                        v: Coll(
+                            NativeColl(
+                                CollByte(
+                                    [
+                                        18,
+                                        24,
+                                    ],
+                                ),
+                            ),
+                        ),
+                    },
+                ),
+                span: 0..17,
+                tpe: Some(
+                    SColl(
+                        SByte,
+                    ),
+                ),
+            }"#]],
+        );
+    }
+
+    #[test]
+    fn from_base64() {
+        check(
+            "fromBase64(\"DiA=\")",
+            ScriptEnv::new(),
+            expect![[r#"
+            Expr {
+                kind: Const(
+                    Constant {
+                        tpe: SColl(
+                            SByte,
+                        ),
+                        v: Coll(
+                            NativeColl(
+                                CollByte(
+                                    [
+                                        14,
+                                        32,
+                                    ],
+                                ),
+                            ),
+                        ),
+                    },
+                ),
+                span: 0..18,
+                tpe: Some(
+                    SColl(
+                        SByte,
+                    ),
+                ),
+            }"#]],
+        );
+    }
+
+    #[test]
+    fn from_base16_invalid() {
+        check(
+            "fromBase16(\"0e2\")",
+            ScriptEnv::new(),
+            expect![[r#"
+                fromBase16: cannot decode "0e2": Base16 data cannot have length 3 (must be even)
+                 --> 1:12
+                  |
+                1 | fromBase16("0e2")
+                  |            ^^^^^"#]],
+        );
+    }
+
+    #[test]
+    fn from_base16_not_a_literal() {
+        check(
+            "1 + fromBase16(HEIGHT)",
+            ScriptEnv::new(),
+            expect![[r#"
+                fromBase16: expected a single string literal argument
+                 --> 1:5
+                  |
+                1 | 1 + fromBase16(HEIGHT)
+                  |     ^^^^^^^^^^^^^^^^^^"#]],
+        );
+    }
+
+    #[test]
+    fn big_int() {
+        check(
+            "bigInt(\"-123456789012345678901234567890\")",
+            ScriptEnv::new(),
+            expect![[r#"
+                Expr {
+                    kind: Const(
+                        Constant {
+                            tpe: SBigInt,
+                            v: BigInt(
+                                BigInt256(
+                                    Int256(-123456789012345678901234567890),
+                                ),
+                            ),
+                        },
+                    ),
+                    span: 0..41,
+                    tpe: Some(
+                        SBigInt,
+                    ),
+                }"#]],
+        );
+    }
+
+    #[test]
+    fn env_binding() {
+        let mut env = ScriptEnv::new();
+        env.insert("tokenId", vec![1u8, 2u8]);
+        check(
+            "tokenId",
+            env,
+            expect![[r#"
+            Expr {
+                kind: Const(
+                    Constant {
+                        tpe: SColl(
+                            SByte,
+                        ),
+                        v: Coll(
+                            NativeColl(
+                                CollByte(
+                                    [
+                                        1,
+                                        2,
+                                    ],
+                                ),
+                            ),
+                        ),
+                    },
+                ),
+                span: 0..7,
+                tpe: Some(
+                    SColl(
+                        SByte,
+                    ),
+                ),
+            }"#]],
+        );
+    }
+
+    fn compile_with(input: &str, env: ScriptEnv) -> ergotree_ir::mir::expr::Expr {
+        crate::compiler::compile_expr(input, env).unwrap()
+    }
+
+    #[test]
+    fn local_val_shadows_global() {
+        assert_eq!(
+            compile_with("{ val HEIGHT = 5 \n HEIGHT + 1 }", ScriptEnv::new()),
+            compile_with("{ val h = 5 \n h + 1 }", ScriptEnv::new())
+        );
+    }
+
+    #[test]
+    fn local_definitions_shadow_env() {
+        let env = || {
+            let mut env = ScriptEnv::new();
+            env.insert("x", 10i32);
+            env
+        };
+        assert_eq!(
+            compile_with("{ def f(x: Int): Int = x + 1 \n f(x) }", env()),
+            compile_with("{ def f(y: Int): Int = y + 1 \n f(x) }", env())
+        );
+        assert_eq!(
+            compile_with("{ val x = 1 \n x + x }", env()),
+            compile_with("{ val y = 1 \n y + y }", env())
+        );
+    }
+
+    #[test]
+    fn local_def_shadows_predef_func() {
+        assert_eq!(
+            compile_with(
+                "{ def fromBase16(s: Int): Int = s \n fromBase16(1) }",
+                ScriptEnv::new()
+            ),
+            compile_with("{ def g(s: Int): Int = s \n g(1) }", ScriptEnv::new())
+        );
+    }
+}
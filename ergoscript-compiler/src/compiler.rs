@@ -4,20 +4,28 @@ use super::binder::BinderError;
 use super::hir::HirLoweringError;
 use crate::ast;
 use crate::binder::Binder;
+use crate::error::trim_trailing_whitespace;
 use crate::hir;
 use crate::mir;
 use crate::parser::parse_error::ParseError;
 use crate::script_env::ScriptEnv;
 use crate::type_infer::assign_type;
 use crate::type_infer::TypeInferenceError;
+use rowan::TextRange;
 use std::convert::TryInto;
 
 extern crate derive_more;
 use derive_more::From;
 use ergotree_ir::ergo_tree::ErgoTree;
+use ergotree_ir::ergo_tree::ErgoTreeConstantError;
 use ergotree_ir::ergo_tree::ErgoTreeError;
+use ergotree_ir::ergo_tree::ErgoTreeHeader;
+use ergotree_ir::mir::constant::Constant;
+use ergotree_ir::optimizer::optimize;
 use ergotree_ir::type_check::TypeCheckError;
+use ergotree_ir::types::stype::SType;
 use mir::lower::MirLoweringError;
+use thiserror::Error;
 
 /// Compilation errors
 #[derive(Debug, PartialEq, From)]
@@ -38,30 +46,226 @@ pub enum CompileError {
     ErgoTreeError(ErgoTreeError),
 }
 
+/// Kind of the compilation error, every kind has a stable code which does not change between
+/// releases and can be used to match on the error in tools (editors, CI, etc.)
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Hash)]
+pub enum ErrorCode {
+    /// `E0101` unexpected token
+    UnexpectedToken,
+    /// `E0102` unexpected end of the source code
+    UnexpectedEof,
+    /// `E0201` syntax tree is incomplete (after parser error recovery)
+    MalformedSyntax,
+    /// `E0202` unknown binary operator
+    UnknownOperator,
+    /// `E0203` block without a result expression or with an expression before it
+    InvalidBlock,
+    /// `E0204` unknown type name
+    UnknownType,
+    /// `E0205` wrong number of type arguments (`Coll[Int, Int]`) or tuple items
+    InvalidTypeArgs,
+    /// `E0301` invalid arguments of the compile time function (`fromBase16`, `bigInt`, etc.)
+    InvalidPredefFuncArgs,
+    /// `E0302` string literal cannot be decoded (invalid Base16/58/64, number, etc.)
+    InvalidLiteralEncoding,
+    /// `E0401` unknown name
+    UnresolvedName,
+    /// `E0402` recursive reference to a value or a function
+    RecursiveDefinition,
+    /// `E0403` types of the operands, arguments or the declared result do not match
+    TypeMismatch,
+    /// `E0404` call of an expression which is not a function
+    NotAFunction,
+    /// `E0405` string literal outside of the compile time function arguments
+    UnexpectedStringLiteral,
+    /// `E0406` type of the expression cannot be inferred
+    CannotInferType,
+    /// `E0501` function without arguments or with too many arguments
+    UnsupportedFunction,
+    /// `E0502` internal error on HIR to MIR lowering (a compiler bug)
+    MirLoweringFailed,
+    /// `E0601` MIR type checking error
+    TypeCheckFailed,
+    /// `E0701` ErgoTree cannot be built from MIR
+    ErgoTreeFailed,
+}
+
+impl ErrorCode {
+    /// Stable code (see the variant docs)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedToken => "E0101",
+            ErrorCode::UnexpectedEof => "E0102",
+            ErrorCode::MalformedSyntax => "E0201",
+            ErrorCode::UnknownOperator => "E0202",
+            ErrorCode::InvalidBlock => "E0203",
+            ErrorCode::UnknownType => "E0204",
+            ErrorCode::InvalidTypeArgs => "E0205",
+            ErrorCode::InvalidPredefFuncArgs => "E0301",
+            ErrorCode::InvalidLiteralEncoding => "E0302",
+            ErrorCode::UnresolvedName => "E0401",
+            ErrorCode::RecursiveDefinition => "E0402",
+            ErrorCode::TypeMismatch => "E0403",
+            ErrorCode::NotAFunction => "E0404",
+            ErrorCode::UnexpectedStringLiteral => "E0405",
+            ErrorCode::CannotInferType => "E0406",
+            ErrorCode::UnsupportedFunction => "E0501",
+            ErrorCode::MirLoweringFailed => "E0502",
+            ErrorCode::TypeCheckFailed => "E0601",
+            ErrorCode::ErgoTreeFailed => "E0701",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl CompileError {
+    /// Kind of the error (of the first one for the parser errors), see [`ErrorCode`]
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            CompileError::ParseError(errors) => errors
+                .first()
+                .map(ParseError::code)
+                .unwrap_or(ErrorCode::UnexpectedEof),
+            CompileError::HirLoweringError(e) => e.code(),
+            CompileError::BinderError(e) => e.code(),
+            CompileError::TypeInferenceError(e) => e.code(),
+            CompileError::MirLoweringError(e) => e.code(),
+            CompileError::TypeCheckError(_) => ErrorCode::TypeCheckFailed,
+            CompileError::ErgoTreeError(_) => ErrorCode::ErgoTreeFailed,
+        }
+    }
+
     /// Pretty formatted error with CST/AST/IR, etc.
+    /// Every error is prefixed with `error[<code>]: ` (see [`CompileError::code`]),
+    /// parser errors are separated by an empty line.
     pub fn pretty_desc(&self, source: &str) -> String {
+        self.diagnostics(source)
+            .into_iter()
+            .map(|d| d.message)
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    }
+
+    /// Error(s) with location in the source code, one for every parser error
+    pub fn diagnostics(&self, source: &str) -> Vec<Diagnostic> {
+        let diagnostic = |code: ErrorCode, span: Option<TextRange>, desc: String| Diagnostic {
+            code,
+            span: span.map(|span| trim_trailing_whitespace(source, span)),
+            message: format!("error[{}]: {}", code, desc),
+        };
         match self {
-            CompileError::ParseError(errors) => {
-                errors.iter().map(|e| e.pretty_desc(source)).collect()
+            CompileError::ParseError(errors) => errors
+                .iter()
+                .map(|e| diagnostic(e.code(), Some(e.span), e.pretty_desc(source)))
+                .collect(),
+            CompileError::HirLoweringError(e) => {
+                vec![diagnostic(e.code(), Some(e.span()), e.pretty_desc(source))]
+            }
+            CompileError::BinderError(e) => {
+                vec![diagnostic(e.code(), Some(e.span()), e.pretty_desc(source))]
+            }
+            CompileError::TypeInferenceError(e) => {
+                vec![diagnostic(e.code(), Some(e.span()), e.pretty_desc(source))]
+            }
+            CompileError::MirLoweringError(e) => {
+                vec![diagnostic(e.code(), Some(e.span()), e.pretty_desc(source))]
+            }
+            CompileError::TypeCheckError(e) => {
+                vec![diagnostic(self.code(), None, e.pretty_desc())]
+            }
+            CompileError::ErgoTreeError(e) => {
+                vec![diagnostic(self.code(), None, format!("{:?}", e))]
             }
-            CompileError::HirLoweringError(e) => e.pretty_desc(source),
-            CompileError::BinderError(e) => e.pretty_desc(source),
-            CompileError::TypeInferenceError(e) => e.pretty_desc(source),
-            CompileError::MirLoweringError(e) => e.pretty_desc(source),
-            CompileError::TypeCheckError(e) => e.pretty_desc(),
-            CompileError::ErgoTreeError(e) => format!("{:?}", e),
         }
     }
 }
 
-/// Compiles given source code to [`ergotree_ir::mir::expr::Expr`], or returns an error
+/// Compilation error location and description
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Diagnostic {
+    /// Kind of the error with a stable code (see [`ErrorCode`])
+    pub code: ErrorCode,
+    /// Location in the source code (byte offsets, without trailing whitespace), if known
+    pub span: Option<TextRange>,
+    /// Pretty formatted description (see [`CompileError::pretty_desc`])
+    pub message: String,
+}
+
+/// Compiles given source code to [`ergotree_ir::mir::expr::Expr`] (not optimized, as written in
+/// the source code), or returns an error
 pub fn compile_expr(
     source: &str,
     env: ScriptEnv,
+) -> Result<ergotree_ir::mir::expr::Expr, CompileError> {
+    compile_expr_with_binder(source, Binder::new(env))
+}
+
+/// Compiles given source code to [`ErgoTree`] (not optimized), or returns an error
+pub fn compile(source: &str, env: ScriptEnv) -> Result<ErgoTree, CompileError> {
+    let expr = compile_expr(source, env)?;
+    Ok(expr.try_into()?)
+}
+
+/// Compiles given source code to [`ErgoTree`] optimized with [`optimize`], or returns an error
+pub fn compile_optimized(source: &str, env: ScriptEnv) -> Result<ErgoTree, CompileError> {
+    let expr = optimize(compile_expr(source, env)?);
+    Ok(expr.try_into()?)
+}
+
+/// Compiles given source code to constant segregated [`ErgoTree`] (not optimized) where every
+/// [`ScriptEnv`] binding becomes a named template parameter, or returns an error.
+/// Parameters are placed first in the tree's constants (sorted by name), followed by the
+/// constants from the source code.
+pub fn compile_template(source: &str, env: ScriptEnv) -> Result<CompiledTemplate, CompileError> {
+    compile_template_with(source, env, |expr| expr)
+}
+
+/// Same as [`compile_template`], but the tree is optimized with [`optimize`]
+pub fn compile_template_optimized(
+    source: &str,
+    env: ScriptEnv,
+) -> Result<CompiledTemplate, CompileError> {
+    compile_template_with(source, env, optimize)
+}
+
+fn compile_template_with(
+    source: &str,
+    env: ScriptEnv,
+    transform: fn(ergotree_ir::mir::expr::Expr) -> ergotree_ir::mir::expr::Expr,
+) -> Result<CompiledTemplate, CompileError> {
+    let bindings: Vec<(String, Constant)> = env
+        .sorted_bindings()
+        .into_iter()
+        .map(|(name, c)| (name.clone(), c.clone()))
+        .collect();
+    let expr = transform(compile_expr_with_binder(
+        source,
+        Binder::with_constant_segregation(env),
+    )?);
+    let parameters = bindings
+        .iter()
+        .enumerate()
+        .map(|(constant_index, (name, c))| TemplateParameter {
+            name: name.clone(),
+            constant_index,
+            tpe: c.tpe.clone(),
+        })
+        .collect();
+    let constants = bindings.into_iter().map(|(_, c)| c).collect();
+    let tree = ErgoTree::with_segregation(ErgoTreeHeader::v0(true), &expr, constants)?;
+    Ok(CompiledTemplate { tree, parameters })
+}
+
+fn compile_expr_with_binder(
+    source: &str,
+    binder: Binder,
 ) -> Result<ergotree_ir::mir::expr::Expr, CompileError> {
     let hir = compile_hir(source)?;
-    let binder = Binder::new(env);
     let bind = binder.bind(hir)?;
     let typed = assign_type(bind)?;
     let mir = mir::lower::lower(typed)?;
@@ -69,10 +273,73 @@ pub fn compile_expr(
     Ok(res)
 }
 
-/// Compiles given source code to [`ErgoTree`], or returns an error
-pub fn compile(source: &str, env: ScriptEnv) -> Result<ErgoTree, CompileError> {
-    let expr = compile_expr(source, env)?;
-    Ok(expr.try_into()?)
+/// Named parameter of the [`CompiledTemplate`] (a [`ScriptEnv`] binding)
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TemplateParameter {
+    /// Identifier of the binding in [`ScriptEnv`]
+    pub name: String,
+    /// Index in the constants of the constant segregated [`ErgoTree`]
+    pub constant_index: usize,
+    /// Type of the value
+    pub tpe: SType,
+}
+
+/// Constant segregated [`ErgoTree`] with named parameters
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct CompiledTemplate {
+    tree: ErgoTree,
+    parameters: Vec<TemplateParameter>,
+}
+
+impl CompiledTemplate {
+    /// Compiled tree (with the parameter values from [`ScriptEnv`])
+    pub fn tree(&self) -> &ErgoTree {
+        &self.tree
+    }
+
+    /// Parameters (sorted by name)
+    pub fn parameters(&self) -> &[TemplateParameter] {
+        self.parameters.as_ref()
+    }
+
+    /// Returns parameter with the given name (if any)
+    pub fn parameter(&self, name: &str) -> Option<&TemplateParameter> {
+        self.parameters.iter().find(|p| p.name == name)
+    }
+
+    /// Returns new template with a new value for the parameter with the given name,
+    /// see [`ErgoTree::with_constant`]
+    pub fn with_parameter(
+        self,
+        name: &str,
+        value: Constant,
+    ) -> Result<CompiledTemplate, SetTemplateParameterError> {
+        let constant_index = self
+            .parameter(name)
+            .ok_or_else(|| SetTemplateParameterError::UnknownParameter(name.to_string()))?
+            .constant_index;
+        Ok(CompiledTemplate {
+            tree: self.tree.with_constant(constant_index, value)?,
+            parameters: self.parameters,
+        })
+    }
+}
+
+impl From<CompiledTemplate> for ErgoTree {
+    fn from(template: CompiledTemplate) -> Self {
+        template.tree
+    }
+}
+
+/// Errors on setting a new template parameter value
+#[derive(Debug, From, Error)]
+pub enum SetTemplateParameterError {
+    /// There is no parameter with the given name
+    #[error("unknown template parameter: {0}")]
+    UnknownParameter(String),
+    /// Fail to set the new constant value in the tree
+    #[error("cannot set the template parameter value: {0:?}")]
+    ErgoTreeConstantError(ErgoTreeConstantError),
 }
 
 pub(crate) fn compile_hir(source: &str) -> Result<hir::Expr, CompileError> {
@@ -99,6 +366,7 @@ fn check(input: &str, expected_tree: expect_test::Expect) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ergotree_ir::mir::expr::Expr;
     use expect_test::expect;
 
     #[test]
@@ -117,10 +385,557 @@ mod tests {
         check(
             "HSB.HEIGHT",
             expect![[r#"
-                error: expected ‘+’, ‘-’, ‘*’, ‘/’, ‘val’, number, number, identifier, ‘-’ or ‘(’, but found an unrecognized token
-                line: 1
-                HSB.HEIGHT
-                  ^^"#]],
+                error[E0101]: expected ‘(’, ‘+’, ‘-’, ‘*’, ‘/’, ‘val’, ‘def’, integer, long integer, string, identifier or ‘{’, but found an unrecognized token
+                 --> 1:4
+                  |
+                1 | HSB.HEIGHT
+                  |    ^"#]],
         );
     }
+
+    #[test]
+    fn test_fn_def_call() {
+        check(
+            "{ def plus(x: Int, y: Int): Int = x + y \n plus(HEIGHT, 1) }",
+            expect![[r#"
+                BlockValue(
+                    BlockValue {
+                        items: [
+                            ValDef(
+                                ValDef {
+                                    id: ValId(
+                                        4,
+                                    ),
+                                    rhs: FuncValue(
+                                        FuncValue {
+                                            args: [
+                                                FuncArg {
+                                                    idx: ValId(
+                                                        1,
+                                                    ),
+                                                    tpe: STuple(
+                                                        STuple {
+                                                            items: BoundedVec {
+                                                                inner: [
+                                                                    SInt,
+                                                                    SInt,
+                                                                ],
+                                                            },
+                                                        },
+                                                    ),
+                                                },
+                                            ],
+                                            body: BlockValue(
+                                                BlockValue {
+                                                    items: [
+                                                        ValDef(
+                                                            ValDef {
+                                                                id: ValId(
+                                                                    2,
+                                                                ),
+                                                                rhs: SelectField(
+                                                                    SelectField {
+                                                                        input: ValUse(
+                                                                            ValUse {
+                                                                                val_id: ValId(
+                                                                                    1,
+                                                                                ),
+                                                                                tpe: STuple(
+                                                                                    STuple {
+                                                                                        items: BoundedVec {
+                                                                                            inner: [
+                                                                                                SInt,
+                                                                                                SInt,
+                                                                                            ],
+                                                                                        },
+                                                                                    },
+                                                                                ),
+                                                                            },
+                                                                        ),
+                                                                        field_index: TupleFieldIndex(
+                                                                            1,
+                                                                        ),
+                                                                        field_tpe: SInt,
+                                                                    },
+                                                                ),
+                                                            },
+                                                        ),
+                                                        ValDef(
+                                                            ValDef {
+                                                                id: ValId(
+                                                                    3,
+                                                                ),
+                                                                rhs: SelectField(
+                                                                    SelectField {
+                                                                        input: ValUse(
+                                                                            ValUse {
+                                                                                val_id: ValId(
+                                                                                    1,
+                                                                                ),
+                                                                                tpe: STuple(
+                                                                                    STuple {
+                                                                                        items: BoundedVec {
+                                                                                            inner: [
+                                                                                                SInt,
+                                                                                                SInt,
+                                                                                            ],
+                                                                                        },
+                                                                                    },
+                                                                                ),
+                                                                            },
+                                                                        ),
+                                                                        field_index: TupleFieldIndex(
+                                                                            2,
+                                                                        ),
+                                                                        field_tpe: SInt,
+                                                                    },
+                                                                ),
+                                                            },
+                                                        ),
+                                                    ],
+                                                    result: BinOp(
+                                                        BinOp {
+                                                            kind: Arith(
+                                                                Plus,
+                                                            ),
+                                                            left: ValUse(
+                                                                ValUse {
+                                                                    val_id: ValId(
+                                                                        2,
+                                                                    ),
+                                                                    tpe: SInt,
+                                                                },
+                                                            ),
+                                                            right: ValUse(
+                                                                ValUse {
+                                                                    val_id: ValId(
+                                                                        3,
+                                                                    ),
+                                                                    tpe: SInt,
+                                                                },
+                                                            ),
+                                                        },
+                                                    ),
+                                                },
+                                            ),
+                                            tpe: SFunc(
+                                                SFunc {
+                                                    t_dom: [
+                                                        STuple(
+                                                            STuple {
+                                                                items: BoundedVec {
+                                                                    inner: [
+                                                                        SInt,
+                                                                        SInt,
+                                                                    ],
+                                                                },
+                                                            },
+                                                        ),
+                                                    ],
+                                                    t_range: SInt,
+                                                    tpe_params: [],
+                                                },
+                                            ),
+                                        },
+                                    ),
+                                },
+                            ),
+                        ],
+                        result: Apply(
+                            Apply {
+                                func: ValUse(
+                                    ValUse {
+                                        val_id: ValId(
+                                            4,
+                                        ),
+                                        tpe: SFunc(
+                                            SFunc {
+                                                t_dom: [
+                                                    STuple(
+                                                        STuple {
+                                                            items: BoundedVec {
+                                                                inner: [
+                                                                    SInt,
+                                                                    SInt,
+                                                                ],
+                                                            },
+                                                        },
+                                                    ),
+                                                ],
+                                                t_range: SInt,
+                                                tpe_params: [],
+                                            },
+                                        ),
+                                    },
+                                ),
+                                args: [
+                                    Tuple(
+                                        Tuple {
+                                            items: BoundedVec {
+                                                inner: [
+                                                    GlobalVars(
+                                                        Height,
+                                                    ),
+                                                    Const(
+                                                        Constant {
+                                                            tpe: SInt,
+                                                            v: Int(
+                                                                1,
+                                                            ),
+                                                        },
+                                                    ),
+                                                ],
+                                            },
+                                        },
+                                    ),
+                                ],
+                                tpe: SInt,
+                            },
+                        ),
+                    },
+                )"#]],
+        );
+    }
+
+    #[test]
+    fn test_fn_def_recursion() {
+        check(
+            "{ def f(x: Int): Int = f(x) \n f(1) }",
+            expect![[r#"
+                error[E0402]: Recursive reference to `f` is not supported
+                 --> 1:24
+                  |
+                1 | { def f(x: Int): Int = f(x) 
+                  |                        ^"#]],
+        );
+    }
+
+    #[test]
+    fn test_fn_def_arg_type_mismatch() {
+        check(
+            "{ def f(x: Long) = x \n f(1) }",
+            expect![[r#"
+                error[E0403]: Function argument types mismatch: expected [SLong], found [SInt]
+                 --> 2:2
+                  |
+                2 |  f(1) }
+                  |  ^^^^"#]],
+        );
+    }
+
+    #[test]
+    fn test_fn_def_result_type_mismatch() {
+        check(
+            "{ def f(x: Int): Long = x \n f(1) }",
+            expect![[r#"
+                error[E0403]: Function result type mismatch: declared SLong, found SInt
+                 --> 1:25
+                  |
+                1 | { def f(x: Int): Long = x 
+                  |                         ^"#]],
+        );
+    }
+
+    #[test]
+    fn test_multiline_error_span() {
+        check(
+            "{ def f(x: Int): Long = {\n  val y = x\n  y\n}\nf(1L) }",
+            expect![[r#"
+                error[E0403]: Function result type mismatch: declared SLong, found SInt
+                 --> 1:25
+                  |
+                1 | { def f(x: Int): Long = {
+                  |                         ^
+                2 |   val y = x
+                  |   ^^^^^^^^^
+                3 |   y
+                  |   ^
+                4 | }
+                  | ^"#]],
+        );
+    }
+
+    #[test]
+    fn test_parser_error_recovery() {
+        check(
+            "val a = \nval b = )\nb",
+            expect![[r#"
+                error[E0101]: expected integer, long integer, string, identifier, ‘-’, ‘(’ or ‘{’, but found ‘val’
+                 --> 2:1
+                  |
+                2 | val b = )
+                  | ^^^
+
+                error[E0101]: expected integer, long integer, string, identifier, ‘-’, ‘(’ or ‘{’, but found ‘)’
+                 --> 2:9
+                  |
+                2 | val b = )
+                  |         ^"#]],
+        );
+    }
+
+    #[test]
+    fn test_error_code() {
+        assert_eq!(
+            compile("HSB.HEIGHT", ScriptEnv::new()).unwrap_err().code(),
+            ErrorCode::UnexpectedToken
+        );
+        assert_eq!(
+            compile("{ val a = 1 \n", ScriptEnv::new())
+                .unwrap_err()
+                .code(),
+            ErrorCode::UnexpectedEof
+        );
+        assert_eq!(
+            compile("x", ScriptEnv::new()).unwrap_err().code(),
+            ErrorCode::UnresolvedName
+        );
+        assert_eq!(
+            compile("HEIGHT + 1L", ScriptEnv::new()).unwrap_err().code(),
+            ErrorCode::TypeMismatch
+        );
+        assert_eq!(
+            compile("fromBase16(\"xyz\")", ScriptEnv::new())
+                .unwrap_err()
+                .code(),
+            ErrorCode::InvalidLiteralEncoding
+        );
+        assert_eq!(ErrorCode::UnresolvedName.as_str(), "E0401");
+    }
+
+    #[test]
+    fn test_env_sigma_prop() {
+        use ergotree_ir::mir::constant::Constant;
+        use ergotree_ir::sigma_protocol::dlog_group;
+        use ergotree_ir::sigma_protocol::sigma_boolean::ProveDlog;
+        use ergotree_ir::sigma_protocol::sigma_boolean::SigmaProp;
+
+        let pk: SigmaProp = ProveDlog::new(dlog_group::generator()).into();
+        let mut env = ScriptEnv::new();
+        env.insert("pk", pk.clone());
+        let tree = compile("pk", env).unwrap();
+        assert_eq!(
+            *tree.proposition().unwrap(),
+            Expr::Const(Constant::from(pk))
+        );
+    }
+
+    #[test]
+    fn test_long_literal_lowercase_suffix() {
+        check(
+            "1l + 2L",
+            expect![[r#"
+                BinOp(
+                    BinOp {
+                        kind: Arith(
+                            Plus,
+                        ),
+                        left: Const(
+                            Constant {
+                                tpe: SLong,
+                                v: Long(
+                                    1,
+                                ),
+                            },
+                        ),
+                        right: Const(
+                            Constant {
+                                tpe: SLong,
+                                v: Long(
+                                    2,
+                                ),
+                            },
+                        ),
+                    },
+                )"#]],
+        );
+    }
+
+    #[test]
+    fn test_compile_template() {
+        let mut env = ScriptEnv::new();
+        env.insert("deadline", 100i32);
+        env.insert("bonus", 7i32);
+        let template =
+            compile_template("{ val h = HEIGHT + bonus \n h + deadline + 1 }", env).unwrap();
+        assert_eq!(
+            template.parameters(),
+            &[
+                TemplateParameter {
+                    name: "bonus".to_string(),
+                    constant_index: 0,
+                    tpe: SType::SInt,
+                },
+                TemplateParameter {
+                    name: "deadline".to_string(),
+                    constant_index: 1,
+                    tpe: SType::SInt,
+                },
+            ]
+        );
+        assert_eq!(template.tree().constants_len().unwrap(), 3);
+        assert_eq!(
+            template.tree().get_constant(2).unwrap().unwrap(),
+            1i32.into()
+        );
+        let instance = template.with_parameter("deadline", 200i32.into()).unwrap();
+        assert_eq!(
+            instance.tree().get_constant(1).unwrap().unwrap(),
+            200i32.into()
+        );
+        check_expr(
+            &instance.tree().proposition().unwrap(),
+            expect![[r#"
+                BlockValue(
+                    BlockValue {
+                        items: [
+                            ValDef(
+                                ValDef {
+                                    id: ValId(
+                                        1,
+                                    ),
+                                    rhs: BinOp(
+                                        BinOp {
+                                            kind: Arith(
+                                                Plus,
+                                            ),
+                                            left: GlobalVars(
+                                                Height,
+                                            ),
+                                            right: Const(
+                                                Constant {
+                                                    tpe: SInt,
+                                                    v: Int(
+                                                        7,
+                                                    ),
+                                                },
+                                            ),
+                                        },
+                                    ),
+                                },
+                            ),
+                        ],
+                        result: BinOp(
+                            BinOp {
+                                kind: Arith(
+                                    Plus,
+                                ),
+                                left: BinOp(
+                                    BinOp {
+                                        kind: Arith(
+                                            Plus,
+                                        ),
+                                        left: ValUse(
+                                            ValUse {
+                                                val_id: ValId(
+                                                    1,
+                                                ),
+                                                tpe: SInt,
+                                            },
+                                        ),
+                                        right: Const(
+                                            Constant {
+                                                tpe: SInt,
+                                                v: Int(
+                                                    200,
+                                                ),
+                                            },
+                                        ),
+                                    },
+                                ),
+                                right: Const(
+                                    Constant {
+                                        tpe: SInt,
+                                        v: Int(
+                                            1,
+                                        ),
+                                    },
+                                ),
+                            },
+                        ),
+                    },
+                )"#]],
+        );
+    }
+
+    #[test]
+    fn test_compile_template_unknown_parameter() {
+        let mut env = ScriptEnv::new();
+        env.insert("deadline", 100i32);
+        let template = compile_template("HEIGHT + deadline", env).unwrap();
+        let err = template
+            .clone()
+            .with_parameter("height", 1i32.into())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            SetTemplateParameterError::UnknownParameter(_)
+        ));
+        assert_eq!(err.to_string(), "unknown template parameter: height");
+        assert!(matches!(
+            template.with_parameter("deadline", 1i64.into()),
+            Err(SetTemplateParameterError::ErgoTreeConstantError(_))
+        ));
+    }
+
+    #[test]
+    fn test_compile_optimized() {
+        let source = "{ val unused = HEIGHT + 1\n val limit = 2 * 50\n HEIGHT + limit }";
+        assert_eq!(
+            compile(source, ScriptEnv::new())
+                .unwrap()
+                .proposition()
+                .unwrap()
+                .as_ref(),
+            &compile_expr(source, ScriptEnv::new()).unwrap()
+        );
+        let tree = compile_optimized(source, ScriptEnv::new()).unwrap();
+        check_expr(
+            &tree.proposition().unwrap(),
+            expect![[r#"
+                BlockValue(
+                    BlockValue {
+                        items: [
+                            ValDef(
+                                ValDef {
+                                    id: ValId(
+                                        2,
+                                    ),
+                                    rhs: Const(
+                                        Constant {
+                                            tpe: SInt,
+                                            v: Int(
+                                                100,
+                                            ),
+                                        },
+                                    ),
+                                },
+                            ),
+                        ],
+                        result: BinOp(
+                            BinOp {
+                                kind: Arith(
+                                    Plus,
+                                ),
+                                left: GlobalVars(
+                                    Height,
+                                ),
+                                right: ValUse(
+                                    ValUse {
+                                        val_id: ValId(
+                                            2,
+                                        ),
+                                        tpe: SInt,
+                                    },
+                                ),
+                            },
+                        ),
+                    },
+                )"#]],
+        );
+    }
+
+    fn check_expr(expr: &Expr, expected_tree: expect_test::Expect) {
+        expected_tree.assert_eq(&expr.debug_tree());
+    }
 }
@@ -1,23 +1,195 @@
 use line_col::LineColLookup;
 use rowan::TextRange;
+use rowan::TextSize;
 
+/// Formats the error message followed by the location (1-based line and column) and the
+/// source lines covered by `span` with the covered part underlined.
+/// Trailing whitespace (trivia) of the span is not highlighted.
 pub fn pretty_error_desc(source: &str, span: TextRange, error_msg: &str) -> String {
+    let span = trim_trailing_whitespace(source, span);
     let line_col_lookup = LineColLookup::new(source);
-    let start_zero_based: usize = usize::from(span.start()) - 1;
-    let end_zero_based: usize = usize::from(span.end()) - 1;
-    let (line_start, col_start) = line_col_lookup.get(start_zero_based);
-    let (line_end, col_end) = line_col_lookup.get(end_zero_based);
-    if line_end != line_start {
-        return "Multiline error spans are not yet supported".to_string();
-    }
-    let source_line = source.lines().nth(line_start - 1).unwrap();
-    let highlight = format!("{0:^>span$}", "^", span = col_end - col_start + 1);
-    format!(
-        "{0}\nline: {1}\n{2}\n{3:>ident$}",
+    let start: usize = span.start().into();
+    let end: usize = span.end().into();
+    let (line_start, _) = line_col_lookup.get(start);
+    // `end` is exclusive, the last highlighted char is the one before it
+    let (line_end, _) = line_col_lookup.get(if end > start { end - 1 } else { end });
+    let lines: Vec<&str> = source.split('\n').collect();
+    let gutter_width = line_end.to_string().len();
+    let mut out = format!(
+        "{0}\n{1:>width$}--> {2}:{3}\n{1:>width$} |",
         error_msg,
+        "",
         line_start,
-        source_line,
-        highlight,
-        ident = col_start + 1,
-    )
+        column(source, &line_col_lookup, start),
+        width = gutter_width,
+    );
+    let mut line_offset: usize = lines.iter().take(line_start - 1).map(|l| l.len() + 1).sum();
+    for (line_idx, raw_line) in lines.iter().enumerate().take(line_end).skip(line_start - 1) {
+        let line = raw_line.trim_end_matches('\r');
+        let hl_start = if start >= line_offset {
+            start - line_offset
+        } else {
+            // continuation line, skip the indentation
+            line.len() - line.trim_start().len()
+        };
+        let hl_end = end.min(line_offset + line.len()).max(start) - line_offset;
+        let indent = line[..hl_start.min(line.len())].chars().count();
+        let highlight_len = line
+            .get(hl_start..hl_end)
+            .map_or(0, |s| s.chars().count())
+            .max(1);
+        out.push_str(&format!(
+            "\n{0:>width$} | {1}\n{2:>width$} | {3}{4}",
+            line_idx + 1,
+            line,
+            "",
+            " ".repeat(indent),
+            "^".repeat(highlight_len),
+            width = gutter_width,
+        ));
+        line_offset += raw_line.len() + 1;
+    }
+    out
+}
+
+/// 1-based column (in chars) of the given byte offset
+fn column(source: &str, line_col_lookup: &LineColLookup, offset: usize) -> usize {
+    let (_, byte_col) = line_col_lookup.get(offset);
+    let line_start = offset + 1 - byte_col;
+    source[line_start..offset].chars().count() + 1
+}
+
+/// Span without the trailing whitespace
+pub fn trim_trailing_whitespace(source: &str, span: TextRange) -> TextRange {
+    let text = source
+        .get(std::ops::Range::<usize>::from(span))
+        .unwrap_or("");
+    let trimmed_len = text.trim_end().len();
+    if trimmed_len == 0 {
+        TextRange::empty(span.start())
+    } else {
+        TextRange::at(span.start(), TextSize::from(trimmed_len as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::expect;
+    use expect_test::Expect;
+
+    fn check(source: &str, span: std::ops::Range<u32>, expected: Expect) {
+        let span = TextRange::new(span.start.into(), span.end.into());
+        expected.assert_eq(&pretty_error_desc(source, span, "error: msg"));
+    }
+
+    #[test]
+    fn span_at_start() {
+        check(
+            "HEIGHT + 1",
+            0..6,
+            expect![[r#"
+                error: msg
+                 --> 1:1
+                  |
+                1 | HEIGHT + 1
+                  | ^^^^^^"#]],
+        );
+    }
+
+    #[test]
+    fn span_in_the_middle() {
+        check(
+            "HEIGHT + 1",
+            7..8,
+            expect![[r#"
+                error: msg
+                 --> 1:8
+                  |
+                1 | HEIGHT + 1
+                  |        ^"#]],
+        );
+    }
+
+    #[test]
+    fn empty_span_at_end() {
+        check(
+            "HEIGHT +",
+            8..8,
+            expect![[r#"
+            error: msg
+             --> 1:9
+              |
+            1 | HEIGHT +
+              |         ^"#]],
+        );
+    }
+
+    #[test]
+    fn trailing_whitespace_is_not_highlighted() {
+        check(
+            "val a = 1\nval b = 2\nb",
+            0..10,
+            expect![[r#"
+            error: msg
+             --> 1:1
+              |
+            1 | val a = 1
+              | ^^^^^^^^^"#]],
+        );
+    }
+
+    #[test]
+    fn multiline_span() {
+        check(
+            "val a = {\n  val b = 1\n  b\n}\na",
+            8..27,
+            expect![[r#"
+                error: msg
+                 --> 1:9
+                  |
+                1 | val a = {
+                  |         ^
+                2 |   val b = 1
+                  |   ^^^^^^^^^
+                3 |   b
+                  |   ^
+                4 | }
+                  | ^"#]],
+        );
+    }
+
+    #[test]
+    fn multibyte_chars_before_span() {
+        check(
+            "val ä = ö",
+            9..11,
+            expect![[r#"
+            error: msg
+             --> 1:9
+              |
+            1 | val ä = ö
+              |         ^"#]],
+        );
+    }
+
+    #[test]
+    fn line_numbers_are_aligned() {
+        check(
+            "1 +\n2 +\n3 +\n4 +\n5 +\n6 +\n7 +\n8 +\n9 +\n10 +\n11",
+            24..38,
+            expect![[r#"
+                error: msg
+                  --> 7:1
+                   |
+                 7 | 7 +
+                   | ^^^
+                 8 | 8 +
+                   | ^^^
+                 9 | 9 +
+                   | ^^^
+                10 | 10 +
+                   | ^^"#]],
+        );
+    }
 }
@@ -0,0 +1,436 @@
+//! ErgoScript source code formatter
+//!
+//! Prints the lossless CST token by token, deciding on the separator (nothing, space or a line
+//! break) between every two tokens from their kinds and syntax nodes:
+//! - every statement (`val`, `def`, block result) on its own line, at most one blank line
+//!   between statements (kept only where the source has one);
+//! - block contents indented by [`INDENT`];
+//! - single spaces around binary operators and `=`, after `,`, `:` and keywords;
+//! - argument and parameter lists that do not fit in [`MAX_WIDTH`] are broken one item per line;
+//! - comments are kept where they are (on the same line or on their own line), a line broken
+//!   after a comment in the middle of an expression is indented one more level.
+//!
+//! Since the output only depends on the tokens and on the line breaks around comments and
+//! between statements (which are preserved), formatting is idempotent:
+//! `format(format(s)) == format(s)`.
+
+use crate::compiler::CompileError;
+use crate::parser::parse;
+use crate::syntax::SyntaxElement;
+use crate::syntax::SyntaxKind;
+use crate::syntax::SyntaxNode;
+use crate::syntax::SyntaxToken;
+
+/// Indentation of one level
+pub const INDENT: &str = "  ";
+
+/// Maximum line width argument and parameter lists are fit in
+pub const MAX_WIDTH: usize = 100;
+
+/// Formats the given ErgoScript source code, or returns parser errors (source with syntax
+/// errors is not formatted)
+pub fn format(source: &str) -> Result<String, CompileError> {
+    let parse = parse(source);
+    if !parse.errors.is_empty() {
+        return Err(CompileError::ParseError(parse.errors));
+    }
+    let mut printer = Printer::new(false);
+    printer.print(&parse.syntax());
+    let mut out = printer.out;
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Returns `true` if the source code is already formatted (`format(source) == source`)
+pub fn is_formatted(source: &str) -> Result<bool, CompileError> {
+    Ok(format(source)? == source)
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum Sep {
+    None,
+    Space,
+    Newline,
+}
+
+struct Printer {
+    out: String,
+    indent: usize,
+    /// Layout of the enclosing argument/parameter lists, `true` if broken (one item per line)
+    lists: Vec<bool>,
+    /// `true` when measuring the width of a list (all lists are laid out flat)
+    measure: bool,
+    prev: Option<SyntaxToken>,
+    /// Line breaks in the source since the previous token
+    newlines: usize,
+    /// Previous token is a comment, the line has to be broken
+    after_comment: bool,
+}
+
+impl Printer {
+    fn new(measure: bool) -> Self {
+        Printer {
+            out: String::new(),
+            indent: 0,
+            lists: Vec::new(),
+            measure,
+            prev: None,
+            newlines: 0,
+            after_comment: false,
+        }
+    }
+
+    fn print(&mut self, node: &SyntaxNode) {
+        for token in node
+            .descendants_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+        {
+            match token.kind() {
+                SyntaxKind::Whitespace => {
+                    self.newlines += token.text().matches('\n').count();
+                }
+                SyntaxKind::Comment => self.comment(token),
+                _ => self.token(token),
+            }
+        }
+    }
+
+    fn comment(&mut self, token: SyntaxToken) {
+        if self.prev.is_some() || self.after_comment {
+            if self.newlines == 0 {
+                self.out.push(' ');
+            } else {
+                let blank_line = self.newlines > 1 && !self.prev_is(SyntaxKind::LBrace);
+                self.newline(self.indent, blank_line);
+            }
+        }
+        self.out.push_str(token.text().trim_end());
+        self.newlines = 0;
+        self.after_comment = true;
+    }
+
+    fn token(&mut self, token: SyntaxToken) {
+        let kind = token.kind();
+        let parent = token.parent().kind();
+        let is_list = matches!(parent, SyntaxKind::ArgList | SyntaxKind::ParamList);
+        let starts_stmt = starts_stmt(&token);
+        let sep = self.separator(&token, starts_stmt);
+        if kind == SyntaxKind::RBrace {
+            self.indent = self.indent.saturating_sub(1);
+        }
+        if kind == SyntaxKind::RParen && is_list && self.lists.pop() == Some(true) {
+            self.indent = self.indent.saturating_sub(1);
+        }
+        if self.prev.is_some() || self.after_comment {
+            match sep {
+                Sep::Newline => {
+                    let blank_line =
+                        starts_stmt && self.newlines > 1 && !self.prev_is(SyntaxKind::LBrace);
+                    self.newline(self.indent, blank_line)
+                }
+                // a comment ends the line, the rest of the expression is on the next line
+                _ if self.after_comment => self.newline(self.indent + 1, false),
+                Sep::Space => self.out.push(' '),
+                Sep::None => (),
+            }
+        }
+        self.out.push_str(token.text());
+        if kind == SyntaxKind::LBrace {
+            self.indent += 1;
+        }
+        if kind == SyntaxKind::LParen && is_list {
+            let broken = !self.measure && self.is_list_too_long(&token.parent());
+            if broken {
+                self.indent += 1;
+            }
+            self.lists.push(broken);
+        }
+        self.prev = Some(token);
+        self.newlines = 0;
+        self.after_comment = false;
+    }
+
+    fn separator(&self, token: &SyntaxToken, starts_stmt: bool) -> Sep {
+        let kind = token.kind();
+        let parent = token.parent().kind();
+        let (prev, prev_parent) = match &self.prev {
+            Some(prev) => (prev.kind(), prev.parent().kind()),
+            None => return Sep::Newline,
+        };
+        let in_broken_list = self.lists.last() == Some(&true);
+        let is_list = |kind| matches!(kind, SyntaxKind::ArgList | SyntaxKind::ParamList);
+        if starts_stmt || kind == SyntaxKind::RBrace || prev == SyntaxKind::LBrace {
+            return Sep::Newline;
+        }
+        match (prev, kind) {
+            (SyntaxKind::LParen, _) if is_list(prev_parent) && in_broken_list => Sep::Newline,
+            (_, SyntaxKind::RParen) if is_list(parent) && in_broken_list => Sep::Newline,
+            (SyntaxKind::Comma, _) if in_broken_list => Sep::Newline,
+            (SyntaxKind::LParen, _) | (_, SyntaxKind::RParen) => Sep::None,
+            (_, SyntaxKind::Comma) | (_, SyntaxKind::Colon) => Sep::None,
+            (SyntaxKind::LBracket, _) | (_, SyntaxKind::LBracket) | (_, SyntaxKind::RBracket) => {
+                Sep::None
+            }
+            (_, SyntaxKind::LParen) if is_list(parent) => Sep::None,
+            (SyntaxKind::Minus, _) if prev_parent == SyntaxKind::PrefixExpr => Sep::None,
+            _ => Sep::Space,
+        }
+    }
+
+    fn is_list_too_long(&self, list: &SyntaxNode) -> bool {
+        let has_comment = list
+            .descendants_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .any(|t| t.kind() == SyntaxKind::Comment);
+        if has_comment {
+            return true;
+        }
+        let mut measure = Printer::new(true);
+        measure.print(list);
+        let width = measure.out.lines().next().map_or(0, |l| l.chars().count());
+        // the opening parenthesis is already printed
+        self.column() - 1 + width > MAX_WIDTH
+    }
+
+    fn column(&self) -> usize {
+        self.out
+            .rsplit('\n')
+            .next()
+            .map_or(0, |line| line.chars().count())
+    }
+
+    fn newline(&mut self, indent: usize, blank_line: bool) {
+        if blank_line {
+            self.out.push('\n');
+        }
+        self.out.push('\n');
+        self.out.push_str(&INDENT.repeat(indent));
+    }
+
+    fn prev_is(&self, kind: SyntaxKind) -> bool {
+        self.prev.as_ref().map(|t| t.kind()) == Some(kind)
+    }
+}
+
+/// The token is the first token of a statement in the root or a block
+fn starts_stmt(token: &SyntaxToken) -> bool {
+    token
+        .parent()
+        .ancestors()
+        .take_while(|node| {
+            node.first_token()
+                .map(|first| is_trivia_before(&first, token))
+                == Some(true)
+        })
+        .any(|node| {
+            matches!(
+                node.parent().map(|p| p.kind()),
+                Some(SyntaxKind::Root) | Some(SyntaxKind::BlockExpr)
+            )
+        })
+}
+
+/// `first` is `token` or there are only trivia tokens between them
+fn is_trivia_before(first: &SyntaxToken, token: &SyntaxToken) -> bool {
+    let mut cur = Some(first.clone());
+    while let Some(t) = cur {
+        if t == *token {
+            return true;
+        }
+        if !matches!(t.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment) {
+            return false;
+        }
+        cur = t.next_token();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::lexer::TokenKind;
+    use expect_test::expect;
+    use expect_test::Expect;
+
+    fn check(input: &str, expected: Expect) {
+        let formatted = format(input).unwrap();
+        expected.assert_eq(&formatted);
+        assert_eq!(format(&formatted).unwrap(), formatted, "not idempotent");
+        assert!(is_formatted(&formatted).unwrap());
+        assert_eq!(
+            tokens(&formatted),
+            tokens(input),
+            "tokens or comments changed"
+        );
+    }
+
+    fn tokens(source: &str) -> Vec<String> {
+        Lexer::new(source)
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| t.text.trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn spaces() {
+        check(
+            "1+2*  3",
+            expect![[r#"
+            1 + 2 * 3
+        "#]],
+        );
+    }
+
+    #[test]
+    fn prefix_and_parens() {
+        check(
+            "- ( 1+2 ) *-3",
+            expect![[r#"
+            -(1 + 2) * -3
+        "#]],
+        );
+    }
+
+    #[test]
+    fn val_defs() {
+        check(
+            "val a=1\nval   b =a+ 1\n\n\n\nb",
+            expect![[r#"
+            val a = 1
+            val b = a + 1
+
+            b
+        "#]],
+        );
+    }
+
+    #[test]
+    fn fn_def_and_call() {
+        check(
+            "{ def f ( x:Int , y : Long ) :Long= y \n f (1,2L) }",
+            expect![[r#"
+                {
+                  def f(x: Int, y: Long): Long = y
+                  f(1, 2L)
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn type_refs() {
+        check(
+            "{ def f ( x:Coll [ Byte ] , g : ( Int,Long )=>Option[Int] ) = x \n f }",
+            expect![[r#"
+                {
+                  def f(x: Coll[Byte], g: (Int, Long) => Option[Int]) = x
+                  f
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn nested_blocks() {
+        check(
+            "{val a = {val b = 1\nb}\n\n  val c = { 2 }\na + c}",
+            expect![[r#"
+                {
+                  val a = {
+                    val b = 1
+                    b
+                  }
+
+                  val c = {
+                    2
+                  }
+                  a + c
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn comments() {
+        check(
+            "// header\n\n{ // block\nval a = 1   // one\n\n// two\nval b = 2\n// result\na + // plus\nb\n} // end",
+            expect![[r#"
+                // header
+
+                { // block
+                  val a = 1 // one
+
+                  // two
+                  val b = 2
+                  // result
+                  a + // plus
+                    b
+                } // end
+            "#]],
+        );
+    }
+
+    #[test]
+    fn long_arg_list() {
+        check(
+            "f(someVeryLongArgumentName, anotherVeryLongArgumentName, yetAnotherVeryLongArgumentName, x + 1, y, z)",
+            expect![[r#"
+                f(
+                  someVeryLongArgumentName,
+                  anotherVeryLongArgumentName,
+                  yetAnotherVeryLongArgumentName,
+                  x + 1,
+                  y,
+                  z
+                )
+            "#]],
+        );
+    }
+
+    #[test]
+    fn long_param_list() {
+        check(
+            "{ def someFunction(someVeryLongParameterName: Int, anotherVeryLongParameterName: Long, x: Int, y: Int) = x \n someFunction(1, 2L, 3, g(3, { val a = 1 \n a })) }",
+            expect![[r#"
+                {
+                  def someFunction(
+                    someVeryLongParameterName: Int,
+                    anotherVeryLongParameterName: Long,
+                    x: Int,
+                    y: Int
+                  ) = x
+                  someFunction(1, 2L, 3, g(3, {
+                    val a = 1
+                    a
+                  }))
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn arg_list_with_comment() {
+        check(
+            "f(a, // first\nb)",
+            expect![[r#"
+            f(
+              a, // first
+              b
+            )
+        "#]],
+        );
+    }
+
+    #[test]
+    fn empty_source() {
+        check("", expect![[r#""#]]);
+    }
+
+    #[test]
+    fn parse_error() {
+        assert!(format("val a = ").is_err());
+    }
+}
@@ -3,11 +3,18 @@
 
 mod rewrite;
 
+use std::convert::TryFrom;
+
+use ergotree_ir::mir::constant::Constant;
+use ergotree_ir::mir::constant::ConstantPlaceholder;
+use ergotree_ir::types::sfunc::SFunc;
+use ergotree_ir::types::stuple::STuple;
 use ergotree_ir::types::stype::SType;
-pub use rewrite::rewrite;
+pub use rewrite::rewrite_in_scope;
 
 use super::ast;
 use crate::ast::AstError;
+use crate::compiler::ErrorCode;
 use crate::error::pretty_error_desc;
 use crate::syntax::SyntaxKind;
 use text_size::TextRange;
@@ -16,17 +23,20 @@ extern crate derive_more;
 use derive_more::From;
 
 pub fn lower(ast: ast::Root) -> Result<Expr, HirLoweringError> {
-    let exprs: Vec<ast::Expr> = ast.children().collect();
-    if exprs.len() > 1 {
-        return Err(HirLoweringError::new(
-            format!("More than one root expr found: {:?}", exprs),
-            ast.span(),
-        ));
+    let stmts: Vec<ast::Stmt> = ast.stmts().collect();
+    if stmts.is_empty() {
+        return Err(
+            AstError::new(format!("Cannot parse empty root: {:?}", ast), ast.span()).into(),
+        );
+    }
+    match stmts.as_slice() {
+        [ast::Stmt::Expr(expr)] => Expr::lower(expr),
+        _ => Block::lower(&stmts, ast.span()).map(|block| Expr {
+            kind: block.into(),
+            span: ast.span(),
+            tpe: None,
+        }),
     }
-    let first_expr = exprs
-        .first()
-        .ok_or_else(|| AstError::new(format!("Cannot parse empty root: {:?}", ast), ast.span()))?;
-    Expr::lower(first_expr)
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -38,23 +48,36 @@ pub struct Expr {
 
 #[derive(Debug, PartialEq)]
 pub struct HirLoweringError {
+    code: ErrorCode,
     msg: String,
     span: TextRange,
 }
 
 impl HirLoweringError {
-    pub fn new(msg: String, span: TextRange) -> Self {
-        HirLoweringError { msg, span }
+    pub fn new(code: ErrorCode, msg: String, span: TextRange) -> Self {
+        HirLoweringError { code, msg, span }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
     }
 
     pub fn pretty_desc(&self, source: &str) -> String {
         pretty_error_desc(source, self.span, &self.msg)
     }
+
+    pub fn span(&self) -> TextRange {
+        self.span
+    }
 }
 
 impl From<AstError> for HirLoweringError {
     fn from(ast: AstError) -> Self {
-        HirLoweringError::new(format!("AST error: {0}", ast.msg), ast.span)
+        HirLoweringError::new(
+            ErrorCode::MalformedSyntax,
+            format!("AST error: {0}", ast.msg),
+            ast.span,
+        )
     }
 }
 
@@ -87,9 +110,40 @@ impl Expr {
                         span: ast.span(),
                         tpe: Some(SType::SLong),
                     },
+                    // there is no string type in ErgoTree, string literals can only be
+                    // arguments of the functions evaluated at compile time (see binder)
+                    ast::LiteralValue::Str(v) => Expr {
+                        kind: Literal::Str(v).into(),
+                        span: ast.span(),
+                        tpe: None,
+                    },
                 };
                 Ok(expr)
             }
+            ast::Expr::BlockExpr(ast) => {
+                let stmts: Vec<ast::Stmt> = ast.stmts().collect();
+                Ok(Expr {
+                    kind: Block::lower(&stmts, ast.span())?.into(),
+                    span: ast.span(),
+                    tpe: None,
+                })
+            }
+            ast::Expr::CallExpr(ast) => {
+                let func = Expr::lower(&ast.func()?)?;
+                let args = ast
+                    .args()?
+                    .map(|arg| Expr::lower(&arg))
+                    .collect::<Result<Vec<Expr>, _>>()?;
+                Ok(Expr {
+                    kind: Apply {
+                        func: Box::new(func),
+                        args,
+                    }
+                    .into(),
+                    span: ast.span(),
+                    tpe: None,
+                })
+            }
         }
     }
 
@@ -123,6 +177,7 @@ impl Binary {
             SyntaxKind::Slash => BinaryOp::Divide,
             _ => {
                 return Err(HirLoweringError::new(
+                    ErrorCode::UnknownOperator,
                     format!("unknown binary operator: {:?}", ast.op()),
                     syntax_token.text_range(),
                 ))
@@ -143,19 +198,232 @@ impl Binary {
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct Block {
+    pub items: Vec<Expr>,
+    pub result: Box<Expr>,
+}
+
+impl Block {
+    fn lower(stmts: &[ast::Stmt], span: TextRange) -> Result<Block, HirLoweringError> {
+        let (last, init) = stmts.split_last().ok_or_else(|| {
+            HirLoweringError::new(ErrorCode::InvalidBlock, "Empty block".to_string(), span)
+        })?;
+        let items = init
+            .iter()
+            .map(|stmt| match stmt {
+                ast::Stmt::VariableDef(ast) => Val::lower_variable_def(ast),
+                ast::Stmt::FnDef(ast) => Val::lower_fn_def(ast),
+                ast::Stmt::Expr(ast) => Err(HirLoweringError::new(
+                    ErrorCode::InvalidBlock,
+                    "Only val and def definitions are allowed before the result expression of a block"
+                        .to_string(),
+                    ast.span(),
+                )),
+            })
+            .collect::<Result<Vec<Expr>, _>>()?;
+        let result = match last {
+            ast::Stmt::Expr(ast) => Expr::lower(ast)?,
+            ast::Stmt::VariableDef(ast) => {
+                return Err(HirLoweringError::new(
+                    ErrorCode::InvalidBlock,
+                    "Block should end with an expression".to_string(),
+                    ast.span(),
+                ))
+            }
+            ast::Stmt::FnDef(ast) => {
+                return Err(HirLoweringError::new(
+                    ErrorCode::InvalidBlock,
+                    "Block should end with an expression".to_string(),
+                    ast.span(),
+                ))
+            }
+        };
+        Ok(Block {
+            items,
+            result: Box::new(result),
+        })
+    }
+}
+
+/// Named value definition (`val` and `def`), can only be an item of a [`Block`]
+#[derive(Debug, PartialEq, Clone)]
+pub struct Val {
+    pub name: String,
+    pub rhs: Box<Expr>,
+}
+
+impl Val {
+    fn lower_variable_def(ast: &ast::VariableDef) -> Result<Expr, HirLoweringError> {
+        let val = Val {
+            name: ast.name()?.text().to_string(),
+            rhs: Box::new(Expr::lower(&ast.rhs()?)?),
+        };
+        Ok(Expr {
+            kind: val.into(),
+            span: ast.span(),
+            tpe: None,
+        })
+    }
+
+    /// `def f(x: Int): Int = body` is lowered as `val f = (x: Int) => body`
+    fn lower_fn_def(ast: &ast::FnDef) -> Result<Expr, HirLoweringError> {
+        let args = ast
+            .params()?
+            .map(|param| {
+                let name = param.name()?.text().to_string();
+                let tpe = lower_type_ref(&param.tpe()?)?;
+                Ok((name, tpe))
+            })
+            .collect::<Result<Vec<(String, SType)>, HirLoweringError>>()?;
+        let res_tpe = ast.res_tpe().map(|t| lower_type_ref(&t)).transpose()?;
+        let lambda = Lambda {
+            args,
+            res_tpe,
+            body: Box::new(Expr::lower(&ast.body()?)?),
+        };
+        let val = Val {
+            name: ast.name()?.text().to_string(),
+            rhs: Box::new(Expr {
+                kind: lambda.into(),
+                span: ast.span(),
+                tpe: None,
+            }),
+        };
+        Ok(Expr {
+            kind: val.into(),
+            span: ast.span(),
+            tpe: None,
+        })
+    }
+}
+
+fn lower_type_ref(ast: &ast::TypeRef) -> Result<SType, HirLoweringError> {
+    if ast.is_func() {
+        let (dom, range) = match ast.type_args().collect::<Vec<_>>().as_slice() {
+            [dom, range] => (lower_func_dom(dom)?, lower_type_ref(range)?),
+            _ => {
+                return Err(AstError::new(
+                    format!("Cannot find function domain and range in {:?}", ast),
+                    ast.span(),
+                )
+                .into())
+            }
+        };
+        return Ok(SType::SFunc(SFunc::new(dom, range)));
+    }
+    let args = ast
+        .type_args()
+        .map(|arg| lower_type_ref(&arg))
+        .collect::<Result<Vec<SType>, HirLoweringError>>()?;
+    if ast.is_tuple() {
+        return match args.len() {
+            // parenthesized type
+            1 => Ok(args[0].clone()),
+            len => STuple::try_from(args).map(SType::STuple).map_err(|_| {
+                HirLoweringError::new(
+                    ErrorCode::InvalidTypeArgs,
+                    format!(
+                        "wrong number of tuple type items: expected 2 to 255, found {}",
+                        len
+                    ),
+                    ast.span(),
+                )
+            }),
+        };
+    }
+    let name = ast.name()?;
+    let invalid_args = |expected: usize| {
+        HirLoweringError::new(
+            ErrorCode::InvalidTypeArgs,
+            format!(
+                "wrong number of type arguments for {}: expected {}, found {}",
+                name.text(),
+                expected,
+                args.len()
+            ),
+            ast.span(),
+        )
+    };
+    match (name.text(), args.as_slice()) {
+        ("Coll", [elem]) => Ok(SType::SColl(Box::new(elem.clone()))),
+        ("Option", [elem]) => Ok(SType::SOption(Box::new(elem.clone()))),
+        ("Coll", _) | ("Option", _) => Err(invalid_args(1)),
+        (name_text, _) => match simple_type(name_text) {
+            Some(tpe) if args.is_empty() => Ok(tpe),
+            Some(_) => Err(invalid_args(0)),
+            None => Err(HirLoweringError::new(
+                ErrorCode::UnknownType,
+                format!("unknown type: {}", name_text),
+                name.text_range(),
+            )),
+        },
+    }
+}
+
+/// Type without type arguments
+fn simple_type(name: &str) -> Option<SType> {
+    Some(match name {
+        "Boolean" => SType::SBoolean,
+        "Byte" => SType::SByte,
+        "Short" => SType::SShort,
+        "Int" => SType::SInt,
+        "Long" => SType::SLong,
+        "BigInt" => SType::SBigInt,
+        "GroupElement" => SType::SGroupElement,
+        "SigmaProp" => SType::SSigmaProp,
+        "Box" => SType::SBox,
+        "AvlTree" => SType::SAvlTree,
+        "Header" => SType::SHeader,
+        "PreHeader" => SType::SPreHeader,
+        _ => return None,
+    })
+}
+
+/// Function argument types, `(Int, Long) => Boolean` is a function of two arguments
+fn lower_func_dom(dom: &ast::TypeRef) -> Result<Vec<SType>, HirLoweringError> {
+    if dom.is_tuple() && !dom.is_func() {
+        dom.type_args().map(|arg| lower_type_ref(&arg)).collect()
+    } else {
+        Ok(vec![lower_type_ref(dom)?])
+    }
+}
+
+/// Anonymous function
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lambda {
+    pub args: Vec<(String, SType)>,
+    pub res_tpe: Option<SType>,
+    pub body: Box<Expr>,
+}
+
+/// Function application
+#[derive(Debug, PartialEq, Clone)]
+pub struct Apply {
+    pub func: Box<Expr>,
+    pub args: Vec<Expr>,
+}
+
 #[derive(Debug, PartialEq, From, Clone)]
 pub enum ExprKind {
     Ident(String),
     Binary(Binary),
     GlobalVars(GlobalVars),
     Literal(Literal),
+    /// Value known at compile time (from [`crate::script_env::ScriptEnv`] or evaluated
+    /// built-in function)
+    Const(Constant),
+    /// Placeholder for a value from [`crate::script_env::ScriptEnv`] in constant segregated
+    /// ErgoTree
+    ConstPlaceholder(ConstantPlaceholder),
+    Block(Block),
+    Val(Val),
+    Lambda(Lambda),
+    Apply(Apply),
     // ...
-    // Block
-    // ValNode
     // Select
     // ApplyTypes
     // MethodCallLike
-    // Lambda
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -189,6 +457,7 @@ impl GlobalVars {
 pub enum Literal {
     Int(i32),
     Long(i64),
+    Str(String),
 }
 
 #[cfg(test)]
@@ -196,6 +465,11 @@ mod tests {
     use expect_test::expect;
 
     use crate::compiler::compile_hir;
+    use ergotree_ir::types::sfunc::SFunc;
+    use ergotree_ir::types::stuple::STuple;
+    use ergotree_ir::types::stype::SType;
+
+    use super::ExprKind;
 
     fn check(input: &str, expected_tree: expect_test::Expect) {
         let res = compile_hir(input);
@@ -224,4 +498,222 @@ mod tests {
             }"#]],
         );
     }
+
+    #[test]
+    fn fn_def() {
+        check(
+            "{ def f(x: Int): Int = x \n f(1) }",
+            expect![[r#"
+                Expr {
+                    kind: Block(
+                        Block {
+                            items: [
+                                Expr {
+                                    kind: Val(
+                                        Val {
+                                            name: "f",
+                                            rhs: Expr {
+                                                kind: Lambda(
+                                                    Lambda {
+                                                        args: [
+                                                            (
+                                                                "x",
+                                                                SInt,
+                                                            ),
+                                                        ],
+                                                        res_tpe: Some(
+                                                            SInt,
+                                                        ),
+                                                        body: Expr {
+                                                            kind: Ident(
+                                                                "x",
+                                                            ),
+                                                            span: 23..27,
+                                                            tpe: None,
+                                                        },
+                                                    },
+                                                ),
+                                                span: 2..27,
+                                                tpe: None,
+                                            },
+                                        },
+                                    ),
+                                    span: 2..27,
+                                    tpe: None,
+                                },
+                            ],
+                            result: Expr {
+                                kind: Apply(
+                                    Apply {
+                                        func: Expr {
+                                            kind: Ident(
+                                                "f",
+                                            ),
+                                            span: 27..28,
+                                            tpe: None,
+                                        },
+                                        args: [
+                                            Expr {
+                                                kind: Literal(
+                                                    Int(
+                                                        1,
+                                                    ),
+                                                ),
+                                                span: 29..30,
+                                                tpe: Some(
+                                                    SInt,
+                                                ),
+                                            },
+                                        ],
+                                    },
+                                ),
+                                span: 27..32,
+                                tpe: None,
+                            },
+                        },
+                    ),
+                    span: 0..33,
+                    tpe: None,
+                }"#]],
+        );
+    }
+
+    #[test]
+    fn expr_before_block_result() {
+        check(
+            "{ 1 2 }",
+            expect![[r#"
+                error[E0203]: Only val and def definitions are allowed before the result expression of a block
+                 --> 1:3
+                  |
+                1 | { 1 2 }
+                  |   ^"#]],
+        );
+    }
+
+    fn param_type(tpe: &str) -> SType {
+        let src = format!("{{ def f(x: {}) = x \n f }}", tpe);
+        let expr = compile_hir(&src).unwrap();
+        match expr.kind {
+            ExprKind::Block(block) => match &block.items[0].kind {
+                ExprKind::Val(val) => match &val.rhs.kind {
+                    ExprKind::Lambda(lambda) => lambda.args[0].1.clone(),
+                    kind => panic!("expected lambda, got {:?}", kind),
+                },
+                kind => panic!("expected val, got {:?}", kind),
+            },
+            kind => panic!("expected block, got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn coll_type() {
+        assert_eq!(param_type("Coll[Byte]"), SType::SColl(SType::SByte.into()));
+        assert_eq!(
+            param_type("Coll[Coll[Long]]"),
+            SType::SColl(SType::SColl(SType::SLong.into()).into())
+        );
+    }
+
+    #[test]
+    fn option_type() {
+        assert_eq!(
+            param_type("Option[Int]"),
+            SType::SOption(SType::SInt.into())
+        );
+        assert_eq!(
+            param_type("Option[Coll[Box]]"),
+            SType::SOption(SType::SColl(SType::SBox.into()).into())
+        );
+    }
+
+    #[test]
+    fn tuple_type() {
+        assert_eq!(
+            param_type("(Int, Coll[Byte])"),
+            SType::STuple(STuple::pair(SType::SInt, SType::SColl(SType::SByte.into())))
+        );
+        assert_eq!(
+            param_type("(Int, Long, Boolean)"),
+            SType::STuple(STuple::triple(SType::SInt, SType::SLong, SType::SBoolean))
+        );
+        assert_eq!(param_type("(Int)"), SType::SInt);
+    }
+
+    #[test]
+    fn func_type() {
+        assert_eq!(
+            param_type("Int => Long"),
+            SType::SFunc(SFunc::new(vec![SType::SInt], SType::SLong))
+        );
+        assert_eq!(
+            param_type("(Int, Coll[Byte]) => Boolean"),
+            SType::SFunc(SFunc::new(
+                vec![SType::SInt, SType::SColl(SType::SByte.into())],
+                SType::SBoolean
+            ))
+        );
+        assert_eq!(
+            param_type("((Int, Long)) => Int"),
+            SType::SFunc(SFunc::new(
+                vec![SType::STuple(STuple::pair(SType::SInt, SType::SLong))],
+                SType::SInt
+            ))
+        );
+        assert_eq!(
+            param_type("Int => Int => Long"),
+            SType::SFunc(SFunc::new(
+                vec![SType::SInt],
+                SType::SFunc(SFunc::new(vec![SType::SInt], SType::SLong))
+            ))
+        );
+        assert_eq!(
+            param_type("() => Int"),
+            SType::SFunc(SFunc::new(vec![], SType::SInt))
+        );
+    }
+
+    #[test]
+    fn invalid_type_args() {
+        check(
+            "{ def f(x: Coll[Int, Long]) = x \n f }",
+            expect![[r#"
+                error[E0205]: wrong number of type arguments for Coll: expected 1, found 2
+                 --> 1:12
+                  |
+                1 | { def f(x: Coll[Int, Long]) = x 
+                  |            ^^^^^^^^^^^^^^^"#]],
+        );
+        check(
+            "{ def f(x: Int[Long]) = x \n f }",
+            expect![[r#"
+                error[E0205]: wrong number of type arguments for Int: expected 0, found 1
+                 --> 1:12
+                  |
+                1 | { def f(x: Int[Long]) = x 
+                  |            ^^^^^^^^^"#]],
+        );
+        check(
+            "{ def f(x: ()) = x \n f }",
+            expect![[r#"
+                error[E0205]: wrong number of tuple type items: expected 2 to 255, found 0
+                 --> 1:12
+                  |
+                1 | { def f(x: ()) = x 
+                  |            ^^"#]],
+        );
+    }
+
+    #[test]
+    fn unknown_param_type() {
+        check(
+            "{ def f(x: Foo) = x \n f(1) }",
+            expect![[r#"
+                error[E0204]: unknown type: Foo
+                 --> 1:12
+                  |
+                1 | { def f(x: Foo) = x 
+                  |            ^^^"#]],
+        );
+    }
 }
@@ -1,29 +1,88 @@
+use std::collections::HashSet;
+
+use super::Apply;
 use super::Binary;
+use super::Block;
 use super::Expr;
 use super::ExprKind;
+use super::Lambda;
+use super::Val;
 
 // pub fn hir_rewrite_safe<F: Fn(&Expr) -> Option<Expr>>(e: Expr, f: F) -> Expr {
 //     let f_wrap = |e| Result::<Option<Expr>, BinderError>::Ok(f(e));
 //     hir_rewrite(e, f_wrap).unwrap()
 // }
 
-pub fn rewrite<E, F: Fn(&Expr) -> Result<Option<Expr>, E>>(e: Expr, f: F) -> Result<Expr, E> {
-    let e = f(&e)?.unwrap_or(e);
-    Ok(match &e.kind {
-        ExprKind::Binary(binary) => match (f(&binary.lhs)?, f(&binary.rhs)?) {
-            (None, None) => e,
-            (l, r) => Expr {
-                kind: Binary {
-                    op: binary.op.clone(),
-                    lhs: Box::new(l.unwrap_or(*binary.lhs.clone())),
-                    rhs: Box::new(r.unwrap_or(*binary.rhs.clone())),
+/// Top-down rewrite: `f` is applied to the expression first, then to the children of the
+/// (possibly rewritten) expression. `f` gets the names bound by the enclosing `val`, `def`
+/// definitions and function arguments. A `val`/`def` name is in scope from its definition (its
+/// right-hand side included) to the end of the block.
+pub fn rewrite_in_scope<E, F: Fn(&Expr, &HashSet<String>) -> Result<Option<Expr>, E>>(
+    e: Expr,
+    f: F,
+) -> Result<Expr, E> {
+    rewrite_with(e, &f, &HashSet::new())
+}
+
+fn rewrite_with<E, F: Fn(&Expr, &HashSet<String>) -> Result<Option<Expr>, E>>(
+    e: Expr,
+    f: &F,
+    scope: &HashSet<String>,
+) -> Result<Expr, E> {
+    let Expr { kind, span, tpe } = f(&e, scope)?.unwrap_or(e);
+    let rewrite_box = |e: Box<Expr>| rewrite_with(*e, f, scope).map(Box::new);
+    let rewrite_vec = |es: Vec<Expr>| {
+        es.into_iter()
+            .map(|e| rewrite_with(e, f, scope))
+            .collect::<Result<Vec<Expr>, E>>()
+    };
+    let kind = match kind {
+        ExprKind::Binary(binary) => Binary {
+            op: binary.op,
+            lhs: rewrite_box(binary.lhs)?,
+            rhs: rewrite_box(binary.rhs)?,
+        }
+        .into(),
+        ExprKind::Block(block) => {
+            let mut block_scope = scope.clone();
+            let mut items = Vec::with_capacity(block.items.len());
+            for item in block.items {
+                if let ExprKind::Val(val) = &item.kind {
+                    block_scope.insert(val.name.clone());
                 }
-                .into(),
-                ..e
-            },
-        },
-        ExprKind::Ident(_) => f(&e)?.unwrap_or(e), // TODO: duplicate call to f?
-        ExprKind::GlobalVars(_) => f(&e)?.unwrap_or(e),
-        ExprKind::Literal(_) => f(&e)?.unwrap_or(e),
-    })
+                items.push(rewrite_with(item, f, &block_scope)?);
+            }
+            Block {
+                items,
+                result: rewrite_with(*block.result, f, &block_scope).map(Box::new)?,
+            }
+            .into()
+        }
+        ExprKind::Val(val) => Val {
+            name: val.name,
+            rhs: rewrite_box(val.rhs)?,
+        }
+        .into(),
+        ExprKind::Lambda(lambda) => {
+            let mut body_scope = scope.clone();
+            body_scope.extend(lambda.args.iter().map(|(name, _)| name.clone()));
+            Lambda {
+                body: rewrite_with(*lambda.body, f, &body_scope).map(Box::new)?,
+                args: lambda.args,
+                res_tpe: lambda.res_tpe,
+            }
+            .into()
+        }
+        ExprKind::Apply(apply) => Apply {
+            func: rewrite_box(apply.func)?,
+            args: rewrite_vec(apply.args)?,
+        }
+        .into(),
+        kind @ ExprKind::Ident(_) => kind,
+        kind @ ExprKind::GlobalVars(_) => kind,
+        kind @ ExprKind::Literal(_) => kind,
+        kind @ ExprKind::Const(_) => kind,
+        kind @ ExprKind::ConstPlaceholder(_) => kind,
+    };
+    Ok(Expr { kind, span, tpe })
 }
@@ -20,9 +20,12 @@ pub enum TokenKind {
     #[regex("[0-9]+")]
     IntNumber,
 
-    #[regex("[0-9]+L")]
+    #[regex("[0-9]+[lL]")]
     LongNumber,
 
+    #[regex(r#""[^"\n]*""#)]
+    StringLiteral,
+
     #[token("+")]
     Plus,
 
@@ -41,6 +44,15 @@ pub enum TokenKind {
     #[token("=")]
     Equals,
 
+    #[token("=>")]
+    FatArrow,
+
+    #[token(":")]
+    Colon,
+
+    #[token(",")]
+    Comma,
+
     #[token("(")]
     LParen,
 
@@ -53,6 +65,12 @@ pub enum TokenKind {
     #[token("}")]
     RBrace,
 
+    #[token("[")]
+    LBracket,
+
+    #[token("]")]
+    RBracket,
+
     #[regex("//.*")]
     Comment,
 
@@ -73,18 +91,24 @@ impl fmt::Display for TokenKind {
             Self::FnKw => "‘def’",
             Self::ValKw => "‘val’",
             Self::Ident => "identifier",
-            Self::IntNumber => "number",
-            Self::LongNumber => "number",
+            Self::IntNumber => "integer",
+            Self::LongNumber => "long integer",
+            Self::StringLiteral => "string",
             Self::Plus => "‘+’",
             Self::Minus => "‘-’",
             Self::Star => "‘*’",
             Self::Slash => "‘/’",
             Self::And => "‘&&’",
             Self::Equals => "‘=’",
+            Self::FatArrow => "‘=>’",
+            Self::Colon => "‘:’",
+            Self::Comma => "‘,’",
             Self::LParen => "‘(’",
             Self::RParen => "‘)’",
             Self::LBrace => "‘{’",
             Self::RBrace => "‘}’",
+            Self::LBracket => "‘[’",
+            Self::RBracket => "‘]’",
             Self::Comment => "comment",
             Self::Error => "an unrecognized token",
         })
@@ -144,6 +168,26 @@ mod tests {
         check("123456", TokenKind::IntNumber);
     }
 
+    #[test]
+    fn lex_long_number() {
+        check("123456L", TokenKind::LongNumber);
+    }
+
+    #[test]
+    fn lex_long_number_lowercase_suffix() {
+        check("123456l", TokenKind::LongNumber);
+    }
+
+    #[test]
+    fn lex_string_literal() {
+        check("\"3yZe7d\"", TokenKind::StringLiteral);
+    }
+
+    #[test]
+    fn lex_empty_string_literal() {
+        check("\"\"", TokenKind::StringLiteral);
+    }
+
     #[test]
     fn lex_plus() {
         check("+", TokenKind::Plus);
@@ -169,6 +213,21 @@ mod tests {
         check("=", TokenKind::Equals);
     }
 
+    #[test]
+    fn lex_fat_arrow() {
+        check("=>", TokenKind::FatArrow);
+    }
+
+    #[test]
+    fn lex_colon() {
+        check(":", TokenKind::Colon);
+    }
+
+    #[test]
+    fn lex_comma() {
+        check(",", TokenKind::Comma);
+    }
+
     #[test]
     fn lex_left_parenthesis() {
         check("(", TokenKind::LParen);
@@ -189,6 +248,16 @@ mod tests {
         check("}", TokenKind::RBrace);
     }
 
+    #[test]
+    fn lex_left_bracket() {
+        check("[", TokenKind::LBracket);
+    }
+
+    #[test]
+    fn lex_right_bracket() {
+        check("]", TokenKind::RBracket);
+    }
+
     #[test]
     fn lex_comment() {
         check("// foo", TokenKind::Comment);
@@ -23,5 +23,7 @@ pub(crate) mod parser;
 pub(crate) mod syntax;
 pub(crate) mod type_infer;
 
+pub mod analysis;
 pub mod compiler;
+pub mod formatter;
 pub mod script_env;
@@ -1,78 +1,343 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use ergotree_ir::mir::apply::Apply;
 use ergotree_ir::mir::bin_op::ArithOp;
 use ergotree_ir::mir::bin_op::BinOp;
 use ergotree_ir::mir::bin_op::BinOpKind;
+use ergotree_ir::mir::block::BlockValue;
 use ergotree_ir::mir::constant::Constant;
 use ergotree_ir::mir::expr::Expr;
+use ergotree_ir::mir::func_value::FuncArg;
+use ergotree_ir::mir::func_value::FuncValue;
 use ergotree_ir::mir::global_vars::GlobalVars;
+use ergotree_ir::mir::select_field::SelectField;
+use ergotree_ir::mir::select_field::TupleFieldIndex;
+use ergotree_ir::mir::tuple::Tuple;
+use ergotree_ir::mir::val_def::ValDef;
+use ergotree_ir::mir::val_def::ValId;
+use ergotree_ir::mir::val_use::ValUse;
+use ergotree_ir::types::sfunc::SFunc;
+use ergotree_ir::types::stuple::STuple;
+use ergotree_ir::types::stype::SType;
 use hir::BinaryOp;
 use rowan::TextRange;
 
+use crate::compiler::ErrorCode;
 use crate::error::pretty_error_desc;
 use crate::hir;
 
 #[derive(Debug, PartialEq)]
 pub struct MirLoweringError {
+    code: ErrorCode,
     msg: String,
     span: TextRange,
 }
 
 impl MirLoweringError {
-    pub fn new(msg: String, span: TextRange) -> Self {
-        Self { msg, span }
+    pub fn new(code: ErrorCode, msg: String, span: TextRange) -> Self {
+        Self { code, msg, span }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
     }
 
     pub fn pretty_desc(&self, source: &str) -> String {
         pretty_error_desc(source, self.span, &self.msg)
     }
+
+    pub fn span(&self) -> TextRange {
+        self.span
+    }
 }
 
 pub fn lower(hir_expr: hir::Expr) -> Result<Expr, MirLoweringError> {
-    let mir: Expr = match &hir_expr.kind {
-        hir::ExprKind::GlobalVars(hir) => match hir {
-            hir::GlobalVars::Height => GlobalVars::Height.into(),
-        },
-        hir::ExprKind::Ident(_) => {
-            return Err(MirLoweringError::new(
-                format!("MIR error: Unresolved Ident {0:?}", hir_expr),
+    Lowering::new().lower(&hir_expr, &HashMap::new())
+}
+
+/// Assigns unique [`ValId`]s to the named values (`val`, `def`, function arguments).
+/// Functions are lowered to single-argument ones (the only kind the node evaluates), arguments
+/// of a multi-argument function are passed as a tuple.
+struct Lowering {
+    next_val_id: u32,
+}
+
+impl Lowering {
+    fn new() -> Self {
+        Lowering { next_val_id: 1 }
+    }
+
+    fn fresh_val_id(&mut self) -> ValId {
+        let id = ValId(self.next_val_id);
+        self.next_val_id += 1;
+        id
+    }
+
+    fn lower(
+        &mut self,
+        hir_expr: &hir::Expr,
+        env: &HashMap<String, ValId>,
+    ) -> Result<Expr, MirLoweringError> {
+        let hir_tpe = hir_expr.tpe.clone().ok_or_else(|| {
+            MirLoweringError::new(
+                ErrorCode::MirLoweringFailed,
+                format!("MIR error: missing tpe for HIR: {0:?}", hir_expr),
                 hir_expr.span,
-            ))
-        }
-        hir::ExprKind::Binary(hir) => {
-            let l = lower(*hir.lhs.clone())?;
-            let r = lower(*hir.rhs.clone())?;
-            BinOp {
-                kind: hir.op.node.clone().into(),
-                left: l.into(),
-                right: r.into(),
+            )
+        })?;
+        let mir: Expr = match &hir_expr.kind {
+            hir::ExprKind::GlobalVars(hir) => match hir {
+                hir::GlobalVars::Height => GlobalVars::Height.into(),
+            },
+            hir::ExprKind::Ident(name) => match env.get(name) {
+                Some(val_id) => ValUse {
+                    val_id: *val_id,
+                    tpe: mir_tpe(&hir_tpe),
+                }
+                .into(),
+                None => {
+                    return Err(MirLoweringError::new(
+                        ErrorCode::MirLoweringFailed,
+                        format!("MIR error: Unresolved Ident {0:?}", hir_expr),
+                        hir_expr.span,
+                    ))
+                }
+            },
+            hir::ExprKind::Binary(hir) => {
+                let l = self.lower(&hir.lhs, env)?;
+                let r = self.lower(&hir.rhs, env)?;
+                BinOp {
+                    kind: hir.op.node.clone().into(),
+                    left: l.into(),
+                    right: r.into(),
+                }
+                .into()
+            }
+            hir::ExprKind::Literal(hir) => {
+                let constant: Constant = match hir {
+                    hir::Literal::Int(v) => (*v).into(),
+                    hir::Literal::Long(v) => (*v).into(),
+                    hir::Literal::Str(_) => {
+                        return Err(MirLoweringError::new(
+                            ErrorCode::MirLoweringFailed,
+                            format!("MIR error: string literals are not supported {0:?}", hir),
+                            hir_expr.span,
+                        ))
+                    }
+                };
+                constant.into()
+            }
+            hir::ExprKind::Const(c) => c.clone().into(),
+            hir::ExprKind::ConstPlaceholder(cp) => cp.clone().into(),
+            hir::ExprKind::Block(hir) => {
+                let mut block_env = env.clone();
+                let mut items = Vec::with_capacity(hir.items.len());
+                for item in &hir.items {
+                    match &item.kind {
+                        hir::ExprKind::Val(val) => {
+                            let rhs = self.lower(&val.rhs, &block_env)?;
+                            let id = self.fresh_val_id();
+                            block_env.insert(val.name.clone(), id);
+                            items.push(
+                                ValDef {
+                                    id,
+                                    rhs: rhs.into(),
+                                }
+                                .into(),
+                            );
+                        }
+                        _ => {
+                            return Err(MirLoweringError::new(
+                                ErrorCode::MirLoweringFailed,
+                                format!("MIR error: expected Val as a block item, got {0:?}", item),
+                                item.span,
+                            ))
+                        }
+                    }
+                }
+                let result = self.lower(&hir.result, &block_env)?;
+                BlockValue {
+                    items,
+                    result: result.into(),
+                }
+                .into()
+            }
+            hir::ExprKind::Val(_) => {
+                return Err(MirLoweringError::new(
+                    ErrorCode::MirLoweringFailed,
+                    format!("MIR error: Val outside of a block {0:?}", hir_expr),
+                    hir_expr.span,
+                ))
             }
-            .into()
+            hir::ExprKind::Lambda(hir) => {
+                let mut body_env = env.clone();
+                match hir.args.as_slice() {
+                    [] => {
+                        return Err(MirLoweringError::new(
+                            ErrorCode::UnsupportedFunction,
+                            "MIR error: functions without arguments are not supported".to_string(),
+                            hir_expr.span,
+                        ))
+                    }
+                    [(name, tpe)] => {
+                        let idx = self.fresh_val_id();
+                        body_env.insert(name.clone(), idx);
+                        let body = self.lower(&hir.body, &body_env)?;
+                        FuncValue::new(
+                            vec![FuncArg {
+                                idx,
+                                tpe: mir_tpe(tpe),
+                            }],
+                            body,
+                        )
+                        .into()
+                    }
+                    args => {
+                        let tuple_tpe = tuple_tpe(
+                            args.iter().map(|(_, tpe)| mir_tpe(tpe)).collect(),
+                            hir_expr.span,
+                        )?;
+                        let tuple_id = self.fresh_val_id();
+                        let mut items = Vec::with_capacity(args.len());
+                        for (i, (name, _)) in args.iter().enumerate() {
+                            let tuple = ValUse {
+                                val_id: tuple_id,
+                                tpe: tuple_tpe.clone(),
+                            };
+                            let field = field_index(i, hir_expr.span).and_then(|field_index| {
+                                SelectField::new(tuple.into(), field_index).map_err(|e| {
+                                    MirLoweringError::new(
+                                        ErrorCode::MirLoweringFailed,
+                                        format!("MIR error: {0}", e),
+                                        hir_expr.span,
+                                    )
+                                })
+                            })?;
+                            let id = self.fresh_val_id();
+                            body_env.insert(name.clone(), id);
+                            items.push(
+                                ValDef {
+                                    id,
+                                    rhs: Box::new(field.into()),
+                                }
+                                .into(),
+                            );
+                        }
+                        let body = self.lower(&hir.body, &body_env)?;
+                        FuncValue::new(
+                            vec![FuncArg {
+                                idx: tuple_id,
+                                tpe: tuple_tpe,
+                            }],
+                            BlockValue {
+                                items,
+                                result: body.into(),
+                            }
+                            .into(),
+                        )
+                        .into()
+                    }
+                }
+            }
+            hir::ExprKind::Apply(hir) => {
+                let func = self.lower(&hir.func, env)?;
+                let mut args = hir
+                    .args
+                    .iter()
+                    .map(|arg| self.lower(arg, env))
+                    .collect::<Result<Vec<Expr>, _>>()?;
+                let arg = match args.len() {
+                    0 => {
+                        return Err(MirLoweringError::new(
+                            ErrorCode::UnsupportedFunction,
+                            "MIR error: function calls without arguments are not supported"
+                                .to_string(),
+                            hir_expr.span,
+                        ))
+                    }
+                    1 => args.remove(0),
+                    _ => Tuple::new(args)
+                        .map_err(|e| {
+                            MirLoweringError::new(
+                                ErrorCode::MirLoweringFailed,
+                                format!("MIR error: {0}", e),
+                                hir_expr.span,
+                            )
+                        })?
+                        .into(),
+                };
+                Apply::new(func, vec![arg])
+                    .map_err(|e| {
+                        MirLoweringError::new(
+                            ErrorCode::MirLoweringFailed,
+                            format!("MIR error: {0}", e),
+                            hir_expr.span,
+                        )
+                    })?
+                    .into()
+            }
+        };
+        if mir.tpe() == mir_tpe(&hir_tpe) {
+            Ok(mir)
+        } else {
+            Err(MirLoweringError::new(
+                ErrorCode::MirLoweringFailed,
+                format!(
+                    "MIR error: lowered MIR type != HIR type ({0:?} != {1:?})",
+                    mir.tpe(),
+                    hir_expr.tpe
+                ),
+                hir_expr.span,
+            ))
         }
-        hir::ExprKind::Literal(hir) => {
-            let constant: Constant = match *hir {
-                hir::Literal::Int(v) => v.into(),
-                hir::Literal::Long(v) => v.into(),
+    }
+}
+
+/// Type of the lowered expression, multi-argument functions take their arguments as a tuple
+fn mir_tpe(tpe: &SType) -> SType {
+    match tpe {
+        SType::SFunc(sfunc) => {
+            let t_dom: Vec<SType> = sfunc.t_dom.iter().map(mir_tpe).collect();
+            let t_range = mir_tpe(&sfunc.t_range);
+            let t_dom = if t_dom.len() > 1 {
+                STuple::try_from(t_dom.clone())
+                    .map(|t| vec![SType::STuple(t)])
+                    .unwrap_or(t_dom)
+            } else {
+                t_dom
             };
-            constant.into()
+            SType::SFunc(SFunc {
+                t_dom,
+                t_range: Box::new(t_range),
+                tpe_params: sfunc.tpe_params.clone(),
+            })
         }
-    };
-    let hir_tpe = hir_expr.tpe.clone().ok_or_else(|| {
+        tpe => tpe.clone(),
+    }
+}
+
+fn tuple_tpe(items: Vec<SType>, span: TextRange) -> Result<SType, MirLoweringError> {
+    STuple::try_from(items).map(SType::STuple).map_err(|e| {
         MirLoweringError::new(
-            format!("MIR error: missing tpe for HIR: {0:?}", hir_expr),
-            hir_expr.span,
+            ErrorCode::UnsupportedFunction,
+            format!("MIR error: invalid function arguments count: {0:?}", e),
+            span,
         )
-    })?;
-    if mir.tpe() == hir_tpe {
-        Ok(mir)
-    } else {
-        Err(MirLoweringError::new(
-            format!(
-                "MIR error: lowered MIR type != HIR type ({0:?} != {1:?})",
-                mir.tpe(),
-                hir_expr.tpe
-            ),
-            hir_expr.span,
-        ))
-    }
+    })
+}
+
+fn field_index(i: usize, span: TextRange) -> Result<TupleFieldIndex, MirLoweringError> {
+    u8::try_from(i + 1)
+        .ok()
+        .and_then(|i| TupleFieldIndex::try_from(i).ok())
+        .ok_or_else(|| {
+            MirLoweringError::new(
+                ErrorCode::UnsupportedFunction,
+                format!("MIR error: invalid argument index {0}", i),
+                span,
+            )
+        })
 }
 
 impl From<hir::BinaryOp> for BinOpKind {
@@ -87,7 +352,7 @@ impl From<hir::BinaryOp> for BinOpKind {
 }
 
 #[cfg(test)]
-pub fn check(input: &str, expected_tree: expect_test::Expect) {
+fn lower_source(input: &str) -> Expr {
     let parse = crate::parser::parse(input);
     let syntax = parse.syntax();
     let root = crate::ast::Root::cast(syntax).unwrap();
@@ -95,16 +360,32 @@ pub fn check(input: &str, expected_tree: expect_test::Expect) {
     let binder = crate::binder::Binder::new(crate::script_env::ScriptEnv::new());
     let bind = binder.bind(hir).unwrap();
     let typed = crate::type_infer::assign_type(bind).unwrap();
-    let res = lower(typed).unwrap();
-    expected_tree.assert_eq(&res.debug_tree());
+    lower(typed).unwrap()
+}
+
+#[cfg(test)]
+pub fn check(input: &str, expected_tree: expect_test::Expect) {
+    expected_tree.assert_eq(&lower_source(input).debug_tree());
 }
 
 #[cfg(test)]
 mod tests {
+    use ergotree_ir::mir::visitor::children;
     use expect_test::expect;
 
     use super::*;
 
+    fn assert_single_arg_functions(expr: &Expr) {
+        match expr {
+            Expr::Apply(apply) => assert_eq!(apply.args.len(), 1, "{:?}", apply),
+            Expr::FuncValue(func) => assert_eq!(func.args().len(), 1, "{:?}", func),
+            _ => (),
+        }
+        children(expr)
+            .into_iter()
+            .for_each(assert_single_arg_functions);
+    }
+
     #[test]
     fn bin_smoke() {
         check(
@@ -219,4 +500,268 @@ mod tests {
             )"#]],
         );
     }
+
+    #[test]
+    fn val_def() {
+        check(
+            "{ val x = HEIGHT \n x + 1 }",
+            expect![[r#"
+                BlockValue(
+                    BlockValue {
+                        items: [
+                            ValDef(
+                                ValDef {
+                                    id: ValId(
+                                        1,
+                                    ),
+                                    rhs: GlobalVars(
+                                        Height,
+                                    ),
+                                },
+                            ),
+                        ],
+                        result: BinOp(
+                            BinOp {
+                                kind: Arith(
+                                    Plus,
+                                ),
+                                left: ValUse(
+                                    ValUse {
+                                        val_id: ValId(
+                                            1,
+                                        ),
+                                        tpe: SInt,
+                                    },
+                                ),
+                                right: Const(
+                                    Constant {
+                                        tpe: SInt,
+                                        v: Int(
+                                            1,
+                                        ),
+                                    },
+                                ),
+                            },
+                        ),
+                    },
+                )"#]],
+        );
+    }
+
+    #[test]
+    fn fn_def_and_call() {
+        check(
+            "{ def f(x: Long, y: Long): Long = x * y \n f(2L, 3L) }",
+            expect![[r#"
+                BlockValue(
+                    BlockValue {
+                        items: [
+                            ValDef(
+                                ValDef {
+                                    id: ValId(
+                                        4,
+                                    ),
+                                    rhs: FuncValue(
+                                        FuncValue {
+                                            args: [
+                                                FuncArg {
+                                                    idx: ValId(
+                                                        1,
+                                                    ),
+                                                    tpe: STuple(
+                                                        STuple {
+                                                            items: BoundedVec {
+                                                                inner: [
+                                                                    SLong,
+                                                                    SLong,
+                                                                ],
+                                                            },
+                                                        },
+                                                    ),
+                                                },
+                                            ],
+                                            body: BlockValue(
+                                                BlockValue {
+                                                    items: [
+                                                        ValDef(
+                                                            ValDef {
+                                                                id: ValId(
+                                                                    2,
+                                                                ),
+                                                                rhs: SelectField(
+                                                                    SelectField {
+                                                                        input: ValUse(
+                                                                            ValUse {
+                                                                                val_id: ValId(
+                                                                                    1,
+                                                                                ),
+                                                                                tpe: STuple(
+                                                                                    STuple {
+                                                                                        items: BoundedVec {
+                                                                                            inner: [
+                                                                                                SLong,
+                                                                                                SLong,
+                                                                                            ],
+                                                                                        },
+                                                                                    },
+                                                                                ),
+                                                                            },
+                                                                        ),
+                                                                        field_index: TupleFieldIndex(
+                                                                            1,
+                                                                        ),
+                                                                        field_tpe: SLong,
+                                                                    },
+                                                                ),
+                                                            },
+                                                        ),
+                                                        ValDef(
+                                                            ValDef {
+                                                                id: ValId(
+                                                                    3,
+                                                                ),
+                                                                rhs: SelectField(
+                                                                    SelectField {
+                                                                        input: ValUse(
+                                                                            ValUse {
+                                                                                val_id: ValId(
+                                                                                    1,
+                                                                                ),
+                                                                                tpe: STuple(
+                                                                                    STuple {
+                                                                                        items: BoundedVec {
+                                                                                            inner: [
+                                                                                                SLong,
+                                                                                                SLong,
+                                                                                            ],
+                                                                                        },
+                                                                                    },
+                                                                                ),
+                                                                            },
+                                                                        ),
+                                                                        field_index: TupleFieldIndex(
+                                                                            2,
+                                                                        ),
+                                                                        field_tpe: SLong,
+                                                                    },
+                                                                ),
+                                                            },
+                                                        ),
+                                                    ],
+                                                    result: BinOp(
+                                                        BinOp {
+                                                            kind: Arith(
+                                                                Multiply,
+                                                            ),
+                                                            left: ValUse(
+                                                                ValUse {
+                                                                    val_id: ValId(
+                                                                        2,
+                                                                    ),
+                                                                    tpe: SLong,
+                                                                },
+                                                            ),
+                                                            right: ValUse(
+                                                                ValUse {
+                                                                    val_id: ValId(
+                                                                        3,
+                                                                    ),
+                                                                    tpe: SLong,
+                                                                },
+                                                            ),
+                                                        },
+                                                    ),
+                                                },
+                                            ),
+                                            tpe: SFunc(
+                                                SFunc {
+                                                    t_dom: [
+                                                        STuple(
+                                                            STuple {
+                                                                items: BoundedVec {
+                                                                    inner: [
+                                                                        SLong,
+                                                                        SLong,
+                                                                    ],
+                                                                },
+                                                            },
+                                                        ),
+                                                    ],
+                                                    t_range: SLong,
+                                                    tpe_params: [],
+                                                },
+                                            ),
+                                        },
+                                    ),
+                                },
+                            ),
+                        ],
+                        result: Apply(
+                            Apply {
+                                func: ValUse(
+                                    ValUse {
+                                        val_id: ValId(
+                                            4,
+                                        ),
+                                        tpe: SFunc(
+                                            SFunc {
+                                                t_dom: [
+                                                    STuple(
+                                                        STuple {
+                                                            items: BoundedVec {
+                                                                inner: [
+                                                                    SLong,
+                                                                    SLong,
+                                                                ],
+                                                            },
+                                                        },
+                                                    ),
+                                                ],
+                                                t_range: SLong,
+                                                tpe_params: [],
+                                            },
+                                        ),
+                                    },
+                                ),
+                                args: [
+                                    Tuple(
+                                        Tuple {
+                                            items: BoundedVec {
+                                                inner: [
+                                                    Const(
+                                                        Constant {
+                                                            tpe: SLong,
+                                                            v: Long(
+                                                                2,
+                                                            ),
+                                                        },
+                                                    ),
+                                                    Const(
+                                                        Constant {
+                                                            tpe: SLong,
+                                                            v: Long(
+                                                                3,
+                                                            ),
+                                                        },
+                                                    ),
+                                                ],
+                                            },
+                                        },
+                                    ),
+                                ],
+                                tpe: SLong,
+                            },
+                        ),
+                    },
+                )"#]],
+        );
+    }
+
+    #[test]
+    fn multi_arg_functions_are_single_arg() {
+        let expr = lower_source(
+            "{ def f(x: Long, y: Long, z: Long): Long = x * y + z \n def g(a: Long, b: Long): Long = f(a, b, a) \n g(2L, 3L) }",
+        );
+        assert_single_arg_functions(&expr);
+    }
 }
@@ -21,7 +21,9 @@ use self::marker::Marker;
 use self::parse_error::ParseError;
 use self::source::Source;
 
-// const RECOVERY_SET: [TokenKind; 1] = [TokenKind::ValKw];
+/// Tokens that start a new statement, they are never swallowed by an error so that parsing
+/// resumes from them and independent errors in the following statements are reported as well.
+const RECOVERY_SET: [TokenKind; 2] = [TokenKind::ValKw, TokenKind::FnKw];
 
 pub struct Parser<'t, 'input> {
     pub source: Source<'t, 'input>,
@@ -75,8 +77,7 @@ impl<'t, 'input> Parser<'t, 'input> {
             span: range,
         }));
 
-        // if !self.at_set(&RECOVERY_SET) && !self.at_end() {
-        if !self.at_end() {
+        if !self.at_set(&RECOVERY_SET) && !self.at_end() {
             let m = self.start();
             self.bump();
             m.complete(self, SyntaxKind::Error);
@@ -90,7 +91,9 @@ impl<'t, 'input> Parser<'t, 'input> {
     }
 
     fn at(&mut self, kind: TokenKind) -> bool {
-        self.expected_kinds.push(kind);
+        if !self.expected_kinds.contains(&kind) {
+            self.expected_kinds.push(kind);
+        }
         self.peek() == Some(kind)
     }
 
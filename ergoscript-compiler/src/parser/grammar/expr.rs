@@ -108,8 +108,15 @@ fn lhs(p: &mut Parser) -> Option<CompletedMarker> {
         int_number(p)
     } else if p.at(TokenKind::LongNumber) {
         long_number(p)
+    } else if p.at(TokenKind::StringLiteral) {
+        string_literal(p)
     } else if p.at(TokenKind::Ident) {
-        ident(p)
+        let ident = ident(p);
+        if p.at(TokenKind::LParen) {
+            call_expr(p, ident)
+        } else {
+            ident
+        }
         // variable_ref(p)
         // } else if p.at(TokenKind::ValKw) {
         //     variable_ref(p)
@@ -117,6 +124,8 @@ fn lhs(p: &mut Parser) -> Option<CompletedMarker> {
         prefix_expr(p)
     } else if p.at(TokenKind::LParen) {
         paren_expr(p)
+    } else if p.at(TokenKind::LBrace) {
+        block_expr(p)
     } else {
         p.error();
         return None;
@@ -167,6 +176,13 @@ fn long_number(p: &mut Parser) -> CompletedMarker {
     m.complete(p, SyntaxKind::LongNumber)
 }
 
+fn string_literal(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::StringLiteral));
+    let m = p.start();
+    p.bump();
+    m.complete(p, SyntaxKind::StringLiteral)
+}
+
 // fn variable_ref(p: &mut Parser) -> CompletedMarker {
 //     assert!(p.at(TokenKind::Ident));
 
@@ -210,6 +226,47 @@ fn paren_expr(p: &mut Parser) -> CompletedMarker {
     m.complete(p, SyntaxKind::ParenExpr)
 }
 
+fn block_expr(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::LBrace));
+
+    let m = p.start();
+    p.bump();
+    while !p.at(TokenKind::RBrace) && !p.at_end() {
+        super::stmt::stmt(p);
+    }
+    p.expect(TokenKind::RBrace);
+
+    m.complete(p, SyntaxKind::BlockExpr)
+}
+
+fn call_expr(p: &mut Parser, func: CompletedMarker) -> CompletedMarker {
+    assert!(p.at(TokenKind::LParen));
+
+    let m = func.precede(p);
+    arg_list(p);
+    m.complete(p, SyntaxKind::CallExpr)
+}
+
+fn arg_list(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::LParen));
+
+    let m = p.start();
+    p.bump();
+    if !p.at(TokenKind::RParen) {
+        loop {
+            expr_binding_power(p, 0);
+            if p.at(TokenKind::Comma) {
+                p.bump();
+            } else {
+                break;
+            }
+        }
+    }
+    p.expect(TokenKind::RParen);
+
+    m.complete(p, SyntaxKind::ArgList)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::parser::check;
@@ -387,7 +444,7 @@ mod tests {
                       IntNumber@1..2
                         IntNumber@1..2 "2"
                       Plus@2..3 "+"
-                error: expected number, number, identifier, ‘-’ or ‘(’
+                error: expected integer, long integer, string, identifier, ‘-’, ‘(’ or ‘{’
                 error: expected ‘)’"#]],
         );
     }
@@ -472,4 +529,104 @@ mod tests {
                       RParen@6..7 ")""#]],
         );
     }
+
+    #[test]
+    fn parse_block() {
+        check(
+            "{ val x = 1 \n x + 2 }",
+            expect![[r#"
+                Root@0..21
+                  BlockExpr@0..21
+                    LBrace@0..1 "{"
+                    Whitespace@1..2 " "
+                    VariableDef@2..14
+                      ValKw@2..5 "val"
+                      Whitespace@5..6 " "
+                      Ident@6..7 "x"
+                      Whitespace@7..8 " "
+                      Equals@8..9 "="
+                      Whitespace@9..10 " "
+                      IntNumber@10..14
+                        IntNumber@10..11 "1"
+                        Whitespace@11..14 " \n "
+                    InfixExpr@14..20
+                      Ident@14..16
+                        Ident@14..15 "x"
+                        Whitespace@15..16 " "
+                      Plus@16..17 "+"
+                      Whitespace@17..18 " "
+                      IntNumber@18..20
+                        IntNumber@18..19 "2"
+                        Whitespace@19..20 " "
+                    RBrace@20..21 "}""#]],
+        );
+    }
+
+    #[test]
+    fn parse_unclosed_block() {
+        check(
+            "{ 1",
+            expect![[r#"
+            Root@0..3
+              BlockExpr@0..3
+                LBrace@0..1 "{"
+                Whitespace@1..2 " "
+                IntNumber@2..3
+                  IntNumber@2..3 "1"
+            error: expected ‘+’, ‘-’, ‘*’, ‘/’ or ‘}’"#]],
+        );
+    }
+
+    #[test]
+    fn parse_call() {
+        check(
+            "f(1, x)",
+            expect![[r#"
+            Root@0..7
+              CallExpr@0..7
+                Ident@0..1
+                  Ident@0..1 "f"
+                ArgList@1..7
+                  LParen@1..2 "("
+                  IntNumber@2..3
+                    IntNumber@2..3 "1"
+                  Comma@3..4 ","
+                  Whitespace@4..5 " "
+                  Ident@5..6
+                    Ident@5..6 "x"
+                  RParen@6..7 ")""#]],
+        );
+    }
+
+    #[test]
+    fn parse_call_without_args() {
+        check(
+            "f()",
+            expect![[r#"
+            Root@0..3
+              CallExpr@0..3
+                Ident@0..1
+                  Ident@0..1 "f"
+                ArgList@1..3
+                  LParen@1..2 "("
+                  RParen@2..3 ")""#]],
+        );
+    }
+
+    #[test]
+    fn parse_string_literal() {
+        check(
+            "fromBase16(\"0e20\")",
+            expect![[r#"
+                Root@0..18
+                  CallExpr@0..18
+                    Ident@0..10
+                      Ident@0..10 "fromBase16"
+                    ArgList@10..18
+                      LParen@10..11 "("
+                      StringLiteral@11..17
+                        StringLiteral@11..17 "\"0e20\""
+                      RParen@17..18 ")""#]],
+        );
+    }
 }
@@ -3,6 +3,8 @@ use super::*;
 pub(super) fn stmt(p: &mut Parser) -> Option<CompletedMarker> {
     if p.at(TokenKind::ValKw) {
         Some(variable_def(p))
+    } else if p.at(TokenKind::FnKw) {
+        Some(fn_def(p))
     } else {
         expr::expr(p)
     }
@@ -21,6 +23,99 @@ fn variable_def(p: &mut Parser) -> CompletedMarker {
     m.complete(p, SyntaxKind::VariableDef)
 }
 
+fn fn_def(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::FnKw));
+    let m = p.start();
+    p.bump();
+
+    p.expect(TokenKind::Ident);
+    param_list(p);
+
+    if p.at(TokenKind::Colon) {
+        p.bump();
+        type_ref(p);
+    }
+
+    p.expect(TokenKind::Equals);
+
+    expr::expr(p);
+
+    m.complete(p, SyntaxKind::FnDef)
+}
+
+fn param_list(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    p.expect(TokenKind::LParen);
+
+    if !p.at(TokenKind::RParen) {
+        loop {
+            param(p);
+            if p.at(TokenKind::Comma) {
+                p.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    p.expect(TokenKind::RParen);
+    m.complete(p, SyntaxKind::ParamList)
+}
+
+fn param(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    p.expect(TokenKind::Ident);
+    p.expect(TokenKind::Colon);
+    type_ref(p);
+    m.complete(p, SyntaxKind::Param)
+}
+
+/// Type name with optional type arguments (`Coll[Byte]`), tuple (`(Int, Long)`) or function
+/// (`Int => Long`, `(Int, Long) => Boolean`) type, nested types are `TypeRef` children
+fn type_ref(p: &mut Parser) -> Option<CompletedMarker> {
+    let cm = if p.at(TokenKind::Ident) {
+        let m = p.start();
+        p.bump();
+        if p.at(TokenKind::LBracket) {
+            p.bump();
+            type_ref_list(p, TokenKind::RBracket);
+        }
+        m.complete(p, SyntaxKind::TypeRef)
+    } else if p.at(TokenKind::LParen) {
+        let m = p.start();
+        p.bump();
+        type_ref_list(p, TokenKind::RParen);
+        m.complete(p, SyntaxKind::TypeRef)
+    } else {
+        p.error();
+        return None;
+    };
+
+    if p.at(TokenKind::FatArrow) {
+        // right associative, `A => B => C` is `A => (B => C)`
+        let m = cm.precede(p);
+        p.bump();
+        type_ref(p);
+        Some(m.complete(p, SyntaxKind::TypeRef))
+    } else {
+        Some(cm)
+    }
+}
+
+fn type_ref_list(p: &mut Parser, close: TokenKind) {
+    if !p.at(close) {
+        loop {
+            type_ref(p);
+            if p.at(TokenKind::Comma) {
+                p.bump();
+            } else {
+                break;
+            }
+        }
+    }
+    p.expect(close);
+}
+
 #[cfg(test)]
 mod tests {
     use crate::parser::check;
@@ -43,4 +138,195 @@ mod tests {
                   Ident@10..13 "bar""#]],
         );
     }
+
+    #[test]
+    fn parse_fn_definition() {
+        check(
+            "def f(x: Int, y: Long): Long = y",
+            expect![[r#"
+                Root@0..32
+                  FnDef@0..32
+                    FnKw@0..3 "def"
+                    Whitespace@3..4 " "
+                    Ident@4..5 "f"
+                    ParamList@5..22
+                      LParen@5..6 "("
+                      Param@6..12
+                        Ident@6..7 "x"
+                        Colon@7..8 ":"
+                        Whitespace@8..9 " "
+                        TypeRef@9..12
+                          Ident@9..12 "Int"
+                      Comma@12..13 ","
+                      Whitespace@13..14 " "
+                      Param@14..21
+                        Ident@14..15 "y"
+                        Colon@15..16 ":"
+                        Whitespace@16..17 " "
+                        TypeRef@17..21
+                          Ident@17..21 "Long"
+                      RParen@21..22 ")"
+                    Colon@22..23 ":"
+                    Whitespace@23..24 " "
+                    TypeRef@24..29
+                      Ident@24..28 "Long"
+                      Whitespace@28..29 " "
+                    Equals@29..30 "="
+                    Whitespace@30..31 " "
+                    Ident@31..32
+                      Ident@31..32 "y""#]],
+        );
+    }
+
+    #[test]
+    fn parse_type_args() {
+        check(
+            "def f(x: Coll[Option[Int]]) = x",
+            expect![[r#"
+            Root@0..31
+              FnDef@0..31
+                FnKw@0..3 "def"
+                Whitespace@3..4 " "
+                Ident@4..5 "f"
+                ParamList@5..28
+                  LParen@5..6 "("
+                  Param@6..26
+                    Ident@6..7 "x"
+                    Colon@7..8 ":"
+                    Whitespace@8..9 " "
+                    TypeRef@9..26
+                      Ident@9..13 "Coll"
+                      LBracket@13..14 "["
+                      TypeRef@14..25
+                        Ident@14..20 "Option"
+                        LBracket@20..21 "["
+                        TypeRef@21..24
+                          Ident@21..24 "Int"
+                        RBracket@24..25 "]"
+                      RBracket@25..26 "]"
+                  RParen@26..27 ")"
+                  Whitespace@27..28 " "
+                Equals@28..29 "="
+                Whitespace@29..30 " "
+                Ident@30..31
+                  Ident@30..31 "x""#]],
+        );
+    }
+
+    #[test]
+    fn parse_tuple_and_func_types() {
+        check(
+            "def f(x: (Int, Long) => Boolean) = x",
+            expect![[r#"
+            Root@0..36
+              FnDef@0..36
+                FnKw@0..3 "def"
+                Whitespace@3..4 " "
+                Ident@4..5 "f"
+                ParamList@5..33
+                  LParen@5..6 "("
+                  Param@6..31
+                    Ident@6..7 "x"
+                    Colon@7..8 ":"
+                    Whitespace@8..9 " "
+                    TypeRef@9..31
+                      TypeRef@9..21
+                        LParen@9..10 "("
+                        TypeRef@10..13
+                          Ident@10..13 "Int"
+                        Comma@13..14 ","
+                        Whitespace@14..15 " "
+                        TypeRef@15..19
+                          Ident@15..19 "Long"
+                        RParen@19..20 ")"
+                        Whitespace@20..21 " "
+                      FatArrow@21..23 "=>"
+                      Whitespace@23..24 " "
+                      TypeRef@24..31
+                        Ident@24..31 "Boolean"
+                  RParen@31..32 ")"
+                  Whitespace@32..33 " "
+                Equals@33..34 "="
+                Whitespace@34..35 " "
+                Ident@35..36
+                  Ident@35..36 "x""#]],
+        );
+    }
+
+    #[test]
+    fn parse_fn_definition_without_result_type() {
+        check(
+            "def f() = 1",
+            expect![[r#"
+            Root@0..11
+              FnDef@0..11
+                FnKw@0..3 "def"
+                Whitespace@3..4 " "
+                Ident@4..5 "f"
+                ParamList@5..8
+                  LParen@5..6 "("
+                  RParen@6..7 ")"
+                  Whitespace@7..8 " "
+                Equals@8..9 "="
+                Whitespace@9..10 " "
+                IntNumber@10..11
+                  IntNumber@10..11 "1""#]],
+        );
+    }
+
+    #[test]
+    fn parse_fn_definition_missing_param_type() {
+        check(
+            "def f(x) = x",
+            expect![[r#"
+            Root@0..12
+              FnDef@0..12
+                FnKw@0..3 "def"
+                Whitespace@3..4 " "
+                Ident@4..5 "f"
+                ParamList@5..12
+                  LParen@5..6 "("
+                  Param@6..11
+                    Ident@6..7 "x"
+                    Error@7..9
+                      RParen@7..8 ")"
+                      Whitespace@8..9 " "
+                    Error@9..11
+                      Equals@9..10 "="
+                      Whitespace@10..11 " "
+                  Error@11..12
+                    Ident@11..12 "x"
+            error: expected ‘:’, but found ‘)’
+            error: expected identifier or ‘(’, but found ‘=’
+            error: expected ‘,’ or ‘)’, but found identifier
+            error: expected ‘:’ or ‘=’
+            error: expected integer, long integer, string, identifier, ‘-’, ‘(’ or ‘{’"#]],
+        );
+    }
+
+    #[test]
+    fn recover_on_next_stmt() {
+        check(
+            "val a =\nval b = 1",
+            expect![[r#"
+            Root@0..17
+              VariableDef@0..8
+                ValKw@0..3 "val"
+                Whitespace@3..4 " "
+                Ident@4..5 "a"
+                Whitespace@5..6 " "
+                Equals@6..7 "="
+                Whitespace@7..8 "\n"
+              VariableDef@8..17
+                ValKw@8..11 "val"
+                Whitespace@11..12 " "
+                Ident@12..13 "b"
+                Whitespace@13..14 " "
+                Equals@14..15 "="
+                Whitespace@15..16 " "
+                IntNumber@16..17
+                  IntNumber@16..17 "1"
+            error: expected integer, long integer, string, identifier, ‘-’, ‘(’ or ‘{’, but found ‘val’"#]],
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use std::fmt;
 use text_size::TextRange;
 
+use crate::compiler::ErrorCode;
 use crate::error::pretty_error_desc;
 use crate::lexer::TokenKind;
 
@@ -12,20 +13,41 @@ pub struct ParseError {
 }
 
 impl ParseError {
+    pub fn code(&self) -> ErrorCode {
+        match self.found {
+            Some(_) => ErrorCode::UnexpectedToken,
+            None => ErrorCode::UnexpectedEof,
+        }
+    }
+
     pub fn pretty_desc(&self, source: &str) -> String {
-        pretty_error_desc(source, self.span, &self.to_string())
+        pretty_error_desc(source, self.span, &self.message())
+    }
+
+    /// Error message without the "error: " prefix
+    pub fn message(&self) -> String {
+        ExpectedFound(self).to_string()
     }
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "error: expected ")?;
+        write!(f, "error: {}", ExpectedFound(self))
+    }
+}
+
+struct ExpectedFound<'a>(&'a ParseError);
+
+impl fmt::Display for ExpectedFound<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ExpectedFound(error) = self;
+        write!(f, "expected ")?;
 
-        let num_expected = self.expected.len();
+        let num_expected = error.expected.len();
         let is_first = |idx| idx == 0;
         let is_last = |idx| idx == num_expected - 1;
 
-        for (idx, expected_kind) in self.expected.iter().enumerate() {
+        for (idx, expected_kind) in error.expected.iter().enumerate() {
             if is_first(idx) {
                 write!(f, "{}", expected_kind)?;
             } else if is_last(idx) {
@@ -35,7 +57,7 @@ impl fmt::Display for ParseError {
             }
         }
 
-        if let Some(found) = self.found {
+        if let Some(found) = error.found {
             write!(f, ", but found {}", found)?;
         }
 
@@ -103,7 +125,17 @@ mod tests {
             ],
             Some(TokenKind::ValKw),
             100..105,
-            "error: expected number, identifier, ‘-’ or ‘(’, but found ‘val’",
+            "error: expected integer, identifier, ‘-’ or ‘(’, but found ‘val’",
+        );
+    }
+
+    #[test]
+    fn int_and_long_expected() {
+        check(
+            vec![TokenKind::IntNumber, TokenKind::LongNumber],
+            None,
+            0..1,
+            "error: expected integer or long integer",
         );
     }
 }
@@ -23,4 +23,24 @@ impl ScriptEnv {
     pub fn get(&self, ident: &str) -> Option<&Constant> {
         self.0.get(ident)
     }
+
+    /// Returns all bindings sorted by identifier
+    pub fn sorted_bindings(&self) -> Vec<(&String, &Constant)> {
+        let mut bindings: Vec<(&String, &Constant)> = self.0.iter().collect();
+        bindings.sort_by(|(a, _), (b, _)| a.cmp(b));
+        bindings
+    }
+
+    /// Binds the value to the given identifier, replacing the previous binding (if any).
+    /// Any value convertible to [`Constant`] can be used, e.g. byte arrays (token ids, script
+    /// hashes), group elements (public keys) or sigma propositions.
+    pub fn insert<T: Into<Constant>>(&mut self, ident: &str, value: T) {
+        self.0.insert(ident.to_string(), value.into());
+    }
+}
+
+impl From<HashMap<String, Constant>> for ScriptEnv {
+    fn from(env: HashMap<String, Constant>) -> Self {
+        ScriptEnv(env)
+    }
 }
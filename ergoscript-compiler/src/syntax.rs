@@ -12,16 +12,22 @@ pub enum SyntaxKind {
     Ident,
     IntNumber,
     LongNumber,
+    StringLiteral,
     Plus,
     Minus,
     Star,
     Slash,
     And,
     Equals,
+    FatArrow,
+    Colon,
+    Comma,
     LParen,
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
     Comment,
     Error,
     Root,
@@ -29,6 +35,13 @@ pub enum SyntaxKind {
     ParenExpr,
     PrefixExpr,
     VariableDef,
+    FnDef,
+    ParamList,
+    Param,
+    TypeRef,
+    BlockExpr,
+    CallExpr,
+    ArgList,
 }
 
 impl From<TokenKind> for SyntaxKind {
@@ -40,15 +53,21 @@ impl From<TokenKind> for SyntaxKind {
             TokenKind::Ident => Self::Ident,
             TokenKind::IntNumber => Self::IntNumber,
             TokenKind::LongNumber => Self::LongNumber,
+            TokenKind::StringLiteral => Self::StringLiteral,
             TokenKind::Plus => Self::Plus,
             TokenKind::Minus => Self::Minus,
             TokenKind::Star => Self::Star,
             TokenKind::Slash => Self::Slash,
             TokenKind::Equals => Self::Equals,
+            TokenKind::FatArrow => Self::FatArrow,
+            TokenKind::Colon => Self::Colon,
+            TokenKind::Comma => Self::Comma,
             TokenKind::LParen => Self::LParen,
             TokenKind::RParen => Self::RParen,
             TokenKind::LBrace => Self::LBrace,
             TokenKind::RBrace => Self::RBrace,
+            TokenKind::LBracket => Self::LBracket,
+            TokenKind::RBracket => Self::RBracket,
             TokenKind::Comment => Self::Comment,
             TokenKind::Error => Self::Error,
             TokenKind::And => Self::And,
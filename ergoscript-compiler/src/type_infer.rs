@@ -1,63 +1,259 @@
+use std::collections::HashMap;
+
+use ergotree_ir::types::sfunc::SFunc;
+use ergotree_ir::types::stype::SType;
 use rowan::TextRange;
 
+use crate::compiler::ErrorCode;
 use crate::error::pretty_error_desc;
 use crate::hir;
+use crate::hir::Apply;
 use crate::hir::Binary;
+use crate::hir::Block;
 use crate::hir::Expr;
 use crate::hir::ExprKind;
+use crate::hir::Lambda;
+use crate::hir::Val;
 
 #[derive(Debug, PartialEq)]
 pub struct TypeInferenceError {
+    code: ErrorCode,
     msg: String,
     span: TextRange,
 }
 
 impl TypeInferenceError {
-    pub fn new(msg: String, span: TextRange) -> Self {
-        Self { msg, span }
+    pub fn new(code: ErrorCode, msg: String, span: TextRange) -> Self {
+        Self { code, msg, span }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
     }
 
     pub fn pretty_desc(&self, source: &str) -> String {
         pretty_error_desc(source, self.span, &self.msg)
     }
+
+    pub fn span(&self) -> TextRange {
+        self.span
+    }
 }
 
 pub fn assign_type(expr: Expr) -> Result<Expr, TypeInferenceError> {
-    hir::rewrite(expr, |e| {
-        Ok(match &e.kind {
-            ExprKind::Binary(Binary { op, lhs, rhs }) => match op.node {
-                hir::BinaryOp::Plus => {
-                    let l = assign_type(*lhs.clone())?;
-                    let r = assign_type(*rhs.clone())?;
-                    let tpe = l.tpe.clone();
-                    Some(Expr {
-                        kind: Binary {
-                            op: op.clone(),
-                            lhs: l.into(),
-                            rhs: r.into(),
-                        }
-                        .into(),
-                        span: e.span,
-                        tpe,
-                    })
+    assign_type_in_env(expr, &TypeEnv::new())
+}
+
+/// Types of the named values in scope. `None` marks a value whose definition is being typed, so
+/// that a reference to it from its own right-hand side (recursion) is reported as an error.
+type TypeEnv = HashMap<String, Option<SType>>;
+
+fn assign_type_in_env(expr: Expr, env: &TypeEnv) -> Result<Expr, TypeInferenceError> {
+    let Expr { kind, span, tpe } = expr;
+    let (kind, tpe): (ExprKind, Option<SType>) = match kind {
+        ExprKind::Ident(name) => match env.get(&name) {
+            Some(Some(ident_tpe)) => {
+                let ident_tpe = ident_tpe.clone();
+                (ExprKind::Ident(name), Some(ident_tpe))
+            }
+            Some(None) => {
+                return Err(TypeInferenceError::new(
+                    ErrorCode::RecursiveDefinition,
+                    format!("Recursive reference to `{}` is not supported", name),
+                    span,
+                ))
+            }
+            None => {
+                return Err(TypeInferenceError::new(
+                    ErrorCode::UnresolvedName,
+                    format!("Cannot find value `{}` in this scope", name),
+                    span,
+                ))
+            }
+        },
+        ExprKind::Binary(Binary { op, lhs, rhs }) => {
+            let l = assign_type_in_env(*lhs, env)?;
+            let r = assign_type_in_env(*rhs, env)?;
+            let tpe = match (&l.tpe, &r.tpe) {
+                (Some(l_tpe), Some(r_tpe)) if l_tpe == r_tpe => l_tpe.clone(),
+                (Some(l_tpe), Some(r_tpe)) => {
+                    return Err(TypeInferenceError::new(
+                        ErrorCode::TypeMismatch,
+                        format!(
+                            "Operand types mismatch: {:?} and {:?}",
+                            l_tpe, r_tpe
+                        ),
+                        span,
+                    ))
+                }
+                (None, _) => return Err(untyped_error(&l)),
+                (_, None) => return Err(untyped_error(&r)),
+            };
+            (
+                Binary {
+                    op,
+                    lhs: l.into(),
+                    rhs: r.into(),
+                }
+                .into(),
+                Some(tpe),
+            )
+        }
+        ExprKind::Block(Block { items, result }) => {
+            let mut block_env = env.clone();
+            let mut typed_items = Vec::with_capacity(items.len());
+            for item in items {
+                let typed_item = assign_type_in_env(item, &block_env)?;
+                if let ExprKind::Val(Val { name, .. }) = &typed_item.kind {
+                    block_env.insert(name.clone(), typed_item.tpe.clone());
                 }
-                _ => todo!(),
-            },
-            _ => None,
-        })
-    })
+                typed_items.push(typed_item);
+            }
+            let typed_result = assign_type_in_env(*result, &block_env)?;
+            let tpe = typed_result.tpe.clone();
+            (
+                Block {
+                    items: typed_items,
+                    result: typed_result.into(),
+                }
+                .into(),
+                tpe,
+            )
+        }
+        ExprKind::Val(Val { name, rhs }) => {
+            let mut rhs_env = env.clone();
+            rhs_env.insert(name.clone(), None);
+            let typed_rhs = assign_type_in_env(*rhs, &rhs_env)?;
+            let tpe = typed_rhs.tpe.clone();
+            (
+                Val {
+                    name,
+                    rhs: typed_rhs.into(),
+                }
+                .into(),
+                tpe,
+            )
+        }
+        ExprKind::Lambda(Lambda {
+            args,
+            res_tpe,
+            body,
+        }) => {
+            let mut body_env = env.clone();
+            for (arg_name, arg_tpe) in &args {
+                body_env.insert(arg_name.clone(), Some(arg_tpe.clone()));
+            }
+            let typed_body = assign_type_in_env(*body, &body_env)?;
+            let body_tpe = typed_body.tpe.clone().ok_or_else(|| {
+                TypeInferenceError::new(
+                    ErrorCode::CannotInferType,
+                    format!("Cannot infer type of function body: {:?}", typed_body),
+                    typed_body.span,
+                )
+            })?;
+            if let Some(res_tpe) = &res_tpe {
+                if *res_tpe != body_tpe {
+                    return Err(TypeInferenceError::new(
+                        ErrorCode::TypeMismatch,
+                        format!(
+                            "Function result type mismatch: declared {:?}, found {:?}",
+                            res_tpe, body_tpe
+                        ),
+                        typed_body.span,
+                    ));
+                }
+            }
+            let t_dom = args.iter().map(|(_, arg_tpe)| arg_tpe.clone()).collect();
+            let tpe = SType::SFunc(SFunc::new(t_dom, body_tpe));
+            (
+                Lambda {
+                    args,
+                    res_tpe,
+                    body: typed_body.into(),
+                }
+                .into(),
+                Some(tpe),
+            )
+        }
+        ExprKind::Apply(Apply { func, args }) => {
+            let typed_func = assign_type_in_env(*func, env)?;
+            let typed_args = args
+                .into_iter()
+                .map(|arg| assign_type_in_env(arg, env))
+                .collect::<Result<Vec<Expr>, _>>()?;
+            let sfunc = match &typed_func.tpe {
+                Some(SType::SFunc(sfunc)) => sfunc.clone(),
+                _ => {
+                    return Err(TypeInferenceError::new(
+                        ErrorCode::NotAFunction,
+                        format!(
+                            "Expected a function, found an expression of type {:?}",
+                            typed_func.tpe
+                        ),
+                        typed_func.span,
+                    ))
+                }
+            };
+            let arg_types: Vec<SType> = typed_args
+                .iter()
+                .map(|arg| arg.tpe.clone().ok_or_else(|| untyped_error(arg)))
+                .collect::<Result<_, _>>()?;
+            if sfunc.t_dom != arg_types {
+                return Err(TypeInferenceError::new(
+                    ErrorCode::TypeMismatch,
+                    format!(
+                        "Function argument types mismatch: expected {:?}, found {:?}",
+                        sfunc.t_dom, arg_types
+                    ),
+                    span,
+                ));
+            }
+            (
+                Apply {
+                    func: typed_func.into(),
+                    args: typed_args,
+                }
+                .into(),
+                Some(*sfunc.t_range),
+            )
+        }
+        ExprKind::Literal(hir::Literal::Str(_)) => {
+            return Err(TypeInferenceError::new(
+                ErrorCode::UnexpectedStringLiteral,
+                "String literal can only be used as an argument of fromBase16, fromBase58, fromBase64 or bigInt"
+                    .to_string(),
+                span,
+            ))
+        }
+        kind @ ExprKind::GlobalVars(_) => (kind, tpe),
+        kind @ ExprKind::Literal(_) => (kind, tpe),
+        kind @ ExprKind::Const(_) => (kind, tpe),
+        kind @ ExprKind::ConstPlaceholder(_) => (kind, tpe),
+    };
+    Ok(Expr { kind, span, tpe })
+}
+
+fn untyped_error(expr: &Expr) -> TypeInferenceError {
+    TypeInferenceError::new(
+        ErrorCode::CannotInferType,
+        format!("Cannot infer type of expression: {:?}", expr.kind),
+        expr.span,
+    )
 }
 
 #[cfg(test)]
-pub fn check(input: &str, expected_tree: expect_test::Expect) {
+fn infer(input: &str) -> Result<Expr, TypeInferenceError> {
     let parse = super::parser::parse(input);
     let syntax = parse.syntax();
     let root = crate::ast::Root::cast(syntax).unwrap();
     let hir = hir::lower(root).unwrap();
     let binder = crate::binder::Binder::new(crate::script_env::ScriptEnv::new());
-    let bind = binder.bind(hir).unwrap();
-    let res = assign_type(bind).unwrap();
-    expected_tree.assert_eq(&res.debug_tree());
+    assign_type(binder.bind(hir).unwrap())
+}
+
+#[cfg(test)]
+pub fn check(input: &str, expected_tree: expect_test::Expect) {
+    expected_tree.assert_eq(&infer(input).unwrap().debug_tree());
 }
 
 #[cfg(test)]
@@ -104,4 +300,217 @@ mod tests {
             }"#]],
         );
     }
+
+    #[test]
+    fn fn_def_and_call() {
+        check(
+            "{ def f(x: Int, y: Int): Int = x + y \n f(1, HEIGHT) }",
+            expect![[r#"
+                Expr {
+                    kind: Block(
+                        Block {
+                            items: [
+                                Expr {
+                                    kind: Val(
+                                        Val {
+                                            name: "f",
+                                            rhs: Expr {
+                                                kind: Lambda(
+                                                    Lambda {
+                                                        args: [
+                                                            (
+                                                                "x",
+                                                                SInt,
+                                                            ),
+                                                            (
+                                                                "y",
+                                                                SInt,
+                                                            ),
+                                                        ],
+                                                        res_tpe: Some(
+                                                            SInt,
+                                                        ),
+                                                        body: Expr {
+                                                            kind: Binary(
+                                                                Binary {
+                                                                    op: Spanned {
+                                                                        node: Plus,
+                                                                        span: 33..34,
+                                                                    },
+                                                                    lhs: Expr {
+                                                                        kind: Ident(
+                                                                            "x",
+                                                                        ),
+                                                                        span: 31..33,
+                                                                        tpe: Some(
+                                                                            SInt,
+                                                                        ),
+                                                                    },
+                                                                    rhs: Expr {
+                                                                        kind: Ident(
+                                                                            "y",
+                                                                        ),
+                                                                        span: 35..39,
+                                                                        tpe: Some(
+                                                                            SInt,
+                                                                        ),
+                                                                    },
+                                                                },
+                                                            ),
+                                                            span: 31..39,
+                                                            tpe: Some(
+                                                                SInt,
+                                                            ),
+                                                        },
+                                                    },
+                                                ),
+                                                span: 2..39,
+                                                tpe: Some(
+                                                    SFunc(
+                                                        SFunc {
+                                                            t_dom: [
+                                                                SInt,
+                                                                SInt,
+                                                            ],
+                                                            t_range: SInt,
+                                                            tpe_params: [],
+                                                        },
+                                                    ),
+                                                ),
+                                            },
+                                        },
+                                    ),
+                                    span: 2..39,
+                                    tpe: Some(
+                                        SFunc(
+                                            SFunc {
+                                                t_dom: [
+                                                    SInt,
+                                                    SInt,
+                                                ],
+                                                t_range: SInt,
+                                                tpe_params: [],
+                                            },
+                                        ),
+                                    ),
+                                },
+                            ],
+                            result: Expr {
+                                kind: Apply(
+                                    Apply {
+                                        func: Expr {
+                                            kind: Ident(
+                                                "f",
+                                            ),
+                                            span: 39..40,
+                                            tpe: Some(
+                                                SFunc(
+                                                    SFunc {
+                                                        t_dom: [
+                                                            SInt,
+                                                            SInt,
+                                                        ],
+                                                        t_range: SInt,
+                                                        tpe_params: [],
+                                                    },
+                                                ),
+                                            ),
+                                        },
+                                        args: [
+                                            Expr {
+                                                kind: Literal(
+                                                    Int(
+                                                        1,
+                                                    ),
+                                                ),
+                                                span: 41..42,
+                                                tpe: Some(
+                                                    SInt,
+                                                ),
+                                            },
+                                            Expr {
+                                                kind: GlobalVars(
+                                                    Height,
+                                                ),
+                                                span: 44..50,
+                                                tpe: Some(
+                                                    SInt,
+                                                ),
+                                            },
+                                        ],
+                                    },
+                                ),
+                                span: 39..52,
+                                tpe: Some(
+                                    SInt,
+                                ),
+                            },
+                        },
+                    ),
+                    span: 0..53,
+                    tpe: Some(
+                        SInt,
+                    ),
+                }"#]],
+        );
+    }
+
+    #[test]
+    fn operand_types_mismatch() {
+        let err = infer("HEIGHT + 1L").unwrap_err();
+        assert_eq!(err.span(), TextRange::new(0.into(), 11.into()));
+        assert!(err.msg.contains("Operand types mismatch"), "{}", err.msg);
+    }
+
+    #[test]
+    fn untyped_operands_and_args() {
+        let span = TextRange::new(0.into(), 1.into());
+        let untyped = Expr {
+            kind: ExprKind::GlobalVars(hir::GlobalVars::Height),
+            span,
+            tpe: None,
+        };
+        let height = Expr {
+            tpe: Some(SType::SInt),
+            ..untyped.clone()
+        };
+        let binary = Expr {
+            kind: Binary {
+                op: hir::Spanned {
+                    node: hir::BinaryOp::Plus,
+                    span,
+                },
+                lhs: height.clone().into(),
+                rhs: untyped.clone().into(),
+            }
+            .into(),
+            span,
+            tpe: None,
+        };
+        assert_eq!(assign_type(binary).unwrap_err(), untyped_error(&untyped));
+
+        let func = Expr {
+            kind: Lambda {
+                args: vec![
+                    ("x".to_string(), SType::SInt),
+                    ("y".to_string(), SType::SInt),
+                ],
+                res_tpe: None,
+                body: height.clone().into(),
+            }
+            .into(),
+            span,
+            tpe: None,
+        };
+        let apply = Expr {
+            kind: Apply {
+                func: func.into(),
+                args: vec![untyped.clone(), height],
+            }
+            .into(),
+            span,
+            tpe: None,
+        };
+        assert_eq!(assign_type(apply).unwrap_err(), untyped_error(&untyped));
+    }
 }
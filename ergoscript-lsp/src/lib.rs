@@ -0,0 +1,96 @@
+//! ErgoScript language server (LSP)
+
+// Coding conventions
+#![forbid(unsafe_code)]
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![deny(dead_code)]
+#![deny(unused_imports)]
+#![deny(missing_docs)]
+#![deny(rustdoc::broken_intra_doc_links)]
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::expect_used)]
+#![deny(clippy::todo)]
+#![deny(clippy::unimplemented)]
+#![deny(clippy::panic)]
+
+mod line_index;
+mod server;
+
+use lsp_server::Connection;
+use lsp_server::Message;
+use lsp_server::ProtocolError;
+use lsp_types::CompletionOptions;
+use lsp_types::HoverProviderCapability;
+use lsp_types::OneOf;
+use lsp_types::ServerCapabilities;
+use lsp_types::TextDocumentSyncCapability;
+use lsp_types::TextDocumentSyncKind;
+use thiserror::Error;
+
+use server::Server;
+
+/// Language server errors
+#[derive(Error, Debug)]
+pub enum LspError {
+    /// LSP protocol violation (e.g. on initialization)
+    #[error("protocol error: {0}")]
+    ProtocolError(#[from] ProtocolError),
+    /// JSON (de)serialization error
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    /// IO error
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    /// Client disconnected before the shutdown request
+    #[error("client disconnected")]
+    Disconnected,
+}
+
+/// Capabilities of the server (full text document sync, hover, go to definition and completion
+/// triggered on `.`)
+pub fn server_capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![".".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Initializes the connection and serves the requests until the client shuts the server down
+pub fn run(connection: &Connection) -> Result<(), LspError> {
+    connection.initialize(serde_json::to_value(server_capabilities())?)?;
+    let mut server = Server::default();
+    for msg in &connection.receiver {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    return Ok(());
+                }
+                let resp = server.handle_request(req);
+                send(connection, resp.into())?;
+            }
+            Message::Notification(not) => {
+                for out in server.handle_notification(not) {
+                    send(connection, out.into())?;
+                }
+            }
+            Message::Response(_) => (),
+        }
+    }
+    Ok(())
+}
+
+fn send(connection: &Connection, msg: Message) -> Result<(), LspError> {
+    connection
+        .sender
+        .send(msg)
+        .map_err(|_| LspError::Disconnected)
+}
@@ -0,0 +1,84 @@
+//! Conversion between byte offsets (compiler) and line/UTF-16 column positions (LSP)
+
+use lsp_types::Position;
+use lsp_types::Range;
+use text_size::TextRange;
+use text_size::TextSize;
+
+/// LSP position of the given byte offset in the text
+pub(crate) fn position(text: &str, offset: TextSize) -> Position {
+    let offset = usize::from(offset).min(text.len());
+    let mut line = 0;
+    let mut line_start = 0;
+    for (idx, c) in text.char_indices() {
+        if idx >= offset {
+            break;
+        }
+        if c == '\n' {
+            line += 1;
+            line_start = idx + 1;
+        }
+    }
+    let character = text
+        .get(line_start..offset)
+        .map_or(0, |s| s.encode_utf16().count());
+    Position::new(line, character as u32)
+}
+
+/// LSP range of the given byte range in the text
+pub(crate) fn range(text: &str, range: TextRange) -> Range {
+    Range::new(position(text, range.start()), position(text, range.end()))
+}
+
+/// Byte offset of the given LSP position in the text. Positions past the end of the line are
+/// clamped to the end of the line.
+pub(crate) fn offset(text: &str, position: Position) -> TextSize {
+    let line_start: usize = text
+        .split('\n')
+        .take(position.line as usize)
+        .map(|line| line.len() + 1)
+        .sum::<usize>()
+        .min(text.len());
+    let line = text[line_start..].split('\n').next().unwrap_or("");
+    let mut utf16_col = 0;
+    let mut col = line.len();
+    for (idx, c) in line.char_indices() {
+        if utf16_col >= position.character as usize {
+            col = idx;
+            break;
+        }
+        utf16_col += c.len_utf16();
+    }
+    TextSize::from((line_start + col) as u32)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_position_roundtrip() {
+        let text = "val a = 1\nval ä = \"€\"\n\nä";
+        for (offset, _) in text.char_indices() {
+            let offset = TextSize::from(offset as u32);
+            assert_eq!(super::offset(text, position(text, offset)), offset);
+        }
+    }
+
+    #[test]
+    fn utf16_columns() {
+        let text = "val a = 1\nval ä = \"𝔸\" + b";
+        let b_offset = TextSize::from(text.find('b').unwrap() as u32);
+        assert_eq!(position(text, b_offset), Position::new(1, 15));
+        assert_eq!(offset(text, Position::new(1, 15)), b_offset);
+    }
+
+    #[test]
+    fn past_the_end() {
+        let text = "a\nbc";
+        assert_eq!(offset(text, Position::new(0, 10)), TextSize::from(1));
+        assert_eq!(offset(text, Position::new(5, 0)), TextSize::from(4));
+        assert_eq!(position(text, TextSize::from(4)), Position::new(1, 2));
+    }
+}
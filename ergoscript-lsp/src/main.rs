@@ -0,0 +1,13 @@
+//! ErgoScript language server over stdio
+
+use ergoscript_lsp::run;
+use ergoscript_lsp::LspError;
+use lsp_server::Connection;
+
+fn main() -> Result<(), LspError> {
+    let (connection, io_threads) = Connection::stdio();
+    run(&connection)?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
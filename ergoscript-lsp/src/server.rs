@@ -0,0 +1,209 @@
+//! Open documents and request/notification handlers
+
+use std::collections::HashMap;
+
+use ergoscript_compiler::analysis;
+use ergoscript_compiler::analysis::CompletionKind;
+use ergoscript_compiler::compiler::compile;
+use ergoscript_compiler::script_env::ScriptEnv;
+use lsp_server::ErrorCode;
+use lsp_server::Notification;
+use lsp_server::Request;
+use lsp_server::RequestId;
+use lsp_server::Response;
+use lsp_types::notification::DidChangeTextDocument;
+use lsp_types::notification::DidCloseTextDocument;
+use lsp_types::notification::DidOpenTextDocument;
+use lsp_types::notification::Notification as _;
+use lsp_types::notification::PublishDiagnostics;
+use lsp_types::request::Completion;
+use lsp_types::request::GotoDefinition;
+use lsp_types::request::HoverRequest;
+use lsp_types::request::Request as _;
+use lsp_types::CompletionItem;
+use lsp_types::CompletionItemKind;
+use lsp_types::CompletionParams;
+use lsp_types::CompletionResponse;
+use lsp_types::Diagnostic;
+use lsp_types::DiagnosticSeverity;
+use lsp_types::DidChangeTextDocumentParams;
+use lsp_types::DidCloseTextDocumentParams;
+use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::GotoDefinitionParams;
+use lsp_types::GotoDefinitionResponse;
+use lsp_types::Hover;
+use lsp_types::HoverContents;
+use lsp_types::HoverParams;
+use lsp_types::Location;
+use lsp_types::MarkupContent;
+use lsp_types::MarkupKind;
+use lsp_types::NumberOrString;
+use lsp_types::PublishDiagnosticsParams;
+use lsp_types::TextDocumentPositionParams;
+use lsp_types::Url;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use text_size::TextRange;
+use text_size::TextSize;
+
+use crate::line_index;
+
+/// Name of the diagnostics source
+const SOURCE: &str = "ergoscript";
+
+/// Language server state (texts of the open documents)
+#[derive(Default)]
+pub(crate) struct Server {
+    documents: HashMap<Url, String>,
+}
+
+impl Server {
+    /// Response to the given request
+    pub(crate) fn handle_request(&self, req: Request) -> Response {
+        match req.method.as_str() {
+            HoverRequest::METHOD => self.respond(req, |s, p: HoverParams| {
+                s.hover(p.text_document_position_params)
+            }),
+            GotoDefinition::METHOD => self.respond(req, |s, p: GotoDefinitionParams| {
+                s.definition(p.text_document_position_params)
+            }),
+            Completion::METHOD => self.respond(req, |s, p: CompletionParams| {
+                s.completion(p.text_document_position)
+            }),
+            _ => Response::new_err(
+                req.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request: {}", req.method),
+            ),
+        }
+    }
+
+    /// Updates the documents and returns the notifications to send to the client
+    /// (diagnostics for the changed document)
+    pub(crate) fn handle_notification(&mut self, not: Notification) -> Vec<Notification> {
+        let uri = match not.method.as_str() {
+            DidOpenTextDocument::METHOD => parse_params::<DidOpenTextDocumentParams>(not.params)
+                .map(|p| {
+                    self.documents
+                        .insert(p.text_document.uri.clone(), p.text_document.text);
+                    p.text_document.uri
+                }),
+            DidChangeTextDocument::METHOD => {
+                parse_params::<DidChangeTextDocumentParams>(not.params).map(|mut p| {
+                    // full text sync, the last change is the whole text
+                    if let Some(change) = p.content_changes.pop() {
+                        self.documents
+                            .insert(p.text_document.uri.clone(), change.text);
+                    }
+                    p.text_document.uri
+                })
+            }
+            DidCloseTextDocument::METHOD => parse_params::<DidCloseTextDocumentParams>(not.params)
+                .map(|p| {
+                    self.documents.remove(&p.text_document.uri);
+                    p.text_document.uri
+                }),
+            _ => None,
+        };
+        uri.map(|uri| {
+            let params = PublishDiagnosticsParams::new(uri.clone(), self.diagnostics(&uri), None);
+            vec![Notification::new(
+                PublishDiagnostics::METHOD.to_string(),
+                params,
+            )]
+        })
+        .unwrap_or_default()
+    }
+
+    fn respond<P, R, F>(&self, req: Request, handler: F) -> Response
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+        F: FnOnce(&Self, P) -> Option<R>,
+    {
+        match serde_json::from_value::<P>(req.params) {
+            Ok(params) => Response::new_ok(req.id, handler(self, params)),
+            Err(e) => invalid_params(req.id, e),
+        }
+    }
+
+    /// Compilation errors of the document (no errors for a closed document)
+    fn diagnostics(&self, uri: &Url) -> Vec<Diagnostic> {
+        let text = match self.documents.get(uri) {
+            Some(text) => text,
+            None => return Vec::new(),
+        };
+        match compile(text, ScriptEnv::new()) {
+            Ok(_) => Vec::new(),
+            Err(e) => e
+                .diagnostics(text)
+                .into_iter()
+                .map(|d| Diagnostic {
+                    range: line_index::range(
+                        text,
+                        d.span
+                            .unwrap_or_else(|| TextRange::empty(TextSize::from(0))),
+                    ),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: Some(NumberOrString::String(d.code.to_string())),
+                    source: Some(SOURCE.to_string()),
+                    message: d.message,
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn hover(&self, pos: TextDocumentPositionParams) -> Option<Hover> {
+        let (text, offset) = self.document_offset(&pos)?;
+        let (span, tpe) = analysis::type_at(text, offset)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```scala\n{}\n```", analysis::type_name(&tpe)),
+            }),
+            range: Some(line_index::range(text, span)),
+        })
+    }
+
+    fn definition(&self, pos: TextDocumentPositionParams) -> Option<GotoDefinitionResponse> {
+        let (text, offset) = self.document_offset(&pos)?;
+        let span = analysis::definition_at(text, offset)?;
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            pos.text_document.uri,
+            line_index::range(text, span),
+        )))
+    }
+
+    fn completion(&self, pos: TextDocumentPositionParams) -> Option<CompletionResponse> {
+        let (text, offset) = self.document_offset(&pos)?;
+        let items = analysis::completions(text, offset)
+            .into_iter()
+            .map(|c| CompletionItem {
+                label: c.label,
+                kind: Some(match c.kind {
+                    CompletionKind::Value => CompletionItemKind::VARIABLE,
+                    CompletionKind::Function => CompletionItemKind::FUNCTION,
+                    CompletionKind::Global => CompletionItemKind::CONSTANT,
+                    CompletionKind::Method => CompletionItemKind::METHOD,
+                }),
+                detail: Some(c.detail).filter(|d| !d.is_empty()),
+                ..Default::default()
+            })
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn document_offset(&self, pos: &TextDocumentPositionParams) -> Option<(&str, TextSize)> {
+        let text = self.documents.get(&pos.text_document.uri)?;
+        Some((text, line_index::offset(text, pos.position)))
+    }
+}
+
+fn parse_params<P: DeserializeOwned>(params: serde_json::Value) -> Option<P> {
+    serde_json::from_value(params).ok()
+}
+
+fn invalid_params(id: RequestId, e: serde_json::Error) -> Response {
+    Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string())
+}
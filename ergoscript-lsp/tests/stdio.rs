@@ -0,0 +1,163 @@
+use std::io::BufReader;
+use std::process::Child;
+use std::process::ChildStdin;
+use std::process::ChildStdout;
+use std::process::Command;
+use std::process::Stdio;
+
+use lsp_server::Message;
+use lsp_server::Notification;
+use lsp_server::Request;
+use lsp_server::RequestId;
+use lsp_server::Response;
+use serde_json::json;
+use serde_json::Value;
+
+const URI: &str = "file:///contract.es";
+
+struct Client {
+    process: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i32,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut process = Command::new(env!("CARGO_BIN_EXE_ergoscript-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = process.stdin.take().unwrap();
+        let stdout = BufReader::new(process.stdout.take().unwrap());
+        Client {
+            process,
+            stdin,
+            stdout,
+            next_id: 1,
+        }
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Response {
+        let id = RequestId::from(self.next_id);
+        self.next_id += 1;
+        Message::Request(Request::new(id.clone(), method.to_string(), params))
+            .write(&mut self.stdin)
+            .unwrap();
+        loop {
+            if let Message::Response(resp) = self.read() {
+                assert_eq!(resp.id, id);
+                return resp;
+            }
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        Message::Notification(Notification::new(method.to_string(), params))
+            .write(&mut self.stdin)
+            .unwrap();
+    }
+
+    fn read_notification(&mut self, method: &str) -> Notification {
+        loop {
+            if let Message::Notification(not) = self.read() {
+                if not.method == method {
+                    return not;
+                }
+            }
+        }
+    }
+
+    fn read(&mut self) -> Message {
+        Message::read(&mut self.stdout).unwrap().unwrap()
+    }
+
+    fn open(&mut self, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "ergoscript", "version": 1, "text": text }
+            }),
+        );
+        self.read_notification("textDocument/publishDiagnostics")
+            .params
+    }
+
+    fn change(&mut self, text: &str) -> Value {
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{ "text": text }]
+            }),
+        );
+        self.read_notification("textDocument/publishDiagnostics")
+            .params
+    }
+
+    fn position_request(&mut self, method: &str, line: u32, character: u32) -> Value {
+        let resp = self.request(
+            method,
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": line, "character": character }
+            }),
+        );
+        assert!(resp.error.is_none(), "{:?}", resp.error);
+        resp.result.unwrap()
+    }
+}
+
+#[test]
+fn lsp_session_over_stdio() {
+    let mut client = Client::start();
+    let init = client.request("initialize", json!({ "capabilities": {} }));
+    let caps = &init.result.unwrap()["capabilities"];
+    assert_eq!(caps["hoverProvider"], json!(true));
+    assert_eq!(caps["definitionProvider"], json!(true));
+    assert_eq!(
+        caps["completionProvider"]["triggerCharacters"],
+        json!(["."])
+    );
+    client.notify("initialized", json!({}));
+
+    let diagnostics = client.open("{ val a = 1L\n  a + b }");
+    assert_eq!(diagnostics["uri"], json!(URI));
+    let diagnostic = &diagnostics["diagnostics"][0];
+    assert_eq!(diagnostic["code"], json!("E0401"));
+    assert_eq!(
+        diagnostic["range"],
+        json!({ "start": { "line": 1, "character": 6 }, "end": { "line": 1, "character": 7 } })
+    );
+    assert!(diagnostic["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("error[E0401]: Cannot find value `b` in this scope"));
+
+    let diagnostics = client.change("{ val a = 1L\n  val c = a\n  c + 2L }");
+    assert_eq!(diagnostics["diagnostics"], json!([]));
+
+    let hover = client.position_request("textDocument/hover", 2, 2);
+    assert_eq!(hover["contents"]["value"], json!("```scala\nLong\n```"));
+
+    let definition = client.position_request("textDocument/definition", 2, 2);
+    assert_eq!(
+        definition,
+        json!({
+            "uri": URI,
+            "range": { "start": { "line": 1, "character": 6 }, "end": { "line": 1, "character": 7 } }
+        })
+    );
+
+    let completion = client.position_request("textDocument/completion", 2, 3);
+    assert_eq!(
+        completion,
+        json!([{ "label": "c", "kind": 6, "detail": "Long" }])
+    );
+
+    let shutdown = client.request("shutdown", Value::Null);
+    assert!(shutdown.error.is_none());
+    client.notify("exit", Value::Null);
+    assert!(client.process.wait().unwrap().success());
+}
@@ -4,7 +4,7 @@ use super::digest32::Digest32;
 
 /// Block id
 #[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct BlockId(pub Digest32);
 
 impl From<BlockId> for Vec<i8> {
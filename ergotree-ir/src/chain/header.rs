@@ -1,66 +1,238 @@
 //! Block header
 use num_bigint::BigInt;
+use num_bigint::Sign;
+use std::convert::TryFrom;
+use thiserror::Error;
 
+use crate::serialization::sigma_byte_reader::SigmaByteRead;
+use crate::serialization::sigma_byte_writer::SigmaByteWrite;
+use crate::serialization::sigma_byte_writer::SigmaByteWriter;
+use crate::serialization::SigmaParsingError;
+use crate::serialization::SigmaSerializable;
+use crate::serialization::SigmaSerializationError;
+use crate::serialization::SigmaSerializeResult;
 use crate::sigma_protocol::dlog_group;
+use crate::sigma_protocol::dlog_group::EcPoint;
 
 use super::block_id::BlockId;
+use super::digest32::blake2b256_hash;
 use super::digest32::{ADDigest, Digest32};
 use super::preheader::PreHeader;
 use super::votes::Votes;
 
 /// Represents data of the block header available in Sigma propositions.
 #[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "json",
+    serde(
+        try_from = "crate::chain::json::header::HeaderJson",
+        into = "crate::chain::json::header::HeaderJson"
+    )
+)]
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Header {
     /// Block version, to be increased on every soft and hardfork.
-    #[cfg_attr(feature = "json", serde(rename = "version"))]
     pub version: u8,
     /// Bytes representation of ModifierId of this Header
-    #[cfg_attr(feature = "json", serde(rename = "id"))]
     pub id: BlockId,
     /// Bytes representation of ModifierId of the parent block
-    #[cfg_attr(feature = "json", serde(rename = "parentId"))]
     pub parent_id: BlockId,
     /// Hash of ADProofs for transactions in a block
-    #[cfg_attr(feature = "json", serde(rename = "adProofsRoot"))]
     pub ad_proofs_root: Digest32,
     /// AvlTree of a state after block application
-    #[cfg_attr(feature = "json", serde(rename = "stateRoot"))]
     pub state_root: ADDigest,
     /// Root hash (for a Merkle tree) of transactions in a block.
-    #[cfg_attr(feature = "json", serde(rename = "transactionsRoot"))]
     pub transaction_root: Digest32,
     /// Timestamp of a block in ms from UNIX epoch
-    #[cfg_attr(feature = "json", serde(rename = "timestamp"))]
     pub timestamp: u64,
     /// Current difficulty in a compressed view.
-    #[cfg_attr(feature = "json", serde(rename = "nBits"))]
     pub n_bits: u64,
     /// Block height
-    #[cfg_attr(feature = "json", serde(rename = "height"))]
     pub height: u32,
     /// Root hash of extension section
-    #[cfg_attr(feature = "json", serde(rename = "extensionHash"))]
     pub extension_root: Digest32,
     /// Public key of miner. Part of Autolykos solution.
-    #[cfg_attr(feature = "json", serde(skip_serializing, skip_deserializing))]
     pub miner_pk: Box<dlog_group::EcPoint>,
     /// One-time public key. Prevents revealing of miners secret.
-    #[cfg_attr(feature = "json", serde(skip_serializing, skip_deserializing))]
     pub pow_onetime_pk: Box<dlog_group::EcPoint>,
     /// nonce
-    #[cfg_attr(feature = "json", serde(skip_serializing, skip_deserializing))]
     pub nonce: Vec<u8>,
     /// Distance between pseudo-random number, corresponding to nonce `nonce` and a secret,
     /// corresponding to `miner_pk`. The lower `pow_distance` is, the harder it was to find this solution.
-    #[cfg_attr(feature = "json", serde(skip_serializing, skip_deserializing))]
     pub pow_distance: BigInt,
     /// Miner votes for changing system parameters.
     /// 3 bytes in accordance to Scala implementation, but will use `Vec` until further improvements
-    #[cfg_attr(feature = "json", serde(rename = "votes"))]
     pub votes: Votes,
 }
 
+/// Header id check error
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum HeaderIdError {
+    /// Header serialization failed
+    #[error("header serialization error: {0}")]
+    SerializationError(#[from] SigmaSerializationError),
+    /// Header id differs from the hash of the serialized header
+    #[error("header id {expected:?} does not match the computed id {computed:?}")]
+    IdMismatch {
+        /// Id of the header
+        expected: BlockId,
+        /// Id computed from the serialized header
+        computed: BlockId,
+    },
+}
+
+impl Header {
+    /// Initial block version (Autolykos v1)
+    pub const INITIAL_VERSION: u8 = 1;
+    /// Height of the genesis block
+    pub const GENESIS_HEIGHT: u32 = 1;
+
+    /// Serialized header without the PoW solution (the message the miner solves the puzzle for)
+    pub fn serialize_without_pow(&self) -> Result<Vec<u8>, SigmaSerializationError> {
+        let mut data = Vec::new();
+        let mut w = SigmaByteWriter::new(&mut data, None);
+        self.serialize_without_pow_to(&mut w)?;
+        Ok(data)
+    }
+
+    fn serialize_without_pow_to<W: SigmaByteWrite>(&self, w: &mut W) -> SigmaSerializeResult {
+        w.put_u8(self.version)?;
+        self.parent_id.0.sigma_serialize(w)?;
+        self.ad_proofs_root.sigma_serialize(w)?;
+        self.transaction_root.sigma_serialize(w)?;
+        self.state_root.sigma_serialize(w)?;
+        w.put_u64(self.timestamp)?;
+        self.extension_root.sigma_serialize(w)?;
+        // nBits are stored as 4 bytes big-endian
+        let n_bits = u32::try_from(self.n_bits)
+            .map_err(|_| SigmaSerializationError::NotSupported("nBits does not fit in u32"))?;
+        w.write_all(&n_bits.to_be_bytes())?;
+        w.put_u32(self.height)?;
+        w.write_all(&self.votes.0)?;
+        if self.version > Header::INITIAL_VERSION {
+            // size of the new fields (none so far)
+            w.put_u8(0)?;
+        }
+        Ok(())
+    }
+
+    /// Computes header id (blake2b256 hash of the serialized header)
+    pub fn compute_id(&self) -> Result<BlockId, SigmaSerializationError> {
+        Ok(BlockId(blake2b256_hash(&self.sigma_serialize_bytes()?)))
+    }
+
+    /// Checks that `id` is the hash of the serialized header
+    pub fn check_id(&self) -> Result<(), HeaderIdError> {
+        let computed = self.compute_id()?;
+        if computed == self.id {
+            Ok(())
+        } else {
+            Err(HeaderIdError::IdMismatch {
+                expected: self.id.clone(),
+                computed,
+            })
+        }
+    }
+}
+
+/// Binary format of the node: header fields followed by the Autolykos solution, which is
+/// `pk, w, nonce, d` for Autolykos v1 (block version 1) and `pk, nonce` for Autolykos v2.
+/// Header id is not serialized, on parsing it's computed from the serialized bytes.
+impl SigmaSerializable for Header {
+    fn sigma_serialize<W: SigmaByteWrite>(&self, w: &mut W) -> SigmaSerializeResult {
+        self.serialize_without_pow_to(w)?;
+        self.miner_pk.sigma_serialize(w)?;
+        if self.version == Header::INITIAL_VERSION {
+            self.pow_onetime_pk.sigma_serialize(w)?;
+        }
+        if self.nonce.len() != 8 {
+            return Err(SigmaSerializationError::NotSupported(
+                "nonce is expected to be 8 bytes",
+            ));
+        }
+        w.write_all(&self.nonce)?;
+        if self.version == Header::INITIAL_VERSION {
+            // unsigned, as `BigIntegers.asUnsignedByteArray` in the node
+            let (sign, d_bytes) = self.pow_distance.to_bytes_be();
+            if sign == Sign::Minus {
+                return Err(SigmaSerializationError::NotSupported(
+                    "pow distance is expected to be non-negative",
+                ));
+            }
+            let d_len = u8::try_from(d_bytes.len())
+                .map_err(|_| SigmaSerializationError::NotSupported("pow distance is too big"))?;
+            w.put_u8(d_len)?;
+            w.write_all(&d_bytes)?;
+        }
+        Ok(())
+    }
+
+    fn sigma_parse<R: SigmaByteRead>(r: &mut R) -> Result<Self, SigmaParsingError> {
+        let version = r.get_u8()?;
+        let parent_id = BlockId(Digest32::sigma_parse(r)?);
+        let ad_proofs_root = Digest32::sigma_parse(r)?;
+        let transaction_root = Digest32::sigma_parse(r)?;
+        let state_root = ADDigest::sigma_parse(r)?;
+        let timestamp = r.get_u64()?;
+        let extension_root = Digest32::sigma_parse(r)?;
+        let mut n_bits_bytes = [0u8; 4];
+        r.read_exact(&mut n_bits_bytes)?;
+        let n_bits = u32::from_be_bytes(n_bits_bytes) as u64;
+        let height = r.get_u32()?;
+        let mut votes = [0u8; 3];
+        r.read_exact(&mut votes)?;
+        if version > Header::INITIAL_VERSION {
+            let new_fields_size = r.get_u8()?;
+            if new_fields_size > 0 {
+                // the node keeps these bytes as they are, without them the header id could not
+                // be computed
+                return Err(SigmaParsingError::NotImplementedYet(format!(
+                    "header (block version {}) with {} bytes of unparsed new fields",
+                    version, new_fields_size
+                )));
+            }
+        }
+        let miner_pk = EcPoint::sigma_parse(r)?;
+        let (pow_onetime_pk, nonce, pow_distance) = if version == Header::INITIAL_VERSION {
+            let pow_onetime_pk = EcPoint::sigma_parse(r)?;
+            let mut nonce = vec![0u8; 8];
+            r.read_exact(&mut nonce)?;
+            let d_len = r.get_u8()?;
+            let mut d_bytes = vec![0u8; d_len as usize];
+            r.read_exact(&mut d_bytes)?;
+            (
+                pow_onetime_pk,
+                nonce,
+                BigInt::from_bytes_be(Sign::Plus, &d_bytes),
+            )
+        } else {
+            let mut nonce = vec![0u8; 8];
+            r.read_exact(&mut nonce)?;
+            // Autolykos v2 has no one-time key and distance
+            (dlog_group::generator(), nonce, BigInt::from(0))
+        };
+        let mut header = Header {
+            version,
+            id: BlockId(Digest32::zero()),
+            parent_id,
+            ad_proofs_root,
+            state_root,
+            transaction_root,
+            timestamp,
+            n_bits,
+            height,
+            extension_root,
+            miner_pk: miner_pk.into(),
+            pow_onetime_pk: pow_onetime_pk.into(),
+            nonce,
+            pow_distance,
+            votes: Votes(votes),
+        };
+        header.id = header.compute_id()?;
+        Ok(header)
+    }
+}
+
 impl From<Header> for PreHeader {
     fn from(bh: Header) -> Self {
         PreHeader {
@@ -76,13 +248,15 @@ impl From<Header> for PreHeader {
 }
 
 #[cfg(feature = "arbitrary")]
+#[allow(clippy::unwrap_used)]
 mod arbitrary {
     use num_bigint::BigInt;
-    use proptest::array::{uniform3, uniform32};
+    use proptest::array::{uniform3, uniform32, uniform8};
     use proptest::prelude::*;
 
     use crate::chain::digest32::ADDigest;
     use crate::chain::digest32::Digest;
+    use crate::sigma_protocol::dlog_group;
     use crate::sigma_protocol::dlog_group::EcPoint;
 
     use super::{BlockId, Header, Votes};
@@ -95,18 +269,18 @@ mod arbitrary {
                 uniform32(1u8..),
                 uniform32(1u8..),
                 uniform32(1u8..),
-                uniform32(1u8..),
                 // Timestamps between 2000-2050
                 946_674_000_000..2_500_400_300_000u64,
-                any::<u64>(),
+                any::<u32>(),
                 0..1_000_000u32,
                 any::<Box<EcPoint>>(),
                 any::<Box<EcPoint>>(),
                 uniform3(1u8..),
+                1..=3u8,
+                (uniform8(any::<u8>()), any::<u64>()),
             )
                 .prop_map(
                     |(
-                        id,
                         parent_id,
                         ad_proofs_root,
                         transaction_root,
@@ -117,30 +291,39 @@ mod arbitrary {
                         miner_pk,
                         pow_onetime_pk,
                         votes,
+                        version,
+                        (nonce, pow_distance),
                     )| {
-                        let id = BlockId(Digest(id.into()));
                         let parent_id = BlockId(Digest(parent_id.into()));
                         let ad_proofs_root = Digest(ad_proofs_root.into());
                         let transaction_root = Digest(transaction_root.into());
                         let extension_root = Digest(extension_root.into());
                         let votes = Votes(votes);
-                        Self {
-                            version: 1,
-                            id,
+                        // Autolykos v2 solution has no one-time key and distance
+                        let (pow_onetime_pk, pow_distance) = if version == Header::INITIAL_VERSION {
+                            (pow_onetime_pk, BigInt::from(pow_distance))
+                        } else {
+                            (Box::new(dlog_group::generator()), BigInt::from(0))
+                        };
+                        let mut header = Self {
+                            version,
+                            id: BlockId(Digest::zero()),
                             parent_id,
                             ad_proofs_root,
                             state_root: ADDigest::zero(),
                             transaction_root,
                             timestamp,
-                            n_bits,
+                            n_bits: n_bits as u64,
                             height,
                             extension_root,
                             miner_pk,
                             pow_onetime_pk,
-                            nonce: Vec::new(),
-                            pow_distance: BigInt::default(),
+                            nonce: nonce.to_vec(),
+                            pow_distance,
                             votes,
-                        }
+                        };
+                        header.id = header.compute_id().unwrap();
+                        header
                     },
                 )
                 .boxed()
@@ -154,65 +337,171 @@ mod arbitrary {
 #[cfg(test)]
 mod tests {
     use crate::chain::header::Header;
+    use crate::serialization::sigma_serialize_roundtrip;
+    use proptest::prelude::*;
+    use sigma_test_util::HEADER_471746_JSON;
+
+    use super::*;
+
+    proptest! {
+
+        #[test]
+        fn ser_roundtrip(v in any::<Header>()) {
+            prop_assert!(v.check_id().is_ok());
+            prop_assert_eq![sigma_serialize_roundtrip(&v), v];
+        }
+
+        #[test]
+        fn json_roundtrip(v in any::<Header>()) {
+            let j = serde_json::to_string(&v)?;
+            let parsed: Header = serde_json::from_str(&j)?;
+            prop_assert_eq![parsed, v];
+        }
+    }
+
+    /// Mainnet header at height 471746 (block version 2)
+    fn header_471746() -> Header {
+        let digest = |s: &str| Digest32::try_from(s.to_string()).unwrap();
+        let point = |s: &str| Box::new(EcPoint::from_base16_str(s.to_string()).unwrap());
+        Header {
+            version: 2,
+            id: BlockId(digest(
+                "4caa17e62fe66ba7bd69597afdc996ae35b1ff12e0ba90c22ff288a4de10e91b",
+            )),
+            parent_id: BlockId(digest(
+                "6481752bace5fa5acba5d5ef7124d48826664742d46c974c98a2d60ace229a34",
+            )),
+            ad_proofs_root: digest(
+                "d882aaf42e0a95eb95fcce5c3705adf758e591532f733efe790ac3c404730c39",
+            ),
+            state_root: ADDigest::try_from(
+                "8ad868627ea4f7de6e2a2fe3f98fafe57f914e0f2ef3331c006def36c697f92713".to_string(),
+            )
+            .unwrap(),
+            transaction_root: digest(
+                "63eaa9aff76a1de3d71c81e4b2d92e8d97ae572a8e9ab9e66599ed0912dd2f8b",
+            ),
+            timestamp: 1618929697400,
+            n_bits: 117586360,
+            height: 471746,
+            extension_root: digest(
+                "3f91f3c680beb26615fdec251aee3f81aaf5a02740806c167c0f3c929471df44",
+            ),
+            miner_pk: point("02b3a06d6eaa8671431ba1db4dd427a77f75a5c2acbd71bfb725d38adc2b55f669"),
+            pow_onetime_pk: Box::new(dlog_group::generator()),
+            nonce: base16::decode("5939ecfee6b0d7f4").unwrap(),
+            pow_distance: BigInt::from(0),
+            votes: Votes([4, 0, 0]),
+        }
+    }
+
+    #[test]
+    fn mainnet_header_id() {
+        let header = header_471746();
+        assert_eq!(header.compute_id().unwrap(), header.id);
+        assert!(header.check_id().is_ok());
+        let bytes = header.sigma_serialize_bytes().unwrap();
+        assert_eq!(Header::sigma_parse_bytes(&bytes).unwrap(), header);
+        let tampered = Header {
+            height: 471747,
+            ..header
+        };
+        assert!(matches!(
+            tampered.check_id(),
+            Err(HeaderIdError::IdMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn v1_pow_distance_is_unsigned() {
+        // the highest bit is set, the signed encoding would have a leading zero byte
+        let d: BigInt = (BigInt::from(1) << 255usize) + 1;
+        let mut header = Header {
+            version: Header::INITIAL_VERSION,
+            pow_distance: d.clone(),
+            ..header_471746()
+        };
+        header.id = header.compute_id().unwrap();
+        let bytes = header.sigma_serialize_bytes().unwrap();
+        let mut d_bytes = vec![32u8, 0x80];
+        d_bytes.extend(vec![0u8; 30]);
+        d_bytes.push(1);
+        assert!(bytes.ends_with(&d_bytes));
+        let parsed = Header::sigma_parse_bytes(&bytes).unwrap();
+        assert_eq!(parsed.pow_distance, d);
+        assert_eq!(parsed, header);
+        let negative = Header {
+            pow_distance: -d,
+            ..header
+        };
+        assert!(negative.sigma_serialize_bytes().is_err());
+    }
 
     #[test]
     fn parse_block_header() {
-        let json = r#"{
-            "extensionId": "d16f25b14457186df4c5f6355579cc769261ce1aebc8209949ca6feadbac5a3f",
-            "difficulty": "626412390187008",
-            "votes": "040000",
-            "timestamp": 1618929697400,
-            "size": 221,
-            "stateRoot": "8ad868627ea4f7de6e2a2fe3f98fafe57f914e0f2ef3331c006def36c697f92713",
-            "height": 471746,
-            "nBits": 117586360,
-            "version": 2,
-            "id": "4caa17e62fe66ba7bd69597afdc996ae35b1ff12e0ba90c22ff288a4de10e91b",
-            "adProofsRoot": "d882aaf42e0a95eb95fcce5c3705adf758e591532f733efe790ac3c404730c39",
-            "transactionsRoot": "63eaa9aff76a1de3d71c81e4b2d92e8d97ae572a8e9ab9e66599ed0912dd2f8b",
-            "extensionHash": "3f91f3c680beb26615fdec251aee3f81aaf5a02740806c167c0f3c929471df44",
-            "powSolutions": {
-              "pk": "02b3a06d6eaa8671431ba1db4dd427a77f75a5c2acbd71bfb725d38adc2b55f669",
-              "w": "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
-              "n": "5939ecfee6b0d7f4",
-              "d": 0
-            },
-            "adProofsId": "86eaa41f328bee598e33e52c9e515952ad3b7874102f762847f17318a776a7ae",
-            "transactionsId": "ac80245714f25aa2fafe5494ad02a26d46e7955b8f5709f3659f1b9440797b3e",
-            "parentId": "6481752bace5fa5acba5d5ef7124d48826664742d46c974c98a2d60ace229a34"
-        }"#;
-        let header: Header = serde_json::from_str(json).unwrap();
-        assert_eq!(header.height, 471746);
+        let header: Header = serde_json::from_str(HEADER_471746_JSON).unwrap();
+        assert_eq!(header, header_471746());
+        // node-only fields (difficulty, size, section ids) are not encoded
+        let expected = r#"{"version":2,"id":"4caa17e62fe66ba7bd69597afdc996ae35b1ff12e0ba90c22ff288a4de10e91b","parentId":"6481752bace5fa5acba5d5ef7124d48826664742d46c974c98a2d60ace229a34","adProofsRoot":"d882aaf42e0a95eb95fcce5c3705adf758e591532f733efe790ac3c404730c39","stateRoot":"8ad868627ea4f7de6e2a2fe3f98fafe57f914e0f2ef3331c006def36c697f92713","transactionsRoot":"63eaa9aff76a1de3d71c81e4b2d92e8d97ae572a8e9ab9e66599ed0912dd2f8b","timestamp":1618929697400,"nBits":117586360,"height":471746,"extensionHash":"3f91f3c680beb26615fdec251aee3f81aaf5a02740806c167c0f3c929471df44","powSolutions":{"pk":"02b3a06d6eaa8671431ba1db4dd427a77f75a5c2acbd71bfb725d38adc2b55f669","w":"0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798","n":"5939ecfee6b0d7f4","d":0},"votes":"040000"}"#;
+        assert_eq!(serde_json::to_string(&header).unwrap(), expected);
+    }
+
+    #[test]
+    fn parse_new_fields() {
+        let header = header_471746();
+        let mut bytes = header.sigma_serialize_bytes().unwrap();
+        let size_pos = header.serialize_without_pow().unwrap().len() - 1;
+        assert_eq!(bytes[size_pos], 0);
+        bytes[size_pos] = 2;
+        bytes.splice(size_pos + 1..size_pos + 1, vec![7, 7]);
+        assert_eq!(
+            Header::sigma_parse_bytes(&bytes),
+            Err(SigmaParsingError::NotImplementedYet(
+                "header (block version 2) with 2 bytes of unparsed new fields".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_block_header_invalid_id() {
+        let json = serde_json::to_string(&header_471746())
+            .unwrap()
+            .replace(r#""n":"5939ecfee6b0d7f4""#, r#""n":"5939ecfee6b0d7f5""#);
+        assert!(serde_json::from_str::<Header>(&json).is_err());
+    }
+
+    #[test]
+    fn parse_pow_distance() {
+        let header = Header {
+            version: 1,
+            pow_onetime_pk: Box::new(dlog_group::exponentiate(
+                &dlog_group::generator(),
+                &k256::Scalar::from(3u64),
+            )),
+            pow_distance: BigInt::from(7u8).pow(70),
+            ..header_471746()
+        };
+        let header = Header {
+            id: header.compute_id().unwrap(),
+            ..header
+        };
+        let json = serde_json::to_string(&header).unwrap();
+        assert!(json.contains(&format!(r#""d":"{}""#, header.pow_distance)));
+        assert_eq!(serde_json::from_str::<Header>(&json).unwrap(), header);
+        // a number too large for u64 is not accepted (it would be rounded)
+        let json = json.replace(
+            &format!(r#""d":"{}""#, header.pow_distance),
+            &format!(r#""d":{}"#, header.pow_distance),
+        );
+        assert!(serde_json::from_str::<Header>(&json).is_err());
     }
 
     #[test]
     fn parse_block_header_explorer_v1() {
         // see https://api.ergoplatform.com/api/v1/blocks/de68a9cd727510d01eae3146f862261661f3bebdfd3c45c19d431b2ae81fb4b6
-        let json = r#"{
-            "extensionId": "d16f25b14457186df4c5f6355579cc769261ce1aebc8209949ca6feadbac5a3f",
-            "difficulty": "626412390187008",
-            "votes": [4,0,0],
-            "timestamp": 1618929697400,
-            "size": 221,
-            "stateRoot": "8ad868627ea4f7de6e2a2fe3f98fafe57f914e0f2ef3331c006def36c697f92713",
-            "height": 471746,
-            "nBits": 117586360,
-            "version": 2,
-            "id": "4caa17e62fe66ba7bd69597afdc996ae35b1ff12e0ba90c22ff288a4de10e91b",
-            "adProofsRoot": "d882aaf42e0a95eb95fcce5c3705adf758e591532f733efe790ac3c404730c39",
-            "transactionsRoot": "63eaa9aff76a1de3d71c81e4b2d92e8d97ae572a8e9ab9e66599ed0912dd2f8b",
-            "extensionHash": "3f91f3c680beb26615fdec251aee3f81aaf5a02740806c167c0f3c929471df44",
-            "powSolutions": {
-              "pk": "02b3a06d6eaa8671431ba1db4dd427a77f75a5c2acbd71bfb725d38adc2b55f669",
-              "w": "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
-              "n": "5939ecfee6b0d7f4",
-              "d": 0
-            },
-            "adProofsId": "86eaa41f328bee598e33e52c9e515952ad3b7874102f762847f17318a776a7ae",
-            "transactionsId": "ac80245714f25aa2fafe5494ad02a26d46e7955b8f5709f3659f1b9440797b3e",
-            "parentId": "6481752bace5fa5acba5d5ef7124d48826664742d46c974c98a2d60ace229a34"
-        }"#;
-        let header: Header = serde_json::from_str(json).unwrap();
-        assert_eq!(header.height, 471746);
+        let json = HEADER_471746_JSON.replace(r#""votes": "040000""#, r#""votes": [4,0,0]"#);
+        assert!(json.contains("[4,0,0]"));
+        let header: Header = serde_json::from_str(&json).unwrap();
+        assert_eq!(header, header_471746());
     }
 }
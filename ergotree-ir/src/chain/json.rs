@@ -4,6 +4,11 @@ use serde::Serializer;
 
 pub(crate) mod ergo_box;
 pub mod ergo_tree;
+pub(crate) mod header;
+pub mod register;
+pub mod sigma_protocol;
+
+pub use ergo_box::rendered as ergo_box_rendered;
 
 /// Serialize bytes ([u8]) as base16 encoded string
 pub fn serialize_bytes<S, T>(bytes: T, serializer: S) -> Result<S::Ok, S::Error>
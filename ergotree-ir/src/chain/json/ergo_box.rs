@@ -2,6 +2,8 @@ use crate::chain::base16_bytes::Base16DecodedBytes;
 use crate::chain::ergo_box::box_value::BoxValue;
 use crate::chain::ergo_box::BoxId;
 use crate::chain::ergo_box::NonMandatoryRegisters;
+use crate::chain::json::register::RegisterValueJson;
+use crate::chain::json::register::RegisterValueJsonError;
 use crate::chain::token::Token;
 use crate::chain::tx_id::TxId;
 use crate::ergo_tree::ErgoTree;
@@ -24,8 +26,9 @@ use thiserror::Error;
 
 mod box_value;
 
+/// ErgoBox JSON, `R` is the encoding of the registers
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
-pub struct ErgoBoxJson {
+pub struct ErgoBoxJson<R = NonMandatoryRegisters> {
     #[serde(rename = "boxId", alias = "id")]
     pub box_id: Option<BoxId>,
     /// amount of money associated with the box
@@ -39,7 +42,7 @@ pub struct ErgoBoxJson {
     pub tokens: Vec<Token>,
     ///  additional registers the box can carry over
     #[serde(rename = "additionalRegisters")]
-    pub additional_registers: NonMandatoryRegisters,
+    pub additional_registers: R,
     /// height when a transaction containing the box was created.
     /// This height is declared by user and should not exceed height of the block,
     /// containing the transaction with this box.
@@ -53,6 +56,56 @@ pub struct ErgoBoxJson {
     pub index: u16,
 }
 
+impl<R> ErgoBoxJson<R> {
+    fn map_registers<T>(self, f: impl FnOnce(R) -> T) -> ErgoBoxJson<T> {
+        ErgoBoxJson {
+            box_id: self.box_id,
+            value: self.value,
+            ergo_tree: self.ergo_tree,
+            tokens: self.tokens,
+            additional_registers: f(self.additional_registers),
+            creation_height: self.creation_height,
+            transaction_id: self.transaction_id,
+            index: self.index,
+        }
+    }
+}
+
+/// ErgoBox with the registers in the rendered format ([`super::register::rendered`]), use with
+/// `#[serde(with = "ergotree_ir::chain::json::ergo_box_rendered")]`
+pub mod rendered {
+    use super::*;
+    use crate::chain::ergo_box::ErgoBox;
+    use serde::Serializer;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(transparent)]
+    struct RenderedRegisters(
+        #[serde(with = "crate::chain::json::register::rendered")] NonMandatoryRegisters,
+    );
+
+    /// Serializes the box with `sigmaType` and `renderedValue` for each register
+    pub fn serialize<S>(ergo_box: &ErgoBox, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        ErgoBoxJson::from(ergo_box.clone())
+            .map_registers(RenderedRegisters)
+            .serialize(serializer)
+    }
+
+    /// Parses the box, checks `sigmaType` of the registers if present
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<ErgoBox, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let json = ErgoBoxJson::<RenderedRegisters>::deserialize(deserializer)?
+            .map_registers(|RenderedRegisters(regs)| regs);
+        ErgoBox::try_from(json).map_err(Error::custom)
+    }
+}
+
 /// Errors on parsing ErgoBox from JSON
 #[derive(Error, PartialEq, Eq, Debug, Clone)]
 pub enum ErgoBoxFromJsonError {
@@ -68,20 +121,24 @@ pub enum ErgoBoxFromJsonError {
 pub struct ConstantHolder(#[serde(deserialize_with = "constant_as_string_or_struct")] RichConstant);
 
 impl From<ConstantHolder> for Constant {
-    fn from(ch: ConstantHolder) -> Self {
-        ch.0.raw_value.0
+    fn from(ConstantHolder(RichConstant(c)): ConstantHolder) -> Self {
+        c
     }
 }
 
+/// Register value either as a serialized constant string or as a struct with `sigmaType`
+/// and `renderedValue`
 #[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
-struct RichConstant {
-    #[serde(rename = "rawValue", alias = "serializedValue")]
-    raw_value: ConstantWrapper,
-}
+#[serde(try_from = "RegisterValueJson")]
+struct RichConstant(Constant);
 
-#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
-#[serde(try_from = "Base16DecodedBytes")]
-struct ConstantWrapper(Constant);
+impl TryFrom<RegisterValueJson> for RichConstant {
+    type Error = RegisterValueJsonError;
+
+    fn try_from(json: RegisterValueJson) -> Result<Self, Self::Error> {
+        Ok(RichConstant(Constant::try_from(json)?))
+    }
+}
 
 #[derive(Error, PartialEq, Eq, Debug, Clone, From)]
 pub enum ConstantParsingError {
@@ -91,23 +148,12 @@ pub enum ConstantParsingError {
     DeserializationError(SigmaParsingError),
 }
 
-impl TryFrom<Base16DecodedBytes> for ConstantWrapper {
-    type Error = ConstantParsingError;
-
-    fn try_from(Base16DecodedBytes(bytes): Base16DecodedBytes) -> Result<Self, Self::Error> {
-        let c = Constant::sigma_parse_bytes(&bytes)?;
-        Ok(ConstantWrapper(c))
-    }
-}
-
 impl FromStr for RichConstant {
     type Err = ConstantParsingError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let Base16DecodedBytes(bytes) = Base16DecodedBytes::try_from(s)?;
         let c = Constant::sigma_parse_bytes(&bytes)?;
-        Ok(RichConstant {
-            raw_value: ConstantWrapper(c),
-        })
+        Ok(RichConstant(c))
     }
 }
 
@@ -170,6 +216,9 @@ mod tests {
     use pretty_assertions::assert_eq;
     use proptest::prelude::*;
 
+    #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+    struct RenderedBox(#[serde(with = "super::rendered")] ErgoBox);
+
     proptest! {
 
         #[test]
@@ -180,6 +229,39 @@ mod tests {
             prop_assert_eq![b, b_parsed];
         }
 
+        #[test]
+        fn ergo_box_rendered_roundtrip(b in any::<ErgoBox>()) {
+            let j = serde_json::to_string(&RenderedBox(b.clone()))?;
+            let b_parsed: RenderedBox = serde_json::from_str(&j)?;
+            prop_assert_eq![&b_parsed.0, &b];
+            // default encoding parses the rendered box
+            let b_parsed: ErgoBox = serde_json::from_str(&j)?;
+            prop_assert_eq![b_parsed, b];
+        }
+
+    }
+
+    #[test]
+    fn ergo_box_rendered() {
+        let box_json = r#"{
+          "value": 67500000000,
+          "ergoTree": "100204a00b08cd021dde34603426402615658f1d970cfa7c7bd92ac81a8b16eeebff264d59ce4604ea02d192a39a8cc7a70173007301",
+          "assets": [],
+          "creationHeight": 284761,
+          "additionalRegisters": {
+            "R4": {"serializedValue": "0500", "sigmaType": "SInt", "renderedValue": "0"}
+          },
+          "transactionId": "9148408c04c2e38a6402a7950d6157730fa7d49e9ab3b9cadec481d7769918e9",
+          "index": 1
+        }"#;
+        // sigmaType is checked in the rendered mode only
+        let b: ErgoBox = serde_json::from_str(box_json).unwrap();
+        assert!(serde_json::from_str::<RenderedBox>(box_json).is_err());
+        let box_json = box_json.replace("SInt", "SLong");
+        assert_eq!(serde_json::from_str::<RenderedBox>(&box_json).unwrap().0, b);
+        assert!(serde_json::to_string(&RenderedBox(b)).unwrap().contains(
+            r#""R4":{"serializedValue":"0500","sigmaType":"SLong","renderedValue":"0"}"#
+        ));
     }
 
     #[test]
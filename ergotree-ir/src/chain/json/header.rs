@@ -0,0 +1,184 @@
+//! Block header JSON encoding (node API format)
+
+use std::convert::TryFrom;
+
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::chain::base16_bytes::Base16DecodedBytes;
+use crate::chain::block_id::BlockId;
+use crate::chain::digest32::{ADDigest, Digest32};
+use crate::chain::header::Header;
+use crate::chain::header::HeaderIdError;
+use crate::chain::votes::Votes;
+use crate::serialization::SigmaParsingError;
+use crate::serialization::SigmaSerializable;
+use crate::sigma_protocol::dlog_group::EcPoint;
+
+/// Block header in the node's JSON format
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct HeaderJson {
+    #[serde(rename = "version")]
+    pub version: u8,
+    #[serde(rename = "id")]
+    pub id: BlockId,
+    #[serde(rename = "parentId")]
+    pub parent_id: BlockId,
+    #[serde(rename = "adProofsRoot")]
+    pub ad_proofs_root: Digest32,
+    #[serde(rename = "stateRoot")]
+    pub state_root: ADDigest,
+    #[serde(rename = "transactionsRoot")]
+    pub transaction_root: Digest32,
+    #[serde(rename = "timestamp")]
+    pub timestamp: u64,
+    #[serde(rename = "nBits")]
+    pub n_bits: u64,
+    #[serde(rename = "height")]
+    pub height: u32,
+    #[serde(rename = "extensionHash")]
+    pub extension_root: Digest32,
+    #[serde(rename = "powSolutions")]
+    pub pow_solutions: AutolykosSolutionJson,
+    #[serde(rename = "votes")]
+    pub votes: Votes,
+}
+
+/// Autolykos solution, `w` is the generator and `d` is 0 for Autolykos v2 (block version > 1)
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct AutolykosSolutionJson {
+    /// Miner public key
+    #[serde(rename = "pk")]
+    pub miner_pk: Base16DecodedBytes,
+    /// One-time public key
+    #[serde(rename = "w")]
+    pub pow_onetime_pk: Base16DecodedBytes,
+    /// Nonce
+    #[serde(rename = "n")]
+    pub nonce: Base16DecodedBytes,
+    /// Distance
+    #[serde(rename = "d", with = "pow_distance")]
+    pub pow_distance: BigInt,
+}
+
+/// Errors on parsing Header from JSON
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum HeaderFromJsonError {
+    /// Invalid group element in the PoW solution
+    #[error("PoW solution parsing error: {0}")]
+    ParsingError(#[from] SigmaParsingError),
+    /// Header id differs from the hash of the serialized header
+    #[error("{0}")]
+    InvalidId(#[from] HeaderIdError),
+}
+
+fn point_bytes(p: &EcPoint) -> Base16DecodedBytes {
+    // EcPoint serialization does not fail
+    Base16DecodedBytes(p.sigma_serialize_bytes().unwrap_or_default())
+}
+
+impl From<Header> for HeaderJson {
+    fn from(h: Header) -> Self {
+        HeaderJson {
+            version: h.version,
+            id: h.id,
+            parent_id: h.parent_id,
+            ad_proofs_root: h.ad_proofs_root,
+            state_root: h.state_root,
+            transaction_root: h.transaction_root,
+            timestamp: h.timestamp,
+            n_bits: h.n_bits,
+            height: h.height,
+            extension_root: h.extension_root,
+            pow_solutions: AutolykosSolutionJson {
+                miner_pk: point_bytes(&h.miner_pk),
+                pow_onetime_pk: point_bytes(&h.pow_onetime_pk),
+                nonce: Base16DecodedBytes(h.nonce),
+                pow_distance: h.pow_distance,
+            },
+            votes: h.votes,
+        }
+    }
+}
+
+impl TryFrom<HeaderJson> for Header {
+    type Error = HeaderFromJsonError;
+
+    /// Checks that the id is the hash of the parsed header
+    fn try_from(h: HeaderJson) -> Result<Self, Self::Error> {
+        let header = Header {
+            version: h.version,
+            id: h.id,
+            parent_id: h.parent_id,
+            ad_proofs_root: h.ad_proofs_root,
+            state_root: h.state_root,
+            transaction_root: h.transaction_root,
+            timestamp: h.timestamp,
+            n_bits: h.n_bits,
+            height: h.height,
+            extension_root: h.extension_root,
+            miner_pk: Box::new(EcPoint::sigma_parse_bytes(&h.pow_solutions.miner_pk.0)?),
+            pow_onetime_pk: Box::new(EcPoint::sigma_parse_bytes(
+                &h.pow_solutions.pow_onetime_pk.0,
+            )?),
+            nonce: h.pow_solutions.nonce.0,
+            pow_distance: h.pow_solutions.pow_distance,
+            votes: h.votes,
+        };
+        header.check_id()?;
+        Ok(header)
+    }
+}
+
+/// `d` is encoded as a number when it fits in u64 (as the node does for v2 headers) and as a
+/// decimal string otherwise, so that v1 distances are not rounded by the serializer.
+/// Both forms are accepted on parsing, fractional numbers are rejected.
+mod pow_distance {
+    use std::convert::TryFrom;
+    use std::fmt;
+    use std::str::FromStr;
+
+    use num_bigint::BigInt;
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S>(d: &BigInt, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match u64::try_from(d) {
+            Ok(n) => serializer.serialize_u64(n),
+            Err(_) => serializer.serialize_str(&d.to_string()),
+        }
+    }
+
+    struct PowDistanceVisitor;
+
+    impl<'de> Visitor<'de> for PowDistanceVisitor {
+        type Value = BigInt;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an integer or a decimal integer string")
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<BigInt, E> {
+            Ok(BigInt::from(v))
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<BigInt, E> {
+            Ok(BigInt::from(v))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<BigInt, E> {
+            BigInt::from_str(v).map_err(E::custom)
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<BigInt, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(PowDistanceVisitor)
+    }
+}
@@ -0,0 +1,293 @@
+//! Register values JSON encoding with the type and human-readable value
+//! (explorer and node indexer API format)
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::chain::base16_bytes::Base16DecodedBytes;
+use crate::chain::ergo_box::NonMandatoryRegisterId;
+use crate::chain::ergo_box::NonMandatoryRegisters;
+use crate::mir::constant::Constant;
+use crate::mir::constant::Literal;
+use crate::mir::value::CollKind;
+use crate::mir::value::NativeColl;
+use crate::serialization::SigmaParsingError;
+use crate::serialization::SigmaSerializable;
+use crate::serialization::SigmaSerializationError;
+use crate::types::stype::SType;
+
+/// Register value with the type and the rendered value, e.g.
+/// `{"serializedValue": "0500", "sigmaType": "SLong", "renderedValue": "0"}`
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct RegisterValueJson {
+    /// Serialized constant (base16)
+    #[serde(rename = "serializedValue", alias = "rawValue")]
+    pub serialized_value: Base16DecodedBytes,
+    /// Type of the value, see [`render_type`]
+    #[serde(rename = "sigmaType", default, skip_serializing_if = "Option::is_none")]
+    pub sigma_type: Option<String>,
+    /// Human-readable value, see [`render_value`]
+    #[serde(
+        rename = "renderedValue",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rendered_value: Option<String>,
+}
+
+/// Errors on register value parsing from JSON
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum RegisterValueJsonError {
+    /// Serialized value parsing error
+    #[error("parsing error: {0}")]
+    ParsingError(#[from] SigmaParsingError),
+    /// Type of the serialized value differs from `sigmaType`
+    #[error("expected type {expected}, serialized value has type {actual}")]
+    TypeMismatch {
+        /// `sigmaType` value
+        expected: String,
+        /// Type of the serialized value
+        actual: String,
+    },
+}
+
+impl TryFrom<&Constant> for RegisterValueJson {
+    type Error = SigmaSerializationError;
+
+    fn try_from(c: &Constant) -> Result<Self, Self::Error> {
+        Ok(RegisterValueJson {
+            serialized_value: Base16DecodedBytes(c.sigma_serialize_bytes()?),
+            sigma_type: Some(render_type(&c.tpe)),
+            rendered_value: Some(render_value(&c.v)?),
+        })
+    }
+}
+
+impl RegisterValueJson {
+    /// Parses the serialized value and checks that it has the type given in `sigmaType` (if
+    /// present), the rendered value is ignored
+    pub fn into_constant_checked(self) -> Result<Constant, RegisterValueJsonError> {
+        let c = Constant::sigma_parse_bytes(&self.serialized_value.0)?;
+        match self.sigma_type {
+            Some(expected) if expected != render_type(&c.tpe) => {
+                Err(RegisterValueJsonError::TypeMismatch {
+                    expected,
+                    actual: render_type(&c.tpe),
+                })
+            }
+            _ => Ok(c),
+        }
+    }
+}
+
+impl TryFrom<RegisterValueJson> for Constant {
+    type Error = RegisterValueJsonError;
+
+    /// Parses the serialized value, `sigmaType` and the rendered value are ignored (see
+    /// [`RegisterValueJson::into_constant_checked`])
+    fn try_from(json: RegisterValueJson) -> Result<Self, Self::Error> {
+        Ok(Constant::sigma_parse_bytes(&json.serialized_value.0)?)
+    }
+}
+
+/// Type name in the explorer/node format, e.g. `SLong`, `Coll[SByte]`, `(SInt, SLong)`
+pub fn render_type(tpe: &SType) -> String {
+    match tpe {
+        SType::STypeVar(tv) => tv.as_string(),
+        SType::SAny => "SAny".to_string(),
+        SType::SBoolean => "SBoolean".to_string(),
+        SType::SByte => "SByte".to_string(),
+        SType::SShort => "SShort".to_string(),
+        SType::SInt => "SInt".to_string(),
+        SType::SLong => "SLong".to_string(),
+        SType::SBigInt => "SBigInt".to_string(),
+        SType::SGroupElement => "SGroupElement".to_string(),
+        SType::SSigmaProp => "SSigmaProp".to_string(),
+        SType::SBox => "SBox".to_string(),
+        SType::SAvlTree => "SAvlTree".to_string(),
+        SType::SOption(t) => format!("Option[{}]", render_type(t)),
+        SType::SColl(t) => format!("Coll[{}]", render_type(t)),
+        SType::STuple(t) => format!(
+            "({})",
+            t.items
+                .iter()
+                .map(render_type)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        SType::SFunc(f) => format!(
+            "({}) => {}",
+            f.t_dom
+                .iter()
+                .map(render_type)
+                .collect::<Vec<String>>()
+                .join(", "),
+            render_type(&f.t_range)
+        ),
+        SType::SContext => "SContext".to_string(),
+        SType::SHeader => "SHeader".to_string(),
+        SType::SPreHeader => "SPreHeader".to_string(),
+        SType::SGlobal => "SGlobal".to_string(),
+    }
+}
+
+/// Human-readable value: numbers in decimal, byte collections, group elements, sigma
+/// propositions (serialized SigmaBoolean), AVL trees and boxes in base16, other collections and
+/// tuples as `[a,b]`
+pub fn render_value(v: &Literal) -> Result<String, SigmaSerializationError> {
+    Ok(match v {
+        Literal::Boolean(b) => b.to_string(),
+        Literal::Byte(b) => b.to_string(),
+        Literal::Short(s) => s.to_string(),
+        Literal::Int(i) => i.to_string(),
+        Literal::Long(l) => l.to_string(),
+        Literal::BigInt(bi) => bi.to_string(),
+        Literal::SigmaProp(sp) => base16::encode_lower(&sp.value().sigma_serialize_bytes()?),
+        Literal::GroupElement(p) => base16::encode_lower(&p.sigma_serialize_bytes()?),
+        Literal::AvlTree(t) => base16::encode_lower(&t.sigma_serialize_bytes()?),
+        Literal::CBox(b) => base16::encode_lower(&b.sigma_serialize_bytes()?),
+        Literal::Coll(CollKind::NativeColl(NativeColl::CollByte(bytes))) => {
+            base16::encode_lower(&bytes.iter().map(|b| *b as u8).collect::<Vec<u8>>())
+        }
+        Literal::Coll(CollKind::WrappedColl { items, .. }) => render_items(items)?,
+        Literal::Opt(opt) => match opt.as_ref() {
+            Some(v) => format!("Some({})", render_value(v)?),
+            None => "None".to_string(),
+        },
+        Literal::Tup(items) => render_items(items.as_slice())?,
+    })
+}
+
+fn render_items(items: &[Literal]) -> Result<String, SigmaSerializationError> {
+    Ok(format!(
+        "[{}]",
+        items
+            .iter()
+            .map(render_value)
+            .collect::<Result<Vec<String>, _>>()?
+            .join(",")
+    ))
+}
+
+/// Registers with the type and the rendered value for each, use with
+/// `#[serde(with = "ergotree_ir::chain::json::register::rendered")]`
+pub mod rendered {
+    use super::*;
+
+    /// Serializes each register as [`RegisterValueJson`] with all fields
+    pub fn serialize<S>(regs: &NonMandatoryRegisters, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::Error;
+        use serde::ser::SerializeMap;
+        let values = regs.get_ordered_values();
+        let mut map = serializer.serialize_map(Some(values.len()))?;
+        for (i, c) in values.iter().enumerate() {
+            map.serialize_entry(
+                &String::from(NonMandatoryRegisterId::get_by_zero_index(i)),
+                &RegisterValueJson::try_from(c).map_err(Error::custom)?,
+            )?;
+        }
+        map.end()
+    }
+
+    /// Parses registers, checks `sigmaType` if present (see
+    /// [`RegisterValueJson::into_constant_checked`])
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<NonMandatoryRegisters, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let values =
+            HashMap::<NonMandatoryRegisterId, RegisterValueJson>::deserialize(deserializer)?
+                .into_iter()
+                .map(|(id, v)| Ok((id, v.into_constant_checked().map_err(Error::custom)?)))
+                .collect::<Result<HashMap<NonMandatoryRegisterId, Constant>, D::Error>>()?;
+        NonMandatoryRegisters::try_from(values).map_err(Error::custom)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::sigma_protocol::sigma_boolean::ProveDlog;
+    use crate::sigma_protocol::sigma_boolean::SigmaProp;
+    use crate::types::stuple::STuple;
+    use proptest::prelude::*;
+    use std::convert::TryInto;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Regs(#[serde(with = "rendered")] NonMandatoryRegisters);
+
+    #[test]
+    fn render() {
+        let c: Constant = 0i64.into();
+        assert_eq!(
+            serde_json::to_string(&RegisterValueJson::try_from(&c).unwrap()).unwrap(),
+            r#"{"serializedValue":"0500","sigmaType":"SLong","renderedValue":"0"}"#
+        );
+        let c: Constant = vec![1u8, 255].into();
+        assert_eq!(render_type(&c.tpe), "Coll[SByte]");
+        assert_eq!(render_value(&c.v).unwrap(), "01ff");
+        let c: Constant = vec![1i32, -2].into();
+        assert_eq!(render_value(&c.v).unwrap(), "[1,-2]");
+        let c = Constant {
+            tpe: SType::STuple(STuple::pair(
+                SType::SColl(SType::SByte.into()),
+                SType::SLong,
+            )),
+            v: Literal::Tup(
+                vec![Constant::from(vec![1u8]).v, 3i64.into()]
+                    .try_into()
+                    .unwrap(),
+            ),
+        };
+        assert_eq!(render_type(&c.tpe), "(Coll[SByte], SLong)");
+        assert_eq!(render_value(&c.v).unwrap(), "[01,3]");
+        let c: Constant = SigmaProp::from(ProveDlog::new(
+            crate::sigma_protocol::dlog_group::generator(),
+        ))
+        .into();
+        assert_eq!(render_type(&c.tpe), "SSigmaProp");
+        assert_eq!(
+            render_value(&c.v).unwrap(),
+            "cd0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+        );
+    }
+
+    #[test]
+    fn parse_type_mismatch() {
+        let expected: NonMandatoryRegisters = vec![0i64.into()].try_into().unwrap();
+        let json = r#"{"R4":{"serializedValue":"0500","sigmaType":"SInt","renderedValue":"1"}}"#;
+        // checked in the rendered mode only
+        assert!(serde_json::from_str::<Regs>(json).is_err());
+        assert_eq!(
+            serde_json::from_str::<NonMandatoryRegisters>(json).unwrap(),
+            expected
+        );
+        // rendered value is never checked
+        let json = r#"{"R4":{"serializedValue":"0500","sigmaType":"SLong","renderedValue":"1"}}"#;
+        assert_eq!(serde_json::from_str::<Regs>(json).unwrap().0, expected);
+        let json = r#"{"R4":{"serializedValue":"0500"}}"#;
+        assert_eq!(serde_json::from_str::<Regs>(json).unwrap().0, expected);
+    }
+
+    proptest! {
+
+        #[test]
+        fn rendered_roundtrip(regs in any::<NonMandatoryRegisters>()) {
+            let regs = Regs(regs);
+            let j = serde_json::to_string(&regs)?;
+            let parsed: Regs = serde_json::from_str(&j)?;
+            prop_assert_eq![&parsed, &regs];
+            // default encoding parses rendered registers
+            let parsed: NonMandatoryRegisters = serde_json::from_str(&j)?;
+            prop_assert_eq![parsed, regs.0];
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! SigmaBoolean JSON encoding (node API format)
+
+use std::convert::TryFrom;
+use std::convert::TryInto;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::chain::base16_bytes::Base16DecodedBytes;
+use crate::has_opcode::HasOpCode;
+use crate::serialization::op_code::OpCode;
+use crate::serialization::SigmaParsingError;
+use crate::serialization::SigmaSerializable;
+use crate::sigma_protocol::dlog_group::EcPoint;
+use crate::sigma_protocol::sigma_boolean::cand::Cand;
+use crate::sigma_protocol::sigma_boolean::cor::Cor;
+use crate::sigma_protocol::sigma_boolean::cthreshold::Cthreshold;
+use crate::sigma_protocol::sigma_boolean::ProveDhTuple;
+use crate::sigma_protocol::sigma_boolean::ProveDlog;
+use crate::sigma_protocol::sigma_boolean::SigmaBoolean;
+use crate::sigma_protocol::sigma_boolean::SigmaConjecture;
+use crate::sigma_protocol::sigma_boolean::SigmaProofOfKnowledgeTree;
+
+/// SigmaBoolean in the node's JSON format, `op` is the (signed) op code and the rest of the
+/// fields depend on it:
+/// `{"op": -51, "h": "..."}`, `{"op": -50, "g": "...", "h": "...", "u": "...", "v": "..."}`,
+/// `{"op": -45, "condition": true}`, `{"op": -106, "args": [...]}`,
+/// `{"op": -104, "k": 2, "args": [...]}`
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub(crate) struct SigmaBooleanJson {
+    op: i8,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    condition: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    k: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    g: Option<Base16DecodedBytes>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    h: Option<Base16DecodedBytes>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    u: Option<Base16DecodedBytes>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    v: Option<Base16DecodedBytes>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    args: Option<Vec<SigmaBooleanJson>>,
+}
+
+/// Errors on SigmaBoolean parsing from JSON
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum SigmaBooleanJsonError {
+    /// Unexpected op code
+    #[error("unexpected op code {0}")]
+    UnexpectedOpCode(i8),
+    /// Required field is missing
+    #[error("missing field {0}")]
+    MissingField(&'static str),
+    /// Field value parsing error
+    #[error("parsing error: {0}")]
+    ParsingError(#[from] SigmaParsingError),
+}
+
+impl SigmaBooleanJson {
+    fn new(op: OpCode) -> Self {
+        SigmaBooleanJson {
+            op: op.value() as i8,
+            condition: None,
+            k: None,
+            g: None,
+            h: None,
+            u: None,
+            v: None,
+            args: None,
+        }
+    }
+}
+
+fn point_bytes(p: &EcPoint) -> Option<Base16DecodedBytes> {
+    // EcPoint serialization does not fail
+    p.sigma_serialize_bytes().ok().map(Base16DecodedBytes)
+}
+
+fn parse_point(
+    field: Option<Base16DecodedBytes>,
+    name: &'static str,
+) -> Result<EcPoint, SigmaBooleanJsonError> {
+    let bytes = field.ok_or(SigmaBooleanJsonError::MissingField(name))?;
+    Ok(EcPoint::sigma_parse_bytes(&bytes.0)?)
+}
+
+fn parse_args(
+    args: Option<Vec<SigmaBooleanJson>>,
+) -> Result<Vec<SigmaBoolean>, SigmaBooleanJsonError> {
+    args.ok_or(SigmaBooleanJsonError::MissingField("args"))?
+        .into_iter()
+        .map(SigmaBoolean::try_from)
+        .collect()
+}
+
+impl From<SigmaBoolean> for SigmaBooleanJson {
+    fn from(sb: SigmaBoolean) -> Self {
+        let mut res = SigmaBooleanJson::new(sb.op_code());
+        match sb {
+            SigmaBoolean::TrivialProp(condition) => res.condition = Some(condition),
+            SigmaBoolean::ProofOfKnowledge(SigmaProofOfKnowledgeTree::ProveDlog(pd)) => {
+                res.h = point_bytes(&pd.h)
+            }
+            SigmaBoolean::ProofOfKnowledge(SigmaProofOfKnowledgeTree::ProveDhTuple(dht)) => {
+                res.g = point_bytes(&dht.g);
+                res.h = point_bytes(&dht.h);
+                res.u = point_bytes(&dht.u);
+                res.v = point_bytes(&dht.v);
+            }
+            SigmaBoolean::SigmaConjecture(SigmaConjecture::Cand(Cand { items }))
+            | SigmaBoolean::SigmaConjecture(SigmaConjecture::Cor(Cor { items })) => {
+                res.args = Some(items.into_iter().map(SigmaBooleanJson::from).collect())
+            }
+            SigmaBoolean::SigmaConjecture(SigmaConjecture::Cthreshold(Cthreshold {
+                k,
+                children,
+            })) => {
+                res.k = Some(k);
+                res.args = Some(children.into_iter().map(SigmaBooleanJson::from).collect())
+            }
+        }
+        res
+    }
+}
+
+impl TryFrom<SigmaBooleanJson> for SigmaBoolean {
+    type Error = SigmaBooleanJsonError;
+
+    fn try_from(json: SigmaBooleanJson) -> Result<Self, Self::Error> {
+        let op = OpCode::parse(json.op as u8);
+        if op == OpCode::TRIVIAL_PROP_TRUE || op == OpCode::TRIVIAL_PROP_FALSE {
+            Ok(SigmaBoolean::TrivialProp(op == OpCode::TRIVIAL_PROP_TRUE))
+        } else if op == OpCode::PROVE_DLOG {
+            Ok(ProveDlog::new(parse_point(json.h, "h")?).into())
+        } else if op == OpCode::PROVE_DIFFIE_HELLMAN_TUPLE {
+            Ok(ProveDhTuple::new(
+                parse_point(json.g, "g")?,
+                parse_point(json.h, "h")?,
+                parse_point(json.u, "u")?,
+                parse_point(json.v, "v")?,
+            )
+            .into())
+        } else if op == OpCode::AND {
+            Ok(Cand {
+                items: parse_args(json.args)?
+                    .try_into()
+                    .map_err(SigmaParsingError::from)?,
+            }
+            .into())
+        } else if op == OpCode::OR {
+            Ok(Cor {
+                items: parse_args(json.args)?
+                    .try_into()
+                    .map_err(SigmaParsingError::from)?,
+            }
+            .into())
+        } else if op == OpCode::ATLEAST {
+            Ok(Cthreshold {
+                k: json.k.ok_or(SigmaBooleanJsonError::MissingField("k"))?,
+                children: parse_args(json.args)?
+                    .try_into()
+                    .map_err(SigmaParsingError::from)?,
+            }
+            .into())
+        } else {
+            Err(SigmaBooleanJsonError::UnexpectedOpCode(json.op))
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::panic)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn parse_node_json() {
+        let json = r#"{
+            "op": -104,
+            "k": 1,
+            "args": [
+                {"op": -51, "h": "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"},
+                {"op": -106, "args": [{"op": -45, "condition": true}, {"op": -46, "condition": false}]}
+            ]
+        }"#;
+        let sb: SigmaBoolean = serde_json::from_str(json).unwrap();
+        let children = match &sb {
+            SigmaBoolean::SigmaConjecture(SigmaConjecture::Cthreshold(ct)) => {
+                assert_eq!(ct.k, 1);
+                ct.children.clone()
+            }
+            _ => panic!("unexpected {:?}", sb),
+        };
+        assert!(matches!(
+            children.as_vec()[0],
+            SigmaBoolean::ProofOfKnowledge(SigmaProofOfKnowledgeTree::ProveDlog(_))
+        ));
+        assert_eq!(
+            serde_json::to_value(&sb).unwrap(),
+            serde_json::from_str::<serde_json::Value>(json).unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_field() {
+        let res = serde_json::from_str::<SigmaBoolean>(r#"{"op": -51}"#);
+        assert!(res.unwrap_err().to_string().contains("missing field h"));
+    }
+
+    proptest! {
+
+        #[test]
+        fn json_roundtrip(v in any::<SigmaBoolean>()) {
+            let j = serde_json::to_string(&v)?;
+            let parsed: SigmaBoolean = serde_json::from_str(&j)?;
+            prop_assert_eq![parsed, v];
+        }
+    }
+}
@@ -0,0 +1,628 @@
+//! Contract templates (EIP-5)
+//!
+//! A template is an ErgoTree with segregated constants where some of the constants are named
+//! parameters (to be provided on instantiation) and the rest have default values.
+//! See <https://github.com/ergoplatform/eips/blob/master/eip-0005.md>
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io::Cursor;
+
+use thiserror::Error;
+
+use crate::ergo_tree::ErgoTree;
+use crate::ergo_tree::ErgoTreeError;
+use crate::ergo_tree::ErgoTreeHeader;
+use crate::mir::constant::Constant;
+use crate::mir::constant::Literal;
+use crate::mir::expr::Expr;
+use crate::serialization::constant_store::ConstantStore;
+use crate::serialization::data::DataSerializer;
+use crate::serialization::sigma_byte_reader::SigmaByteRead;
+use crate::serialization::sigma_byte_reader::SigmaByteReader;
+use crate::serialization::sigma_byte_writer::SigmaByteWrite;
+use crate::serialization::sigma_byte_writer::SigmaByteWriter;
+use crate::serialization::SigmaParsingError;
+use crate::serialization::SigmaSerializable;
+use crate::serialization::SigmaSerializeResult;
+use crate::types::stype::SType;
+
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json")]
+pub use json::ContractTemplateJsonError;
+
+/// Named parameter of the template
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parameter {
+    /// Name
+    pub name: String,
+    /// Description
+    pub description: String,
+    /// Index of the constant in the template's constants
+    #[cfg_attr(feature = "json", serde(rename = "constantIndex"))]
+    pub constant_index: u32,
+}
+
+/// Contract template (EIP-5)
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(
+    feature = "json",
+    derive(serde::Deserialize),
+    serde(try_from = "json::ContractTemplateJson")
+)]
+pub struct ContractTemplate {
+    tree_version: Option<u8>,
+    name: String,
+    description: String,
+    const_types: Vec<SType>,
+    const_values: Option<Vec<Option<Constant>>>,
+    parameters: Vec<Parameter>,
+    expression_body: Expr,
+}
+
+/// Contract template creation and instantiation errors
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum ContractTemplateError {
+    /// Number of the constant default values does not match the number of the constant types
+    #[error("expected {expected} constant values, got {actual}")]
+    ConstValuesCountMismatch {
+        /// Number of the constant types
+        expected: usize,
+        /// Number of the constant values
+        actual: usize,
+    },
+    /// Type of the value does not match the type of the constant
+    #[error("constant {index}: expected type {expected:?}, got {actual:?}")]
+    TypeMismatch {
+        /// Constant index
+        index: usize,
+        /// Constant type
+        expected: SType,
+        /// Type of the value
+        actual: SType,
+    },
+    /// Parameter refers to a constant that does not exist
+    #[error("parameter {0} refers to the constant index out of bounds")]
+    ConstantIndexOutOfBounds(String),
+    /// Parameter name is used more than once
+    #[error("duplicate parameter name {0}")]
+    DuplicateParameter(String),
+    /// Value is not provided for the parameter without default value
+    #[error("value is not provided for the parameter {0}")]
+    MissingParameter(String),
+    /// Constant is not a parameter and has no default value
+    #[error("no value for the constant {0}")]
+    MissingConstant(usize),
+    /// Provided value for an unknown parameter
+    #[error("unknown parameter {0}")]
+    UnknownParameter(String),
+    /// Tree version is not supported
+    #[error("unsupported ErgoTree version {0}")]
+    UnsupportedTreeVersion(u8),
+    /// ErgoTree error
+    #[error("ErgoTree error: {0:?}")]
+    ErgoTreeError(#[from] ErgoTreeError),
+    /// Expression body parsing error
+    #[error("parsing error: {0}")]
+    ParsingError(#[from] SigmaParsingError),
+}
+
+impl ContractTemplate {
+    /// Creates a template, checks that the default values and the parameters match the
+    /// constants. `expression_body` refers to the constants with
+    /// [`crate::mir::constant::ConstantPlaceholder`]s.
+    pub fn new(
+        tree_version: Option<u8>,
+        name: String,
+        description: String,
+        const_types: Vec<SType>,
+        const_values: Option<Vec<Option<Constant>>>,
+        parameters: Vec<Parameter>,
+        expression_body: Expr,
+    ) -> Result<Self, ContractTemplateError> {
+        if let Some(values) = &const_values {
+            if values.len() != const_types.len() {
+                return Err(ContractTemplateError::ConstValuesCountMismatch {
+                    expected: const_types.len(),
+                    actual: values.len(),
+                });
+            }
+            for (index, (value, tpe)) in values.iter().zip(const_types.iter()).enumerate() {
+                if let Some(value) = value {
+                    check_type(index, tpe, value)?;
+                }
+            }
+        }
+        let mut names = HashSet::new();
+        for p in parameters.iter() {
+            if p.constant_index as usize >= const_types.len() {
+                return Err(ContractTemplateError::ConstantIndexOutOfBounds(
+                    p.name.clone(),
+                ));
+            }
+            if !names.insert(p.name.as_str()) {
+                return Err(ContractTemplateError::DuplicateParameter(p.name.clone()));
+            }
+        }
+        Ok(ContractTemplate {
+            tree_version,
+            name,
+            description,
+            const_types,
+            const_values,
+            parameters,
+            expression_body,
+        })
+    }
+
+    /// Creates a template from the tree, the constants of the tree (all constants segregated)
+    /// are the default values
+    pub fn from_ergo_tree(
+        tree: &ErgoTree,
+        name: String,
+        description: String,
+        parameters: Vec<Parameter>,
+    ) -> Result<Self, ContractTemplateError> {
+        let version = tree.header().version().into();
+        let header = if version == 0 {
+            ErgoTreeHeader::v0(true)
+        } else {
+            ErgoTreeHeader::v1(true)
+        };
+        let segregated = ErgoTree::new(header, &*tree.proposition()?)?;
+        let constants_len = segregated
+            .constants_len()
+            .map_err(ErgoTreeError::ConstantsParsingError)?;
+        let constants = (0..constants_len)
+            .filter_map(|i| segregated.get_constant(i).transpose())
+            .collect::<Result<Vec<Constant>, _>>()
+            .map_err(ErgoTreeError::ConstantsParsingError)?;
+        let body_bytes = segregated.template_bytes()?;
+        let mut r = SigmaByteReader::new(
+            Cursor::new(&body_bytes[..]),
+            ConstantStore::new(constants.clone()),
+        );
+        let expression_body = Expr::sigma_parse(&mut r)?;
+        ContractTemplate::new(
+            Some(version),
+            name,
+            description,
+            constants.iter().map(|c| c.tpe.clone()).collect(),
+            Some(constants.into_iter().map(Some).collect()),
+            parameters,
+            expression_body,
+        )
+    }
+
+    /// ErgoTree version (0 if not set)
+    pub fn tree_version(&self) -> Option<u8> {
+        self.tree_version
+    }
+
+    /// Name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Description
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Types of the constants
+    pub fn const_types(&self) -> &[SType] {
+        &self.const_types
+    }
+
+    /// Default values of the constants
+    pub fn const_values(&self) -> Option<&[Option<Constant>]> {
+        self.const_values.as_deref()
+    }
+
+    /// Parameters
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// Root expression with the constant placeholders
+    pub fn expression_body(&self) -> &Expr {
+        &self.expression_body
+    }
+
+    /// Creates an ErgoTree with the given parameter values (by parameter name), the constants
+    /// that are not provided get their default values
+    pub fn apply(
+        &self,
+        params: &HashMap<String, Constant>,
+    ) -> Result<ErgoTree, ContractTemplateError> {
+        if let Some(unknown) = params
+            .keys()
+            .find(|name| !self.parameters.iter().any(|p| &p.name == *name))
+        {
+            return Err(ContractTemplateError::UnknownParameter(unknown.clone()));
+        }
+        let mut constants = Vec::with_capacity(self.const_types.len());
+        for (index, tpe) in self.const_types.iter().enumerate() {
+            let param = self
+                .parameters
+                .iter()
+                .find(|p| p.constant_index as usize == index);
+            let default = self
+                .const_values
+                .as_ref()
+                .and_then(|values| values.get(index).cloned().flatten());
+            let value = match (param.and_then(|p| params.get(&p.name)), default, param) {
+                (Some(value), _, _) => value.clone(),
+                (None, Some(default), _) => default,
+                (None, None, Some(p)) => {
+                    return Err(ContractTemplateError::MissingParameter(p.name.clone()))
+                }
+                (None, None, None) => return Err(ContractTemplateError::MissingConstant(index)),
+            };
+            check_type(index, tpe, &value)?;
+            constants.push(value);
+        }
+        let header = match self.tree_version.unwrap_or(0) {
+            0 => ErgoTreeHeader::v0(true),
+            1 => ErgoTreeHeader::v1(true),
+            v => return Err(ContractTemplateError::UnsupportedTreeVersion(v)),
+        };
+        Ok(ErgoTree::with_segregation(
+            header,
+            &self.expression_body,
+            constants,
+        )?)
+    }
+
+    /// Parses the expression body (serialized with the constant placeholders)
+    fn parse_expression_body(
+        bytes: &[u8],
+        const_types: &[SType],
+    ) -> Result<Expr, SigmaParsingError> {
+        // only the types of the constants are needed to parse the placeholders
+        let constants = const_types
+            .iter()
+            .map(|tpe| Constant {
+                tpe: tpe.clone(),
+                v: Literal::Boolean(false),
+            })
+            .collect();
+        let mut r = SigmaByteReader::new(Cursor::new(bytes), ConstantStore::new(constants));
+        Expr::sigma_parse(&mut r)
+    }
+}
+
+fn check_type(index: usize, tpe: &SType, value: &Constant) -> Result<(), ContractTemplateError> {
+    if value.tpe == *tpe {
+        Ok(())
+    } else {
+        Err(ContractTemplateError::TypeMismatch {
+            index,
+            expected: tpe.clone(),
+            actual: value.tpe.clone(),
+        })
+    }
+}
+
+fn put_string<W: SigmaByteWrite>(w: &mut W, s: &str) -> SigmaSerializeResult {
+    w.put_usize_as_u32_unwrapped(s.len())?;
+    w.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+fn get_string<R: SigmaByteRead>(r: &mut R) -> Result<String, SigmaParsingError> {
+    let len = r.get_u32()? as usize;
+    let bytes = r.get_bytes(len)?;
+    String::from_utf8(bytes).map_err(|e| SigmaParsingError::Misc(e.to_string()))
+}
+
+fn get_flag<R: SigmaByteRead>(r: &mut R) -> Result<bool, SigmaParsingError> {
+    match r.get_u8()? {
+        0 => Ok(false),
+        1 => Ok(true),
+        b => Err(SigmaParsingError::ValueOutOfBounds(format!(
+            "expected option flag 0 or 1, got {}",
+            b
+        ))),
+    }
+}
+
+/// Binary format: tree version (option of byte), name, description (VLQ length prefixed
+/// UTF-8), constant types (VLQ count and types), default values (option with an option of a
+/// value (without type) for each constant), parameters (VLQ count, name, description, VLQ
+/// constant index for each) and the expression body (VLQ length prefixed)
+impl SigmaSerializable for ContractTemplate {
+    fn sigma_serialize<W: SigmaByteWrite>(&self, w: &mut W) -> SigmaSerializeResult {
+        w.put_option(self.tree_version, &|w, v| w.put_u8(v))?;
+        put_string(w, &self.name)?;
+        put_string(w, &self.description)?;
+        w.put_usize_as_u32_unwrapped(self.const_types.len())?;
+        for tpe in self.const_types.iter() {
+            tpe.sigma_serialize(w)?;
+        }
+        match &self.const_values {
+            Some(values) => {
+                w.put_u8(1)?;
+                for value in values {
+                    match value {
+                        Some(c) => {
+                            w.put_u8(1)?;
+                            DataSerializer::sigma_serialize(&c.v, w)?;
+                        }
+                        None => w.put_u8(0)?,
+                    }
+                }
+            }
+            None => w.put_u8(0)?,
+        }
+        w.put_usize_as_u32_unwrapped(self.parameters.len())?;
+        for p in self.parameters.iter() {
+            put_string(w, &p.name)?;
+            put_string(w, &p.description)?;
+            w.put_u32(p.constant_index)?;
+        }
+        let mut body = Vec::new();
+        self.expression_body
+            .sigma_serialize(&mut SigmaByteWriter::new(&mut body, None))?;
+        w.put_usize_as_u32_unwrapped(body.len())?;
+        w.write_all(&body)?;
+        Ok(())
+    }
+
+    fn sigma_parse<R: SigmaByteRead>(r: &mut R) -> Result<Self, SigmaParsingError> {
+        let tree_version = if get_flag(r)? {
+            Some(r.get_u8()?)
+        } else {
+            None
+        };
+        let name = get_string(r)?;
+        let description = get_string(r)?;
+        let const_types_len = r.get_u32()?;
+        let mut const_types = Vec::new();
+        for _ in 0..const_types_len {
+            const_types.push(SType::sigma_parse(r)?);
+        }
+        let const_values = if get_flag(r)? {
+            let mut values = Vec::with_capacity(const_types.len());
+            for tpe in const_types.iter() {
+                values.push(if get_flag(r)? {
+                    Some(Constant {
+                        tpe: tpe.clone(),
+                        v: DataSerializer::sigma_parse(tpe, r)?,
+                    })
+                } else {
+                    None
+                });
+            }
+            Some(values)
+        } else {
+            None
+        };
+        let parameters_len = r.get_u32()?;
+        let mut parameters = Vec::new();
+        for _ in 0..parameters_len {
+            parameters.push(Parameter {
+                name: get_string(r)?,
+                description: get_string(r)?,
+                constant_index: r.get_u32()?,
+            });
+        }
+        let body_len = r.get_u32()? as usize;
+        let body = r.get_bytes(body_len)?;
+        let expression_body = ContractTemplate::parse_expression_body(&body, &const_types)?;
+        ContractTemplate::new(
+            tree_version,
+            name,
+            description,
+            const_types,
+            const_values,
+            parameters,
+            expression_body,
+        )
+        .map_err(|e| SigmaParsingError::Misc(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::mir::bin_op::BinOp;
+    use crate::mir::bin_op::RelationOp;
+    use crate::mir::bool_to_sigma::BoolToSigmaProp;
+    use crate::mir::constant::ConstantPlaceholder;
+    use crate::mir::global_vars::GlobalVars;
+    use crate::mir::sigma_and::SigmaAnd;
+    use crate::serialization::sigma_serialize_roundtrip;
+    use crate::sigma_protocol::dlog_group::EcPoint;
+    use crate::sigma_protocol::sigma_boolean::ProveDlog;
+    use crate::sigma_protocol::sigma_boolean::SigmaProp;
+    use sigma_ser::vlq_encode::WriteSigmaVlqExt;
+
+    /// `sigmaProp(HEIGHT >= deadline) && owner`
+    fn timelock() -> ContractTemplate {
+        let body: Expr = SigmaAnd::new(vec![
+            BoolToSigmaProp {
+                input: Box::new(
+                    BinOp {
+                        kind: RelationOp::Ge.into(),
+                        left: Box::new(GlobalVars::Height.into()),
+                        right: Box::new(
+                            ConstantPlaceholder {
+                                id: 0,
+                                tpe: SType::SInt,
+                            }
+                            .into(),
+                        ),
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+            ConstantPlaceholder {
+                id: 1,
+                tpe: SType::SSigmaProp,
+            }
+            .into(),
+        ])
+        .unwrap()
+        .into();
+        ContractTemplate::new(
+            None,
+            "timelock".to_string(),
+            "owner can spend after the deadline".to_string(),
+            vec![SType::SInt, SType::SSigmaProp],
+            Some(vec![Some(100i32.into()), None]),
+            vec![
+                Parameter {
+                    name: "deadline".to_string(),
+                    description: "height".to_string(),
+                    constant_index: 0,
+                },
+                Parameter {
+                    name: "owner".to_string(),
+                    description: "".to_string(),
+                    constant_index: 1,
+                },
+            ],
+            body,
+        )
+        .unwrap()
+    }
+
+    fn owner() -> Constant {
+        SigmaProp::from(ProveDlog::new(EcPoint::default())).into()
+    }
+
+    #[test]
+    fn ser_roundtrip() {
+        let template = timelock();
+        assert_eq!(sigma_serialize_roundtrip(&template), template);
+    }
+
+    #[test]
+    fn apply() {
+        let template = timelock();
+        let params: HashMap<String, Constant> =
+            vec![("owner".to_string(), owner())].into_iter().collect();
+        let tree = template.apply(&params).unwrap();
+        assert_eq!(tree.get_constant(0).unwrap(), Some(Constant::from(100i32)));
+        assert_eq!(tree.get_constant(1).unwrap(), Some(owner()));
+        assert_eq!(
+            ContractTemplate::from_ergo_tree(
+                &tree,
+                template.name().to_string(),
+                template.description().to_string(),
+                template.parameters().to_vec(),
+            )
+            .unwrap()
+            .expression_body(),
+            template.expression_body()
+        );
+        let params: HashMap<String, Constant> = vec![
+            ("owner".to_string(), owner()),
+            ("deadline".to_string(), 500i32.into()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            template.apply(&params).unwrap().get_constant(0).unwrap(),
+            Some(Constant::from(500i32))
+        );
+    }
+
+    #[test]
+    fn apply_errors() {
+        let template = timelock();
+        assert_eq!(
+            template.apply(&HashMap::new()),
+            Err(ContractTemplateError::MissingParameter("owner".to_string()))
+        );
+        let params: HashMap<String, Constant> = vec![
+            ("owner".to_string(), owner()),
+            ("deadline".to_string(), 500i64.into()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            template.apply(&params),
+            Err(ContractTemplateError::TypeMismatch {
+                index: 0,
+                expected: SType::SInt,
+                actual: SType::SLong
+            })
+        );
+        let params: HashMap<String, Constant> = vec![
+            ("owner".to_string(), owner()),
+            ("height".to_string(), 500i32.into()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            template.apply(&params),
+            Err(ContractTemplateError::UnknownParameter(
+                "height".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_huge_lengths() {
+        let max_len = [0xff, 0xff, 0xff, 0xff, 0x0f];
+        // name
+        let mut bytes = vec![0];
+        bytes.extend_from_slice(&max_len);
+        assert!(ContractTemplate::sigma_parse_bytes(&bytes).is_err());
+        // body
+        let template = timelock();
+        let bytes = template.sigma_serialize_bytes().unwrap();
+        let body = template.expression_body().sigma_serialize_bytes().unwrap();
+        let mut body_len = Vec::new();
+        body_len.put_usize_as_u32_unwrapped(body.len()).unwrap();
+        let mut truncated = bytes[..bytes.len() - body.len() - body_len.len()].to_vec();
+        truncated.extend_from_slice(&max_len);
+        truncated.extend_from_slice(&body);
+        assert!(ContractTemplate::sigma_parse_bytes(&truncated).is_err());
+    }
+
+    #[test]
+    fn invalid_template() {
+        let template = timelock();
+        assert_eq!(
+            ContractTemplate::new(
+                None,
+                "".to_string(),
+                "".to_string(),
+                template.const_types().to_vec(),
+                Some(vec![Some(1i64.into()), None]),
+                vec![],
+                template.expression_body().clone(),
+            ),
+            Err(ContractTemplateError::TypeMismatch {
+                index: 0,
+                expected: SType::SInt,
+                actual: SType::SLong
+            })
+        );
+        assert_eq!(
+            ContractTemplate::new(
+                None,
+                "".to_string(),
+                "".to_string(),
+                template.const_types().to_vec(),
+                None,
+                vec![Parameter {
+                    name: "x".to_string(),
+                    description: "".to_string(),
+                    constant_index: 2
+                }],
+                template.expression_body().clone(),
+            ),
+            Err(ContractTemplateError::ConstantIndexOutOfBounds(
+                "x".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! EIP-5 JSON representation of the contract template
+
+use std::convert::TryFrom;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde::Serializer;
+use thiserror::Error;
+
+use super::ContractTemplate;
+use super::ContractTemplateError;
+use super::Parameter;
+use crate::chain::base16_bytes::Base16DecodedBytes;
+use crate::mir::constant::Constant;
+use crate::serialization::data::DataSerializer;
+use crate::serialization::sigma_byte_reader;
+use crate::serialization::sigma_byte_writer::SigmaByteWriter;
+use crate::serialization::SigmaParsingError;
+use crate::serialization::SigmaSerializable;
+use crate::serialization::SigmaSerializationError;
+use crate::types::stype::SType;
+
+/// Contract template in EIP-5 JSON format (types, values and the expression body are base16
+/// encoded)
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub(crate) struct ContractTemplateJson {
+    #[serde(rename = "treeVersion")]
+    tree_version: Option<u8>,
+    name: String,
+    description: String,
+    #[serde(rename = "constTypes")]
+    const_types: Vec<Base16DecodedBytes>,
+    #[serde(rename = "constValues")]
+    const_values: Option<Vec<Option<Base16DecodedBytes>>>,
+    parameters: Vec<Parameter>,
+    #[serde(rename = "expressionBody")]
+    expression_body: Base16DecodedBytes,
+}
+
+/// Errors on contract template conversion from JSON
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum ContractTemplateJsonError {
+    /// Parsing error
+    #[error("parsing error: {0}")]
+    ParsingError(#[from] SigmaParsingError),
+    /// Invalid template
+    #[error("invalid template: {0}")]
+    ContractTemplateError(#[from] ContractTemplateError),
+}
+
+fn serialize_value(c: &Constant) -> Result<Vec<u8>, SigmaSerializationError> {
+    let mut bytes = Vec::new();
+    DataSerializer::sigma_serialize(&c.v, &mut SigmaByteWriter::new(&mut bytes, None))?;
+    Ok(bytes)
+}
+
+impl TryFrom<&ContractTemplate> for ContractTemplateJson {
+    type Error = SigmaSerializationError;
+
+    fn try_from(t: &ContractTemplate) -> Result<Self, Self::Error> {
+        let const_types = t
+            .const_types
+            .iter()
+            .map(|tpe| tpe.sigma_serialize_bytes().map(Base16DecodedBytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        let const_values = t
+            .const_values
+            .as_ref()
+            .map(|values| {
+                values
+                    .iter()
+                    .map(|v| {
+                        v.as_ref()
+                            .map(|c| serialize_value(c).map(Base16DecodedBytes))
+                            .transpose()
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        Ok(ContractTemplateJson {
+            tree_version: t.tree_version,
+            name: t.name.clone(),
+            description: t.description.clone(),
+            const_types,
+            const_values,
+            parameters: t.parameters.clone(),
+            expression_body: Base16DecodedBytes(t.expression_body.sigma_serialize_bytes()?),
+        })
+    }
+}
+
+impl Serialize for ContractTemplate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::Error;
+        ContractTemplateJson::try_from(self)
+            .map_err(Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl TryFrom<ContractTemplateJson> for ContractTemplate {
+    type Error = ContractTemplateJsonError;
+
+    fn try_from(t: ContractTemplateJson) -> Result<Self, Self::Error> {
+        let const_types = t
+            .const_types
+            .iter()
+            .map(|bytes| SType::sigma_parse_bytes(&bytes.0))
+            .collect::<Result<Vec<SType>, _>>()?;
+        let const_values = match t.const_values {
+            Some(values) => {
+                if values.len() != const_types.len() {
+                    return Err(ContractTemplateError::ConstValuesCountMismatch {
+                        expected: const_types.len(),
+                        actual: values.len(),
+                    }
+                    .into());
+                }
+                let mut parsed = Vec::with_capacity(values.len());
+                for (value, tpe) in values.iter().zip(const_types.iter()) {
+                    parsed.push(match value {
+                        Some(bytes) => Some(Constant {
+                            tpe: tpe.clone(),
+                            v: DataSerializer::sigma_parse(
+                                tpe,
+                                &mut sigma_byte_reader::from_bytes(&bytes.0),
+                            )?,
+                        }),
+                        None => None,
+                    });
+                }
+                Some(parsed)
+            }
+            None => None,
+        };
+        let expression_body =
+            ContractTemplate::parse_expression_body(&t.expression_body.0, &const_types)?;
+        Ok(ContractTemplate::new(
+            t.tree_version,
+            t.name,
+            t.description,
+            const_types,
+            const_values,
+            t.parameters,
+            expression_body,
+        )?)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::mir::bin_op::ArithOp;
+    use crate::mir::bin_op::BinOp;
+    use crate::mir::constant::ConstantPlaceholder;
+    use crate::mir::expr::Expr;
+
+    #[test]
+    fn json_roundtrip() {
+        let body: Expr = BinOp {
+            kind: ArithOp::Plus.into(),
+            left: Box::new(
+                ConstantPlaceholder {
+                    id: 0,
+                    tpe: SType::SInt,
+                }
+                .into(),
+            ),
+            right: Box::new(
+                ConstantPlaceholder {
+                    id: 1,
+                    tpe: SType::SInt,
+                }
+                .into(),
+            ),
+        }
+        .into();
+        let template = ContractTemplate::new(
+            Some(0),
+            "sum".to_string(),
+            "".to_string(),
+            vec![SType::SInt, SType::SInt],
+            Some(vec![None, Some(1i32.into())]),
+            vec![Parameter {
+                name: "x".to_string(),
+                description: "".to_string(),
+                constant_index: 0,
+            }],
+            body,
+        )
+        .unwrap();
+        let json = serde_json::to_string(&template).unwrap();
+        assert_eq!(
+            json,
+            r#"{"treeVersion":0,"name":"sum","description":"","constTypes":["04","04"],"constValues":[null,"02"],"parameters":[{"name":"x","description":"","constantIndex":0}],"expressionBody":"9a73007301"}"#
+        );
+        let parsed: ContractTemplate = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, template);
+    }
+}
@@ -2,6 +2,7 @@
 use crate::mir::constant::Constant;
 use crate::mir::constant::TryExtractFromError;
 use crate::mir::expr::Expr;
+use crate::pretty_printer::print_expr_with_constants;
 use crate::serialization::SigmaSerializationError;
 use crate::serialization::SigmaSerializeResult;
 use crate::serialization::{
@@ -324,28 +325,7 @@ impl ErgoTree {
     /// Creates a tree using provided header and root expression
     pub fn new(header: ErgoTreeHeader, expr: &Expr) -> Result<Self, ErgoTreeError> {
         Ok(if header.is_constant_segregation() {
-            let mut data = Vec::new();
-            let cs = ConstantStore::empty();
-            let mut w = SigmaByteWriter::new(&mut data, Some(cs));
-            expr.sigma_serialize(&mut w)?;
-            #[allow(clippy::unwrap_used)]
-            // We set constant store earlier
-            let constants = w.constant_store_mut_ref().unwrap().get_all();
-            let cursor = Cursor::new(&mut data[..]);
-            let new_cs = ConstantStore::new(constants.clone());
-            let mut sr = SigmaByteReader::new(cursor, new_cs);
-            let parsed_expr =
-                Expr::sigma_parse(&mut sr).map_err(|error| ErgoTreeRootParsingError {
-                    root_expr_bytes: data,
-                    error,
-                })?;
-            ErgoTree {
-                header: ErgoTreeHeader(ErgoTreeHeader::CONSTANT_SEGREGATION_FLAG | header.0),
-                tree: Ok(ParsedTree {
-                    constants,
-                    root: Ok(Rc::new(parsed_expr)),
-                }),
-            }
+            ErgoTree::with_segregation(header, expr, Vec::new())?
         } else {
             ErgoTree {
                 header,
@@ -357,9 +337,45 @@ impl ErgoTree {
         })
     }
 
+    /// Creates a constant segregated tree using provided header, root expression and constants.
+    /// `ConstantPlaceholder` nodes in `expr` refer to the given `constants` (by index), while
+    /// `Constant` nodes in `expr` are segregated and appended after the given `constants`.
+    pub fn with_segregation(
+        header: ErgoTreeHeader,
+        expr: &Expr,
+        constants: Vec<Constant>,
+    ) -> Result<Self, ErgoTreeError> {
+        let mut data = Vec::new();
+        let cs = ConstantStore::new(constants);
+        let mut w = SigmaByteWriter::new(&mut data, Some(cs));
+        expr.sigma_serialize(&mut w)?;
+        #[allow(clippy::unwrap_used)]
+        // We set constant store earlier
+        let constants = w.constant_store_mut_ref().unwrap().get_all();
+        let cursor = Cursor::new(&mut data[..]);
+        let new_cs = ConstantStore::new(constants.clone());
+        let mut sr = SigmaByteReader::new(cursor, new_cs);
+        let parsed_expr = Expr::sigma_parse(&mut sr).map_err(|error| ErgoTreeRootParsingError {
+            root_expr_bytes: data,
+            error,
+        })?;
+        Ok(ErgoTree {
+            header: ErgoTreeHeader(ErgoTreeHeader::CONSTANT_SEGREGATION_FLAG | header.0),
+            tree: Ok(ParsedTree {
+                constants,
+                root: Ok(Rc::new(parsed_expr)),
+            }),
+        })
+    }
+
     /// Reasonable limit for the number of constants allowed in the ErgoTree
     pub const MAX_CONSTANTS_COUNT: usize = 4096;
 
+    /// ErgoTree header
+    pub fn header(&self) -> &ErgoTreeHeader {
+        &self.header
+    }
+
     /// get Expr out of ErgoTree
     pub fn proposition(&self) -> Result<Rc<Expr>, ErgoTreeError> {
         let tree = self
@@ -397,6 +413,20 @@ impl ErgoTree {
         tree
     }
 
+    /// Prints the root expression as ErgoScript source code, with segregated constants printed
+    /// in place of their placeholders (see [`crate::pretty_printer`])
+    pub fn pretty_print(&self) -> Result<String, ErgoTreeError> {
+        let tree = self
+            .tree
+            .as_ref()
+            .map_err(|e| ErgoTreeError::ConstantsParsingError(e.clone()))?;
+        let root = tree
+            .root
+            .as_ref()
+            .map_err(|e| ErgoTreeError::RootParsingError(e.clone()))?;
+        Ok(print_expr_with_constants(root, &tree.constants))
+    }
+
     /// Returns Base16-encoded serialized bytes
     pub fn to_base16_bytes(&self) -> Result<String, SigmaSerializationError> {
         let bytes = self.sigma_serialize_bytes()?;
@@ -597,7 +627,9 @@ mod tests {
     use super::*;
     use crate::chain::address::AddressEncoder;
     use crate::chain::address::NetworkPrefix;
+    use crate::mir::constant::ConstantPlaceholder;
     use crate::mir::constant::Literal;
+    use crate::mir::tuple::Tuple;
     use proptest::prelude::*;
 
     proptest! {
@@ -772,6 +804,34 @@ mod tests {
         assert_eq!(new_ergo_tree.get_constant(0).unwrap().unwrap(), true.into());
     }
 
+    #[test]
+    fn test_with_segregation() {
+        let placeholder = Expr::ConstPlaceholder(ConstantPlaceholder {
+            id: 0,
+            tpe: SType::SBoolean,
+        });
+        let expr = Expr::Tuple(Tuple::new(vec![placeholder, Expr::Const(1i32.into())]).unwrap());
+        let ergo_tree =
+            ErgoTree::with_segregation(ErgoTreeHeader::v0(false), &expr, vec![false.into()])
+                .unwrap();
+        assert!(ergo_tree.header.is_constant_segregation());
+        assert_eq!(ergo_tree.constants_len().unwrap(), 2);
+        assert_eq!(ergo_tree.get_constant(0).unwrap().unwrap(), false.into());
+        assert_eq!(ergo_tree.get_constant(1).unwrap().unwrap(), 1i32.into());
+        let new_ergo_tree = ergo_tree.with_constant(0, true.into()).unwrap();
+        let bytes = new_ergo_tree.sigma_serialize_bytes().unwrap();
+        let parsed_expr = ErgoTree::sigma_parse_bytes(&bytes)
+            .unwrap()
+            .proposition()
+            .unwrap();
+        assert_eq!(
+            *parsed_expr,
+            Expr::Tuple(
+                Tuple::new(vec![Expr::Const(true.into()), Expr::Const(1i32.into())]).unwrap()
+            )
+        );
+    }
+
     #[test]
     fn dex_t2tpool_parse() {
         let base16_str = "19a3030f0400040204020404040404060406058080a0f6f4acdbe01b058080a0f6f4acdbe01b050004d00f0400040005000500d81ad601b2a5730000d602e4c6a70405d603db63087201d604db6308a7d605b27203730100d606b27204730200d607b27203730300d608b27204730400d609b27203730500d60ab27204730600d60b9973078c720602d60c999973088c720502720bd60d8c720802d60e998c720702720dd60f91720e7309d6108c720a02d6117e721006d6127e720e06d613998c7209027210d6147e720d06d615730ad6167e721306d6177e720c06d6187e720b06d6199c72127218d61a9c72167218d1edededededed93c27201c2a793e4c672010405720292c17201c1a793b27203730b00b27204730c00938c7205018c720601ed938c7207018c720801938c7209018c720a019593720c730d95720f929c9c721172127e7202069c7ef07213069a9c72147e7215067e9c720e720206929c9c721472167e7202069c7ef0720e069a9c72117e7215067e9c721372020695ed720f917213730e907217a19d721972149d721a7211ed9272199c7217721492721a9c72177211";
@@ -23,8 +23,11 @@ mod has_opcode;
 pub mod base16_str;
 pub mod bigint256;
 pub mod chain;
+pub mod contract_template;
 pub mod ergo_tree;
 pub mod mir;
+pub mod optimizer;
+pub mod pretty_printer;
 pub mod serialization;
 pub mod sigma_protocol;
 pub mod type_check;
@@ -109,6 +109,7 @@ pub mod val_def;
 /// Variable reference
 pub mod val_use;
 pub mod value;
+pub mod visitor;
 /// Byte-wise XOR op
 pub mod xor;
 /// XOR for collection of booleans
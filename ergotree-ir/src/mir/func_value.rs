@@ -72,6 +72,11 @@ impl FuncValue {
         &self.body
     }
 
+    /// Mutable function body, rewrites must preserve the type of the body
+    pub(crate) fn body_mut(&mut self) -> &mut Expr {
+        &mut self.body
+    }
+
     /// Type
     pub fn tpe(&self) -> SType {
         self.tpe.clone()
@@ -0,0 +1,800 @@
+//! Traversal of MIR ([`Expr`]) trees.
+//!
+//! [`Visitor`] walks the tree by reference, [`MutVisitor`] changes the nodes in place and
+//! [`Folder`] rebuilds the tree from owned nodes. The default implementations recurse into every
+//! child expression of every node (see [`children`]), so an implementation only needs to match
+//! on the nodes it is interested in and call the `walk_*` function to continue into the children.
+//!
+//! Every kind of node (except [`Expr::Context`] and [`Expr::Global`], which have no data) has
+//! its own hook as well (`visit_bin_op`, `visit_bin_op_mut`, `fold_bin_op`, etc.), so that a
+//! single node type can be handled without matching on [`Expr`]. The hooks are called by
+//! [`walk_expr`] and [`walk_expr_mut`] before the children of the node are visited and by
+//! [`fold_children`] after the children are folded. The default hooks do nothing (keep the node).
+//!
+//! Some nodes cache the types of their operands instead of computing them (e.g. the function
+//! type in `FuncValue`, the result type in `Apply`, the value type in `ValUse`). A [`MutVisitor`]
+//! or a [`Folder`] which replaces a node must replace it with an expression of the same type
+//! ([`Expr::tpe`]), the cached types are not updated and the tree would become ill-typed.
+//!
+//! ```
+//! use ergotree_ir::mir::expr::Expr;
+//! use ergotree_ir::mir::val_use::ValUse;
+//! use ergotree_ir::mir::visitor::walk_expr;
+//! use ergotree_ir::mir::visitor::Visitor;
+//!
+//! /// Counts the references to the values defined with `val`
+//! struct ValUseCounter(usize);
+//!
+//! impl<'a> Visitor<'a> for ValUseCounter {
+//!     fn visit_expr(&mut self, expr: &'a Expr) {
+//!         if let Expr::ValUse(_) = expr {
+//!             self.0 += 1;
+//!         }
+//!         walk_expr(self, expr)
+//!     }
+//! }
+//!
+//! /// The same with the node hook
+//! struct ValUseHookCounter(usize);
+//!
+//! impl<'a> Visitor<'a> for ValUseHookCounter {
+//!     fn visit_val_use(&mut self, _node: &'a ValUse) {
+//!         self.0 += 1;
+//!     }
+//! }
+//! ```
+
+use crate::mir::and::And;
+use crate::mir::apply::Apply;
+use crate::mir::atleast::Atleast;
+use crate::mir::bin_op::BinOp;
+use crate::mir::bin_op::BinOpKind;
+use crate::mir::bin_op::LogicalOp;
+use crate::mir::bit_inversion::BitInversion;
+use crate::mir::block::BlockValue;
+use crate::mir::bool_to_sigma::BoolToSigmaProp;
+use crate::mir::byte_array_to_bigint::ByteArrayToBigInt;
+use crate::mir::byte_array_to_long::ByteArrayToLong;
+use crate::mir::calc_blake2b256::CalcBlake2b256;
+use crate::mir::calc_sha256::CalcSha256;
+use crate::mir::coll_append::Append;
+use crate::mir::coll_by_index::ByIndex;
+use crate::mir::coll_exists::Exists;
+use crate::mir::coll_filter::Filter;
+use crate::mir::coll_fold::Fold;
+use crate::mir::coll_forall::ForAll;
+use crate::mir::coll_map::Map;
+use crate::mir::coll_size::SizeOf;
+use crate::mir::coll_slice::Slice;
+use crate::mir::collection::Collection;
+use crate::mir::constant::Constant;
+use crate::mir::constant::ConstantPlaceholder;
+use crate::mir::create_avl_tree::CreateAvlTree;
+use crate::mir::create_prove_dh_tuple::CreateProveDhTuple;
+use crate::mir::create_provedlog::CreateProveDlog;
+use crate::mir::decode_point::DecodePoint;
+use crate::mir::deserialize_context::DeserializeContext;
+use crate::mir::deserialize_register::DeserializeRegister;
+use crate::mir::downcast::Downcast;
+use crate::mir::exponentiate::Exponentiate;
+use crate::mir::expr::Expr;
+use crate::mir::extract_amount::ExtractAmount;
+use crate::mir::extract_bytes::ExtractBytes;
+use crate::mir::extract_bytes_with_no_ref::ExtractBytesWithNoRef;
+use crate::mir::extract_creation_info::ExtractCreationInfo;
+use crate::mir::extract_id::ExtractId;
+use crate::mir::extract_reg_as::ExtractRegisterAs;
+use crate::mir::extract_script_bytes::ExtractScriptBytes;
+use crate::mir::func_value::FuncValue;
+use crate::mir::get_var::GetVar;
+use crate::mir::global_vars::GlobalVars;
+use crate::mir::if_op::If;
+use crate::mir::logical_not::LogicalNot;
+use crate::mir::long_to_byte_array::LongToByteArray;
+use crate::mir::method_call::MethodCall;
+use crate::mir::multiply_group::MultiplyGroup;
+use crate::mir::negation::Negation;
+use crate::mir::option_get::OptionGet;
+use crate::mir::option_get_or_else::OptionGetOrElse;
+use crate::mir::option_is_defined::OptionIsDefined;
+use crate::mir::or::Or;
+use crate::mir::property_call::PropertyCall;
+use crate::mir::select_field::SelectField;
+use crate::mir::sigma_and::SigmaAnd;
+use crate::mir::sigma_or::SigmaOr;
+use crate::mir::sigma_prop_bytes::SigmaPropBytes;
+use crate::mir::subst_const::SubstConstants;
+use crate::mir::tree_lookup::TreeLookup;
+use crate::mir::tuple::Tuple;
+use crate::mir::upcast::Upcast;
+use crate::mir::val_def::ValDef;
+use crate::mir::val_use::ValUse;
+use crate::mir::xor::Xor;
+use crate::mir::xor_of::XorOf;
+
+/// Passes the list of the [`Expr`] variants with data (variant, node type and the names of the
+/// [`Visitor`], [`MutVisitor`] and [`Folder`] hooks) to the given macro
+macro_rules! with_expr_nodes {
+    ($m:ident) => {
+        $m! {
+            Append(Append): visit_append, visit_append_mut, fold_append;
+            Const(Constant): visit_const, visit_const_mut, fold_const;
+            ConstPlaceholder(ConstantPlaceholder):
+                visit_const_placeholder, visit_const_placeholder_mut, fold_const_placeholder;
+            SubstConstants(SubstConstants):
+                visit_subst_constants, visit_subst_constants_mut, fold_subst_constants;
+            ByteArrayToLong(ByteArrayToLong):
+                visit_byte_array_to_long, visit_byte_array_to_long_mut, fold_byte_array_to_long;
+            ByteArrayToBigInt(ByteArrayToBigInt):
+                visit_byte_array_to_bigint,
+                visit_byte_array_to_bigint_mut,
+                fold_byte_array_to_bigint;
+            LongToByteArray(LongToByteArray):
+                visit_long_to_byte_array, visit_long_to_byte_array_mut, fold_long_to_byte_array;
+            Collection(Collection): visit_collection, visit_collection_mut, fold_collection;
+            Tuple(Tuple): visit_tuple, visit_tuple_mut, fold_tuple;
+            CalcBlake2b256(CalcBlake2b256):
+                visit_calc_blake2b256, visit_calc_blake2b256_mut, fold_calc_blake2b256;
+            CalcSha256(CalcSha256): visit_calc_sha256, visit_calc_sha256_mut, fold_calc_sha256;
+            GlobalVars(GlobalVars): visit_global_vars, visit_global_vars_mut, fold_global_vars;
+            FuncValue(FuncValue): visit_func_value, visit_func_value_mut, fold_func_value;
+            Apply(Apply): visit_apply, visit_apply_mut, fold_apply;
+            MethodCall(MethodCall): visit_method_call, visit_method_call_mut, fold_method_call;
+            ProperyCall(PropertyCall):
+                visit_property_call, visit_property_call_mut, fold_property_call;
+            BlockValue(BlockValue): visit_block_value, visit_block_value_mut, fold_block_value;
+            ValDef(ValDef): visit_val_def, visit_val_def_mut, fold_val_def;
+            ValUse(ValUse): visit_val_use, visit_val_use_mut, fold_val_use;
+            If(If): visit_if, visit_if_mut, fold_if;
+            BinOp(BinOp): visit_bin_op, visit_bin_op_mut, fold_bin_op;
+            And(And): visit_and, visit_and_mut, fold_and;
+            Or(Or): visit_or, visit_or_mut, fold_or;
+            Xor(Xor): visit_xor, visit_xor_mut, fold_xor;
+            Atleast(Atleast): visit_atleast, visit_atleast_mut, fold_atleast;
+            LogicalNot(LogicalNot): visit_logical_not, visit_logical_not_mut, fold_logical_not;
+            Negation(Negation): visit_negation, visit_negation_mut, fold_negation;
+            BitInversion(BitInversion):
+                visit_bit_inversion, visit_bit_inversion_mut, fold_bit_inversion;
+            OptionGet(OptionGet): visit_option_get, visit_option_get_mut, fold_option_get;
+            OptionIsDefined(OptionIsDefined):
+                visit_option_is_defined, visit_option_is_defined_mut, fold_option_is_defined;
+            OptionGetOrElse(OptionGetOrElse):
+                visit_option_get_or_else, visit_option_get_or_else_mut, fold_option_get_or_else;
+            ExtractAmount(ExtractAmount):
+                visit_extract_amount, visit_extract_amount_mut, fold_extract_amount;
+            ExtractRegisterAs(ExtractRegisterAs):
+                visit_extract_register_as, visit_extract_register_as_mut, fold_extract_register_as;
+            ExtractBytes(ExtractBytes):
+                visit_extract_bytes, visit_extract_bytes_mut, fold_extract_bytes;
+            ExtractBytesWithNoRef(ExtractBytesWithNoRef):
+                visit_extract_bytes_with_no_ref,
+                visit_extract_bytes_with_no_ref_mut,
+                fold_extract_bytes_with_no_ref;
+            ExtractScriptBytes(ExtractScriptBytes):
+                visit_extract_script_bytes,
+                visit_extract_script_bytes_mut,
+                fold_extract_script_bytes;
+            ExtractCreationInfo(ExtractCreationInfo):
+                visit_extract_creation_info,
+                visit_extract_creation_info_mut,
+                fold_extract_creation_info;
+            ExtractId(ExtractId): visit_extract_id, visit_extract_id_mut, fold_extract_id;
+            ByIndex(ByIndex): visit_by_index, visit_by_index_mut, fold_by_index;
+            SizeOf(SizeOf): visit_size_of, visit_size_of_mut, fold_size_of;
+            Slice(Slice): visit_slice, visit_slice_mut, fold_slice;
+            Fold(Fold): visit_fold, visit_fold_mut, fold_fold;
+            Map(Map): visit_map, visit_map_mut, fold_map;
+            Filter(Filter): visit_filter, visit_filter_mut, fold_filter;
+            Exists(Exists): visit_exists, visit_exists_mut, fold_exists;
+            ForAll(ForAll): visit_for_all, visit_for_all_mut, fold_for_all;
+            SelectField(SelectField): visit_select_field, visit_select_field_mut, fold_select_field;
+            BoolToSigmaProp(BoolToSigmaProp):
+                visit_bool_to_sigma_prop, visit_bool_to_sigma_prop_mut, fold_bool_to_sigma_prop;
+            Upcast(Upcast): visit_upcast, visit_upcast_mut, fold_upcast;
+            Downcast(Downcast): visit_downcast, visit_downcast_mut, fold_downcast;
+            CreateProveDlog(CreateProveDlog):
+                visit_create_prove_dlog, visit_create_prove_dlog_mut, fold_create_prove_dlog;
+            CreateProveDhTuple(CreateProveDhTuple):
+                visit_create_prove_dh_tuple,
+                visit_create_prove_dh_tuple_mut,
+                fold_create_prove_dh_tuple;
+            SigmaPropBytes(SigmaPropBytes):
+                visit_sigma_prop_bytes, visit_sigma_prop_bytes_mut, fold_sigma_prop_bytes;
+            DecodePoint(DecodePoint): visit_decode_point, visit_decode_point_mut, fold_decode_point;
+            SigmaAnd(SigmaAnd): visit_sigma_and, visit_sigma_and_mut, fold_sigma_and;
+            SigmaOr(SigmaOr): visit_sigma_or, visit_sigma_or_mut, fold_sigma_or;
+            GetVar(GetVar): visit_get_var, visit_get_var_mut, fold_get_var;
+            DeserializeRegister(DeserializeRegister):
+                visit_deserialize_register,
+                visit_deserialize_register_mut,
+                fold_deserialize_register;
+            DeserializeContext(DeserializeContext):
+                visit_deserialize_context,
+                visit_deserialize_context_mut,
+                fold_deserialize_context;
+            MultiplyGroup(MultiplyGroup):
+                visit_multiply_group, visit_multiply_group_mut, fold_multiply_group;
+            Exponentiate(Exponentiate):
+                visit_exponentiate, visit_exponentiate_mut, fold_exponentiate;
+            XorOf(XorOf): visit_xor_of, visit_xor_of_mut, fold_xor_of;
+            TreeLookup(TreeLookup): visit_tree_lookup, visit_tree_lookup_mut, fold_tree_lookup;
+            CreateAvlTree(CreateAvlTree):
+                visit_create_avl_tree, visit_create_avl_tree_mut, fold_create_avl_tree;
+        }
+    };
+}
+
+macro_rules! visitor_hooks {
+    ($($variant:ident($node:ty): $visit:ident, $visit_mut:ident, $fold:ident;)*) => {
+        $(
+            #[doc = concat!(
+                "Called by [`walk_expr`] for [`Expr::", stringify!($variant),
+                "`] before its children are visited"
+            )]
+            fn $visit(&mut self, _node: &'a $node) {}
+        )*
+    };
+}
+
+macro_rules! mut_visitor_hooks {
+    ($($variant:ident($node:ty): $visit:ident, $visit_mut:ident, $fold:ident;)*) => {
+        $(
+            #[doc = concat!(
+                "Called by [`walk_expr_mut`] for [`Expr::", stringify!($variant),
+                "`] before its children are visited. The node must keep its type"
+            )]
+            fn $visit_mut(&mut self, _node: &mut $node) {}
+        )*
+    };
+}
+
+macro_rules! folder_hooks {
+    ($($variant:ident($node:ty): $visit:ident, $visit_mut:ident, $fold:ident;)*) => {
+        $(
+            #[doc = concat!(
+                "Called by [`fold_children`] for [`Expr::", stringify!($variant),
+                "`] after its children are folded, returns the replacement for it (of the same type)"
+            )]
+            fn $fold(&mut self, node: $node) -> Expr {
+                Expr::$variant(node)
+            }
+        )*
+    };
+}
+
+macro_rules! node_hook_dispatch {
+    ($($variant:ident($node:ty): $visit:ident, $visit_mut:ident, $fold:ident;)*) => {
+        fn visit_node<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, expr: &'a Expr) {
+            match expr {
+                $(Expr::$variant(node) => visitor.$visit(node),)*
+                Expr::Context | Expr::Global => (),
+            }
+        }
+
+        fn visit_node_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+            match expr {
+                $(Expr::$variant(node) => visitor.$visit_mut(node),)*
+                Expr::Context | Expr::Global => (),
+            }
+        }
+
+        fn fold_node<F: Folder + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+            match expr {
+                $(Expr::$variant(node) => folder.$fold(node),)*
+                expr @ (Expr::Context | Expr::Global) => expr,
+            }
+        }
+    };
+}
+
+with_expr_nodes!(node_hook_dispatch);
+
+/// Traversal of the expression tree by reference
+pub trait Visitor<'a> {
+    /// Called for every expression in the tree, parents first. The default implementation visits
+    /// the children (see [`walk_expr`])
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        walk_expr(self, expr)
+    }
+
+    with_expr_nodes!(visitor_hooks);
+}
+
+/// Calls the node hook of `expr` (e.g. [`Visitor::visit_bin_op`]) and visits its child
+/// expressions
+pub fn walk_expr<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, expr: &'a Expr) {
+    visit_node(visitor, expr);
+    for child in children(expr) {
+        visitor.visit_expr(child);
+    }
+}
+
+/// Traversal of the expression tree with in-place modification of the nodes
+pub trait MutVisitor {
+    /// Called for every expression in the tree, parents first. The default implementation visits
+    /// the children (see [`walk_expr_mut`]). A replaced node must keep its type ([`Expr::tpe`])
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    with_expr_nodes!(mut_visitor_hooks);
+}
+
+/// Calls the node hook of `expr` (e.g. [`MutVisitor::visit_bin_op_mut`]) and visits its child
+/// expressions
+pub fn walk_expr_mut<V: MutVisitor + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    visit_node_mut(visitor, expr);
+    for child in children_mut(expr) {
+        visitor.visit_expr_mut(child);
+    }
+}
+
+/// Rebuilding of the expression tree
+pub trait Folder {
+    /// Called for every expression in the tree, returns the replacement for it (of the same type,
+    /// see [`Expr::tpe`]). The default implementation folds the children (see [`fold_children`])
+    /// and keeps the node itself
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_children(self, expr)
+    }
+
+    with_expr_nodes!(folder_hooks);
+}
+
+/// Replaces every child expression of `expr` with the result of its folding, then passes the
+/// node to its hook (e.g. [`Folder::fold_bin_op`])
+pub fn fold_children<F: Folder + ?Sized>(folder: &mut F, mut expr: Expr) -> Expr {
+    for child in children_mut(&mut expr) {
+        // a placeholder while the child is moved out
+        let c = std::mem::replace(child, Expr::Global);
+        *child = folder.fold_expr(c);
+    }
+    fold_node(folder, expr)
+}
+
+/// Mutable references to the child expressions (operands) of the expression, in the order of
+/// their serialization
+pub fn children_mut(expr: &mut Expr) -> Vec<&mut Expr> {
+    children_mut_with_evaluation(expr)
+        .into_iter()
+        .map(|(child, _)| child)
+        .collect()
+}
+
+/// When the child expression is evaluated
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub(crate) enum Evaluation {
+    /// Every time the parent expression is evaluated
+    Always,
+    /// Maybe, depending on the values (branches of `if`, right operand of `&&` and `||`,
+    /// default values, function bodies)
+    Maybe,
+}
+
+/// The operands of every kind of node with their [`Evaluation`] (in the order of their
+/// serialization), expanded for shared (`iter`, `as_deref`, `body`) and mutable (`iter_mut`,
+/// `as_deref_mut`, `body_mut`, `mut`) references. The only list of the operands, so that the
+/// shared and mutable traversals cannot diverge.
+macro_rules! children_with_evaluation {
+    ($expr:expr, $iter:ident, $as_deref:ident, $body:ident $(, $mut:tt)?) => {{
+        use Evaluation::*;
+        match $expr {
+            Expr::Const(_)
+            | Expr::ConstPlaceholder(_)
+            | Expr::Context
+            | Expr::Global
+            | Expr::GlobalVars(_)
+            | Expr::ValUse(_)
+            | Expr::GetVar(_)
+            | Expr::DeserializeContext(_) => vec![],
+            Expr::Append(op) => vec![(&$($mut)? *op.input, Always), (&$($mut)? *op.col_2, Always)],
+            Expr::SubstConstants(op) => vec![
+                (&$($mut)? *op.script_bytes, Always),
+                (&$($mut)? *op.positions, Always),
+                (&$($mut)? *op.new_values, Always),
+            ],
+            Expr::ByteArrayToLong(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::ByteArrayToBigInt(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::LongToByteArray(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::Collection(Collection::BoolConstants(_)) => vec![],
+            Expr::Collection(Collection::Exprs { items, .. }) => {
+                items.$iter().map(|e| (e, Always)).collect()
+            }
+            Expr::Tuple(op) => op.items.$iter().map(|e| (e, Always)).collect(),
+            Expr::CalcBlake2b256(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::CalcSha256(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::FuncValue(op) => vec![(op.$body(), Maybe)],
+            Expr::Apply(op) => std::iter::once((&$($mut)? *op.func, Always))
+                .chain(op.args.$iter().map(|e| (e, Always)))
+                .collect(),
+            Expr::MethodCall(op) => std::iter::once((&$($mut)? *op.obj, Always))
+                .chain(op.args.$iter().map(|e| (e, Always)))
+                .collect(),
+            Expr::ProperyCall(op) => vec![(&$($mut)? *op.obj, Always)],
+            Expr::BlockValue(op) => op
+                .items
+                .$iter()
+                .chain(std::iter::once(&$($mut)? *op.result))
+                .map(|e| (e, Always))
+                .collect(),
+            Expr::ValDef(op) => vec![(&$($mut)? *op.rhs, Always)],
+            Expr::If(op) => vec![
+                (&$($mut)? *op.condition, Always),
+                (&$($mut)? *op.true_branch, Maybe),
+                (&$($mut)? *op.false_branch, Maybe),
+            ],
+            Expr::BinOp(op) => {
+                let right = match op.kind {
+                    BinOpKind::Logical(LogicalOp::And) | BinOpKind::Logical(LogicalOp::Or) => Maybe,
+                    _ => Always,
+                };
+                vec![(&$($mut)? *op.left, Always), (&$($mut)? *op.right, right)]
+            }
+            Expr::And(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::Or(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::Xor(op) => vec![(&$($mut)? *op.left, Always), (&$($mut)? *op.right, Always)],
+            Expr::Atleast(op) => vec![(&$($mut)? *op.bound, Always), (&$($mut)? *op.input, Always)],
+            Expr::LogicalNot(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::Negation(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::BitInversion(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::OptionGet(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::OptionIsDefined(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::OptionGetOrElse(op) => vec![
+                (&$($mut)? *op.input, Always),
+                (&$($mut)? *op.default, Maybe),
+            ],
+            Expr::ExtractAmount(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::ExtractRegisterAs(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::ExtractBytes(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::ExtractBytesWithNoRef(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::ExtractScriptBytes(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::ExtractCreationInfo(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::ExtractId(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::ByIndex(ByIndex {
+                input,
+                index,
+                default,
+                ..
+            }) => {
+                let mut res = vec![(&$($mut)? **input, Always), (&$($mut)? **index, Always)];
+                res.extend(default.$as_deref().map(|e| (e, Maybe)));
+                res
+            }
+            Expr::SizeOf(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::Slice(op) => vec![
+                (&$($mut)? *op.input, Always),
+                (&$($mut)? *op.from, Always),
+                (&$($mut)? *op.until, Always),
+            ],
+            Expr::Fold(op) => vec![
+                (&$($mut)? *op.input, Always),
+                (&$($mut)? *op.zero, Always),
+                (&$($mut)? *op.fold_op, Always),
+            ],
+            Expr::Map(op) => vec![(&$($mut)? *op.input, Always), (&$($mut)? *op.mapper, Always)],
+            Expr::Filter(op) => vec![
+                (&$($mut)? *op.input, Always),
+                (&$($mut)? *op.condition, Always),
+            ],
+            Expr::Exists(op) => vec![
+                (&$($mut)? *op.input, Always),
+                (&$($mut)? *op.condition, Always),
+            ],
+            Expr::ForAll(op) => vec![
+                (&$($mut)? *op.input, Always),
+                (&$($mut)? *op.condition, Always),
+            ],
+            Expr::SelectField(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::BoolToSigmaProp(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::Upcast(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::Downcast(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::CreateProveDlog(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::CreateProveDhTuple(op) => vec![
+                (&$($mut)? *op.g, Always),
+                (&$($mut)? *op.h, Always),
+                (&$($mut)? *op.u, Always),
+                (&$($mut)? *op.v, Always),
+            ],
+            Expr::SigmaPropBytes(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::DecodePoint(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::SigmaAnd(op) => op.items.$iter().map(|e| (e, Always)).collect(),
+            Expr::SigmaOr(op) => op.items.$iter().map(|e| (e, Always)).collect(),
+            Expr::DeserializeRegister(op) => op
+                .default
+                .$as_deref()
+                .map(|e| (e, Maybe))
+                .into_iter()
+                .collect(),
+            Expr::MultiplyGroup(op) => vec![
+                (&$($mut)? *op.left, Always),
+                (&$($mut)? *op.right, Always),
+            ],
+            Expr::Exponentiate(op) => vec![
+                (&$($mut)? *op.left, Always),
+                (&$($mut)? *op.right, Always),
+            ],
+            Expr::XorOf(op) => vec![(&$($mut)? *op.input, Always)],
+            Expr::TreeLookup(op) => vec![
+                (&$($mut)? *op.tree, Always),
+                (&$($mut)? *op.key, Always),
+                (&$($mut)? *op.proof, Always),
+            ],
+            Expr::CreateAvlTree(op) => {
+                let mut res = vec![
+                    (&$($mut)? *op.flags, Always),
+                    (&$($mut)? *op.digest, Always),
+                    (&$($mut)? *op.key_length, Always),
+                ];
+                res.extend(op.value_length.$as_deref().map(|e| (e, Always)));
+                res
+            }
+        }
+    }};
+}
+
+/// Child expressions (operands) of the expression, in the order of their serialization
+pub fn children(expr: &Expr) -> Vec<&Expr> {
+    children_with_evaluation!(expr, iter, as_deref, body)
+        .into_iter()
+        .map(|(child, _)| child)
+        .collect()
+}
+
+/// Child expressions of the given expression and when they are evaluated
+pub(crate) fn children_mut_with_evaluation(expr: &mut Expr) -> Vec<(&mut Expr, Evaluation)> {
+    children_with_evaluation!(expr, iter_mut, as_deref_mut, body_mut, mut)
+}
+
+/// Calls `f` on the expression and all its subexpressions (parents first)
+pub fn for_each<'a, F: FnMut(&'a Expr)>(expr: &'a Expr, f: F) {
+    struct ForEach<F>(F);
+    impl<'a, F: FnMut(&'a Expr)> Visitor<'a> for ForEach<F> {
+        fn visit_expr(&mut self, expr: &'a Expr) {
+            (self.0)(expr);
+            walk_expr(self, expr)
+        }
+    }
+    ForEach(f).visit_expr(expr)
+}
+
+/// Rewrites the expression bottom-up (children first) with `f`, which must keep the type of the
+/// expression (see [`Folder`])
+pub fn rewrite_bottom_up<F: FnMut(Expr) -> Expr>(expr: Expr, f: F) -> Expr {
+    struct BottomUp<F>(F);
+    impl<F: FnMut(Expr) -> Expr> Folder for BottomUp<F> {
+        fn fold_expr(&mut self, expr: Expr) -> Expr {
+            let expr = fold_children(self, expr);
+            (self.0)(expr)
+        }
+    }
+    BottomUp(f).fold_expr(expr)
+}
+
+/// Expressions in the tree (parents first) for which `predicate` returns true
+pub fn find_all<P: Fn(&Expr) -> bool>(expr: &Expr, predicate: P) -> Vec<&Expr> {
+    let mut res = Vec::new();
+    for_each(expr, |e| {
+        if predicate(e) {
+            res.push(e)
+        }
+    });
+    res
+}
+
+/// Constants ([`Expr::Const`]) in the tree, in the order they are encountered.
+/// Segregated constants (referenced with [`Expr::ConstPlaceholder`]) are stored in
+/// [`crate::ergo_tree::ErgoTree`] and not included.
+pub fn collect_constants(expr: &Expr) -> Vec<&Constant> {
+    let mut res = Vec::new();
+    for_each(expr, |e| {
+        if let Expr::Const(c) = e {
+            res.push(c)
+        }
+    });
+    res
+}
+
+/// Method calls in the tree (parents first)
+pub fn find_method_calls(expr: &Expr) -> Vec<&MethodCall> {
+    let mut res = Vec::new();
+    for_each(expr, |e| {
+        if let Expr::MethodCall(mc) = e {
+            res.push(mc)
+        }
+    });
+    res
+}
+
+/// Property calls (methods without arguments) in the tree (parents first)
+pub fn find_property_calls(expr: &Expr) -> Vec<&PropertyCall> {
+    let mut res = Vec::new();
+    for_each(expr, |e| {
+        if let Expr::ProperyCall(pc) = e {
+            res.push(pc)
+        }
+    });
+    res
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::mir::bin_op::ArithOp;
+    use crate::mir::bin_op::BinOp;
+    use crate::mir::bin_op::RelationOp;
+    use crate::mir::global_vars::GlobalVars;
+    use crate::types::scoll;
+    use crate::types::scontext;
+    use crate::types::stype::SType;
+    use crate::types::stype_param::STypeVar;
+
+    fn bin_op(kind: impl Into<BinOpKind>, left: Expr, right: Expr) -> Expr {
+        BinOp {
+            kind: kind.into(),
+            left: left.into(),
+            right: right.into(),
+        }
+        .into()
+    }
+
+    fn index_of() -> Expr {
+        MethodCall::new(
+            vec![1i64, 2i64].into(),
+            scoll::INDEX_OF_METHOD
+                .clone()
+                .with_concrete_types(&[(STypeVar::t(), SType::SLong)].iter().cloned().collect()),
+            vec![2i64.into(), 0i32.into()],
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn collects_constants_and_method_calls() {
+        let data_inputs: Expr =
+            PropertyCall::new(Expr::Context, scontext::DATA_INPUTS_PROPERTY.clone())
+                .unwrap()
+                .into();
+        let e = bin_op(
+            LogicalOp::And,
+            bin_op(RelationOp::Gt, index_of(), 1i32.into()),
+            bin_op(RelationOp::Eq, GlobalVars::Height.into(), 100i32.into()),
+        );
+        let constants: Vec<Constant> = collect_constants(&e).into_iter().cloned().collect();
+        assert_eq!(
+            constants,
+            vec![
+                vec![1i64, 2i64].into(),
+                2i64.into(),
+                0i32.into(),
+                1i32.into(),
+                100i32.into()
+            ]
+        );
+        let method_calls = find_method_calls(&e);
+        assert_eq!(method_calls.len(), 1);
+        assert_eq!(method_calls[0].method.name(), "indexOf");
+        assert!(find_property_calls(&e).is_empty());
+        assert_eq!(find_property_calls(&data_inputs).len(), 1);
+        assert_eq!(
+            find_all(&e, |e| matches!(e, Expr::GlobalVars(_))),
+            vec![&Expr::GlobalVars(GlobalVars::Height)]
+        );
+    }
+
+    #[test]
+    fn mut_visitor_and_folder() {
+        struct IncInts;
+        impl MutVisitor for IncInts {
+            fn visit_expr_mut(&mut self, expr: &mut Expr) {
+                if let Expr::Const(Constant {
+                    v: crate::mir::constant::Literal::Int(v),
+                    ..
+                }) = expr
+                {
+                    *v += 1;
+                }
+                walk_expr_mut(self, expr)
+            }
+        }
+        struct HeightToConst;
+        impl Folder for HeightToConst {
+            fn fold_expr(&mut self, expr: Expr) -> Expr {
+                match expr {
+                    Expr::GlobalVars(GlobalVars::Height) => 10i32.into(),
+                    e => fold_children(self, e),
+                }
+            }
+        }
+        let mut e = bin_op(
+            ArithOp::Plus,
+            GlobalVars::Height.into(),
+            bin_op(ArithOp::Multiply, 1i32.into(), GlobalVars::Height.into()),
+        );
+        IncInts.visit_expr_mut(&mut e);
+        assert_eq!(
+            HeightToConst.fold_expr(e),
+            bin_op(
+                ArithOp::Plus,
+                10i32.into(),
+                bin_op(ArithOp::Multiply, 2i32.into(), 10i32.into()),
+            )
+        );
+    }
+
+    #[test]
+    fn node_hooks() {
+        struct BinOpCounter(usize);
+        impl<'a> Visitor<'a> for BinOpCounter {
+            fn visit_bin_op(&mut self, _node: &'a BinOp) {
+                self.0 += 1;
+            }
+        }
+        struct IncInts;
+        impl MutVisitor for IncInts {
+            fn visit_const_mut(&mut self, node: &mut Constant) {
+                if let crate::mir::constant::Literal::Int(v) = &mut node.v {
+                    *v += 1;
+                }
+            }
+        }
+        struct HeightToConst;
+        impl Folder for HeightToConst {
+            fn fold_global_vars(&mut self, node: GlobalVars) -> Expr {
+                match node {
+                    GlobalVars::Height => 10i32.into(),
+                    node => node.into(),
+                }
+            }
+        }
+        let mut e = bin_op(
+            ArithOp::Plus,
+            GlobalVars::Height.into(),
+            bin_op(ArithOp::Multiply, 1i32.into(), GlobalVars::Height.into()),
+        );
+        let mut counter = BinOpCounter(0);
+        counter.visit_expr(&e);
+        assert_eq!(counter.0, 2);
+        IncInts.visit_expr_mut(&mut e);
+        assert_eq!(
+            HeightToConst.fold_expr(e),
+            bin_op(
+                ArithOp::Plus,
+                10i32.into(),
+                bin_op(ArithOp::Multiply, 2i32.into(), 10i32.into()),
+            )
+        );
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[allow(clippy::panic)]
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+
+            #![proptest_config(ProptestConfig::with_cases(64))]
+
+            #[test]
+            fn children_and_children_mut_agree(e in any::<Expr>()) {
+                let mut e_mut = e.clone();
+                let expected: Vec<Expr> = children(&e).into_iter().cloned().collect();
+                let actual: Vec<Expr> = children_mut(&mut e_mut).into_iter().map(|c| c.clone()).collect();
+                prop_assert_eq![actual, expected];
+            }
+
+            #[test]
+            fn default_traversals_keep_expr(e in any::<Expr>()) {
+                struct Noop;
+                impl MutVisitor for Noop {}
+                impl Folder for Noop {}
+                let mut e_mut = e.clone();
+                Noop.visit_expr_mut(&mut e_mut);
+                prop_assert_eq![&e_mut, &e];
+                prop_assert_eq![Noop.fold_expr(e.clone()), e];
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Optimization passes over MIR ([`Expr`]).
+//!
+//! Every pass preserves the type of the expression and the result of its evaluation, with one
+//! exception: an unused [`ValDef`] is removed even if its evaluation would fail (same as in the
+//! reference implementation).
+
+mod cse;
+mod dead_val_defs;
+mod fold;
+
+use std::collections::HashSet;
+
+use crate::mir::expr::Expr;
+use crate::mir::val_def::ValDef;
+use crate::mir::val_def::ValId;
+use crate::mir::visitor::for_each;
+
+pub use cse::share_common_subexpressions;
+pub use dead_val_defs::remove_unused_val_defs;
+pub use fold::fold_constants;
+
+/// Runs all optimization passes: constant folding, removal of the unused [`ValDef`]s and
+/// sharing of the common subexpressions
+pub fn optimize(expr: Expr) -> Expr {
+    share_common_subexpressions(remove_unused_val_defs(fold_constants(expr)))
+}
+
+/// Ids of the values referenced with `ValUse` in the expression
+pub(crate) fn used_val_ids(expr: &Expr) -> HashSet<ValId> {
+    let mut ids = HashSet::new();
+    for_each(expr, |e| {
+        if let Expr::ValUse(val_use) = e {
+            ids.insert(val_use.val_id);
+        }
+    });
+    ids
+}
+
+/// Ids of the values defined in the expression (`ValDef` and function arguments)
+pub(crate) fn defined_val_ids(expr: &Expr) -> HashSet<ValId> {
+    let mut ids = HashSet::new();
+    for_each(expr, |e| match e {
+        Expr::ValDef(ValDef { id, .. }) => {
+            ids.insert(*id);
+        }
+        Expr::FuncValue(func) => ids.extend(func.args().iter().map(|arg| arg.idx)),
+        _ => (),
+    });
+    ids
+}
+
+#[cfg(test)]
+#[cfg(feature = "arbitrary")]
+#[allow(clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::serialization::sigma_serialize_roundtrip;
+    use proptest::prelude::*;
+
+    proptest! {
+
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn preserves_type(e in any::<Expr>()) {
+            let optimized = optimize(e.clone());
+            prop_assert_eq![optimized.tpe(), e.tpe()];
+            prop_assert_eq![sigma_serialize_roundtrip(&optimized), optimized];
+        }
+
+        #[test]
+        fn idempotent(e in any::<Expr>()) {
+            let optimized = optimize(e);
+            prop_assert_eq![optimize(optimized.clone()), optimized];
+        }
+    }
+}
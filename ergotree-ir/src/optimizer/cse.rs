@@ -0,0 +1,393 @@
+//! Sharing of the common subexpressions
+
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+
+use crate::mir::block::BlockValue;
+use crate::mir::expr::Expr;
+use crate::mir::val_def::ValDef;
+use crate::mir::val_def::ValId;
+use crate::mir::val_use::ValUse;
+use crate::mir::visitor::children_mut_with_evaluation;
+use crate::mir::visitor::Evaluation;
+use crate::serialization::SigmaSerializable;
+use crate::types::stype::SType;
+
+use super::defined_val_ids;
+use super::used_val_ids;
+
+/// Size of the block opcode and the number of items (less than 128)
+const BLOCK_OVERHEAD: usize = 2;
+
+/// Replaces the repeated subexpressions with [`ValUse`]s of the [`ValDef`]s added to the
+/// beginning of the scope, if it makes the serialized tree smaller.
+/// A scope is the whole expression or a subexpression that is not always evaluated (a branch
+/// of `if`, the right operand of `&&`/`||`, a default value or a function body), a
+/// subexpression is moved to the beginning of the scope only if it's evaluated every time the
+/// scope is evaluated. Subexpressions are never moved into or out of function bodies.
+pub fn share_common_subexpressions(mut expr: Expr) -> Expr {
+    let mut ids = defined_val_ids(&expr);
+    ids.extend(used_val_ids(&expr));
+    let mut next_id = ids.into_iter().map(|id| id.0 + 1).max().unwrap_or(0);
+    share_in_scope(&mut expr, &mut next_id);
+    expr
+}
+
+struct Candidate {
+    expr: Expr,
+    /// Serialized size
+    size: usize,
+    /// Number of occurrences
+    count: usize,
+    /// At least one occurrence is evaluated every time the scope is evaluated
+    always_evaluated: bool,
+}
+
+fn share_in_scope(scope: &mut Expr, next_id: &mut u32) {
+    let mut bound_in_scope = defined_val_ids(scope);
+    if let Expr::BlockValue(block) = scope {
+        // values defined in the block itself can be used in the shared subexpressions
+        for item in block.items.iter() {
+            if let Expr::ValDef(val_def) = item {
+                bound_in_scope.remove(&val_def.id);
+            }
+        }
+    }
+    let mut defs: Vec<ValDef> = Vec::new();
+    while let Some(shared) = most_profitable(scope, &mut defs, &bound_in_scope, *next_id) {
+        let val_use: Expr = ValUse {
+            val_id: ValId(*next_id),
+            tpe: shared.tpe(),
+        }
+        .into();
+        replace(scope, &shared, &val_use);
+        for def in defs.iter_mut() {
+            replace(&mut def.rhs, &shared, &val_use);
+        }
+        defs.push(ValDef {
+            id: ValId(*next_id),
+            rhs: shared.into(),
+        });
+        *next_id += 1;
+    }
+    if !defs.is_empty() {
+        let defs = defs.into_iter().map(Expr::from);
+        match scope {
+            Expr::BlockValue(block) => {
+                let items = defs.chain(block.items.drain(..)).collect();
+                block.items = sort_by_dependencies(items);
+            }
+            _ => {
+                // a placeholder while the expression is moved out
+                let result = std::mem::replace(scope, Expr::Global);
+                *scope = BlockValue {
+                    items: sort_by_dependencies(defs.collect()),
+                    result: result.into(),
+                }
+                .into();
+            }
+        }
+    }
+    share_in_nested_scopes(scope, next_id);
+}
+
+/// Every block is a scope (with its own values), as well as every subexpression that is not
+/// always evaluated
+fn share_in_nested_scopes(expr: &mut Expr, next_id: &mut u32) {
+    for (child, evaluation) in children_mut_with_evaluation(expr) {
+        if evaluation == Evaluation::Maybe || matches!(child, Expr::BlockValue(_)) {
+            share_in_scope(child, next_id);
+        } else {
+            share_in_nested_scopes(child, next_id);
+        }
+    }
+}
+
+/// The largest subexpression worth sharing (the one that saves the most bytes on ties)
+fn most_profitable(
+    scope: &mut Expr,
+    defs: &mut [ValDef],
+    bound_in_scope: &HashSet<ValId>,
+    next_id: u32,
+) -> Option<Expr> {
+    let mut candidates = IndexMap::new();
+    for def in defs.iter_mut() {
+        collect(&mut def.rhs, true, &mut candidates);
+    }
+    collect(scope, true, &mut candidates);
+    // the type is not serialized
+    let use_size = Expr::from(ValUse {
+        val_id: ValId(next_id),
+        tpe: SType::SAny,
+    })
+    .sigma_serialize_bytes()
+    .map(|bytes| bytes.len())
+    .ok()?;
+    let block_overhead = if defs.is_empty() && !matches!(scope, Expr::BlockValue(_)) {
+        BLOCK_OVERHEAD
+    } else {
+        0
+    };
+    candidates
+        .into_iter()
+        .map(|(_, c)| c)
+        .filter(|c| {
+            let def_size = use_size + c.size;
+            c.always_evaluated
+                && c.count > 1
+                && c.count * c.size > def_size + c.count * use_size + block_overhead
+        })
+        .filter_map(|c| {
+            let mut free = used_val_ids(&c.expr);
+            for id in defined_val_ids(&c.expr) {
+                free.remove(&id);
+            }
+            free.is_disjoint(bound_in_scope).then_some(c)
+        })
+        .rev()
+        .max_by_key(|c| c.size)
+        .map(|c| c.expr)
+}
+
+/// Collects the subexpressions of the scope (without the function bodies) by their serialized
+/// bytes
+fn collect(expr: &mut Expr, always_evaluated: bool, candidates: &mut IndexMap<Vec<u8>, Candidate>) {
+    if is_shareable(expr) {
+        if let Ok(bytes) = expr.sigma_serialize_bytes() {
+            let c = candidates
+                .entry(bytes)
+                .or_insert_with_key(|bytes| Candidate {
+                    expr: expr.clone(),
+                    size: bytes.len(),
+                    count: 0,
+                    always_evaluated: false,
+                });
+            c.count += 1;
+            c.always_evaluated |= always_evaluated;
+        }
+    }
+    if matches!(expr, Expr::FuncValue(_)) {
+        return;
+    }
+    for (child, evaluation) in children_mut_with_evaluation(expr) {
+        collect(
+            child,
+            always_evaluated && evaluation == Evaluation::Always,
+            candidates,
+        );
+    }
+}
+
+fn is_shareable(expr: &Expr) -> bool {
+    !matches!(
+        expr,
+        Expr::ValUse(_)
+            | Expr::ValDef(_)
+            | Expr::FuncValue(_)
+            | Expr::DeserializeContext(_)
+            | Expr::DeserializeRegister(_)
+    )
+}
+
+/// Replaces every occurrence of `target` (except in the function bodies)
+fn replace(expr: &mut Expr, target: &Expr, with: &Expr) {
+    if expr == target {
+        *expr = with.clone();
+        return;
+    }
+    if matches!(expr, Expr::FuncValue(_)) {
+        return;
+    }
+    for (child, _) in children_mut_with_evaluation(expr) {
+        replace(child, target, with);
+    }
+}
+
+/// Orders the block items so that every definition comes after the definitions it uses
+/// (otherwise keeping the order)
+fn sort_by_dependencies(mut items: Vec<Expr>) -> Vec<Expr> {
+    let mut sorted = Vec::with_capacity(items.len());
+    let mut pending: HashSet<ValId> = items.iter().filter_map(val_def_id).collect();
+    while !items.is_empty() {
+        let ready = items
+            .iter()
+            .position(|item| used_val_ids(item).is_disjoint(&pending))
+            // dependencies between the definitions have no cycles
+            .unwrap_or(0);
+        let item = items.remove(ready);
+        if let Some(id) = val_def_id(&item) {
+            pending.remove(&id);
+        }
+        sorted.push(item);
+    }
+    sorted
+}
+
+fn val_def_id(expr: &Expr) -> Option<ValId> {
+    match expr {
+        Expr::ValDef(val_def) => Some(val_def.id),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+#[allow(clippy::panic)]
+mod tests {
+    use super::*;
+    use crate::mir::bin_op::ArithOp;
+    use crate::mir::bin_op::BinOp;
+    use crate::mir::bin_op::RelationOp;
+    use crate::mir::calc_blake2b256::CalcBlake2b256;
+    use crate::mir::coll_append::Append;
+    use crate::mir::func_value::FuncArg;
+    use crate::mir::func_value::FuncValue;
+    use crate::mir::global_vars::GlobalVars;
+    use crate::mir::if_op::If;
+
+    fn hash(input: Expr) -> Expr {
+        CalcBlake2b256 {
+            input: input.into(),
+        }
+        .into()
+    }
+
+    fn bin_op(kind: impl Into<crate::mir::bin_op::BinOpKind>, left: Expr, right: Expr) -> Expr {
+        BinOp {
+            kind: kind.into(),
+            left: left.into(),
+            right: right.into(),
+        }
+        .into()
+    }
+
+    fn val_use(id: u32, tpe: SType) -> Expr {
+        ValUse {
+            val_id: id.into(),
+            tpe,
+        }
+        .into()
+    }
+
+    fn bytes() -> Expr {
+        Expr::Const(vec![7u8; 32].into())
+    }
+
+    #[test]
+    fn shares_repeated() {
+        let e = bin_op(RelationOp::Eq, hash(bytes()), hash(bytes()));
+        let hash_tpe = hash(bytes()).tpe();
+        let expected: Expr = BlockValue {
+            items: vec![ValDef {
+                id: 0.into(),
+                rhs: hash(bytes()).into(),
+            }
+            .into()],
+            result: bin_op(
+                RelationOp::Eq,
+                val_use(0, hash_tpe.clone()),
+                val_use(0, hash_tpe),
+            )
+            .into(),
+        }
+        .into();
+        assert_eq!(share_common_subexpressions(e), expected);
+    }
+
+    #[test]
+    fn keeps_small() {
+        let height_plus_1 = bin_op(ArithOp::Plus, GlobalVars::Height.into(), 1i32.into());
+        let e = bin_op(RelationOp::Eq, height_plus_1.clone(), height_plus_1);
+        assert_eq!(share_common_subexpressions(e.clone()), e);
+    }
+
+    #[test]
+    fn keeps_maybe_evaluated_in_place() {
+        let e: Expr = If {
+            condition: Box::new(true.into()),
+            true_branch: bin_op(RelationOp::Eq, hash(bytes()), bytes()).into(),
+            false_branch: bin_op(RelationOp::NEq, hash(bytes()), bytes()).into(),
+        }
+        .into();
+        let shared = share_common_subexpressions(e.clone());
+        // one occurrence in each branch, the constant is shared in each branch
+        match shared {
+            Expr::If(If {
+                true_branch,
+                false_branch,
+                ..
+            }) => {
+                assert!(matches!(*true_branch, Expr::BlockValue(_)));
+                assert!(matches!(*false_branch, Expr::BlockValue(_)));
+            }
+            _ => panic!("unexpected: {:?}", shared),
+        }
+    }
+
+    #[test]
+    fn shares_maybe_evaluated_if_always_evaluated() {
+        let e: Expr = If {
+            condition: bin_op(RelationOp::Eq, hash(bytes()), bytes()).into(),
+            true_branch: Box::new(hash(bytes())),
+            false_branch: Box::new(bytes()),
+        }
+        .into();
+        let shared = share_common_subexpressions(e);
+        match shared {
+            Expr::BlockValue(BlockValue { items, result }) => {
+                assert_eq!(items.len(), 2);
+                assert!(matches!(*result, Expr::If(_)));
+            }
+            _ => panic!("unexpected: {:?}", shared),
+        }
+    }
+
+    #[test]
+    fn shares_in_function_body() {
+        let tpe = SType::SColl(SType::SByte.into());
+        let arg = val_use(1, tpe.clone());
+        let append: Expr = Append::new(hash(arg), bytes()).unwrap().into();
+        let body = bin_op(RelationOp::Eq, append.clone(), append);
+        let func: Expr = FuncValue::new(vec![FuncArg { idx: 1.into(), tpe }], body).into();
+        let shared = share_common_subexpressions(func);
+        let body = match &shared {
+            Expr::FuncValue(func) => func.body(),
+            _ => panic!("unexpected: {:?}", shared),
+        };
+        assert!(matches!(body, Expr::BlockValue(_)));
+    }
+
+    #[test]
+    fn shares_after_block_values() {
+        let tpe = SType::SColl(SType::SByte.into());
+        let local = val_use(1, tpe.clone());
+        let append: Expr = Append::new(hash(local), bytes()).unwrap().into();
+        let e: Expr = BlockValue {
+            items: vec![ValDef {
+                id: 1.into(),
+                rhs: hash(GlobalVars::MinerPubKey.into()).into(),
+            }
+            .into()],
+            result: bin_op(RelationOp::Eq, append.clone(), append.clone()).into(),
+        }
+        .into();
+        let expected: Expr = BlockValue {
+            items: vec![
+                ValDef {
+                    id: 1.into(),
+                    rhs: hash(GlobalVars::MinerPubKey.into()).into(),
+                }
+                .into(),
+                ValDef {
+                    id: 2.into(),
+                    rhs: append.into(),
+                }
+                .into(),
+            ],
+            result: bin_op(RelationOp::Eq, val_use(2, tpe.clone()), val_use(2, tpe)).into(),
+        }
+        .into();
+        assert_eq!(share_common_subexpressions(e), expected);
+    }
+}
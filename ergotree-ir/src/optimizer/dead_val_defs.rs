@@ -0,0 +1,141 @@
+//! Removal of the unused value definitions
+
+use crate::mir::block::BlockValue;
+use crate::mir::expr::Expr;
+use crate::mir::visitor::rewrite_bottom_up;
+
+use super::used_val_ids;
+
+/// Removes [`crate::mir::val_def::ValDef`]s that are not referenced in the rest of their block,
+/// and blocks left without definitions
+pub fn remove_unused_val_defs(expr: Expr) -> Expr {
+    rewrite_bottom_up(expr, |e| match e {
+        Expr::BlockValue(block) => remove_from_block(block),
+        e => e,
+    })
+}
+
+fn remove_from_block(block: BlockValue) -> Expr {
+    let BlockValue { items, result } = block;
+    let mut used = used_val_ids(&result);
+    let mut kept = Vec::with_capacity(items.len());
+    // a definition can only be referenced by the definitions after it
+    for item in items.into_iter().rev() {
+        let is_used = match &item {
+            Expr::ValDef(val_def) => used.contains(&val_def.id),
+            _ => true,
+        };
+        if is_used {
+            used.extend(used_val_ids(&item));
+            kept.push(item);
+        }
+    }
+    if kept.is_empty() {
+        *result
+    } else {
+        kept.reverse();
+        BlockValue {
+            items: kept,
+            result,
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mir::bin_op::ArithOp;
+    use crate::mir::bin_op::BinOp;
+    use crate::mir::func_value::FuncArg;
+    use crate::mir::func_value::FuncValue;
+    use crate::mir::global_vars::GlobalVars;
+    use crate::mir::val_def::ValDef;
+    use crate::mir::val_use::ValUse;
+    use crate::types::stype::SType;
+
+    fn val_def(id: u32, rhs: Expr) -> Expr {
+        ValDef {
+            id: id.into(),
+            rhs: rhs.into(),
+        }
+        .into()
+    }
+
+    fn val_use(id: u32) -> Expr {
+        ValUse {
+            val_id: id.into(),
+            tpe: SType::SInt,
+        }
+        .into()
+    }
+
+    fn plus(left: Expr, right: Expr) -> Expr {
+        BinOp {
+            kind: ArithOp::Plus.into(),
+            left: left.into(),
+            right: right.into(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn removes_unused() {
+        let block: Expr = BlockValue {
+            items: vec![
+                val_def(1, 1i32.into()),
+                val_def(2, 2i32.into()),
+                val_def(3, plus(val_use(1), 3i32.into())),
+            ],
+            result: val_use(1).into(),
+        }
+        .into();
+        let expected: Expr = BlockValue {
+            items: vec![val_def(1, 1i32.into())],
+            result: val_use(1).into(),
+        }
+        .into();
+        assert_eq!(remove_unused_val_defs(block), expected);
+    }
+
+    #[test]
+    fn keeps_transitively_used() {
+        let block: Expr = BlockValue {
+            items: vec![
+                val_def(1, 1i32.into()),
+                val_def(2, plus(val_use(1), 2i32.into())),
+            ],
+            result: val_use(2).into(),
+        }
+        .into();
+        assert_eq!(remove_unused_val_defs(block.clone()), block);
+    }
+
+    #[test]
+    fn keeps_used_in_function_body() {
+        let func: Expr = FuncValue::new(
+            vec![FuncArg {
+                idx: 2.into(),
+                tpe: SType::SInt,
+            }],
+            plus(val_use(1), val_use(2)),
+        )
+        .into();
+        let block: Expr = BlockValue {
+            items: vec![val_def(1, GlobalVars::Height.into())],
+            result: func.into(),
+        }
+        .into();
+        assert_eq!(remove_unused_val_defs(block.clone()), block);
+    }
+
+    #[test]
+    fn removes_empty_block() {
+        let block: Expr = BlockValue {
+            items: vec![val_def(1, 1i32.into())],
+            result: Box::new(2i32.into()),
+        }
+        .into();
+        assert_eq!(remove_unused_val_defs(block), 2i32.into());
+    }
+}
@@ -0,0 +1,327 @@
+//! Constant folding
+
+use std::ops::BitAnd;
+use std::ops::BitOr;
+use std::ops::BitXor;
+
+use num_traits::CheckedAdd;
+use num_traits::CheckedDiv;
+use num_traits::CheckedMul;
+use num_traits::CheckedNeg;
+use num_traits::CheckedRem;
+use num_traits::CheckedSub;
+
+use crate::mir::bin_op::ArithOp;
+use crate::mir::bin_op::BinOp;
+use crate::mir::bin_op::BinOpKind;
+use crate::mir::bin_op::BitOp;
+use crate::mir::bin_op::LogicalOp;
+use crate::mir::bin_op::RelationOp;
+use crate::mir::block::BlockValue;
+use crate::mir::collection::Collection;
+use crate::mir::constant::Constant;
+use crate::mir::constant::Literal;
+use crate::mir::expr::Expr;
+use crate::mir::if_op::If;
+use crate::mir::logical_not::LogicalNot;
+use crate::mir::negation::Negation;
+use crate::mir::visitor::rewrite_bottom_up;
+
+/// Evaluates the operations on constants (arithmetic, comparison, bitwise and boolean ops),
+/// `if` with a constant condition and `&&`/`||` with a constant operand.
+/// Operations that would fail on evaluation (overflow, division by zero) are left as is.
+pub fn fold_constants(expr: Expr) -> Expr {
+    rewrite_bottom_up(expr, fold)
+}
+
+fn fold(expr: Expr) -> Expr {
+    match expr {
+        Expr::BinOp(bin_op) => fold_bin_op(bin_op),
+        Expr::If(If {
+            condition,
+            true_branch,
+            false_branch,
+        }) => match bool_const(&condition) {
+            Some(true) => *true_branch,
+            Some(false) => *false_branch,
+            None => If {
+                condition,
+                true_branch,
+                false_branch,
+            }
+            .into(),
+        },
+        Expr::LogicalNot(LogicalNot { input }) => match *input {
+            Expr::Const(Constant {
+                v: Literal::Boolean(b),
+                ..
+            }) => (!b).into(),
+            Expr::LogicalNot(LogicalNot { input }) => *input,
+            input => LogicalNot {
+                input: input.into(),
+            }
+            .into(),
+        },
+        Expr::And(and) => match all_bool_consts(&and.input) {
+            Some(items) => items.iter().all(|b| *b).into(),
+            None => and.into(),
+        },
+        Expr::Or(or) => match all_bool_consts(&or.input) {
+            Some(items) => items.iter().any(|b| *b).into(),
+            None => or.into(),
+        },
+        Expr::Negation(Negation { input }) => match &*input {
+            Expr::Const(c) => match &c.v {
+                Literal::Byte(v) => checked_neg(v),
+                Literal::Short(v) => checked_neg(v),
+                Literal::Int(v) => checked_neg(v),
+                Literal::Long(v) => checked_neg(v),
+                Literal::BigInt(v) => checked_neg(v),
+                _ => None,
+            },
+            _ => None,
+        }
+        .map(Expr::Const)
+        .unwrap_or_else(|| Negation { input }.into()),
+        Expr::BlockValue(BlockValue { items, result }) if items.is_empty() => *result,
+        e => e,
+    }
+}
+
+fn fold_bin_op(bin_op: BinOp) -> Expr {
+    let folded = match (&*bin_op.left, &*bin_op.right) {
+        (Expr::Const(l), Expr::Const(r)) => match (&l.v, &r.v) {
+            (Literal::Boolean(l), Literal::Boolean(r)) => fold_bool(bin_op.kind, *l, *r),
+            (Literal::Byte(l), Literal::Byte(r)) => fold_numeric(bin_op.kind, *l, *r),
+            (Literal::Short(l), Literal::Short(r)) => fold_numeric(bin_op.kind, *l, *r),
+            (Literal::Int(l), Literal::Int(r)) => fold_numeric(bin_op.kind, *l, *r),
+            (Literal::Long(l), Literal::Long(r)) => fold_numeric(bin_op.kind, *l, *r),
+            (Literal::BigInt(l), Literal::BigInt(r)) => {
+                fold_numeric(bin_op.kind, l.clone(), r.clone())
+            }
+            _ => None,
+        }
+        .map(Expr::Const),
+        _ => None,
+    };
+    if let Some(folded) = folded {
+        return folded;
+    }
+    let left = bool_const(&bin_op.left);
+    let right = bool_const(&bin_op.right);
+    match (bin_op.kind, left, right) {
+        // the right operand is not evaluated
+        (BinOpKind::Logical(LogicalOp::And), Some(false), _) => false.into(),
+        (BinOpKind::Logical(LogicalOp::Or), Some(true), _) => true.into(),
+        (BinOpKind::Logical(LogicalOp::And), Some(true), _)
+        | (BinOpKind::Logical(LogicalOp::Or), Some(false), _)
+        | (BinOpKind::Logical(LogicalOp::Xor), Some(false), _) => *bin_op.right,
+        // the left operand is always evaluated, can be dropped only if it's the result
+        (BinOpKind::Logical(LogicalOp::And), _, Some(true))
+        | (BinOpKind::Logical(LogicalOp::Or), _, Some(false))
+        | (BinOpKind::Logical(LogicalOp::Xor), _, Some(false)) => *bin_op.left,
+        _ => bin_op.into(),
+    }
+}
+
+fn fold_bool(kind: BinOpKind, l: bool, r: bool) -> Option<Constant> {
+    match kind {
+        BinOpKind::Logical(LogicalOp::And) => Some((l && r).into()),
+        BinOpKind::Logical(LogicalOp::Or) => Some((l || r).into()),
+        BinOpKind::Logical(LogicalOp::Xor) => Some((l ^ r).into()),
+        BinOpKind::Relation(RelationOp::Eq) => Some((l == r).into()),
+        BinOpKind::Relation(RelationOp::NEq) => Some((l != r).into()),
+        _ => None,
+    }
+}
+
+fn fold_numeric<T>(kind: BinOpKind, l: T, r: T) -> Option<Constant>
+where
+    T: CheckedAdd
+        + CheckedSub
+        + CheckedMul
+        + CheckedDiv
+        + CheckedRem
+        + Ord
+        + BitAnd<Output = T>
+        + BitOr<Output = T>
+        + BitXor<Output = T>
+        + Into<Constant>,
+{
+    match kind {
+        BinOpKind::Arith(op) => match op {
+            ArithOp::Plus => l.checked_add(&r),
+            ArithOp::Minus => l.checked_sub(&r),
+            ArithOp::Multiply => l.checked_mul(&r),
+            ArithOp::Divide => l.checked_div(&r),
+            ArithOp::Modulo => l.checked_rem(&r),
+            ArithOp::Max => Some(l.max(r)),
+            ArithOp::Min => Some(l.min(r)),
+        }
+        .map(Into::into),
+        BinOpKind::Relation(op) => Some(
+            match op {
+                RelationOp::Eq => l == r,
+                RelationOp::NEq => l != r,
+                RelationOp::Ge => l >= r,
+                RelationOp::Gt => l > r,
+                RelationOp::Le => l <= r,
+                RelationOp::Lt => l < r,
+            }
+            .into(),
+        ),
+        BinOpKind::Bit(op) => Some(
+            match op {
+                BitOp::BitAnd => l & r,
+                BitOp::BitOr => l | r,
+                BitOp::BitXor => l ^ r,
+            }
+            .into(),
+        ),
+        BinOpKind::Logical(_) => None,
+    }
+}
+
+fn checked_neg<T: CheckedNeg + Into<Constant>>(v: &T) -> Option<Constant> {
+    v.checked_neg().map(Into::into)
+}
+
+fn bool_const(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::Const(Constant {
+            v: Literal::Boolean(b),
+            ..
+        }) => Some(*b),
+        _ => None,
+    }
+}
+
+/// Values of the collection if all its elements are boolean constants
+fn all_bool_consts(expr: &Expr) -> Option<Vec<bool>> {
+    match expr {
+        Expr::Collection(Collection::BoolConstants(items)) => Some(items.clone()),
+        Expr::Collection(Collection::Exprs { items, .. }) => items.iter().map(bool_const).collect(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::bigint256::BigInt256;
+    use crate::mir::and::And;
+    use crate::mir::global_vars::GlobalVars;
+
+    fn bin_op(kind: impl Into<BinOpKind>, left: Expr, right: Expr) -> Expr {
+        BinOp {
+            kind: kind.into(),
+            left: left.into(),
+            right: right.into(),
+        }
+        .into()
+    }
+
+    fn height_gt_0() -> Expr {
+        bin_op(RelationOp::Gt, GlobalVars::Height.into(), 0i32.into())
+    }
+
+    #[test]
+    fn arithmetic() {
+        let e = bin_op(
+            ArithOp::Plus,
+            bin_op(ArithOp::Multiply, 2i64.into(), 3i64.into()),
+            bin_op(ArithOp::Max, 4i64.into(), (-1i64).into()),
+        );
+        assert_eq!(fold_constants(e), 10i64.into());
+        let e = bin_op(
+            ArithOp::Minus,
+            BigInt256::from(5i64).into(),
+            BigInt256::from(7i64).into(),
+        );
+        assert_eq!(fold_constants(e), BigInt256::from(-2i64).into());
+        let e = bin_op(BitOp::BitXor, 6i8.into(), 3i8.into());
+        assert_eq!(fold_constants(e), 5i8.into());
+    }
+
+    #[test]
+    fn failing_arithmetic_is_kept() {
+        let overflow = bin_op(ArithOp::Plus, i32::MAX.into(), 1i32.into());
+        assert_eq!(fold_constants(overflow.clone()), overflow);
+        let div_by_zero = bin_op(ArithOp::Divide, 1i16.into(), 0i16.into());
+        assert_eq!(fold_constants(div_by_zero.clone()), div_by_zero);
+        let neg_overflow: Expr = Negation {
+            input: Box::new(i64::MIN.into()),
+        }
+        .into();
+        assert_eq!(fold_constants(neg_overflow.clone()), neg_overflow);
+    }
+
+    #[test]
+    fn relations() {
+        let e = bin_op(
+            LogicalOp::And,
+            bin_op(RelationOp::Lt, 1i32.into(), 2i32.into()),
+            bin_op(RelationOp::NEq, true.into(), false.into()),
+        );
+        assert_eq!(fold_constants(e), true.into());
+    }
+
+    #[test]
+    fn logical_with_one_constant() {
+        let e = bin_op(LogicalOp::And, false.into(), height_gt_0());
+        assert_eq!(fold_constants(e), false.into());
+        let e = bin_op(LogicalOp::Or, false.into(), height_gt_0());
+        assert_eq!(fold_constants(e), height_gt_0());
+        let e = bin_op(LogicalOp::And, height_gt_0(), true.into());
+        assert_eq!(fold_constants(e), height_gt_0());
+        // the left operand is evaluated (and might fail)
+        let e = bin_op(LogicalOp::And, height_gt_0(), false.into());
+        assert_eq!(fold_constants(e.clone()), e);
+    }
+
+    #[test]
+    fn if_and_not() {
+        let e: Expr = If {
+            condition: Box::new(
+                LogicalNot {
+                    input: Box::new(bin_op(RelationOp::Ge, 1i32.into(), 2i32.into())),
+                }
+                .into(),
+            ),
+            true_branch: Box::new(GlobalVars::Height.into()),
+            false_branch: Box::new(0i32.into()),
+        }
+        .into();
+        assert_eq!(fold_constants(e), GlobalVars::Height.into());
+        let not_not: Expr = LogicalNot {
+            input: Box::new(
+                LogicalNot {
+                    input: Box::new(height_gt_0()),
+                }
+                .into(),
+            ),
+        }
+        .into();
+        assert_eq!(fold_constants(not_not), height_gt_0());
+    }
+
+    #[test]
+    fn and_of_constants() {
+        let e: Expr = And {
+            input: Box::new(
+                Collection::new(
+                    crate::types::stype::SType::SBoolean,
+                    vec![
+                        true.into(),
+                        bin_op(RelationOp::Eq, 1i32.into(), 1i32.into()),
+                    ],
+                )
+                .unwrap()
+                .into(),
+            ),
+        }
+        .into();
+        assert_eq!(fold_constants(e), true.into());
+    }
+}
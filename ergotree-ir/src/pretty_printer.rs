@@ -0,0 +1,821 @@
+//! Printing of MIR ([`Expr`]) as ErgoScript source code (decompiler).
+//!
+//! Bound values get names in the order of their definition in the printed source: `v1`, `v2`,
+//! ... for values, `f1`, `f2`, ... for functions and `x1`, `x2`, ... for function arguments, so
+//! the same tree is always printed the same way regardless of the ids of the values in it.
+//! References to values that are not defined in the printed expression are printed as
+//! `unbound<id>`.
+
+use std::collections::HashMap;
+
+use crate::mir::bin_op::ArithOp;
+use crate::mir::bin_op::BinOp;
+use crate::mir::bin_op::BinOpKind;
+use crate::mir::bin_op::BitOp;
+use crate::mir::bin_op::LogicalOp;
+use crate::mir::bin_op::RelationOp;
+use crate::mir::block::BlockValue;
+use crate::mir::collection::Collection;
+use crate::mir::constant::Constant;
+use crate::mir::constant::Literal;
+use crate::mir::expr::Expr;
+use crate::mir::func_value::FuncArg;
+use crate::mir::func_value::FuncValue;
+use crate::mir::global_vars::GlobalVars;
+use crate::mir::val_def::ValDef;
+use crate::mir::val_def::ValId;
+use crate::mir::value::CollKind;
+use crate::mir::value::NativeColl;
+use crate::serialization::SigmaSerializable;
+use crate::sigma_protocol::dlog_group::EcPoint;
+use crate::sigma_protocol::sigma_boolean::SigmaBoolean;
+use crate::sigma_protocol::sigma_boolean::SigmaConjecture;
+use crate::sigma_protocol::sigma_boolean::SigmaProofOfKnowledgeTree;
+use crate::types::stype::SType;
+
+/// Indentation of one level
+const INDENT: &str = "  ";
+
+/// Operator precedence (higher binds tighter), same as in Scala
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+enum Prec {
+    /// `if`, needs parentheses when used as an operand
+    Lowest,
+    /// `||`, `|`
+    Or,
+    /// `^`
+    Xor,
+    /// `&&`, `&`
+    And,
+    /// `==`, `!=`
+    Eq,
+    /// `<`, `>`, `<=`, `>=`
+    Rel,
+    /// `+`, `-`, `++`
+    Add,
+    /// `*`, `/`, `%`
+    Mul,
+    /// `!`, `-`, `~`
+    Prefix,
+    /// Literals, names, calls, field access, blocks
+    Atom,
+}
+
+/// Prints the expression, constant placeholders are printed as `placeholder[<type>](<index>)`
+pub fn print_expr(expr: &Expr) -> String {
+    Printer::new(None).print(expr, 0).0
+}
+
+/// Prints the expression, constant placeholders are printed as the constant values from
+/// `constants` (the constants of the [`crate::ergo_tree::ErgoTree`])
+pub fn print_expr_with_constants(expr: &Expr, constants: &[Constant]) -> String {
+    Printer::new(Some(constants)).print(expr, 0).0
+}
+
+/// Prints the constant value as ErgoScript expression
+pub fn print_constant(c: &Constant) -> String {
+    literal(&c.tpe, &c.v).0
+}
+
+/// Prints the type in ErgoScript syntax (`Coll[Byte]`, `Option[Int]`, `(Int, Long) => Long`,
+/// etc.)
+pub fn print_type(tpe: &SType) -> String {
+    match tpe {
+        SType::STypeVar(v) => v.as_string(),
+        SType::SAny => "Any".to_string(),
+        SType::SBoolean => "Boolean".to_string(),
+        SType::SByte => "Byte".to_string(),
+        SType::SShort => "Short".to_string(),
+        SType::SInt => "Int".to_string(),
+        SType::SLong => "Long".to_string(),
+        SType::SBigInt => "BigInt".to_string(),
+        SType::SGroupElement => "GroupElement".to_string(),
+        SType::SSigmaProp => "SigmaProp".to_string(),
+        SType::SBox => "Box".to_string(),
+        SType::SAvlTree => "AvlTree".to_string(),
+        SType::SOption(t) => format!("Option[{}]", print_type(t)),
+        SType::SColl(t) => format!("Coll[{}]", print_type(t)),
+        SType::STuple(t) => format!("({})", print_types(t.items.iter())),
+        SType::SFunc(f) => format!(
+            "({}) => {}",
+            print_types(f.t_dom.iter()),
+            print_type(&f.t_range)
+        ),
+        SType::SContext => "Context".to_string(),
+        SType::SHeader => "Header".to_string(),
+        SType::SPreHeader => "PreHeader".to_string(),
+        SType::SGlobal => "Global".to_string(),
+    }
+}
+
+fn print_types<'a>(types: impl Iterator<Item = &'a SType>) -> String {
+    types.map(print_type).collect::<Vec<String>>().join(", ")
+}
+
+struct Printer<'a> {
+    constants: Option<&'a [Constant]>,
+    names: HashMap<ValId, String>,
+    vals: usize,
+    funcs: usize,
+    args: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn new(constants: Option<&'a [Constant]>) -> Self {
+        Printer {
+            constants,
+            names: HashMap::new(),
+            vals: 0,
+            funcs: 0,
+            args: 0,
+        }
+    }
+
+    /// Printed expression and its precedence
+    fn print(&mut self, expr: &Expr, indent: usize) -> (String, Prec) {
+        match expr {
+            Expr::Const(c) => literal(&c.tpe, &c.v),
+            Expr::ConstPlaceholder(ph) => {
+                match self.constants.and_then(|cs| cs.get(ph.id as usize)) {
+                    Some(c) => literal(&c.tpe, &c.v),
+                    None => atom(format!("placeholder[{}]({})", print_type(&ph.tpe), ph.id)),
+                }
+            }
+            Expr::Context => atom("CONTEXT"),
+            Expr::Global => atom("Global"),
+            Expr::GlobalVars(v) => atom(match v {
+                GlobalVars::Inputs => "INPUTS",
+                GlobalVars::Outputs => "OUTPUTS",
+                GlobalVars::Height => "HEIGHT",
+                GlobalVars::SelfBox => "SELF",
+                GlobalVars::MinerPubKey => "minerPubKey",
+                GlobalVars::GroupGenerator => "groupGenerator",
+            }),
+            Expr::ValUse(v) => atom(self.name(v.val_id)),
+            Expr::BlockValue(BlockValue { items, result }) => {
+                let inner = INDENT.repeat(indent + 1);
+                let mut out = "{\n".to_string();
+                for item in items {
+                    out.push_str(&inner);
+                    out.push_str(&self.print(item, indent + 1).0);
+                    out.push('\n');
+                }
+                out.push_str(&inner);
+                out.push_str(&self.print(result, indent + 1).0);
+                out.push('\n');
+                out.push_str(&INDENT.repeat(indent));
+                out.push('}');
+                atom(out)
+            }
+            Expr::ValDef(ValDef { id, rhs }) => match &**rhs {
+                Expr::FuncValue(func) => {
+                    self.funcs += 1;
+                    let name = format!("f{}", self.funcs);
+                    self.names.insert(*id, name.clone());
+                    let params = self.params(func.args());
+                    let body = self.print(func.body(), indent).0;
+                    (format!("def {}({}) = {}", name, params, body), Prec::Lowest)
+                }
+                rhs => {
+                    let rhs = self.print(rhs, indent).0;
+                    self.vals += 1;
+                    let name = format!("v{}", self.vals);
+                    self.names.insert(*id, name.clone());
+                    (format!("val {} = {}", name, rhs), Prec::Lowest)
+                }
+            },
+            Expr::FuncValue(func) => self.lambda(func, indent),
+            Expr::Apply(apply) => {
+                let func = self.operand(&apply.func, indent, Prec::Atom);
+                let args = self.args(&apply.args, indent);
+                atom(format!("{}({})", func, args))
+            }
+            Expr::MethodCall(mc) => {
+                let obj = self.operand(&mc.obj, indent, Prec::Atom);
+                let args = self.args(&mc.args, indent);
+                atom(format!("{}.{}({})", obj, mc.method.name(), args))
+            }
+            Expr::ProperyCall(pc) => self.property(&pc.obj, pc.method.name(), indent),
+            Expr::If(op) => {
+                let condition = self.print(&op.condition, indent).0;
+                let true_branch = self.print(&op.true_branch, indent).0;
+                let false_branch = self.print(&op.false_branch, indent).0;
+                (
+                    format!("if ({}) {} else {}", condition, true_branch, false_branch),
+                    Prec::Lowest,
+                )
+            }
+            Expr::BinOp(op) => self.bin_op(op, indent),
+            Expr::SigmaAnd(op) => self.infix_items(op.items.as_slice(), "&&", Prec::And, indent),
+            Expr::SigmaOr(op) => self.infix_items(op.items.as_slice(), "||", Prec::Or, indent),
+            Expr::Append(op) => self.infix(&op.input, "++", &op.col_2, Prec::Add, indent),
+            Expr::LogicalNot(op) => self.prefix("!", &op.input, indent),
+            Expr::Negation(op) => self.prefix("-", &op.input, indent),
+            Expr::BitInversion(op) => self.prefix("~", &op.input, indent),
+            Expr::Collection(Collection::BoolConstants(items)) => atom(if items.is_empty() {
+                "Coll[Boolean]()".to_string()
+            } else {
+                format!(
+                    "Coll({})",
+                    items
+                        .iter()
+                        .map(|b| b.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }),
+            Expr::Collection(Collection::Exprs { elem_tpe, items }) => {
+                if items.is_empty() {
+                    atom(format!("Coll[{}]()", print_type(elem_tpe)))
+                } else {
+                    self.call("Coll", &items.iter().collect::<Vec<&Expr>>(), indent)
+                }
+            }
+            Expr::Tuple(op) => {
+                let items = self.args(op.items.as_slice(), indent);
+                atom(format!("({})", items))
+            }
+            Expr::SubstConstants(op) => self.call(
+                "substConstants",
+                &[&*op.script_bytes, &*op.positions, &*op.new_values],
+                indent,
+            ),
+            Expr::ByteArrayToLong(op) => self.call1("byteArrayToLong", &op.input, indent),
+            Expr::ByteArrayToBigInt(op) => self.call1("byteArrayToBigInt", &op.input, indent),
+            Expr::LongToByteArray(op) => self.call1("longToByteArray", &op.input, indent),
+            Expr::CalcBlake2b256(op) => self.call1("blake2b256", &op.input, indent),
+            Expr::CalcSha256(op) => self.call1("sha256", &op.input, indent),
+            Expr::And(op) => self.call1("allOf", &op.input, indent),
+            Expr::Or(op) => self.call1("anyOf", &op.input, indent),
+            Expr::XorOf(op) => self.call1("xorOf", &op.input, indent),
+            Expr::Xor(op) => self.call("xor", &[&*op.left, &*op.right], indent),
+            Expr::Atleast(op) => self.call("atLeast", &[&*op.bound, &*op.input], indent),
+            Expr::BoolToSigmaProp(op) => self.call1("sigmaProp", &op.input, indent),
+            Expr::CreateProveDlog(op) => self.call1("proveDlog", &op.input, indent),
+            Expr::CreateProveDhTuple(op) => {
+                self.call("proveDHTuple", &[&*op.g, &*op.h, &*op.u, &*op.v], indent)
+            }
+            Expr::DecodePoint(op) => self.call1("decodePoint", &op.input, indent),
+            Expr::CreateAvlTree(op) => {
+                let mut args = vec![&*op.flags, &*op.digest, &*op.key_length];
+                args.extend(op.value_length.as_deref());
+                self.call("avlTree", &args, indent)
+            }
+            Expr::TreeLookup(op) => {
+                self.call("treeLookup", &[&*op.tree, &*op.key, &*op.proof], indent)
+            }
+            Expr::OptionGet(op) => self.property(&op.input, "get", indent),
+            Expr::OptionIsDefined(op) => self.property(&op.input, "isDefined", indent),
+            Expr::OptionGetOrElse(op) => {
+                self.method(&op.input, "getOrElse", &[&*op.default], indent)
+            }
+            Expr::ExtractAmount(op) => self.property(&op.input, "value", indent),
+            Expr::ExtractRegisterAs(op) => {
+                let reg = format!("R{}[{}]", op.register_id, print_type(&op.elem_tpe));
+                self.property(&op.input, &reg, indent)
+            }
+            Expr::ExtractBytes(op) => self.property(&op.input, "bytes", indent),
+            Expr::ExtractBytesWithNoRef(op) => self.property(&op.input, "bytesWithoutRef", indent),
+            Expr::ExtractScriptBytes(op) => self.property(&op.input, "propositionBytes", indent),
+            Expr::ExtractCreationInfo(op) => self.property(&op.input, "creationInfo", indent),
+            Expr::ExtractId(op) => self.property(&op.input, "id", indent),
+            Expr::SigmaPropBytes(op) => self.property(&op.input, "propBytes", indent),
+            Expr::SizeOf(op) => self.property(&op.input, "size", indent),
+            Expr::ByIndex(op) => match &op.default {
+                Some(default) => {
+                    self.method(&op.input, "getOrElse", &[&*op.index, &**default], indent)
+                }
+                None => {
+                    let input = self.operand(&op.input, indent, Prec::Atom);
+                    let index = self.print(&op.index, indent).0;
+                    atom(format!("{}({})", input, index))
+                }
+            },
+            Expr::Slice(op) => self.method(&op.input, "slice", &[&*op.from, &*op.until], indent),
+            Expr::Fold(op) => self.method(&op.input, "fold", &[&*op.zero, &*op.fold_op], indent),
+            Expr::Map(op) => self.method(&op.input, "map", &[&*op.mapper], indent),
+            Expr::Filter(op) => self.method(&op.input, "filter", &[&*op.condition], indent),
+            Expr::Exists(op) => self.method(&op.input, "exists", &[&*op.condition], indent),
+            Expr::ForAll(op) => self.method(&op.input, "forall", &[&*op.condition], indent),
+            Expr::SelectField(op) => {
+                let field = format!("_{}", op.field_index.zero_based_index() + 1);
+                self.property(&op.input, &field, indent)
+            }
+            Expr::Upcast(op) => self.property(&op.input, &conversion(&op.tpe), indent),
+            Expr::Downcast(op) => self.property(&op.input, &conversion(&op.tpe), indent),
+            Expr::MultiplyGroup(op) => self.method(&op.left, "multiply", &[&*op.right], indent),
+            Expr::Exponentiate(op) => self.method(&op.left, "exp", &[&*op.right], indent),
+            Expr::GetVar(op) => atom(format!(
+                "getVar[{}]({})",
+                print_type(&op.var_tpe),
+                op.var_id
+            )),
+            Expr::DeserializeContext(op) => atom(format!(
+                "executeFromVar[{}]({})",
+                print_type(&op.tpe),
+                op.id
+            )),
+            Expr::DeserializeRegister(op) => match &op.default {
+                Some(default) => {
+                    let default = self.print(default, indent).0;
+                    atom(format!(
+                        "executeFromSelfRegWithDefault[{}]({}, {})",
+                        print_type(&op.tpe),
+                        op.reg,
+                        default
+                    ))
+                }
+                None => atom(format!(
+                    "executeFromSelfReg[{}]({})",
+                    print_type(&op.tpe),
+                    op.reg
+                )),
+            },
+        }
+    }
+
+    /// Printed expression, in parentheses if its precedence is lower than `min`
+    fn operand(&mut self, expr: &Expr, indent: usize, min: Prec) -> String {
+        let (s, prec) = self.print(expr, indent);
+        if prec < min {
+            format!("({})", s)
+        } else {
+            s
+        }
+    }
+
+    fn name(&self, id: ValId) -> String {
+        self.names
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| format!("unbound{}", id.0))
+    }
+
+    fn params(&mut self, args: &[FuncArg]) -> String {
+        args.iter()
+            .map(|arg| {
+                self.args += 1;
+                let name = format!("x{}", self.args);
+                self.names.insert(arg.idx, name.clone());
+                format!("{}: {}", name, print_type(&arg.tpe))
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn lambda(&mut self, func: &FuncValue, indent: usize) -> (String, Prec) {
+        let params = self.params(func.args());
+        let body = self.print(func.body(), indent).0;
+        atom(format!("{{ ({}) => {} }}", params, body))
+    }
+
+    fn args(&mut self, args: &[Expr], indent: usize) -> String {
+        args.iter()
+            .map(|arg| self.print(arg, indent).0)
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn call(&mut self, name: &str, args: &[&Expr], indent: usize) -> (String, Prec) {
+        let args = args
+            .iter()
+            .map(|arg| self.print(arg, indent).0)
+            .collect::<Vec<String>>()
+            .join(", ");
+        atom(format!("{}({})", name, args))
+    }
+
+    fn call1(&mut self, name: &str, arg: &Expr, indent: usize) -> (String, Prec) {
+        self.call(name, &[arg], indent)
+    }
+
+    fn method(&mut self, obj: &Expr, name: &str, args: &[&Expr], indent: usize) -> (String, Prec) {
+        let obj = self.operand(obj, indent, Prec::Atom);
+        let (call, _) = self.call(name, args, indent);
+        atom(format!("{}.{}", obj, call))
+    }
+
+    fn property(&mut self, obj: &Expr, name: &str, indent: usize) -> (String, Prec) {
+        let obj = self.operand(obj, indent, Prec::Atom);
+        atom(format!("{}.{}", obj, name))
+    }
+
+    fn prefix(&mut self, op: &str, input: &Expr, indent: usize) -> (String, Prec) {
+        let input = self.operand(input, indent, Prec::Prefix);
+        (format!("{}{}", op, input), Prec::Prefix)
+    }
+
+    /// Left associative infix operator
+    fn infix(
+        &mut self,
+        left: &Expr,
+        op: &str,
+        right: &Expr,
+        prec: Prec,
+        indent: usize,
+    ) -> (String, Prec) {
+        let left = self.operand(left, indent, prec);
+        let right = self.right_operand(right, indent, prec);
+        (format!("{} {} {}", left, op, right), prec)
+    }
+
+    fn infix_items(
+        &mut self,
+        items: &[Expr],
+        op: &str,
+        prec: Prec,
+        indent: usize,
+    ) -> (String, Prec) {
+        let items = items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                if i == 0 {
+                    self.operand(item, indent, prec)
+                } else {
+                    self.right_operand(item, indent, prec)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(&format!(" {} ", op));
+        (items, prec)
+    }
+
+    /// Right operand of a left associative operator needs parentheses on the same precedence
+    fn right_operand(&mut self, expr: &Expr, indent: usize, prec: Prec) -> String {
+        let (s, p) = self.print(expr, indent);
+        if p <= prec {
+            format!("({})", s)
+        } else {
+            s
+        }
+    }
+
+    fn bin_op(&mut self, op: &BinOp, indent: usize) -> (String, Prec) {
+        let (sym, prec) = match op.kind {
+            BinOpKind::Arith(ArithOp::Max) => {
+                return self.call("max", &[&*op.left, &*op.right], indent)
+            }
+            BinOpKind::Arith(ArithOp::Min) => {
+                return self.call("min", &[&*op.left, &*op.right], indent)
+            }
+            BinOpKind::Arith(ArithOp::Plus) => ("+", Prec::Add),
+            BinOpKind::Arith(ArithOp::Minus) => ("-", Prec::Add),
+            BinOpKind::Arith(ArithOp::Multiply) => ("*", Prec::Mul),
+            BinOpKind::Arith(ArithOp::Divide) => ("/", Prec::Mul),
+            BinOpKind::Arith(ArithOp::Modulo) => ("%", Prec::Mul),
+            BinOpKind::Relation(RelationOp::Eq) => ("==", Prec::Eq),
+            BinOpKind::Relation(RelationOp::NEq) => ("!=", Prec::Eq),
+            BinOpKind::Relation(RelationOp::Ge) => (">=", Prec::Rel),
+            BinOpKind::Relation(RelationOp::Gt) => (">", Prec::Rel),
+            BinOpKind::Relation(RelationOp::Le) => ("<=", Prec::Rel),
+            BinOpKind::Relation(RelationOp::Lt) => ("<", Prec::Rel),
+            BinOpKind::Logical(LogicalOp::And) => ("&&", Prec::And),
+            BinOpKind::Logical(LogicalOp::Or) => ("||", Prec::Or),
+            BinOpKind::Logical(LogicalOp::Xor) => ("^", Prec::Xor),
+            BinOpKind::Bit(BitOp::BitAnd) => ("&", Prec::And),
+            BinOpKind::Bit(BitOp::BitOr) => ("|", Prec::Or),
+            BinOpKind::Bit(BitOp::BitXor) => ("^", Prec::Xor),
+        };
+        self.infix(&op.left, sym, &op.right, prec, indent)
+    }
+}
+
+fn atom(s: impl Into<String>) -> (String, Prec) {
+    (s.into(), Prec::Atom)
+}
+
+/// Numeric conversion method (`toByte`, `toLong`, etc.)
+fn conversion(tpe: &SType) -> String {
+    format!("to{}", print_type(tpe))
+}
+
+fn literal(tpe: &SType, v: &Literal) -> (String, Prec) {
+    match v {
+        Literal::Boolean(b) => atom(b.to_string()),
+        Literal::Byte(n) => numeric(*n < 0, n.to_string(), ".toByte"),
+        Literal::Short(n) => numeric(*n < 0, n.to_string(), ".toShort"),
+        Literal::Int(n) => numeric(*n < 0, n.to_string(), ""),
+        Literal::Long(n) => numeric(*n < 0, n.to_string(), "L"),
+        Literal::BigInt(n) => atom(format!("bigInt(\"{}\")", n)),
+        Literal::SigmaProp(sp) => sigma_boolean(sp.value()),
+        Literal::GroupElement(p) => atom(group_element(p)),
+        Literal::AvlTree(t) => atom(format!(
+            "avlTree({}.toByte, {}, {}, {})",
+            t.tree_flags.serialize(),
+            bytes(t.digest.0.as_ref()),
+            t.key_length,
+            t.value_length_opt
+                .as_ref()
+                .map_or_else(|| "None".to_string(), |l| format!("Some({})", l))
+        )),
+        Literal::CBox(b) => atom(format!("box(\"{}\")", String::from(b.box_id()))),
+        Literal::Coll(CollKind::NativeColl(NativeColl::CollByte(bs))) => {
+            let bs: Vec<u8> = bs.iter().map(|b| *b as u8).collect();
+            atom(bytes(&bs))
+        }
+        Literal::Coll(CollKind::WrappedColl { elem_tpe, items }) => atom(if items.is_empty() {
+            format!("Coll[{}]()", print_type(elem_tpe))
+        } else {
+            format!(
+                "Coll({})",
+                items
+                    .iter()
+                    .map(|item| literal(elem_tpe, item).0)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )
+        }),
+        Literal::Opt(opt) => match (&**opt, tpe) {
+            (Some(v), SType::SOption(elem_tpe)) => {
+                atom(format!("Some({})", literal(elem_tpe, v).0))
+            }
+            (Some(v), _) => atom(format!("Some({})", literal(&SType::SAny, v).0)),
+            (None, _) => atom("None"),
+        },
+        Literal::Tup(items) => {
+            let types: Vec<SType> = match tpe {
+                SType::STuple(t) => t.items.iter().cloned().collect(),
+                _ => vec![],
+            };
+            atom(format!(
+                "({})",
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| literal(types.get(i).unwrap_or(&SType::SAny), item).0)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ))
+        }
+    }
+}
+
+fn numeric(negative: bool, n: String, suffix: &str) -> (String, Prec) {
+    match (negative, suffix.starts_with('.')) {
+        (true, true) => atom(format!("({}){}", n, suffix)),
+        (true, false) => (format!("{}{}", n, suffix), Prec::Prefix),
+        (false, _) => atom(format!("{}{}", n, suffix)),
+    }
+}
+
+fn bytes(bs: &[u8]) -> String {
+    format!("fromBase16(\"{}\")", base16::encode_lower(bs))
+}
+
+fn group_element(p: &EcPoint) -> String {
+    let bs = p.sigma_serialize_bytes().unwrap_or_default();
+    format!("decodePoint({})", bytes(&bs))
+}
+
+fn sigma_boolean(sb: &SigmaBoolean) -> (String, Prec) {
+    let items = |items: &[SigmaBoolean], op: &str, prec: Prec| {
+        items
+            .iter()
+            .map(|item| {
+                let (s, p) = sigma_boolean(item);
+                if p <= prec {
+                    format!("({})", s)
+                } else {
+                    s
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(op)
+    };
+    match sb {
+        SigmaBoolean::TrivialProp(b) => atom(format!("sigmaProp({})", b)),
+        SigmaBoolean::ProofOfKnowledge(SigmaProofOfKnowledgeTree::ProveDlog(pd)) => {
+            atom(format!("proveDlog({})", group_element(&pd.h)))
+        }
+        SigmaBoolean::ProofOfKnowledge(SigmaProofOfKnowledgeTree::ProveDhTuple(dh)) => {
+            atom(format!(
+                "proveDHTuple({}, {}, {}, {})",
+                group_element(&dh.g),
+                group_element(&dh.h),
+                group_element(&dh.u),
+                group_element(&dh.v)
+            ))
+        }
+        SigmaBoolean::SigmaConjecture(SigmaConjecture::Cand(cand)) => {
+            (items(cand.items.as_slice(), " && ", Prec::And), Prec::And)
+        }
+        SigmaBoolean::SigmaConjecture(SigmaConjecture::Cor(cor)) => {
+            (items(cor.items.as_slice(), " || ", Prec::Or), Prec::Or)
+        }
+        SigmaBoolean::SigmaConjecture(SigmaConjecture::Cthreshold(ct)) => atom(format!(
+            "atLeast({}, Coll({}))",
+            ct.k,
+            items(ct.children.as_slice(), ", ", Prec::Lowest)
+        )),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::ergo_tree::ErgoTree;
+    use crate::ergo_tree::ErgoTreeHeader;
+    use crate::mir::coll_map::Map;
+    use crate::mir::constant::ConstantPlaceholder;
+    use crate::mir::extract_reg_as::ExtractRegisterAs;
+    use crate::mir::if_op::If;
+    use crate::mir::logical_not::LogicalNot;
+    use crate::mir::method_call::MethodCall;
+    use crate::mir::option_get::OptionGet;
+    use crate::mir::property_call::PropertyCall;
+    use crate::mir::unary_op::OneArgOpTryBuild;
+    use crate::mir::val_use::ValUse;
+    use crate::sigma_protocol::sigma_boolean::ProveDlog;
+    use crate::types::scoll;
+    use crate::types::scontext;
+    use crate::types::stype_param::STypeVar;
+
+    fn bin_op(kind: impl Into<BinOpKind>, left: Expr, right: Expr) -> Expr {
+        BinOp {
+            kind: kind.into(),
+            left: left.into(),
+            right: right.into(),
+        }
+        .into()
+    }
+
+    fn val_def(id: u32, rhs: Expr) -> Expr {
+        ValDef {
+            id: id.into(),
+            rhs: rhs.into(),
+        }
+        .into()
+    }
+
+    fn val_use(id: u32, tpe: SType) -> Expr {
+        ValUse {
+            val_id: id.into(),
+            tpe,
+        }
+        .into()
+    }
+
+    #[test]
+    fn block_with_stable_names() {
+        let block: Expr = BlockValue {
+            items: vec![
+                val_def(7, GlobalVars::Height.into()),
+                val_def(
+                    3,
+                    bin_op(ArithOp::Plus, val_use(7, SType::SInt), 1i32.into()),
+                ),
+            ],
+            result: Box::new(bin_op(
+                RelationOp::Gt,
+                val_use(3, SType::SInt),
+                val_use(7, SType::SInt),
+            )),
+        }
+        .into();
+        assert_eq!(
+            print_expr(&block),
+            "{\n  val v1 = HEIGHT\n  val v2 = v1 + 1\n  v2 > v1\n}"
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let arg = FuncArg {
+            idx: 2.into(),
+            tpe: SType::SLong,
+        };
+        let func: Expr = FuncValue::new(
+            vec![arg.clone()],
+            bin_op(ArithOp::Multiply, val_use(2, SType::SLong), 2i64.into()),
+        )
+        .into();
+        let block: Expr = BlockValue {
+            items: vec![val_def(1, func.clone())],
+            result: Box::new(
+                Map::new(
+                    vec![1i64, -2i64].into(),
+                    FuncValue::new(vec![arg], val_use(2, SType::SLong)).into(),
+                )
+                .unwrap()
+                .into(),
+            ),
+        }
+        .into();
+        assert_eq!(
+            print_expr(&block),
+            "{\n  def f1(x1: Long) = x1 * 2L\n  Coll(1L, -2L).map({ (x2: Long) => x2 })\n}"
+        );
+        assert_eq!(print_expr(&func), "{ (x1: Long) => x1 * 2L }");
+    }
+
+    #[test]
+    fn method_and_property_calls() {
+        let mc: Expr = MethodCall::new(
+            vec![1i64, 2i64].into(),
+            scoll::INDEX_OF_METHOD
+                .clone()
+                .with_concrete_types(&[(STypeVar::t(), SType::SLong)].iter().cloned().collect()),
+            vec![2i64.into(), 0i32.into()],
+        )
+        .unwrap()
+        .into();
+        assert_eq!(print_expr(&mc), "Coll(1L, 2L).indexOf(2L, 0)");
+        let pc: Expr = PropertyCall::new(Expr::Context, scontext::DATA_INPUTS_PROPERTY.clone())
+            .unwrap()
+            .into();
+        assert_eq!(print_expr(&pc), "CONTEXT.dataInputs");
+        let reg: Expr = OptionGet::try_build(
+            ExtractRegisterAs::new(
+                GlobalVars::SelfBox.into(),
+                4,
+                SType::SOption(SType::SColl(SType::SByte.into()).into()),
+            )
+            .unwrap()
+            .into(),
+        )
+        .unwrap()
+        .into();
+        assert_eq!(print_expr(&reg), "SELF.R4[Coll[Byte]].get");
+    }
+
+    #[test]
+    fn parentheses() {
+        let e = bin_op(
+            ArithOp::Multiply,
+            bin_op(ArithOp::Plus, 1i32.into(), 2i32.into()),
+            bin_op(ArithOp::Minus, 3i32.into(), 4i32.into()),
+        );
+        assert_eq!(print_expr(&e), "(1 + 2) * (3 - 4)");
+        let e = bin_op(
+            ArithOp::Minus,
+            bin_op(ArithOp::Minus, 1i32.into(), 2i32.into()),
+            bin_op(ArithOp::Minus, 3i32.into(), 4i32.into()),
+        );
+        assert_eq!(print_expr(&e), "1 - 2 - (3 - 4)");
+        let e = bin_op(
+            LogicalOp::Or,
+            bin_op(LogicalOp::And, true.into(), false.into()),
+            LogicalNot {
+                input: Box::new(bin_op(RelationOp::Eq, 1i8.into(), (-1i8).into())),
+            }
+            .into(),
+        );
+        assert_eq!(
+            print_expr(&e),
+            "true && false || !(1.toByte == (-1).toByte)"
+        );
+        let e: Expr = If {
+            condition: Box::new(true.into()),
+            true_branch: Box::new(1i32.into()),
+            false_branch: Box::new(2i32.into()),
+        }
+        .into();
+        let e = bin_op(ArithOp::Plus, e, 3i32.into());
+        assert_eq!(print_expr(&e), "(if (true) 1 else 2) + 3");
+    }
+
+    #[test]
+    fn placeholders_and_constants() {
+        let e: Expr = bin_op(
+            RelationOp::Ge,
+            GlobalVars::Height.into(),
+            ConstantPlaceholder {
+                id: 0,
+                tpe: SType::SInt,
+            }
+            .into(),
+        );
+        assert_eq!(print_expr(&e), "HEIGHT >= placeholder[Int](0)");
+        assert_eq!(
+            print_expr_with_constants(&e, &[100i32.into()]),
+            "HEIGHT >= 100"
+        );
+        let pk = ProveDlog::new(EcPoint::default());
+        let sp: Expr = bin_op(
+            LogicalOp::And,
+            e.clone(),
+            bin_op(
+                RelationOp::Eq,
+                GlobalVars::MinerPubKey.into(),
+                vec![1i8, 2i8].into(),
+            ),
+        );
+        assert_eq!(
+            print_expr_with_constants(&sp, &[100i32.into()]),
+            "HEIGHT >= 100 && minerPubKey == fromBase16(\"0102\")"
+        );
+        assert_eq!(
+            print_constant(&pk.into()),
+            "proveDlog(decodePoint(fromBase16(\"000000000000000000000000000000000000000000000000000000000000000000\")))"
+        );
+    }
+
+    #[test]
+    fn ergo_tree_with_segregated_constants() {
+        let e = bin_op(RelationOp::Ge, GlobalVars::Height.into(), 100i32.into());
+        let tree = ErgoTree::new(ErgoTreeHeader::v0(true), &e).unwrap();
+        assert_eq!(tree.pretty_print().unwrap(), "HEIGHT >= 100");
+    }
+}
@@ -127,6 +127,14 @@ impl HasOpCode for SigmaConjecture {
 /// Algebraic data type of sigma proposition expressions
 /// Values of this type are used as values of SigmaProp type
 #[derive(PartialEq, Eq, Debug, Clone, From, TryInto)]
+#[cfg_attr(
+    feature = "json",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        into = "crate::chain::json::sigma_protocol::SigmaBooleanJson",
+        try_from = "crate::chain::json::sigma_protocol::SigmaBooleanJson"
+    )
+)]
 pub enum SigmaBoolean {
     /// Represents boolean values (true/false)
     TrivialProp(bool),
@@ -153,6 +161,186 @@ impl HasOpCode for SigmaBoolean {
     }
 }
 
+impl SigmaBoolean {
+    /// Simplifies the proposition: flattens nested AND/OR, removes trivial propositions (with
+    /// partial evaluation), removes duplicated children of AND/OR and converts degenerate
+    /// thresholds (k = 1, k = n) to OR/AND. The result is equivalent to the original proposition.
+    pub fn normalize(self) -> SigmaBoolean {
+        match self {
+            SigmaBoolean::TrivialProp(_) | SigmaBoolean::ProofOfKnowledge(_) => self,
+            SigmaBoolean::SigmaConjecture(SigmaConjecture::Cand(Cand { items })) => {
+                normalize_and(items.into_iter().map(SigmaBoolean::normalize).collect())
+            }
+            SigmaBoolean::SigmaConjecture(SigmaConjecture::Cor(Cor { items })) => {
+                normalize_or(items.into_iter().map(SigmaBoolean::normalize).collect())
+            }
+            SigmaBoolean::SigmaConjecture(SigmaConjecture::Cthreshold(Cthreshold {
+                k,
+                children,
+            })) => normalize_threshold(
+                k as usize,
+                children.into_iter().map(SigmaBoolean::normalize).collect(),
+            ),
+        }
+    }
+
+    /// All distinct public keys (leaves) of the proposition in the order of appearance
+    pub fn leaves(&self) -> Vec<&SigmaProofOfKnowledgeTree> {
+        let mut res: Vec<&SigmaProofOfKnowledgeTree> = Vec::new();
+        self.collect_leaves(&mut res);
+        res
+    }
+
+    fn collect_leaves<'a>(&'a self, res: &mut Vec<&'a SigmaProofOfKnowledgeTree>) {
+        match self {
+            SigmaBoolean::TrivialProp(_) => (),
+            SigmaBoolean::ProofOfKnowledge(leaf) => {
+                if !res.contains(&leaf) {
+                    res.push(leaf)
+                }
+            }
+            SigmaBoolean::SigmaConjecture(conj) => {
+                conj.children().iter().for_each(|ch| ch.collect_leaves(res))
+            }
+        }
+    }
+
+    /// Checks whether the proposition can be proven knowing the secrets for the given public keys
+    pub fn is_satisfied_by(&self, keys: &[SigmaProofOfKnowledgeTree]) -> bool {
+        match self {
+            SigmaBoolean::TrivialProp(b) => *b,
+            SigmaBoolean::ProofOfKnowledge(leaf) => keys.contains(leaf),
+            SigmaBoolean::SigmaConjecture(SigmaConjecture::Cand(Cand { items })) => {
+                items.iter().all(|it| it.is_satisfied_by(keys))
+            }
+            SigmaBoolean::SigmaConjecture(SigmaConjecture::Cor(Cor { items })) => {
+                items.iter().any(|it| it.is_satisfied_by(keys))
+            }
+            SigmaBoolean::SigmaConjecture(SigmaConjecture::Cthreshold(Cthreshold {
+                k,
+                children,
+            })) => {
+                children
+                    .iter()
+                    .filter(|ch| ch.is_satisfied_by(keys))
+                    .count()
+                    >= *k as usize
+            }
+        }
+    }
+}
+
+impl SigmaConjecture {
+    /// Children of the conjecture
+    pub fn children(&self) -> &[SigmaBoolean] {
+        match self {
+            SigmaConjecture::Cand(Cand { items }) => items.as_slice(),
+            SigmaConjecture::Cor(Cor { items }) => items.as_slice(),
+            SigmaConjecture::Cthreshold(Cthreshold { children, .. }) => children.as_slice(),
+        }
+    }
+}
+
+/// AND of normalized children
+fn normalize_and(children: Vec<SigmaBoolean>) -> SigmaBoolean {
+    let mut res: Vec<SigmaBoolean> = Vec::new();
+    for ch in children {
+        match ch {
+            SigmaBoolean::TrivialProp(false) => return false.into(),
+            SigmaBoolean::TrivialProp(true) => (),
+            SigmaBoolean::SigmaConjecture(SigmaConjecture::Cand(Cand { items })) => {
+                items.into_iter().for_each(|it| push_distinct(&mut res, it))
+            }
+            SigmaBoolean::ProofOfKnowledge(_)
+            | SigmaBoolean::SigmaConjecture(SigmaConjecture::Cor(_))
+            | SigmaBoolean::SigmaConjecture(SigmaConjecture::Cthreshold(_)) => {
+                push_distinct(&mut res, ch)
+            }
+        }
+    }
+    conjunction(res, |items| Cand { items }.into(), true)
+}
+
+/// OR of normalized children
+fn normalize_or(children: Vec<SigmaBoolean>) -> SigmaBoolean {
+    let mut res: Vec<SigmaBoolean> = Vec::new();
+    for ch in children {
+        match ch {
+            SigmaBoolean::TrivialProp(true) => return true.into(),
+            SigmaBoolean::TrivialProp(false) => (),
+            SigmaBoolean::SigmaConjecture(SigmaConjecture::Cor(Cor { items })) => {
+                items.into_iter().for_each(|it| push_distinct(&mut res, it))
+            }
+            SigmaBoolean::ProofOfKnowledge(_)
+            | SigmaBoolean::SigmaConjecture(SigmaConjecture::Cand(_))
+            | SigmaBoolean::SigmaConjecture(SigmaConjecture::Cthreshold(_)) => {
+                push_distinct(&mut res, ch)
+            }
+        }
+    }
+    conjunction(res, |items| Cor { items }.into(), false)
+}
+
+/// THRESHOLD of normalized children (duplicates are kept since each of them counts)
+fn normalize_threshold(k: usize, children: Vec<SigmaBoolean>) -> SigmaBoolean {
+    let mut k = k;
+    let mut res: Vec<SigmaBoolean> = Vec::new();
+    for ch in children {
+        match ch {
+            SigmaBoolean::TrivialProp(true) => k = k.saturating_sub(1),
+            SigmaBoolean::TrivialProp(false) => (),
+            SigmaBoolean::ProofOfKnowledge(_) | SigmaBoolean::SigmaConjecture(_) => res.push(ch),
+        }
+    }
+    if k == 0 {
+        true.into()
+    } else if k > res.len() {
+        false.into()
+    } else if k == 1 {
+        normalize_or(res)
+    } else if k == res.len() {
+        normalize_and(res)
+    } else {
+        // 1 < k < n, so there are 3..=255 children and k fits in u8
+        #[allow(clippy::unwrap_used)]
+        Cthreshold {
+            k: k as u8,
+            children: SigmaConjectureItems::from_vec(res).unwrap(),
+        }
+        .into()
+    }
+}
+
+fn push_distinct(res: &mut Vec<SigmaBoolean>, item: SigmaBoolean) {
+    if !res.contains(&item) {
+        res.push(item)
+    }
+}
+
+/// Builds AND/OR (`conj`) of the given items, `empty` is the value of the empty conjunction.
+/// More than 255 items are split into nested conjunctions.
+fn conjunction(
+    mut items: Vec<SigmaBoolean>,
+    conj: fn(SigmaConjectureItems<SigmaBoolean>) -> SigmaBoolean,
+    empty: bool,
+) -> SigmaBoolean {
+    match items.len() {
+        0 => empty.into(),
+        1 => items.remove(0),
+        // 2..=255 items
+        #[allow(clippy::unwrap_used)]
+        n if n <= 255 => conj(SigmaConjectureItems::from_vec(items).unwrap()),
+        _ => conjunction(
+            items
+                .chunks(255)
+                .map(|chunk| conjunction(chunk.to_vec(), conj, empty))
+                .collect(),
+            conj,
+            empty,
+        ),
+    }
+}
+
 /// Failed to extract specified underlying type from SigmaBoolean
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct ConversionError;
@@ -372,11 +560,150 @@ mod arbitrary {
 #[allow(clippy::panic)]
 #[cfg(test)]
 #[allow(clippy::panic)]
+#[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
     use crate::serialization::sigma_serialize_roundtrip;
+    use crate::sigma_protocol::dlog_group::exponentiate;
+    use crate::sigma_protocol::dlog_group::generator;
+    use k256::Scalar;
+    use proptest::collection::vec;
     use proptest::prelude::*;
 
+    fn and(items: Vec<SigmaBoolean>) -> SigmaBoolean {
+        Cand {
+            items: items.try_into().unwrap(),
+        }
+        .into()
+    }
+
+    fn or(items: Vec<SigmaBoolean>) -> SigmaBoolean {
+        Cor {
+            items: items.try_into().unwrap(),
+        }
+        .into()
+    }
+
+    fn atleast(k: u8, children: Vec<SigmaBoolean>) -> SigmaBoolean {
+        Cthreshold {
+            k,
+            children: children.try_into().unwrap(),
+        }
+        .into()
+    }
+
+    /// distinct public keys
+    fn pks() -> (SigmaBoolean, SigmaBoolean, SigmaBoolean) {
+        let pk = |x: u64| exponentiate(&generator(), &Scalar::from(x));
+        (
+            ProveDlog::new(pk(1)).into(),
+            ProveDlog::new(pk(2)).into(),
+            ProveDhTuple::new(generator(), pk(3), pk(4), pk(12)).into(),
+        )
+    }
+
+    /// Nested AND/OR/THRESHOLD (with any k) of trivial propositions and a few public keys (so
+    /// that they are repeated)
+    fn nested_with_trivial() -> impl Strategy<Value = SigmaBoolean> {
+        let (a, b, c) = pks();
+        prop_oneof![
+            any::<bool>().prop_map_into(),
+            proptest::sample::select(vec![a, b, c]),
+        ]
+        .prop_recursive(3, 32, 4, |elem| {
+            prop_oneof![
+                vec(elem.clone(), 2..=4).prop_map(and),
+                vec(elem.clone(), 2..=4).prop_map(or),
+                (0..=5u8, vec(elem, 2..=4)).prop_map(|(k, children)| atleast(k, children)),
+            ]
+        })
+    }
+
+    #[test]
+    fn normalize_flattens_and_dedups() {
+        let (a, b, c) = pks();
+        let sb = and(vec![
+            a.clone(),
+            and(vec![b.clone(), true.into(), a.clone()]),
+            or(vec![c.clone(), or(vec![c.clone(), false.into()])]),
+        ]);
+        assert_eq!(sb.normalize(), and(vec![a, b, c]));
+    }
+
+    #[test]
+    fn normalize_trivial() {
+        let (a, b, _) = pks();
+        assert_eq!(
+            and(vec![
+                a.clone(),
+                or(vec![b.clone(), false.into()]),
+                false.into()
+            ])
+            .normalize(),
+            false.into()
+        );
+        assert_eq!(or(vec![a.clone(), true.into()]).normalize(), true.into());
+        assert_eq!(and(vec![a.clone(), true.into()]).normalize(), a);
+        assert_eq!(
+            or(vec![false.into(), false.into()]).normalize(),
+            false.into()
+        );
+        assert_eq!(
+            atleast(2, vec![a.clone(), true.into(), b.clone()]).normalize(),
+            or(vec![a.clone(), b.clone()])
+        );
+        assert_eq!(
+            atleast(2, vec![a.clone(), false.into(), false.into()]).normalize(),
+            false.into()
+        );
+        assert_eq!(
+            atleast(2, vec![true.into(), true.into(), a]).normalize(),
+            true.into()
+        );
+    }
+
+    #[test]
+    fn normalize_degenerate_threshold() {
+        let (a, b, c) = pks();
+        assert_eq!(
+            atleast(1, vec![a.clone(), b.clone(), c.clone()]).normalize(),
+            or(vec![a.clone(), b.clone(), c.clone()])
+        );
+        assert_eq!(
+            atleast(3, vec![a.clone(), b.clone(), c.clone()]).normalize(),
+            and(vec![a.clone(), b.clone(), c.clone()])
+        );
+        assert_eq!(
+            atleast(
+                2,
+                vec![a.clone(), and(vec![b.clone(), true.into()]), c.clone()]
+            )
+            .normalize(),
+            atleast(2, vec![a.clone(), b, c])
+        );
+        // duplicates count in threshold
+        assert_eq!(atleast(2, vec![a.clone(), a.clone()]).normalize(), a);
+    }
+
+    #[test]
+    fn leaves_and_satisfaction() {
+        let (a, b, c) = pks();
+        let leaf = |sb: &SigmaBoolean| match sb {
+            SigmaBoolean::ProofOfKnowledge(l) => l.clone(),
+            _ => panic!("not a leaf"),
+        };
+        let sb = or(vec![
+            and(vec![a.clone(), b.clone()]),
+            atleast(2, vec![a.clone(), b.clone(), c.clone()]),
+        ]);
+        assert_eq!(sb.leaves(), vec![&leaf(&a), &leaf(&b), &leaf(&c)]);
+        assert!(sb.is_satisfied_by(&[leaf(&a), leaf(&c)]));
+        assert!(sb.is_satisfied_by(&[leaf(&b), leaf(&a)]));
+        assert!(!sb.is_satisfied_by(&[leaf(&c)]));
+        assert!(!sb.is_satisfied_by(&[]));
+        assert!(SigmaBoolean::TrivialProp(true).is_satisfied_by(&[]));
+    }
+
     proptest! {
 
         #[test]
@@ -384,5 +711,34 @@ mod tests {
             v in any::<SigmaBoolean>()) {
                 prop_assert_eq![sigma_serialize_roundtrip(&v), v]
         }
+
+        #[test]
+        fn normalize_keeps_semantics(v in nested_with_trivial()) {
+            let normalized = v.clone().normalize();
+            let (a, b, c) = pks();
+            let keys: Vec<SigmaProofOfKnowledgeTree> = vec![a, b, c]
+                .into_iter()
+                .filter_map(|sb| match sb {
+                    SigmaBoolean::ProofOfKnowledge(leaf) => Some(leaf),
+                    _ => None,
+                })
+                .collect();
+            // every subset of the known secrets
+            for mask in 0..(1 << keys.len()) {
+                let known: Vec<SigmaProofOfKnowledgeTree> = keys
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << i) != 0)
+                    .map(|(_, key)| key.clone())
+                    .collect();
+                prop_assert_eq!(normalized.is_satisfied_by(&known), v.is_satisfied_by(&known));
+            }
+        }
+
+        #[test]
+        fn normalize_is_idempotent(v in nested_with_trivial()) {
+            let normalized = v.normalize();
+            prop_assert_eq!(normalized.clone().normalize(), normalized);
+        }
     }
 }
@@ -1,6 +1,7 @@
 use super::zig_zag_encode;
 use std::convert::TryFrom;
 use std::io;
+use std::io::Read;
 
 use bitvec::order::Lsb0;
 use bitvec::prelude::BitVec;
@@ -226,6 +227,18 @@ pub trait ReadSigmaVlqExt: io::Read {
         Ok(bits.iter().map(|x| *x).collect::<Vec<bool>>())
     }
 
+    /// Read `len` bytes without decoding, the buffer grows only with the bytes actually read
+    /// (`len` may come from untrusted input)
+    fn get_bytes(&mut self, len: usize) -> Result<Vec<u8>, io::Error> {
+        let mut bytes = Vec::new();
+        (&mut *self).take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() == len {
+            Ok(bytes)
+        } else {
+            Err(io::Error::from(io::ErrorKind::UnexpectedEof))
+        }
+    }
+
     /// Reads a string from the reader. Reads a byte (size), and the string
     fn get_short_string(&mut self) -> Result<String, VlqEncodingError> {
         let size_bytes = self.get_u8()?;
@@ -449,6 +462,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn get_bytes() {
+        let mut r = Cursor::new([1u8, 2, 3]);
+        assert_eq!(r.get_bytes(2).unwrap(), vec![1, 2]);
+        assert_eq!(r.get_bytes(0).unwrap(), Vec::<u8>::new());
+        assert_eq!(
+            r.get_bytes(u32::MAX as usize).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
     #[test]
     fn i16_corner_cases() {
         fn roundtrip(v: i16, expected_bytes: &[u8]) {
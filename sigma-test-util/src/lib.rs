@@ -11,3 +11,6 @@ pub fn force_any_val_with<T: Arbitrary>(args: T::Parameters) -> T {
     let mut runner = TestRunner::default();
     any_with::<T>(args).new_tree(&mut runner).unwrap().current()
 }
+
+/// Mainnet block header at height 471746 (block version 2) as returned by the node API
+pub const HEADER_471746_JSON: &str = include_str!("../fixtures/header_471746.json");